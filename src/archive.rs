@@ -0,0 +1,355 @@
+//! Formatting of archives of Rust source files, e.g. source bundles that are shipped as `.tar` or
+//! `.zip` files.
+
+use crate::batch::{FileResult, Outcome};
+use crate::glob::Glob;
+use crate::{Error, Options};
+use std::io::{BufRead, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// The signature at the start of zip archives.
+const ZIP_MAGIC: &[u8] = b"PK";
+
+/// Options for formatting archives.
+///
+/// By default, all `.rs` entries are formatted. Other entries are copied unchanged.
+///
+/// # Examples
+///
+/// ```
+/// use rust_format::archive::ArchiveOptions;
+/// use rust_format::{Edition, Options};
+///
+/// let options = ArchiveOptions::new()
+///     .options(Options::new().edition(Edition::E2021))
+///     .include("src/**/*.rs");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ArchiveOptions {
+    options: Options,
+    include: Vec<Glob>,
+}
+
+impl ArchiveOptions {
+    /// Create a new instance with default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the options for formatting the entries.
+    ///
+    /// Configuration files are not discovered inside the archive, so the configuration must be
+    /// part of the options.
+    ///
+    /// # Arguments
+    ///
+    /// * `options`: The options.
+    pub fn options(mut self, options: Options) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Only format the entries whose path matches a glob pattern. If any pattern is added, entries
+    /// are formatted if they match at least one of them, regardless of their extension.
+    ///
+    /// See [`crate::batch::BatchOptions::config_for`] for the pattern syntax.
+    ///
+    /// # Arguments
+    ///
+    /// * `glob`: The glob pattern.
+    pub fn include(mut self, glob: impl Into<String>) -> Self {
+        self.include.push(Glob::new(glob));
+        self
+    }
+
+    /// Determine whether an entry is formatted.
+    fn includes(&self, path: &Path) -> bool {
+        match self.include.is_empty() {
+            true => path.extension().is_some_and(|extension| extension == "rs"),
+            false => self.include.iter().any(|glob| glob.matches(path)),
+        }
+    }
+}
+
+/// Format the Rust source files of an archive and write the result as a new archive.
+///
+/// The format of the archive is determined from its content: zip archives require the `zip`
+/// feature, tar archives the `tar` feature. Compressed tar archives can be handled by wrapping the
+/// reader and the writer, e.g. with a gzip decoder and encoder.
+///
+/// The output contains the same entries in the same order. The metadata of the entries, such as
+/// the modification time and the permissions, is preserved. Entries that cannot be formatted are
+/// copied unchanged and reported in the results.
+///
+/// # Arguments
+///
+/// * `input`: The reader that provides the archive.
+/// * `output`: The writer for the new archive.
+/// * `options`: The options.
+///
+/// # Returns
+///
+/// The results of the formatted entries, in the order of the archive. An error is only returned if
+/// the archive itself cannot be read or written.
+///
+/// # Examples
+///
+/// ```no_run
+/// use rust_format::archive::{format_archive, ArchiveOptions};
+///
+/// let input = std::fs::File::open("bundle.tar")?;
+/// let output = std::fs::File::create("bundle.formatted.tar")?;
+/// let results = format_archive(input, output, &ArchiveOptions::new())?;
+/// # Ok::<(), rust_format::Error>(())
+/// ```
+pub fn format_archive(
+    input: impl Read,
+    output: impl Write,
+    options: &ArchiveOptions,
+) -> Result<Vec<FileResult>, Error> {
+    let mut input = std::io::BufReader::new(input);
+    let is_zip = input.fill_buf()?.starts_with(ZIP_MAGIC);
+    #[cfg(feature = "zip")]
+    if is_zip {
+        return format_zip(input, output, options);
+    }
+    #[cfg(feature = "tar")]
+    if !is_zip {
+        return format_tar(input, output, options);
+    }
+    let _ = output;
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "The archive format is not supported; enable the 'tar' or 'zip' feature.",
+    )
+    .into())
+}
+
+/// Format the content of an entry.
+///
+/// # Returns
+///
+/// The new content of the entry, which is the original content if formatting failed, and the
+/// result.
+fn format_entry(
+    path: PathBuf,
+    content: Vec<u8>,
+    options: &ArchiveOptions,
+) -> (Vec<u8>, FileResult) {
+    let start = std::time::Instant::now();
+    let (content, result) = match String::from_utf8(content) {
+        Ok(text) => match crate::format::format_source(&text, None, &options.options) {
+            Ok(formatted) => (formatted.into_bytes(), Ok(Outcome::Formatted)),
+            Err(error) => (text.into_bytes(), Err(error)),
+        },
+        Err(error) => (
+            error.into_bytes(),
+            Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "The entry does not contain valid UTF-8.",
+            )
+            .into()),
+        ),
+    };
+    let result = FileResult {
+        path,
+        result,
+        duplicate: None,
+        children: Vec::new(),
+        elapsed: start.elapsed(),
+        diff_stat: None,
+        request_id: None,
+    };
+    (content, result)
+}
+
+/// Format the entries of a tar archive.
+#[cfg(feature = "tar")]
+fn format_tar(
+    input: impl Read,
+    output: impl Write,
+    options: &ArchiveOptions,
+) -> Result<Vec<FileResult>, Error> {
+    let mut archive = tar::Archive::new(input);
+    let mut builder = tar::Builder::new(output);
+    let mut results = Vec::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        let mut header = entry.header().clone();
+        let kind = header.entry_type();
+        if kind.is_symlink() || kind.is_hard_link() {
+            let target = entry.link_name()?.unwrap_or_default().into_owned();
+            builder.append_link(&mut header, &path, target)?;
+            continue;
+        }
+
+        let mut content = Vec::new();
+        entry.read_to_end(&mut content)?;
+        if kind.is_file() && options.includes(&path) {
+            let (formatted, result) = format_entry(path.clone(), content, options);
+            content = formatted;
+            results.push(result);
+        }
+        header.set_size(content.len() as u64);
+        builder.append_data(&mut header, &path, content.as_slice())?;
+    }
+    builder.into_inner()?.flush()?;
+    Ok(results)
+}
+
+/// Format the entries of a zip archive.
+#[cfg(feature = "zip")]
+fn format_zip(
+    mut input: impl Read,
+    mut output: impl Write,
+    options: &ArchiveOptions,
+) -> Result<Vec<FileResult>, Error> {
+    use zip::write::SimpleFileOptions;
+
+    let zip_error = |error| Error::from(std::io::Error::from(error));
+    let mut data = Vec::new();
+    input.read_to_end(&mut data)?;
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(data)).map_err(zip_error)?;
+    let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    writer.set_raw_comment(archive.comment().into());
+
+    let mut results = Vec::new();
+    for index in 0..archive.len() {
+        let file = archive.by_index_raw(index).map_err(zip_error)?;
+        let path = PathBuf::from(file.name());
+        if !file.is_file() || !options.includes(&path) {
+            writer.raw_copy_file(file).map_err(zip_error)?;
+            continue;
+        }
+        drop(file);
+
+        let mut file = archive.by_index(index).map_err(zip_error)?;
+        let name = file.name().to_string();
+        let mut entry_options = SimpleFileOptions::default().compression_method(file.compression());
+        if let Some(modified) = file.last_modified() {
+            entry_options = entry_options.last_modified_time(modified);
+        }
+        if let Some(mode) = file.unix_mode() {
+            entry_options = entry_options.unix_permissions(mode);
+        }
+        let mut content = Vec::new();
+        file.read_to_end(&mut content)?;
+        drop(file);
+
+        let (formatted, result) = format_entry(path, content, options);
+        writer.start_file(name, entry_options).map_err(zip_error)?;
+        writer.write_all(&formatted)?;
+        results.push(result);
+    }
+    let archive = writer.finish().map_err(zip_error)?;
+    output.write_all(&archive.into_inner())?;
+    output.flush()?;
+    Ok(results)
+}
+
+#[cfg(test)]
+mod test {
+    use super::ArchiveOptions;
+    use std::io::Read;
+
+    #[cfg(feature = "tar")]
+    #[test]
+    fn test_format_tar() {
+        use crate::batch::Outcome;
+
+        let mut builder = tar::Builder::new(Vec::new());
+        for (path, content) in [
+            ("src/main.rs", "fn main(){}"),
+            ("src/broken.rs", "fn main(){"),
+            ("README.md", "fn main(){}"),
+        ] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o640);
+            header.set_mtime(1_700_000_000);
+            builder
+                .append_data(&mut header, path, content.as_bytes())
+                .unwrap();
+        }
+        let input = builder.into_inner().unwrap();
+
+        let mut output = Vec::new();
+        let results =
+            super::format_archive(input.as_slice(), &mut output, &ArchiveOptions::new()).unwrap();
+
+        assert_eq!(2, results.len());
+        assert_eq!(Outcome::Formatted, *results[0].result.as_ref().unwrap());
+        assert!(results[1].result.is_err());
+        let mut archive = tar::Archive::new(output.as_slice());
+        let entries: Vec<(String, u32, u64, String)> = archive
+            .entries()
+            .unwrap()
+            .map(|entry| {
+                let mut entry = entry.unwrap();
+                let mut content = String::new();
+                entry.read_to_string(&mut content).unwrap();
+                let header = entry.header();
+                (
+                    entry.path().unwrap().display().to_string(),
+                    header.mode().unwrap(),
+                    header.mtime().unwrap(),
+                    content,
+                )
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                (
+                    "src/main.rs".to_string(),
+                    0o640,
+                    1_700_000_000,
+                    "fn main() {}\n".to_string()
+                ),
+                (
+                    "src/broken.rs".to_string(),
+                    0o640,
+                    1_700_000_000,
+                    "fn main(){".to_string()
+                ),
+                (
+                    "README.md".to_string(),
+                    0o640,
+                    1_700_000_000,
+                    "fn main(){}".to_string()
+                ),
+            ],
+            entries
+        );
+    }
+
+    #[cfg(feature = "zip")]
+    #[test]
+    fn test_format_zip() {
+        use std::io::Write;
+        use zip::write::SimpleFileOptions;
+
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        for (path, content) in [("src/lib.rs", "fn f(){}"), ("src/gen.rs", "fn g(){}")] {
+            let options = SimpleFileOptions::default().unix_permissions(0o600);
+            writer.start_file(path, options).unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+        let input = writer.finish().unwrap().into_inner();
+
+        let mut output = Vec::new();
+        let options = ArchiveOptions::new().include("src/lib.rs");
+        let results = super::format_archive(input.as_slice(), &mut output, &options).unwrap();
+
+        assert_eq!(1, results.len());
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(output)).unwrap();
+        let mut read = |name: &str| {
+            let mut file = archive.by_name(name).unwrap();
+            let mut content = String::new();
+            file.read_to_string(&mut content).unwrap();
+            (content, file.unix_mode().map(|mode| mode & 0o777))
+        };
+        assert_eq!(("fn f() {}\n".to_string(), Some(0o600)), read("src/lib.rs"));
+        assert_eq!(("fn g(){}".to_string(), Some(0o600)), read("src/gen.rs"));
+    }
+}
@@ -0,0 +1,171 @@
+//! An audit trail of files modified by formatting.
+
+use crate::json::JsonObject;
+use crate::Error;
+use std::fmt::{Debug, Formatter};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+/// A record of a single file modification.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AuditRecord {
+    /// The path of the modified file.
+    pub path: PathBuf,
+    /// The SHA-256 hash of the content before formatting, in hexadecimal notation.
+    pub hash_before: String,
+    /// The SHA-256 hash of the content after formatting, in hexadecimal notation.
+    pub hash_after: String,
+    /// The version of the formatting tool.
+    pub tool_version: String,
+    /// The SHA-256 hash of the configuration passed to the formatting tool, in hexadecimal
+    /// notation.
+    pub config_hash: String,
+    /// The time of the modification.
+    pub timestamp: SystemTime,
+}
+
+impl AuditRecord {
+    /// Encode the record as a single-line JSON object.
+    pub fn to_json(&self) -> String {
+        JsonObject::new()
+            .string("path", &self.path.to_string_lossy())
+            .string("hash_before", &self.hash_before)
+            .string("hash_after", &self.hash_after)
+            .string("tool_version", &self.tool_version)
+            .string("config_hash", &self.config_hash)
+            .string("timestamp", &crate::json::timestamp(self.timestamp))
+            .build()
+    }
+}
+
+enum Sink {
+    File(std::fs::File),
+    Callback(Box<dyn FnMut(&AuditRecord) + Send>),
+}
+
+/// A sink for audit records.
+///
+/// A record is written for every file that is modified by formatting it in place. Files whose
+/// content does not change are not recorded. The log can be cloned cheaply; clones write to the
+/// same sink.
+///
+/// # Examples
+///
+/// ```no_run
+/// use rust_format::audit::AuditLog;
+/// use rust_format::Options;
+///
+/// let log = AuditLog::to_file("target/format-audit.jsonl").unwrap();
+/// rust_format::format_file_with("src/lib.rs", &Options::new().audit_log(log)).unwrap();
+/// ```
+#[derive(Clone)]
+pub struct AuditLog {
+    sink: Arc<Mutex<Sink>>,
+}
+
+impl Debug for AuditLog {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AuditLog").finish_non_exhaustive()
+    }
+}
+
+impl AuditLog {
+    /// Create a log that appends records as JSON lines to a file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: The path of the file. The file is created if it does not exist.
+    pub fn to_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        Ok(Self::with_sink(Sink::File(file)))
+    }
+
+    /// Create a log that passes records to a callback.
+    ///
+    /// # Arguments
+    ///
+    /// * `callback`: The callback.
+    pub fn with_callback(callback: impl FnMut(&AuditRecord) + Send + 'static) -> Self {
+        Self::with_sink(Sink::Callback(Box::new(callback)))
+    }
+
+    fn with_sink(sink: Sink) -> Self {
+        Self {
+            sink: Arc::new(Mutex::new(sink)),
+        }
+    }
+
+    /// Write a record.
+    pub(crate) fn record(&self, record: &AuditRecord) -> Result<(), Error> {
+        let mut sink = self.sink.lock().expect("audit log lock poisoned");
+        match &mut *sink {
+            Sink::File(file) => writeln!(file, "{}", record.to_json())?,
+            Sink::Callback(callback) => callback(record),
+        }
+        Ok(())
+    }
+
+    /// Record the modification of a file, if its content changed.
+    pub(crate) fn record_change(
+        &self,
+        path: &Path,
+        before: &[u8],
+        after: &[u8],
+        options: &crate::Options,
+    ) -> Result<(), Error> {
+        if before == after {
+            return Ok(());
+        }
+        self.record(&AuditRecord {
+            path: path.to_path_buf(),
+            hash_before: crate::hash::sha256_hex(before),
+            hash_after: crate::hash::sha256_hex(after),
+            tool_version: options.get_backend().version()?,
+            config_hash: crate::hash::sha256_hex(
+                options
+                    .effective_config()
+                    .to_arg()
+                    .unwrap_or_default()
+                    .as_bytes(),
+            ),
+            timestamp: SystemTime::now(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::AuditLog;
+    use crate::Options;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_audit_log() {
+        const TARGET_PATH: &str = "target/audit_sample_code.rs";
+        std::fs::copy("resources/test/sample_code.rs", TARGET_PATH).unwrap();
+        let records = Arc::new(Mutex::new(Vec::new()));
+        let log = {
+            let records = records.clone();
+            AuditLog::with_callback(move |record| records.lock().unwrap().push(record.clone()))
+        };
+
+        let options = Options::new().audit_log(log);
+        crate::format_file_with(TARGET_PATH, &options).unwrap();
+        crate::format_file_with(TARGET_PATH, &options).unwrap();
+
+        let records = records.lock().unwrap();
+        assert_eq!(1, records.len());
+        assert_eq!(
+            crate::hash::sha256_hex(&std::fs::read(TARGET_PATH).unwrap()),
+            records[0].hash_after
+        );
+        assert!(records[0]
+            .to_json()
+            .starts_with(r#"{"path":"target/audit_sample_code.rs""#));
+    }
+}
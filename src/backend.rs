@@ -0,0 +1,636 @@
+//! Backends that perform the actual formatting.
+
+use crate::error::IoContext;
+use crate::events::{EventSink, MetricsSink};
+use crate::{ConfigKey, Error, Options, PostProcessor};
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Debug;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+#[cfg(feature = "process-backend")]
+mod child;
+#[cfg(feature = "process-backend")]
+mod rustfmt;
+
+#[cfg(feature = "process-backend")]
+pub use rustfmt::{Rustfmt, ToolInfo};
+
+/// A backend that formats Rust source code.
+///
+/// The functions of the crate perform input validation, verification and bookkeeping themselves
+/// and delegate the formatting to a backend. The default backend is `Rustfmt`, which requires the
+/// `process-backend` feature. Without that feature, a backend must be set explicitly through
+/// [`Options::backend`]. The trait is object safe.
+pub trait FormatBackend: Debug + Send + Sync {
+    /// Format source code and return the result.
+    ///
+    /// # Arguments
+    ///
+    /// * `source`: The source code.
+    /// * `options`: The options.
+    fn format_source(&self, source: &str, options: &Options) -> Result<String, Error>;
+
+    /// Format source code as if it were located in a directory.
+    ///
+    /// Backends that discover configuration files use the directory as the starting point. By
+    /// default, the directory is ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `source`: The source code.
+    /// * `dir`: The directory.
+    /// * `options`: The options.
+    fn format_source_in(
+        &self,
+        source: &str,
+        dir: &Path,
+        options: &Options,
+    ) -> Result<String, Error> {
+        let _ = dir;
+        self.format_source(source, options)
+    }
+
+    /// Format a file in place.
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: The path to the file.
+    /// * `options`: The options.
+    fn format_path(&self, path: &Path, options: &Options) -> Result<(), Error>;
+
+    /// Format a file in place, along with the files of its child modules unless
+    /// [`Options::skip_children`] is set.
+    ///
+    /// By default, [`FormatBackend::format_path`] is called and no child modules are reported.
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: The path to the file.
+    /// * `options`: The options.
+    ///
+    /// # Returns
+    ///
+    /// The paths of the files of child modules that were modified.
+    fn format_path_with_children(
+        &self,
+        path: &Path,
+        options: &Options,
+    ) -> Result<Vec<PathBuf>, Error> {
+        self.format_path(path, options)?;
+        Ok(Vec::new())
+    }
+
+    /// Check whether a file is formatted correctly, without modifying it.
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: The path to the file.
+    /// * `options`: The options.
+    fn check_path(&self, path: &Path, options: &Options) -> Result<bool, Error>;
+
+    /// Format multiple files in place with the same options.
+    ///
+    /// Backends that run an external tool can format all files with a single run. By default,
+    /// [`FormatBackend::format_path`] is called for every file.
+    ///
+    /// # Arguments
+    ///
+    /// * `paths`: The paths to the files.
+    /// * `options`: The options.
+    fn format_paths(&self, paths: &[PathBuf], options: &Options) -> Result<(), Error> {
+        paths
+            .iter()
+            .try_for_each(|path| self.format_path(path, options))
+    }
+
+    /// Check whether multiple files are formatted correctly with the same options, without
+    /// modifying them.
+    ///
+    /// By default, [`FormatBackend::check_path`] is called for every file.
+    ///
+    /// # Arguments
+    ///
+    /// * `paths`: The paths to the files.
+    /// * `options`: The options.
+    ///
+    /// # Returns
+    ///
+    /// Whether each file is formatted correctly, in the order of the paths.
+    fn check_paths(&self, paths: &[PathBuf], options: &Options) -> Result<Vec<bool>, Error> {
+        paths
+            .iter()
+            .map(|path| self.check_path(path, options))
+            .collect()
+    }
+
+    /// Retrieve the version of the backend.
+    fn version(&self) -> Result<String, Error>;
+
+    /// Retrieve the configuration settings that the backend supports.
+    ///
+    /// By default, no settings are supported.
+    fn config_schema(&self) -> Result<Vec<ConfigKey>, Error> {
+        Ok(Vec::new())
+    }
+
+    /// Retrieve the complete configuration that applies to a file, i.e. the configuration files
+    /// that apply to it combined with the settings of the options.
+    ///
+    /// By default, only the settings of the options are returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: The path to the file, which does not need to exist.
+    /// * `options`: The options.
+    ///
+    /// # Returns
+    ///
+    /// The value of every setting by its name. Strings are unquoted; other values are encoded as
+    /// in a `rustfmt.toml` file.
+    fn print_config(
+        &self,
+        path: &Path,
+        options: &Options,
+    ) -> Result<BTreeMap<String, String>, Error> {
+        let _ = path;
+        Ok(options
+            .tool_config()
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect())
+    }
+}
+
+/// A function that creates a backend from an optional argument.
+type Factory = Box<dyn Fn(Option<&str>) -> Result<Arc<dyn FormatBackend>, Error> + Send + Sync>;
+
+/// A registry of backends, [post-processors](PostProcessor) and [metrics sinks](MetricsSink) by
+/// name, so that they can be chosen at runtime, e.g. by a configuration file.
+///
+/// Backends are created from specifications of the form `name` or `name:argument`, where the
+/// meaning of the argument is up to the backend. The registry of [`Registry::with_defaults`]
+/// contains the `rustfmt` backend, whose argument is the toolchain (e.g. `rustfmt:nightly`).
+/// Post-processors and metrics sinks are registered as instances, which are shared by all options
+/// that use them.
+///
+/// # Examples
+///
+/// ```
+/// use rust_format::backend::{Mock, Registry};
+/// use rust_format::Options;
+///
+/// let registry = Registry::new().register_boxed_backend("mock", Box::new(Mock::new()));
+/// let options = Options::new().backend(registry.create("mock")?);
+/// assert_eq!("fn main(){}", rust_format::format_str_with("fn main(){}", &options)?);
+/// # Ok::<(), rust_format::Error>(())
+/// ```
+#[derive(Default)]
+pub struct Registry {
+    factories: BTreeMap<String, Factory>,
+    post_processors: BTreeMap<String, Arc<dyn PostProcessor>>,
+    metrics_sinks: BTreeMap<String, EventSink>,
+}
+
+impl Registry {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a registry with the backends of the crate.
+    pub fn with_defaults() -> Self {
+        let registry = Self::new();
+        #[cfg(feature = "process-backend")]
+        let registry = registry.register("rustfmt", |toolchain| {
+            Ok(match toolchain {
+                Some(toolchain) => Arc::new(Rustfmt::with_toolchain(toolchain)),
+                None => Arc::new(Rustfmt::new()),
+            })
+        });
+        registry
+    }
+
+    /// Register a function that creates a backend, replacing any backend of the same name.
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: The name of the backend.
+    /// * `factory`: The function, which receives the argument of the specification, if any.
+    pub fn register(
+        mut self,
+        name: impl Into<String>,
+        factory: impl Fn(Option<&str>) -> Result<Arc<dyn FormatBackend>, Error> + Send + Sync + 'static,
+    ) -> Self {
+        self.factories.insert(name.into(), Box::new(factory));
+        self
+    }
+
+    /// Register a backend instance, which is shared by all options that are created from the
+    /// registry. Arguments of specifications are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: The name of the backend.
+    /// * `backend`: The backend.
+    pub fn register_backend(
+        self,
+        name: impl Into<String>,
+        backend: Arc<dyn FormatBackend>,
+    ) -> Self {
+        self.register(name, move |_| Ok(backend.clone()))
+    }
+
+    /// Register a boxed backend instance. See [`Registry::register_backend`].
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: The name of the backend.
+    /// * `backend`: The backend.
+    pub fn register_boxed_backend(
+        self,
+        name: impl Into<String>,
+        backend: Box<dyn FormatBackend>,
+    ) -> Self {
+        self.register_backend(name, Arc::from(backend))
+    }
+
+    /// Register a post-processor, replacing any post-processor of the same name.
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: The name of the post-processor.
+    /// * `processor`: The post-processor.
+    pub fn register_post_processor(
+        mut self,
+        name: impl Into<String>,
+        processor: Box<dyn PostProcessor>,
+    ) -> Self {
+        self.post_processors
+            .insert(name.into(), Arc::from(processor));
+        self
+    }
+
+    /// Register a metrics sink, replacing any metrics sink of the same name.
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: The name of the metrics sink.
+    /// * `sink`: The metrics sink.
+    pub fn register_metrics_sink(
+        mut self,
+        name: impl Into<String>,
+        sink: Box<dyn MetricsSink>,
+    ) -> Self {
+        self.metrics_sinks
+            .insert(name.into(), EventSink::to_metrics_sink(sink));
+        self
+    }
+
+    /// Iterate over the names of the registered backends in alphabetical order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.factories.keys().map(String::as_str)
+    }
+
+    /// Iterate over the names of the registered post-processors in alphabetical order.
+    pub fn post_processor_names(&self) -> impl Iterator<Item = &str> {
+        self.post_processors.keys().map(String::as_str)
+    }
+
+    /// Iterate over the names of the registered metrics sinks in alphabetical order.
+    pub fn metrics_sink_names(&self) -> impl Iterator<Item = &str> {
+        self.metrics_sinks.keys().map(String::as_str)
+    }
+
+    /// Create a backend from a specification.
+    ///
+    /// # Arguments
+    ///
+    /// * `spec`: The specification, e.g. `rustfmt` or `rustfmt:nightly`.
+    ///
+    /// # Returns
+    ///
+    /// The backend, or an [`Error::UnknownBackend`] if no backend of the name is registered.
+    pub fn create(&self, spec: &str) -> Result<Arc<dyn FormatBackend>, Error> {
+        let (name, argument) = match spec.split_once(':') {
+            Some((name, argument)) => (name, Some(argument)),
+            None => (spec, None),
+        };
+        match self.factories.get(name) {
+            Some(factory) => factory(argument),
+            None => Err(Error::UnknownBackend {
+                name: name.to_string(),
+                available: self.names().map(str::to_string).collect(),
+            }),
+        }
+    }
+
+    /// Retrieve a registered post-processor, e.g. for [`Options::post_processor`].
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: The name of the post-processor.
+    ///
+    /// # Returns
+    ///
+    /// The post-processor, or an [`Error::UnknownPlugin`] if none of the name is registered.
+    pub fn create_post_processor(&self, name: &str) -> Result<Arc<dyn PostProcessor>, Error> {
+        lookup(&self.post_processors, "post-processor", name).cloned()
+    }
+
+    /// Retrieve the event sink of a registered metrics sink, e.g. for [`Options::events`].
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: The name of the metrics sink.
+    ///
+    /// # Returns
+    ///
+    /// The event sink, or an [`Error::UnknownPlugin`] if no metrics sink of the name is
+    /// registered.
+    pub fn create_metrics_sink(&self, name: &str) -> Result<EventSink, Error> {
+        lookup(&self.metrics_sinks, "metrics sink", name).cloned()
+    }
+}
+
+/// Look up a registered plugin by name.
+fn lookup<'a, T>(
+    plugins: &'a BTreeMap<String, T>,
+    kind: &'static str,
+    name: &str,
+) -> Result<&'a T, Error> {
+    plugins.get(name).ok_or_else(|| Error::UnknownPlugin {
+        kind,
+        name: name.to_string(),
+        available: plugins.keys().cloned().collect(),
+    })
+}
+
+impl Debug for Registry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Registry")
+            .field("names", &self.names().collect::<Vec<_>>())
+            .field(
+                "post_processors",
+                &self.post_processor_names().collect::<Vec<_>>(),
+            )
+            .field(
+                "metrics_sinks",
+                &self.metrics_sink_names().collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+/// The default backend if the `process-backend` feature is disabled, which always fails.
+#[cfg(not(feature = "process-backend"))]
+#[derive(Debug)]
+pub(crate) struct Unavailable;
+
+#[cfg(not(feature = "process-backend"))]
+impl FormatBackend for Unavailable {
+    fn format_source(&self, _source: &str, _options: &Options) -> Result<String, Error> {
+        Err(Error::ToolMissing { tool: "rustfmt" })
+    }
+
+    fn format_path(&self, _path: &Path, _options: &Options) -> Result<(), Error> {
+        Err(Error::ToolMissing { tool: "rustfmt" })
+    }
+
+    fn check_path(&self, _path: &Path, _options: &Options) -> Result<bool, Error> {
+        Err(Error::ToolMissing { tool: "rustfmt" })
+    }
+
+    fn version(&self) -> Result<String, Error> {
+        Err(Error::ToolMissing { tool: "rustfmt" })
+    }
+}
+
+/// A call that was made to a [`Mock`] backend.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Call {
+    /// A call to [`FormatBackend::format_source`].
+    FormatSource(String),
+    /// A call to [`FormatBackend::format_path`].
+    FormatPath(PathBuf),
+    /// A call to [`FormatBackend::check_path`].
+    CheckPath(PathBuf),
+    /// A call to [`FormatBackend::version`].
+    Version,
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    calls: Vec<Call>,
+    responses: VecDeque<Result<String, Error>>,
+}
+
+/// A backend for tests that records all calls and returns canned outputs.
+///
+/// Every formatting call consumes the next response that was queued with [`Mock::respond`]. If no
+/// response is queued, the input is returned unchanged. Files are read and written as usual, so
+/// [`FormatBackend::format_path`] writes the response to the file and
+/// [`FormatBackend::check_path`] compares the response with the file content. No processes are
+/// spawned.
+///
+/// # Examples
+///
+/// ```
+/// use rust_format::backend::{Call, Mock};
+/// use rust_format::Options;
+/// use std::sync::Arc;
+///
+/// let mock = Arc::new(Mock::new());
+/// mock.respond(Ok("fn main() {}\n".to_string()));
+///
+/// let options = Options::new().backend(mock.clone());
+/// assert_eq!("fn main() {}\n", rust_format::format_str_with("fn main(){}", &options).unwrap());
+/// assert_eq!(vec![Call::FormatSource("fn main(){}".to_string())], mock.calls());
+/// ```
+#[derive(Debug, Default)]
+pub struct Mock {
+    version: String,
+    state: Mutex<MockState>,
+}
+
+impl Mock {
+    /// Create a new mock backend.
+    pub fn new() -> Self {
+        Self {
+            version: "mock".to_string(),
+            state: Mutex::default(),
+        }
+    }
+
+    /// Queue a response for a future formatting call.
+    ///
+    /// # Arguments
+    ///
+    /// * `response`: The formatted source code or the error to return.
+    pub fn respond(&self, response: Result<String, Error>) {
+        self.lock().responses.push_back(response);
+    }
+
+    /// Retrieve all calls that were made so far.
+    pub fn calls(&self) -> Vec<Call> {
+        self.lock().calls.clone()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, MockState> {
+        self.state.lock().expect("mock lock poisoned")
+    }
+
+    fn call(&self, call: Call, input: &str) -> Result<String, Error> {
+        let mut state = self.lock();
+        state.calls.push(call);
+        state
+            .responses
+            .pop_front()
+            .unwrap_or_else(|| Ok(input.to_string()))
+    }
+}
+
+impl FormatBackend for Mock {
+    fn format_source(&self, source: &str, _options: &Options) -> Result<String, Error> {
+        self.call(Call::FormatSource(source.to_string()), source)
+    }
+
+    fn format_path(&self, path: &Path, _options: &Options) -> Result<(), Error> {
+        let content = std::fs::read_to_string(path).at(path)?;
+        let formatted = self.call(Call::FormatPath(path.to_path_buf()), &content)?;
+        if formatted != content {
+            std::fs::write(path, formatted).at(path)?;
+        }
+        Ok(())
+    }
+
+    fn check_path(&self, path: &Path, _options: &Options) -> Result<bool, Error> {
+        let content = std::fs::read_to_string(path).at(path)?;
+        let formatted = self.call(Call::CheckPath(path.to_path_buf()), &content)?;
+        Ok(formatted == content)
+    }
+
+    fn version(&self) -> Result<String, Error> {
+        self.lock().calls.push(Call::Version);
+        Ok(self.version.clone())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Call, Mock, Registry};
+    use crate::events::{Event, MetricsSink};
+    use crate::{Error, Options, PostProcessor};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_mock() {
+        let mock = Arc::new(Mock::new());
+        mock.respond(Err(Error::NoResultCode {
+            backend: "mock".into(),
+            path: None,
+        }));
+        let options = Options::new().backend(mock.clone());
+
+        assert!(matches!(
+            crate::format_str_with("a", &options),
+            Err(Error::NoResultCode { .. })
+        ));
+        assert_eq!("b", crate::format_str_with("b", &options).unwrap());
+
+        const TARGET_PATH: &str = "target/mock_sample_code.rs";
+        std::fs::copy("resources/test/sample_code.rs", TARGET_PATH).unwrap();
+        mock.respond(Ok("formatted".to_string()));
+        assert!(!crate::check_file_with(TARGET_PATH, &options).unwrap());
+
+        assert_eq!(
+            vec![
+                Call::FormatSource("a".to_string()),
+                Call::FormatSource("b".to_string()),
+                Call::CheckPath(TARGET_PATH.into()),
+            ],
+            mock.calls()
+        );
+
+        let missing = std::path::Path::new("target/mock_missing.rs");
+        assert!(matches!(
+            super::FormatBackend::check_path(mock.as_ref(), missing, &options),
+            Err(Error::FileAccess { path, .. }) if path == missing
+        ));
+    }
+
+    #[test]
+    fn test_registry() {
+        let registry = Registry::with_defaults().register_backend("mock", Arc::new(Mock::new()));
+        #[cfg(feature = "process-backend")]
+        {
+            assert_eq!(
+                vec!["mock", "rustfmt"],
+                registry.names().collect::<Vec<_>>()
+            );
+            let backend = registry.create("rustfmt:nightly").unwrap();
+            assert_eq!(
+                "Rustfmt { toolchain: Some(\"nightly\") }",
+                format!("{:?}", backend)
+            );
+        }
+        assert!(registry.create("mock:ignored").is_ok());
+        match registry.create("clang-format") {
+            Err(Error::UnknownBackend { name, available }) => {
+                assert_eq!("clang-format", name);
+                assert!(available.contains(&"mock".to_string()));
+            }
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_registry_plugins() {
+        #[derive(Debug)]
+        struct Uppercase;
+
+        impl PostProcessor for Uppercase {
+            fn id(&self) -> &str {
+                "uppercase"
+            }
+
+            fn process(&self, formatted: String, _options: &Options) -> Result<String, Error> {
+                Ok(formatted.to_uppercase())
+            }
+        }
+
+        struct Names(Arc<Mutex<Vec<&'static str>>>);
+
+        impl MetricsSink for Names {
+            fn record(&mut self, event: &Event) {
+                self.0.lock().unwrap().push(event.kind.name());
+            }
+        }
+
+        let names = Arc::new(Mutex::new(Vec::new()));
+        let registry = Registry::new()
+            .register_boxed_backend("mock", Box::new(Mock::new()))
+            .register_post_processor("uppercase", Box::new(Uppercase))
+            .register_metrics_sink("names", Box::new(Names(names.clone())));
+        let options = Options::new()
+            .backend(registry.create("mock").unwrap())
+            .post_processor(registry.create_post_processor("uppercase").unwrap())
+            .events(registry.create_metrics_sink("names").unwrap());
+        let cache = crate::CleanCache::new(options).cache_formatted(true);
+        assert_eq!("FN MAIN(){}", cache.format("fn main(){}").unwrap());
+        assert_eq!("FN MAIN(){}", cache.format("fn main(){}").unwrap());
+        assert_eq!(vec!["cache-hit"], *names.lock().unwrap());
+
+        match registry.create_metrics_sink("statsd") {
+            Err(Error::UnknownPlugin {
+                kind,
+                name,
+                available,
+            }) => {
+                assert_eq!(("metrics sink", "statsd"), (kind, name.as_str()));
+                assert_eq!(vec!["names".to_string()], available);
+            }
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,203 @@
+//! Supervision of the processes of the formatting tool, so that they do not outlive the operation
+//! that started them.
+
+use std::process::{Child, Command, ExitStatus};
+
+/// A running process of the formatting tool.
+///
+/// A process that was not waited for when the guard is dropped, e.g. because the thread panicked
+/// while the output was read, is killed and reaped.
+#[derive(Debug)]
+pub(crate) struct Supervised {
+    child: Child,
+    reaped: bool,
+}
+
+impl Supervised {
+    /// Start a process.
+    ///
+    /// # Arguments
+    ///
+    /// * `command`: The command to start.
+    /// * `forward_interrupts`: Whether interrupts of the host are forwarded to the process (see
+    ///   [`crate::Options::forward_interrupts`]).
+    #[cfg_attr(not(feature = "signals"), allow(unused_variables))]
+    pub(crate) fn spawn(command: &mut Command, forward_interrupts: bool) -> std::io::Result<Self> {
+        #[cfg(feature = "signals")]
+        if forward_interrupts {
+            signals::install()?;
+            #[cfg(unix)]
+            std::os::unix::process::CommandExt::process_group(command, 0);
+        }
+        let child = command.spawn()?;
+        #[cfg(feature = "signals")]
+        if forward_interrupts {
+            signals::register(child.id());
+        }
+        Ok(Self {
+            child,
+            reaped: false,
+        })
+    }
+
+    /// Retrieve the process, e.g. to take its streams.
+    pub(crate) fn child_mut(&mut self) -> &mut Child {
+        &mut self.child
+    }
+
+    /// Wait for the process to exit.
+    pub(crate) fn wait(&mut self) -> std::io::Result<ExitStatus> {
+        let status = self.child.wait()?;
+        self.reaped = true;
+        #[cfg(feature = "signals")]
+        signals::unregister(self.child.id());
+        Ok(status)
+    }
+}
+
+impl Drop for Supervised {
+    fn drop(&mut self) {
+        if self.reaped {
+            return;
+        }
+        #[cfg(feature = "signals")]
+        if signals::unregister(self.child.id()) {
+            // The process leads its own process group; kill anything that it started as well.
+            signals::kill_group(self.child.id(), signals::Signal::Kill);
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Forwarding of interrupts to the processes of the formatting tool.
+#[cfg(feature = "signals")]
+mod signals {
+    use std::collections::BTreeSet;
+    use std::sync::{Mutex, OnceLock};
+    use std::time::{Duration, Instant};
+
+    /// How long the processes get to exit after an interrupt was forwarded to them.
+    const GRACE_PERIOD: Duration = Duration::from_secs(2);
+
+    /// The exit code of the host after an interrupt, as for a shell (`128 + SIGINT`).
+    const INTERRUPTED: i32 = 130;
+
+    /// The processes that interrupts are forwarded to, which lead their own process groups.
+    static CHILDREN: Mutex<BTreeSet<u32>> = Mutex::new(BTreeSet::new());
+
+    /// A signal that is sent to a process group.
+    #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+    pub(super) enum Signal {
+        /// Ask the processes to exit (`SIGINT`).
+        Interrupt,
+        /// Terminate the processes immediately (`SIGKILL`).
+        Kill,
+    }
+
+    /// Lock the registered processes.
+    fn children() -> std::sync::MutexGuard<'static, BTreeSet<u32>> {
+        CHILDREN.lock().unwrap_or_else(|error| error.into_inner())
+    }
+
+    /// Install the handler for interrupts of the host, unless it is installed already.
+    ///
+    /// The handler forwards the interrupt to the processes, kills the processes that do not exit
+    /// within the grace period and then exits the host, as the default handler would.
+    pub(super) fn install() -> std::io::Result<()> {
+        static INSTALLED: OnceLock<Result<(), String>> = OnceLock::new();
+
+        INSTALLED
+            .get_or_init(|| ctrlc::set_handler(interrupted).map_err(|error| error.to_string()))
+            .clone()
+            .map_err(|error| {
+                std::io::Error::other(format!("cannot install the interrupt handler: {}", error))
+            })
+    }
+
+    /// Handle an interrupt of the host.
+    fn interrupted() {
+        let pids: Vec<u32> = children().iter().copied().collect();
+        for &pid in &pids {
+            kill_group(pid, Signal::Interrupt);
+        }
+        let deadline = Instant::now() + GRACE_PERIOD;
+        while !children().is_empty() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        for &pid in children().iter() {
+            kill_group(pid, Signal::Kill);
+        }
+        std::process::exit(INTERRUPTED);
+    }
+
+    /// Forward interrupts to a process.
+    pub(super) fn register(pid: u32) {
+        children().insert(pid);
+    }
+
+    /// Stop forwarding interrupts to a process.
+    ///
+    /// # Returns
+    ///
+    /// Whether the process was registered.
+    pub(super) fn unregister(pid: u32) -> bool {
+        children().remove(&pid)
+    }
+
+    /// Send a signal to the process group of a process.
+    #[cfg(unix)]
+    pub(super) fn kill_group(pid: u32, signal: Signal) {
+        let signal = match signal {
+            Signal::Interrupt => libc::SIGINT,
+            Signal::Kill => libc::SIGKILL,
+        };
+        // SAFETY: `kill` has no memory safety requirements. The process is not reaped yet, so its
+        // ID still identifies its process group.
+        unsafe {
+            libc::kill(-(pid as libc::pid_t), signal);
+        }
+    }
+
+    /// Send a signal to the process group of a process. Processes that share the console of the
+    /// host receive its interrupts anyway, and are killed through their handle.
+    #[cfg(not(unix))]
+    pub(super) fn kill_group(_pid: u32, _signal: Signal) {}
+}
+
+#[cfg(test)]
+mod test {
+    use super::Supervised;
+    use std::process::Command;
+
+    #[test]
+    #[cfg(unix)]
+    fn test_drop_kills_process() {
+        let is_running = |pid: u32| {
+            Command::new("kill")
+                .args(["-0", &pid.to_string()])
+                .status()
+                .unwrap()
+                .success()
+        };
+        let mut command = Command::new("sleep");
+        command.arg("30");
+
+        let process = Supervised::spawn(&mut command, false).unwrap();
+        let pid = process.child.id();
+        assert!(is_running(pid));
+        drop(process);
+        assert!(!is_running(pid));
+
+        #[cfg(feature = "signals")]
+        {
+            let process = Supervised::spawn(&mut command, true).unwrap();
+            let pid = process.child.id();
+            assert!(super::signals::unregister(pid));
+            super::signals::register(pid);
+            drop(process);
+            assert!(!is_running(pid));
+            assert!(!super::signals::unregister(pid));
+        }
+    }
+}
@@ -0,0 +1,786 @@
+//! The backend that runs the 'rustfmt' tool in a subprocess.
+
+use super::child::Supervised;
+use super::FormatBackend;
+use crate::error::IoContext;
+use crate::{ConfigKey, Error, IoStream, Options};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// The backend that runs the 'rustfmt' tool in a subprocess.
+///
+/// By default, the tool of the current toolchain is used. A specific toolchain can be selected
+/// with [`Rustfmt::with_toolchain`], in which case the tool is started through `rustup`.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub struct Rustfmt {
+    toolchain: Option<String>,
+}
+
+impl Rustfmt {
+    /// Create a backend that uses the tool of the current toolchain.
+    pub const fn new() -> Self {
+        Self { toolchain: None }
+    }
+
+    /// Create a backend that uses the tool of a specific toolchain.
+    ///
+    /// # Arguments
+    ///
+    /// * `toolchain`: The name of the toolchain, e.g. `nightly` or `1.70.0`.
+    pub fn with_toolchain(toolchain: impl Into<String>) -> Self {
+        Self {
+            toolchain: Some(toolchain.into()),
+        }
+    }
+
+    /// Retrieve the name of the selected toolchain, if any.
+    pub fn toolchain(&self) -> Option<&str> {
+        self.toolchain.as_deref()
+    }
+
+    /// Determine whether the tool can be run.
+    pub fn is_available(&self) -> bool {
+        self.ensure_available().is_ok()
+    }
+
+    /// Verify that the tool can be run and describe it.
+    ///
+    /// This is meant for the startup of applications, which can report a missing tool right away
+    /// instead of failing on the first file.
+    ///
+    /// # Returns
+    ///
+    /// The description of the tool, or [`Error::ToolUnavailable`] with instructions for installing
+    /// the tool on the current platform.
+    pub fn ensure_available(&self) -> Result<ToolInfo, Error> {
+        let unavailable = || Error::ToolUnavailable {
+            tool: TOOL_NAME,
+            hint: install_hint(self.toolchain.as_deref()),
+        };
+        let path = match &self.toolchain {
+            Some(toolchain) => {
+                let output = std::process::Command::new("rustup")
+                    .args(["which", "--toolchain", toolchain, TOOL_NAME])
+                    .output()
+                    .map_err(|_| unavailable())?;
+                if !output.status.success() {
+                    return Err(unavailable());
+                }
+                PathBuf::from(String::from_utf8_lossy(&output.stdout).trim())
+            }
+            None => toolchain_find::find_installed_component(TOOL_NAME).ok_or_else(unavailable)?,
+        };
+        // A `rustup` proxy exists even if the component is not installed; running it tells.
+        let version = self.version().map_err(|_| unavailable())?;
+        Ok(ToolInfo {
+            path,
+            version,
+            toolchain: self.toolchain.clone(),
+        })
+    }
+
+    /// Install the tool with `rustup` if it is not available.
+    ///
+    /// # Returns
+    ///
+    /// An error if the tool is missing and cannot be installed, e.g. because `rustup` is not
+    /// installed.
+    #[cfg(feature = "install")]
+    pub fn install(&self) -> Result<(), Error> {
+        if self.is_available() {
+            return Ok(());
+        }
+        let mut command = std::process::Command::new("rustup");
+        command.args(["component", "add", TOOL_NAME]);
+        if let Some(toolchain) = &self.toolchain {
+            command.args(["--toolchain", toolchain]);
+        }
+        run(command, None, Capture::default())
+            .map_err(|error| error.with_backend(&self.spec(), None))?;
+        Ok(())
+    }
+
+    /// Create a [`std::process::Command`] for the tool, installing the tool first if the options
+    /// ask for it.
+    fn command_for(&self, options: &Options) -> Result<std::process::Command, Error> {
+        #[cfg(feature = "install")]
+        if options.get_auto_install() {
+            // Every toolchain is only checked once per process.
+            static CHECKED: Mutex<Vec<Option<String>>> = Mutex::new(Vec::new());
+            let mut checked = CHECKED.lock().expect("install lock poisoned");
+            if !checked.contains(&self.toolchain) {
+                self.install()?;
+                checked.push(self.toolchain.clone());
+            }
+        }
+        #[cfg(not(feature = "install"))]
+        let _ = options;
+        self.command()
+    }
+
+    /// Retrieve the specification of the backend for errors, e.g. `rustfmt:nightly` (see
+    /// [`crate::backend::Registry::create`]).
+    fn spec(&self) -> String {
+        match &self.toolchain {
+            Some(toolchain) => format!("{}:{}", TOOL_NAME, toolchain),
+            None => TOOL_NAME.to_string(),
+        }
+    }
+
+    /// Create a [`std::process::Command`] for the tool.
+    fn command(&self) -> Result<std::process::Command, Error> {
+        match &self.toolchain {
+            Some(toolchain) => {
+                let mut command = std::process::Command::new("rustup");
+                command.args(["run", toolchain, "rustfmt"]);
+                Ok(command)
+            }
+            None => rustfmt_command(),
+        }
+    }
+}
+
+impl FormatBackend for Rustfmt {
+    fn format_source(&self, source: &str, options: &Options) -> Result<String, Error> {
+        let mut command = self.command_for(options)?;
+        options.apply(&mut command);
+        command.args(["--emit", "stdout"]);
+        run(command, Some(source.as_bytes()), Capture::of(options))
+            .and_then(|stdout| emitted_stdout(source, stdout))
+            .map_err(|error| error.with_backend(&self.spec(), None))
+    }
+
+    fn format_source_in(
+        &self,
+        source: &str,
+        dir: &Path,
+        options: &Options,
+    ) -> Result<String, Error> {
+        let mut command = self.command_for(options)?;
+        // The tool searches for its configuration starting at the working directory when reading
+        // from its standard input.
+        command.current_dir(dir);
+        options.apply(&mut command);
+        command.args(["--emit", "stdout"]);
+        run(command, Some(source.as_bytes()), Capture::of(options))
+            .and_then(|stdout| emitted_stdout(source, stdout))
+            .map_err(|error| error.with_backend(&self.spec(), None))
+    }
+
+    fn format_path(&self, path: &Path, options: &Options) -> Result<(), Error> {
+        self.format_path_with_children(path, options).map(|_| ())
+    }
+
+    fn format_path_with_children(
+        &self,
+        path: &Path,
+        options: &Options,
+    ) -> Result<Vec<PathBuf>, Error> {
+        let mut command = self.command_for(options)?;
+        options.apply(&mut command);
+        if options.get_backup() == &crate::backup::Backup::Adjacent {
+            command.arg("--backup");
+        }
+        // The tool lists the paths of all files that it modified as they are derived from the given
+        // path, so they are not necessarily absolute. Both are canonicalized for comparing them.
+        command
+            .args(["--emit", "files", "--files-with-diff"])
+            .arg(path.as_os_str());
+        let attribute = |error: Error| error.with_backend(&self.spec(), Some(path));
+        let stdout = run(command, None, Capture::of(options)).map_err(attribute)?;
+        let root = std::fs::canonicalize(path).ok();
+        Ok(emitted_files(&stdout)
+            .map_err(attribute)?
+            .into_iter()
+            .filter(|modified| std::fs::canonicalize(modified).ok() != root)
+            .collect())
+    }
+
+    fn check_path(&self, path: &Path, options: &Options) -> Result<bool, Error> {
+        let mut command = self.command_for(options)?;
+        options.apply(&mut command);
+        command.arg("--check").arg(path.as_os_str());
+        match run(command, None, Capture::of(options)) {
+            Ok(_) => Ok(true),
+            // The tool reports a formatting difference with exit code 1 and a diff on stdout.
+            Err(Error::ToolExecutionError {
+                code: 1,
+                stdout: IoStream::Text(stdout),
+                ..
+            }) if stdout.starts_with("Diff in") => Ok(false),
+            Err(error) => Err(error.with_backend(&self.spec(), Some(path))),
+        }
+    }
+
+    fn format_paths(&self, paths: &[PathBuf], options: &Options) -> Result<(), Error> {
+        let mut command = self.command_for(options)?;
+        options.apply(&mut command);
+        if options.get_backup() == &crate::backup::Backup::Adjacent {
+            command.arg("--backup");
+        }
+        command
+            .args(["--emit", "files", "--files-with-diff"])
+            .args(paths);
+        let attribute = |error: Error| error.with_backend(&self.spec(), single(paths));
+        let stdout = run(command, None, Capture::of(options)).map_err(attribute)?;
+        let emitted = emitted_files(&stdout).map(|_| ()).map_err(attribute);
+        crate::pool::give(stdout);
+        emitted
+    }
+
+    fn check_paths(&self, paths: &[PathBuf], options: &Options) -> Result<Vec<bool>, Error> {
+        let mut command = self.command_for(options)?;
+        options.apply(&mut command);
+        // The tool lists the absolute paths of all files that are not formatted correctly.
+        command.args(["--check", "--files-with-diff"]).args(paths);
+        let stdout = match run(command, None, Capture::of(options)) {
+            Ok(stdout) => String::from_utf8_lossy(&stdout).into_owned(),
+            // Errors, e.g. of files that cannot be parsed, are reported on stderr. The other files
+            // are still checked, so the list on stdout cannot be trusted in that case.
+            Err(Error::ToolExecutionError {
+                code: 1,
+                stdout: IoStream::Text(stdout),
+                stderr: IoStream::Text(stderr),
+                ..
+            }) if !stdout.is_empty() && stderr.trim().is_empty() => stdout,
+            Err(error) => return Err(error.with_backend(&self.spec(), single(paths))),
+        };
+        let unformatted: Vec<PathBuf> = stdout
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(|line| std::fs::canonicalize(line).unwrap_or_else(|_| PathBuf::from(line)))
+            .collect();
+        Ok(paths
+            .iter()
+            .map(|path| {
+                let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.clone());
+                !unformatted.contains(&path)
+            })
+            .collect())
+    }
+
+    fn version(&self) -> Result<String, Error> {
+        // The versions are cached per toolchain for the lifetime of the process.
+        static VERSIONS: Mutex<Option<HashMap<Option<String>, String>>> = Mutex::new(None);
+        let cached = |versions: &Option<HashMap<_, String>>| {
+            versions
+                .as_ref()
+                .and_then(|versions| versions.get(&self.toolchain).cloned())
+        };
+        if let Some(version) = cached(&VERSIONS.lock().expect("version lock poisoned")) {
+            return Ok(version);
+        }
+
+        let attribute = |error: Error| error.with_backend(&self.spec(), None);
+        let mut command = self.command()?;
+        command.arg("--version");
+        let stdout = run(command, None, Capture::default()).map_err(attribute)?;
+        let version = String::from_utf8(stdout)
+            .map_err(|_| attribute(invalid_utf8_output()))?
+            .trim()
+            .to_string();
+        VERSIONS
+            .lock()
+            .expect("version lock poisoned")
+            .get_or_insert_with(HashMap::new)
+            .insert(self.toolchain.clone(), version.clone());
+        Ok(version)
+    }
+
+    fn config_schema(&self) -> Result<Vec<ConfigKey>, Error> {
+        // The settings are cached per toolchain like the versions, because configurations are
+        // validated before every run of the tool.
+        static SCHEMAS: Mutex<Option<HashMap<Option<String>, Vec<ConfigKey>>>> = Mutex::new(None);
+        if let Some(schema) = SCHEMAS
+            .lock()
+            .expect("schema lock poisoned")
+            .as_ref()
+            .and_then(|schemas| schemas.get(&self.toolchain))
+        {
+            return Ok(schema.clone());
+        }
+
+        // Nightly versions only describe their unstable settings with `--unstable-features`, which
+        // stable versions reject.
+        let attribute = |error: Error| error.with_backend(&self.spec(), None);
+        let mut command = self.command()?;
+        command.args(["--unstable-features", "--help=config"]);
+        let stdout = match run(command, None, Capture::default()) {
+            Ok(stdout) => stdout,
+            Err(Error::ToolExecutionError { .. }) => {
+                let mut command = self.command()?;
+                command.arg("--help=config");
+                run(command, None, Capture::default()).map_err(attribute)?
+            }
+            Err(error) => return Err(attribute(error)),
+        };
+        let help = String::from_utf8(stdout).map_err(|_| attribute(invalid_utf8_output()))?;
+        let schema = crate::schema::parse(&help);
+        SCHEMAS
+            .lock()
+            .expect("schema lock poisoned")
+            .get_or_insert_with(HashMap::new)
+            .insert(self.toolchain.clone(), schema.clone());
+        Ok(schema)
+    }
+
+    fn print_config(
+        &self,
+        path: &Path,
+        options: &Options,
+    ) -> Result<BTreeMap<String, String>, Error> {
+        // The tool reports a missing directory on stderr, but succeeds without any output.
+        let dir = crate::format::parent_dir(path);
+        std::fs::metadata(dir).at(dir)?;
+        let mut command = self.command_for(options)?;
+        options.apply(&mut command);
+        command
+            .args(["--print-config", "current"])
+            .arg(path.as_os_str());
+        let attribute = |error: Error| error.with_backend(&self.spec(), Some(path));
+        let stdout = run(command, None, Capture::of(options)).map_err(attribute)?;
+        let text = String::from_utf8(stdout).map_err(|_| attribute(invalid_utf8_output()))?;
+        Ok(text
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .map(|(key, value)| {
+                let value = value.trim();
+                let value = match value
+                    .strip_prefix('"')
+                    .and_then(|value| value.strip_suffix('"'))
+                {
+                    Some(unquoted) => unquoted,
+                    None => value,
+                };
+                (key.trim().to_string(), value.to_string())
+            })
+            .collect())
+    }
+}
+
+/// A description of an available formatting tool.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct ToolInfo {
+    /// The path of the executable.
+    pub path: PathBuf,
+    /// The version, as reported by `rustfmt --version`.
+    pub version: String,
+    /// The selected toolchain, if any.
+    pub toolchain: Option<String>,
+}
+
+/// The name of the tool.
+const TOOL_NAME: &str = "rustfmt";
+
+/// Describe how to install the tool on the current platform.
+fn install_hint(toolchain: Option<&str>) -> String {
+    let rustup = match toolchain {
+        Some(toolchain) => format!(
+            "rustup toolchain install {} --component {}",
+            toolchain, TOOL_NAME
+        ),
+        None => format!("rustup component add {}", TOOL_NAME),
+    };
+    let alternative = if cfg!(target_os = "macos") {
+        " Without rustup, it can be installed with 'brew install rustfmt'."
+    } else if cfg!(target_os = "linux") {
+        " Without rustup, it is typically provided by the 'rustfmt' package of the distribution."
+    } else if cfg!(windows) {
+        " rustup can be installed from https://rustup.rs."
+    } else {
+        ""
+    };
+    format!("Install it with '{}'.{}", rustup, alternative)
+}
+
+/// Verify that the tool emitted the formatted code on stdout, as requested with `--emit stdout`.
+///
+/// Versions of the tool that ignore the argument emit nothing for code that is not empty.
+fn emitted_stdout(source: &str, stdout: Vec<u8>) -> Result<String, Error> {
+    if stdout.is_empty() && !source.trim().is_empty() {
+        return Err(Error::UnexpectedEmitBehavior {
+            backend: TOOL_NAME.to_string(),
+            path: None,
+            emit: "stdout",
+            stdout: IoStream::Text(String::new()),
+        });
+    }
+    String::from_utf8(stdout).map_err(|_| invalid_utf8_output())
+}
+
+/// Verify that the tool modified the files in place, as requested with `--emit files`, and
+/// retrieve the paths of the modified files, which it lists with `--files-with-diff`.
+///
+/// Versions of the tool that ignore the argument emit other output instead, e.g. formatted code.
+fn emitted_files(stdout: &[u8]) -> Result<Vec<PathBuf>, Error> {
+    let text = String::from_utf8_lossy(stdout);
+    let paths: Vec<PathBuf> = text
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(PathBuf::from)
+        .collect();
+    match paths.iter().all(|path| path.is_file()) {
+        true => Ok(paths),
+        false => Err(Error::UnexpectedEmitBehavior {
+            backend: TOOL_NAME.to_string(),
+            path: None,
+            emit: "files",
+            stdout: IoStream::Text(text.into_owned()),
+        }),
+    }
+}
+
+/// Create a [`std::process::Command`] for the 'rustfmt' tool of the current toolchain.
+fn rustfmt_command() -> Result<std::process::Command, Error> {
+    let rustfmt = toolchain_find::find_installed_component(TOOL_NAME)
+        .ok_or(Error::ToolMissing { tool: TOOL_NAME })?;
+    Ok(std::process::Command::new(rustfmt))
+}
+
+/// The maximum number of bytes that are captured from the output streams of the formatting tool,
+/// and whether interrupts are forwarded to it.
+#[derive(Debug, Copy, Clone, Default)]
+struct Capture {
+    stdout: Option<u64>,
+    stderr: Option<u64>,
+    forward_interrupts: bool,
+}
+
+impl Capture {
+    /// Retrieve the settings of the options.
+    fn of(options: &Options) -> Self {
+        Self {
+            stdout: options.get_max_stdout_bytes(),
+            stderr: options.get_max_stderr_bytes(),
+            #[cfg(feature = "signals")]
+            forward_interrupts: options.get_forward_interrupts(),
+            #[cfg(not(feature = "signals"))]
+            forward_interrupts: false,
+        }
+    }
+}
+
+/// Run the formatting tool and return its stdout.
+///
+/// The input is written on a separate thread while stdout and stderr are read, so that the process
+/// cannot block on a full pipe buffer, regardless of the size of the input and output. The process
+/// is killed if the run is aborted, e.g. by a panic.
+///
+/// # Arguments
+///
+/// * `command`: The command to run.
+/// * `input`: Bytes to write to the stdin of the process, if any.
+/// * `capture`: The limits of the captured output and the forwarding of interrupts.
+fn run(
+    command: std::process::Command,
+    input: Option<&[u8]>,
+    capture: Capture,
+) -> Result<Vec<u8>, Error> {
+    crate::request::scope(|request| {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!(
+            "rustfmt",
+            request = %request,
+            program = ?command.get_program(),
+            args = ?command.get_args().collect::<Vec<_>>(),
+        )
+        .entered();
+        let result = run_in_scope(command, input, capture);
+        #[cfg(feature = "tracing")]
+        if let Err(error) = &result {
+            tracing::debug!(%error, "run failed");
+        }
+        result.map_err(|error| identify(error, request))
+    })
+}
+
+/// Attach the identifier of the operation to the errors of the tool.
+fn identify(mut error: Error, id: crate::RequestId) -> Error {
+    if let Error::ToolExecutionError { request, .. } | Error::ToolCrashed { request, .. } =
+        &mut error
+    {
+        request.get_or_insert(id);
+    }
+    error
+}
+
+/// Retrieve the path of a run on a single file.
+fn single(paths: &[PathBuf]) -> Option<&Path> {
+    match paths {
+        [path] => Some(path),
+        _ => None,
+    }
+}
+
+/// Create the error for output of the tool that is not valid UTF-8.
+fn invalid_utf8_output() -> Error {
+    Error::InvalidUtf8Output {
+        backend: TOOL_NAME.to_string(),
+        path: None,
+    }
+}
+
+fn run_in_scope(
+    mut command: std::process::Command,
+    input: Option<&[u8]>,
+    capture: Capture,
+) -> Result<Vec<u8>, Error> {
+    command
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped());
+    let mut process = Supervised::spawn(&mut command, capture.forward_interrupts)?;
+    #[cfg(feature = "tracing")]
+    tracing::debug!(pid = process.child_mut().id(), "spawned");
+
+    // Taking stdin out of the child closes it once written, signalling EOF to the tool.
+    let child = process.child_mut();
+    let stdin = child.stdin.take().expect("stdin is piped");
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let stderr = child.stderr.take().expect("stderr is piped");
+    let (status, (stdout, stdout_size), (stderr, stderr_size)) = std::thread::scope(|scope| {
+        let writer = scope.spawn(move || write_input(stdin, input.unwrap_or_default()));
+        let stderr = scope.spawn(move || read_capped(stderr, capture.stderr));
+        let stdout = read_capped(stdout, capture.stdout);
+        let stderr = stderr.join().expect("stderr reader panicked");
+        let status = process.wait();
+        writer.join().expect("stdin writer panicked")?;
+        Ok::<_, std::io::Error>((status?, stdout?, stderr?))
+    })?;
+    let stdout_truncated = stdout_size > stdout.len() as u64;
+    let out = std::process::Output {
+        status,
+        stdout: mark_truncated(stdout, stdout_size),
+        stderr: mark_truncated(stderr, stderr_size),
+    };
+    if let Some(error) = crash(&out) {
+        return Err(error);
+    }
+    let code = out.status.code().ok_or_else(|| Error::NoResultCode {
+        backend: TOOL_NAME.to_string(),
+        path: None,
+    })?;
+    #[cfg(feature = "tracing")]
+    tracing::debug!(code, "exited");
+    if code != 0 {
+        Err(Error::ToolExecutionError {
+            backend: TOOL_NAME.to_string(),
+            path: None,
+            code,
+            stdout: String::from_utf8(out.stdout).into(),
+            stderr: String::from_utf8(out.stderr).into(),
+            context: None,
+            request: None,
+        })
+    } else if let (true, Some(limit)) = (stdout_truncated, capture.stdout) {
+        Err(Error::OutputTooLarge {
+            size: stdout_size,
+            limit,
+        })
+    } else {
+        crate::pool::give(out.stderr);
+        Ok(out.stdout)
+    }
+}
+
+/// Read a stream to its end, keeping at most a number of bytes and discarding the rest.
+///
+/// # Returns
+///
+/// The kept bytes and the total number of bytes of the stream.
+fn read_capped(mut reader: impl Read, limit: Option<u64>) -> std::io::Result<(Vec<u8>, u64)> {
+    let mut kept = crate::pool::take(0);
+    (&mut reader)
+        .take(limit.unwrap_or(u64::MAX))
+        .read_to_end(&mut kept)?;
+    let discarded = std::io::copy(&mut reader, &mut std::io::sink())?;
+    let size = kept.len() as u64 + discarded;
+    Ok((kept, size))
+}
+
+/// Append a marker to captured output that was truncated.
+fn mark_truncated(mut bytes: Vec<u8>, size: u64) -> Vec<u8> {
+    let discarded = size - bytes.len() as u64;
+    if discarded > 0 {
+        // Do not leave a partial character behind, so that text remains text.
+        if let Err(error) = std::str::from_utf8(&bytes) {
+            if error.error_len().is_none() {
+                bytes.truncate(error.valid_up_to());
+            }
+        }
+        let discarded = size - bytes.len() as u64;
+        bytes.extend_from_slice(format!("\n[{} bytes truncated]\n", discarded).as_bytes());
+    }
+    bytes
+}
+
+/// Write the input to the stdin of the formatting tool.
+fn write_input(mut stdin: std::process::ChildStdin, input: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+    match stdin.write_all(input) {
+        // The tool exited without reading all of its input. Its exit status tells why.
+        Err(error) if error.kind() == std::io::ErrorKind::BrokenPipe => Ok(()),
+        result => result,
+    }
+}
+
+/// Classify a process termination as a crash, if it is one.
+fn crash(out: &std::process::Output) -> Option<Error> {
+    /// The exit code of a Windows process that overflowed its stack (`STATUS_STACK_OVERFLOW`).
+    const STATUS_STACK_OVERFLOW: i32 = 0xC00000FDu32 as i32;
+
+    #[cfg(unix)]
+    let signal = std::os::unix::process::ExitStatusExt::signal(&out.status);
+    #[cfg(not(unix))]
+    let signal: Option<i32> = None;
+
+    let stack_overflow = out.status.code() == Some(STATUS_STACK_OVERFLOW)
+        || String::from_utf8_lossy(&out.stderr).contains("has overflowed its stack");
+    if signal.is_none() && !stack_overflow {
+        return None;
+    }
+    Some(Error::ToolCrashed {
+        backend: TOOL_NAME.to_string(),
+        path: None,
+        signal,
+        stack_overflow,
+        stderr: String::from_utf8(out.stderr.clone()).into(),
+        request: None,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    #[cfg(unix)]
+    fn test_run_large_input() {
+        // `cat` writes its output while it is still reading, which fills the pipe buffers of both
+        // streams unless they are served concurrently.
+        let input = "fn main() {}\n".repeat(100_000);
+        let output = super::run(
+            std::process::Command::new("cat"),
+            Some(input.as_bytes()),
+            Default::default(),
+        )
+        .unwrap();
+        assert_eq!(input.as_bytes(), output);
+    }
+
+    #[test]
+    fn test_ensure_available() {
+        use super::Rustfmt;
+        use crate::Error;
+
+        let tool = Rustfmt::new().ensure_available().unwrap();
+        assert!(tool.path.is_file());
+        assert!(tool.version.starts_with("rustfmt "));
+
+        match Rustfmt::with_toolchain("0.0.0-missing").ensure_available() {
+            Err(Error::ToolUnavailable { hint, .. }) => {
+                assert!(hint.contains("rustup toolchain install 0.0.0-missing"))
+            }
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_print_config() {
+        use crate::{Config, Error, Options};
+        use std::path::PathBuf;
+
+        let dir = PathBuf::from("target/print_config");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("rustfmt.toml"), "tab_spaces = 2\n").unwrap();
+        let options = Options::new().config(Config::new().max_width(80));
+
+        let config = crate::effective_rustfmt_config_with(dir.join("lib.rs"), &options).unwrap();
+        assert_eq!("2", config["tab_spaces"]);
+        assert_eq!("80", config["max_width"]);
+        assert_eq!("Auto", config["newline_style"]);
+        assert!(matches!(
+            crate::effective_rustfmt_config_with(dir.join("missing/lib.rs"), &options),
+            Err(Error::FileAccess { .. })
+        ));
+    }
+
+    #[test]
+    fn test_emitted() {
+        use crate::Error;
+
+        assert_eq!(
+            "fn main() {}\n",
+            super::emitted_stdout("fn main(){}", b"fn main() {}\n".to_vec()).unwrap()
+        );
+        assert_eq!("", super::emitted_stdout("\n", Vec::new()).unwrap());
+        assert!(matches!(
+            super::emitted_stdout("fn main(){}", Vec::new()),
+            Err(Error::UnexpectedEmitBehavior { emit: "stdout", .. })
+        ));
+
+        assert_eq!(
+            vec![std::path::PathBuf::from("Cargo.toml")],
+            super::emitted_files(b"Cargo.toml\n").unwrap()
+        );
+        let error = super::emitted_files(b"fn main() {}\n").unwrap_err();
+        assert!(matches!(
+            error,
+            Error::UnexpectedEmitBehavior { emit: "files", .. }
+        ));
+        assert!(error.to_string().contains("fn main() {}"));
+    }
+
+    #[cfg(feature = "install")]
+    #[test]
+    fn test_install() {
+        use crate::Options;
+
+        // The tool is available, so nothing is installed.
+        crate::install_rustfmt(None).unwrap();
+        let options = Options::new().auto_install(true);
+        assert_eq!(
+            "fn main() {}\n",
+            crate::format_str_with("fn main(){}", &options).unwrap()
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_run_capped_output() {
+        use super::Capture;
+        use crate::{Error, IoStream};
+
+        let input = "ä".repeat(100_000);
+        let capture = Capture {
+            stdout: Some(1001),
+            stderr: Some(7),
+            ..Capture::default()
+        };
+        match super::run(
+            std::process::Command::new("cat"),
+            Some(input.as_bytes()),
+            capture,
+        ) {
+            Err(Error::OutputTooLarge { size, limit }) => {
+                assert_eq!((200_000, 1001), (size, limit))
+            }
+            other => panic!("Unexpected result: {:?}", other),
+        }
+
+        let mut command = std::process::Command::new("sh");
+        command.args(["-c", "cat; echo 0123456789 >&2; exit 3"]);
+        match super::run(command, Some(b"0123456789"), capture) {
+            Err(Error::ToolExecutionError {
+                stdout: IoStream::Text(stdout),
+                stderr: IoStream::Text(stderr),
+                ..
+            }) => {
+                assert_eq!("0123456789", stdout);
+                assert_eq!("0123456\n[4 bytes truncated]\n", stderr);
+            }
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+}
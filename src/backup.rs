@@ -0,0 +1,169 @@
+//! Backups of files before they are formatted in place.
+
+use crate::error::IoContext;
+use crate::Error;
+use std::path::{Path, PathBuf};
+
+/// The extension of backup files created in a backup directory.
+const EXTENSION: &str = "bak";
+
+/// The backup policy for files that are formatted in place.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub enum Backup {
+    /// No backups are created.
+    #[default]
+    None,
+    /// The formatting tool creates a backup with the `.bk` extension next to every modified file.
+    Adjacent,
+    /// Backups are created in a directory, with timestamped names.
+    ///
+    /// Only backups of files that were modified by the formatting tool are retained.
+    Directory(PathBuf),
+}
+
+impl Backup {
+    /// Create a backup of a file before it is formatted, if the policy requires one.
+    ///
+    /// # Returns
+    ///
+    /// The path of the backup, if one was created.
+    pub(crate) fn create(&self, path: &Path) -> Result<Option<PathBuf>, Error> {
+        let dir = match self {
+            Backup::Directory(dir) => dir,
+            _ => return Ok(None),
+        };
+        std::fs::create_dir_all(dir).at(dir)?;
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        let backup = dir.join(format!(
+            "{}.{:020}.{}",
+            escape(&std::fs::canonicalize(path).at(path)?),
+            timestamp,
+            EXTENSION
+        ));
+        std::fs::copy(path, &backup).at(&backup)?;
+        Ok(Some(backup))
+    }
+
+    /// Discard a backup that was created by [`Backup::create`] if the file was not modified.
+    pub(crate) fn discard_if_unchanged(backup: &Path, path: &Path) -> Result<(), Error> {
+        if std::fs::read(backup).at(backup)? == std::fs::read(path).at(path)? {
+            std::fs::remove_file(backup).at(backup)?;
+        }
+        Ok(())
+    }
+
+    /// Find the most recent backup of a file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: The path of the original file.
+    pub fn latest(&self, path: impl AsRef<Path>) -> Result<Option<PathBuf>, Error> {
+        let path = path.as_ref();
+        match self {
+            Backup::None => Ok(None),
+            Backup::Adjacent => {
+                let backup = path.with_extension("bk");
+                Ok(backup.is_file().then_some(backup))
+            }
+            Backup::Directory(dir) => {
+                let prefix = format!("{}.", escape(&std::fs::canonicalize(path).at(path)?));
+                let mut latest: Option<String> = None;
+                for entry in std::fs::read_dir(dir).at(dir)? {
+                    let name = entry.at(dir)?.file_name().to_string_lossy().into_owned();
+                    let timestamp = match name
+                        .strip_prefix(&prefix)
+                        .and_then(|rest| rest.strip_suffix(&format!(".{}", EXTENSION)))
+                    {
+                        Some(timestamp) => timestamp,
+                        None => continue,
+                    };
+                    // Timestamps are zero-padded, so they sort lexicographically.
+                    let is_timestamp =
+                        timestamp.len() == 20 && timestamp.bytes().all(|b| b.is_ascii_digit());
+                    if is_timestamp && latest.as_ref().is_none_or(|latest| *latest < name) {
+                        latest = Some(name);
+                    }
+                }
+                Ok(latest.map(|name| dir.join(name)))
+            }
+        }
+    }
+}
+
+/// Restore a file from its most recent backup.
+///
+/// The backup is removed after it has been restored.
+///
+/// # Arguments
+///
+/// * `path`: The path of the original file.
+/// * `backup`: The backup policy that was used when formatting the file.
+///
+/// # Examples
+///
+/// ```no_run
+/// use rust_format::backup::{restore_backup, Backup};
+/// use rust_format::Options;
+///
+/// let backup = Backup::Directory("target/backups".into());
+/// rust_format::format_file_with("src/lib.rs", &Options::new().backup(backup.clone())).unwrap();
+/// restore_backup("src/lib.rs", &backup).unwrap();
+/// ```
+pub fn restore_backup(path: impl AsRef<Path>, backup: &Backup) -> Result<(), Error> {
+    let path = path.as_ref();
+    let latest = backup.latest(path)?.ok_or_else(|| Error::NoBackup {
+        path: path.to_path_buf(),
+    })?;
+    std::fs::copy(&latest, path).at(path)?;
+    std::fs::remove_file(&latest).at(&latest)?;
+    Ok(())
+}
+
+/// Encode a path into a single file name.
+fn escape(path: &Path) -> String {
+    let mut escaped = String::new();
+    for c in path.to_string_lossy().chars() {
+        match c {
+            '%' => escaped.push_str("%25"),
+            '/' => escaped.push_str("%2F"),
+            '\\' => escaped.push_str("%5C"),
+            ':' => escaped.push_str("%3A"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::{restore_backup, Backup};
+    use crate::Options;
+    use std::fs::read_to_string;
+
+    #[test]
+    fn test_restore_backup() {
+        const TARGET_PATH: &str = "target/backup_sample_code.rs";
+        let dir = std::path::PathBuf::from("target/test_backups");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::copy("resources/test/sample_code.rs", TARGET_PATH).unwrap();
+        let backup = Backup::Directory(dir.clone());
+
+        let options = Options::new().backup(backup.clone());
+        crate::format_file_with(TARGET_PATH, &options).unwrap();
+        assert!(backup.latest(TARGET_PATH).unwrap().is_some());
+
+        // Formatting an unchanged file does not leave a backup behind.
+        crate::format_file_with(TARGET_PATH, &options).unwrap();
+        assert_eq!(1, std::fs::read_dir(&dir).unwrap().count());
+
+        restore_backup(TARGET_PATH, &backup).unwrap();
+        assert_eq!(
+            read_to_string("resources/test/sample_code.rs").unwrap(),
+            read_to_string(TARGET_PATH).unwrap()
+        );
+        assert!(backup.latest(TARGET_PATH).unwrap().is_none());
+    }
+}
@@ -0,0 +1,264 @@
+//! Header and footer banners for generated files.
+
+use crate::{Error, Options};
+use std::collections::BTreeMap;
+
+/// The placeholder for the date in templates.
+const DATE: &str = "{{date}}";
+
+/// A header and footer that are added to formatted source code, e.g. a "generated file" notice.
+///
+/// The templates may refer to the following variables:
+///
+/// * `{{date}}`: The current date (`YYYY-MM-DD`, UTC). If the `SOURCE_DATE_EPOCH` environment
+///   variable is set, the date is derived from it instead. It can also be pinned with
+///   [`Banner::date`].
+/// * `{{tool_version}}`: The version of the formatting backend.
+/// * Any variable defined with [`Banner::var`], e.g. `{{source_schema}}`.
+///
+/// Unknown variables are left as they are.
+///
+/// The header is put after the shebang line of scripts, if any. A banner is only added once: if the source code already starts with the header (or ends with
+/// the footer), it is left untouched, even if its date differs. This keeps the output stable when
+/// generated code is formatted again on another day.
+///
+/// # Examples
+///
+/// ```
+/// use rust_format::{Banner, Options};
+///
+/// let banner = Banner::new()
+///     .header("// Generated from {{source_schema}} on {{date}}. Do not edit.")
+///     .var("source_schema", "schema.json")
+///     .date("2024-01-01");
+/// let options = Options::new().banner(banner);
+/// let formatted = rust_format::format_str_with("fn main(){}", &options).unwrap();
+/// assert_eq!(
+///     "// Generated from schema.json on 2024-01-01. Do not edit.\nfn main() {}\n",
+///     formatted
+/// );
+/// assert_eq!(formatted, rust_format::format_str_with(&formatted, &options).unwrap());
+/// ```
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub struct Banner {
+    header: Option<String>,
+    footer: Option<String>,
+    date: Option<String>,
+    vars: BTreeMap<String, String>,
+}
+
+impl Banner {
+    /// Create a banner without header and footer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the template of the header, which is put in front of the source code.
+    ///
+    /// # Arguments
+    ///
+    /// * `template`: The template.
+    pub fn header(mut self, template: impl Into<String>) -> Self {
+        self.header = Some(template.into());
+        self
+    }
+
+    /// Set the template of the footer, which is put after the source code.
+    ///
+    /// # Arguments
+    ///
+    /// * `template`: The template.
+    pub fn footer(mut self, template: impl Into<String>) -> Self {
+        self.footer = Some(template.into());
+        self
+    }
+
+    /// Pin the value of the `{{date}}` variable, for reproducible output.
+    ///
+    /// # Arguments
+    ///
+    /// * `date`: The date.
+    pub fn date(mut self, date: impl Into<String>) -> Self {
+        self.date = Some(date.into());
+        self
+    }
+
+    /// Define a variable.
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: The name of the variable, without braces.
+    /// * `value`: The value.
+    pub fn var(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.vars.insert(name.into(), value.into());
+        self
+    }
+
+    /// Add the header and footer to formatted source code, unless they are already present.
+    pub(crate) fn apply(&self, source: String, options: &Options) -> Result<String, Error> {
+        let header = self
+            .header
+            .as_deref()
+            .map(|template| self.render(template, options));
+        let footer = self
+            .footer
+            .as_deref()
+            .map(|template| self.render(template, options));
+
+        let mut output = source;
+        if let Some(header) = header.transpose()? {
+            // The header goes after a shebang line, which must remain the first line.
+            let start = match crate::lexer::shebang_end(&output) {
+                Some(end) if end < output.len() => end + 1,
+                Some(end) => {
+                    output.push('\n');
+                    end + 1
+                }
+                None => 0,
+            };
+            let line_count = header.lines().count();
+            let existing: String = output[start..]
+                .split_inclusive('\n')
+                .take(line_count)
+                .collect();
+            if !matches(&header, &existing) {
+                output.insert_str(start, &self.finish(&header));
+            }
+        }
+        if let Some(footer) = footer.transpose()? {
+            let line_count = footer.lines().count();
+            let lines: Vec<&str> = output.split_inclusive('\n').collect();
+            let existing: String = lines[lines.len().saturating_sub(line_count)..].concat();
+            if !matches(&footer, &existing) {
+                if !output.is_empty() && !output.ends_with('\n') {
+                    output.push('\n');
+                }
+                output.push_str(&self.finish(&footer));
+            }
+        }
+        Ok(output)
+    }
+
+    /// Substitute all variables of a template, except for the date.
+    fn render(&self, template: &str, options: &Options) -> Result<String, Error> {
+        let mut rendered = template.to_string();
+        if rendered.contains("{{tool_version}}") {
+            let version = options.get_backend().version()?;
+            rendered = rendered.replace("{{tool_version}}", &version);
+        }
+        for (name, value) in &self.vars {
+            rendered = rendered.replace(&format!("{{{{{}}}}}", name), value);
+        }
+        Ok(rendered)
+    }
+
+    /// Substitute the date of a rendered template and terminate it with a newline.
+    fn finish(&self, rendered: &str) -> String {
+        let mut finished = match rendered.contains(DATE) {
+            true => rendered.replace(DATE, &self.date.clone().unwrap_or_else(today)),
+            false => rendered.to_string(),
+        };
+        if !finished.ends_with('\n') {
+            finished.push('\n');
+        }
+        finished
+    }
+
+    /// Add the banner to a fingerprint.
+    pub(crate) fn fingerprint(&self, fingerprint: &mut crate::options::Fingerprint) {
+        fingerprint.text("banner.header", self.header.as_deref());
+        fingerprint.text("banner.footer", self.footer.as_deref());
+        fingerprint.text("banner.date", self.date.as_deref());
+        fingerprint.value("banner.vars", self.vars.len());
+        for (name, value) in &self.vars {
+            fingerprint.text("banner.var", Some(name));
+            fingerprint.text("banner.value", Some(value));
+        }
+    }
+}
+
+/// Determine whether text matches a rendered template, where the date may have any value within
+/// its line.
+fn matches(rendered: &str, text: &str) -> bool {
+    let text = text.strip_suffix('\n').unwrap_or(text);
+    let rendered = rendered.strip_suffix('\n').unwrap_or(rendered);
+    let mut pieces = rendered.split(DATE);
+    let first = pieces.next().unwrap_or_default();
+    let mut rest = match text.strip_prefix(first) {
+        Some(rest) => rest,
+        None => return false,
+    };
+    for piece in pieces {
+        let line_end = rest.find('\n').unwrap_or(rest.len());
+        let found = match piece.is_empty() {
+            true => Some(line_end),
+            false => rest.find(piece).filter(|&index| index <= line_end),
+        };
+        match found {
+            Some(index) => rest = &rest[index + piece.len()..],
+            None => return false,
+        }
+    }
+    rest.is_empty()
+}
+
+/// Determine the current date, honoring `SOURCE_DATE_EPOCH`.
+fn today() -> String {
+    let seconds = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.trim().parse::<u64>().ok())
+        .unwrap_or_else(|| {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |duration| duration.as_secs())
+        });
+    let (year, month, day) = civil_date(seconds / 86_400);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Convert a number of days since 1970-01-01 into a proleptic Gregorian calendar date.
+fn civil_date(days: u64) -> (u64, u64, u64) {
+    // See http://howardhinnant.github.io/date_algorithms.html#civil_from_days.
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod test {
+    use super::Banner;
+    use crate::Options;
+
+    #[test]
+    fn test_civil_date() {
+        assert_eq!((1970, 1, 1), super::civil_date(0));
+        assert_eq!((2000, 2, 29), super::civil_date(11_016));
+        assert_eq!((2024, 12, 31), super::civil_date(20_088));
+    }
+
+    #[test]
+    fn test_existing_banner() {
+        let banner = Banner::new()
+            .header("// Generated on {{date}}.")
+            .footer("// End of {{name}}.")
+            .var("name", "file")
+            .date("2024-06-01");
+        let source = "// Generated on 2023-01-01.\nfn main() {}\n".to_string();
+        assert_eq!(
+            "// Generated on 2023-01-01.\nfn main() {}\n// End of file.\n",
+            banner.apply(source, &Options::new()).unwrap()
+        );
+    }
+}
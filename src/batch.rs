@@ -0,0 +1,2167 @@
+//! Formatting of multiple files at once.
+
+pub use crate::checkpoint::Checkpoint;
+
+use crate::error::IoContext;
+use crate::events::EventKind;
+use crate::file_system::{FileSystem, StdFileSystem};
+use crate::glob::Glob;
+use crate::modules::ModuleResolver;
+use crate::report::{CheckResults, DiffStat};
+use crate::source::Sendable;
+use crate::targets::TargetKind;
+use crate::{
+    CleanCache, Config, Edition, Error, Options, Output, ReadOnlyPolicy, RequestId, Source,
+};
+use std::collections::{HashMap, VecDeque};
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// The outcome of processing a single file as part of a batch.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Outcome {
+    /// The file was formatted in place.
+    Formatted,
+    /// The file was checked and is formatted correctly.
+    Clean,
+    /// The file was checked and is not formatted correctly.
+    Unformatted,
+    /// The file was left untouched.
+    Skipped(SkipReason),
+}
+
+/// The reason why a file of a batch was left untouched.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum SkipReason {
+    /// The file is read-only (see [`crate::ReadOnlyPolicy::Skip`]).
+    ReadOnly,
+    /// The file opts out of formatting with a file-level `#![rustfmt::skip]` attribute. Such files
+    /// are detected before the formatting tool is run, which would leave them unchanged anyway.
+    FileLevelSkip,
+}
+
+impl Display for SkipReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SkipReason::ReadOnly => write!(f, "read-only"),
+            SkipReason::FileLevelSkip => write!(f, "file-level #![rustfmt::skip]"),
+        }
+    }
+}
+
+/// The reason why a file of a batch was not processed on its own.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum Duplicate {
+    /// The path refers to the same file as an earlier path of the batch, e.g. through `..` or a
+    /// symbolic link. The file is only processed once.
+    SameFile(PathBuf),
+    /// The file has the same content as an earlier file of the batch, whose result was reused
+    /// (see [`BatchOptions::dedup`]).
+    SameContent(PathBuf),
+}
+
+impl Duplicate {
+    /// Retrieve the path of the file that was processed instead.
+    pub fn of(&self) -> &Path {
+        match self {
+            Duplicate::SameFile(path) | Duplicate::SameContent(path) => path,
+        }
+    }
+}
+
+/// The result of formatting a single file as part of a batch.
+#[derive(Debug)]
+pub struct FileResult {
+    /// The path of the file.
+    pub path: PathBuf,
+    /// The result.
+    pub result: Result<Outcome, Error>,
+    /// The file whose result was reused for this file, if any.
+    pub duplicate: Option<Duplicate>,
+    /// The files of child modules that were modified along with this file, as reported by the
+    /// backend, sorted by path. These files are not part of the batch themselves (see
+    /// [`Options::skip_children`]).
+    pub children: Vec<PathBuf>,
+    /// The time spent on processing the file. This is zero if the result of another path that
+    /// refers to the same file was reused.
+    pub elapsed: Duration,
+    /// The changes to the file, if it was changed or is not formatted correctly and diff
+    /// statistics were requested (see `BatchOptions::diff_stats`).
+    pub diff_stat: Option<DiffStat>,
+    /// The operation that processed the file. Duplicates share the operation of the file that was
+    /// processed instead, and files that were processed together share a single operation. This is
+    /// `None` for results that were restored from a [`Checkpoint`].
+    pub request_id: Option<RequestId>,
+}
+
+/// A rule that overrides the batch options for files matching a glob pattern.
+#[derive(Debug, Clone)]
+struct Rule {
+    glob: Glob,
+    config: Option<Config>,
+    check: Option<bool>,
+    edition: Option<Edition>,
+}
+
+/// Options for formatting multiple files.
+///
+/// Files are formatted with the base [`Options`], unless rules registered by
+/// [`BatchOptions::config_for`] or [`BatchOptions::check_for`] match their path. Rules are applied
+/// in the order in which they were added, so later rules take precedence.
+///
+/// # Examples
+///
+/// ```
+/// use rust_format::batch::BatchOptions;
+/// use rust_format::Config;
+///
+/// let options = BatchOptions::new()
+///     .config_for("tests/fixtures/**", Config::new().max_width(80))
+///     .check_for("tests/fixtures/**", true);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct BatchOptions {
+    options: Options,
+    check: bool,
+    dedup: bool,
+    root: Option<PathBuf>,
+    rules: Vec<Rule>,
+    file_system: Option<Arc<dyn FileSystem>>,
+    detect_edition: bool,
+    group_invocations: bool,
+    #[cfg(feature = "diff")]
+    diff_stats: bool,
+    checkpoint: Option<Checkpoint>,
+    stamps: bool,
+    cache: Option<Arc<CleanCache>>,
+    module_resolver: Option<ModuleResolver>,
+    /// The detected editions by directory.
+    editions: Arc<Mutex<HashMap<PathBuf, Option<Edition>>>>,
+}
+
+impl BatchOptions {
+    /// Create a new instance with default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the base options for all files.
+    ///
+    /// # Arguments
+    ///
+    /// * `options`: The options.
+    pub fn options(mut self, options: Options) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Set whether files are only checked instead of formatted in place. Disabled by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `check`: Whether to only check the files.
+    pub fn check(mut self, check: bool) -> Self {
+        self.check = check;
+        self
+    }
+
+    /// Set whether files with identical content are processed only once. Disabled by default.
+    ///
+    /// Files are considered duplicates if their content, their resolved configuration and the
+    /// `rustfmt.toml` file that the tool discovers for them are identical. Only the first of them is passed to the formatting tool; its result is applied
+    /// to the others. If processing the first file fails, the duplicates are processed
+    /// individually.
+    ///
+    /// This is useful for trees of generated code, which often contain the same boilerplate many
+    /// times. Use [`crate::report::ReportExt::dedup_stats`] to evaluate the effect.
+    ///
+    /// # Arguments
+    ///
+    /// * `dedup`: Whether to detect duplicates.
+    pub fn dedup(mut self, dedup: bool) -> Self {
+        self.dedup = dedup;
+        self
+    }
+
+    /// Set the directory against which the glob patterns of rules are matched.
+    ///
+    /// Paths below this directory are matched relative to it. By default, paths are matched as they
+    /// were provided.
+    ///
+    /// # Arguments
+    ///
+    /// * `root`: The root directory.
+    pub fn root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.root = Some(root.into());
+        self
+    }
+
+    /// Record the progress of runs in a checkpoint, and skip the files that an earlier,
+    /// interrupted run with the checkpoint completed.
+    ///
+    /// The results of skipped files are restored from the checkpoint and delivered first; all
+    /// other files are processed as usual and recorded in the checkpoint once they succeed. A new
+    /// checkpoint skips no files, so the same options serve for the first run and for resuming it.
+    ///
+    /// # Arguments
+    ///
+    /// * `checkpoint`: The checkpoint.
+    pub fn resume_from(mut self, checkpoint: Checkpoint) -> Self {
+        self.checkpoint = Some(checkpoint);
+        self
+    }
+
+    /// Retrieve the checkpoint of runs, if any.
+    pub fn get_checkpoint(&self) -> Option<&Checkpoint> {
+        self.checkpoint.as_ref()
+    }
+
+    /// Set a cache of formatted code that the runs share, so that a file is not passed to the
+    /// formatting tool twice, e.g. when it is checked and then fixed (see
+    /// [`crate::report::CheckResults::write_all_fixes`]).
+    ///
+    /// Files are formatted in memory and written by the crate. The formatted code is cached by the
+    /// content of a file and the [fingerprint](Options::fingerprint) of its options; the options of
+    /// the cache itself do not apply. Enable [`CleanCache::cache_formatted`] for caching the code
+    /// of files that are not formatted correctly, and use [`CleanCache::stats`] to evaluate the
+    /// effect. Child modules of a file are not formatted along with it.
+    ///
+    /// # Arguments
+    ///
+    /// * `cache`: The cache.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use rust_format::batch::{check_files_with, BatchOptions};
+    /// use rust_format::{CleanCache, Options};
+    /// use std::sync::Arc;
+    ///
+    /// let cache = Arc::new(CleanCache::new(Options::new()).cache_formatted(true));
+    /// let options = BatchOptions::new().cache(cache.clone());
+    /// let results = check_files_with(["src/a.rs", "src/b.rs"], &options);
+    /// results.write_all_fixes();
+    /// eprintln!("Reused {} results.", cache.stats().hits);
+    /// ```
+    pub fn cache(mut self, cache: Arc<CleanCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Retrieve the cache of formatted code, if any.
+    pub fn get_cache(&self) -> Option<&CleanCache> {
+        self.cache.as_deref()
+    }
+
+    /// Set the resolver of the modules of crates, so that [`format_crate_with`] and the functions
+    /// that build on it also format the module files that `#[path]` attributes locate outside the
+    /// target directories. Disabled by default.
+    ///
+    /// Every file is formatted once, even if it is both found in a target directory and declared
+    /// as a module. The file system of the options is used for resolving the modules.
+    ///
+    /// # Arguments
+    ///
+    /// * `resolver`: The resolver.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use rust_format::batch::{format_crate_with, BatchOptions};
+    /// use rust_format::modules::{ModuleResolver, PathAttributePolicy};
+    ///
+    /// let resolver = ModuleResolver::new().path_attributes(PathAttributePolicy::WithinCrate);
+    /// let results = format_crate_with(".", &BatchOptions::new().module_resolver(resolver))?;
+    /// # Ok::<(), rust_format::Error>(())
+    /// ```
+    pub fn module_resolver(mut self, resolver: ModuleResolver) -> Self {
+        self.module_resolver = Some(resolver);
+        self
+    }
+
+    /// Retrieve the resolver of the modules of crates, if any.
+    pub fn get_module_resolver(&self) -> Option<&ModuleResolver> {
+        self.module_resolver.as_ref()
+    }
+
+    /// Override the configuration for files matching a glob pattern.
+    ///
+    /// The configuration is merged with the configuration of the base options. In the pattern, `*`
+    /// and `?` match within a path segment and `**` matches any number of segments.
+    ///
+    /// # Arguments
+    ///
+    /// * `glob`: The glob pattern.
+    /// * `config`: The overriding configuration.
+    pub fn config_for(mut self, glob: impl Into<String>, config: Config) -> Self {
+        self.rules.push(Rule {
+            glob: Glob::new(glob),
+            config: Some(config),
+            check: None,
+            edition: None,
+        });
+        self
+    }
+
+    /// Override whether files matching a glob pattern are only checked.
+    ///
+    /// # Arguments
+    ///
+    /// * `glob`: The glob pattern.
+    /// * `check`: Whether to only check the files.
+    pub fn check_for(mut self, glob: impl Into<String>, check: bool) -> Self {
+        self.rules.push(Rule {
+            glob: Glob::new(glob),
+            config: None,
+            check: Some(check),
+            edition: None,
+        });
+        self
+    }
+
+    /// Override the configuration for the files of a kind of target, e.g. to allow longer lines in
+    /// integration tests.
+    ///
+    /// The files are matched relative to the root directory (see [`BatchOptions::root`]), which
+    /// [`format_crate_with`] sets to the root of the crate.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind`: The kind of target.
+    /// * `config`: The overriding configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rust_format::batch::BatchOptions;
+    /// use rust_format::targets::TargetKind;
+    /// use rust_format::Config;
+    ///
+    /// let options =
+    ///     BatchOptions::new().config_for_target(TargetKind::Tests, Config::new().max_width(120));
+    /// ```
+    pub fn config_for_target(self, kind: TargetKind, config: Config) -> Self {
+        self.config_for(kind.glob(), config)
+    }
+
+    /// Set whether the edition of every file is detected from the `Cargo.toml` of its package.
+    /// Disabled by default.
+    ///
+    /// This is useful for workspaces whose packages use different editions. The `edition` key of
+    /// the nearest manifest with a `[package]` section is used, including editions inherited from
+    /// the workspace; packages without one use the 2015 edition. The edition of the base options
+    /// is used for files outside of packages, and rules registered by [`BatchOptions::edition_for`]
+    /// take precedence.
+    ///
+    /// # Arguments
+    ///
+    /// * `detect_edition`: Whether to detect the editions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rust_format::batch::BatchOptions;
+    /// use rust_format::Edition;
+    ///
+    /// let options = BatchOptions::new()
+    ///     .detect_edition(true)
+    ///     .edition_for("legacy/**", Edition::E2015);
+    /// ```
+    pub fn detect_edition(mut self, detect_edition: bool) -> Self {
+        self.detect_edition = detect_edition;
+        self
+    }
+
+    /// Set whether files with the same configuration are passed to the backend together. Disabled
+    /// by default.
+    ///
+    /// Files whose resolved options, check mode and `rustfmt.toml` file are identical are
+    /// processed in runs of up to 64 files, which saves starting the formatting tool for every
+    /// file. Files whose options involve steps besides running the backend, such as verification,
+    /// post-processing, input size limits, audit logs or backup directories, are still processed
+    /// individually, as are read-only files and batches with diff statistics, stamps or a cache.
+    /// If a run fails, its files are processed individually to report their own errors.
+    ///
+    /// The modified files of child modules are not reported for files that were processed
+    /// together (see [`FileResult::children`]), and the elapsed time of a run is split evenly
+    /// between its files.
+    ///
+    /// # Arguments
+    ///
+    /// * `group_invocations`: Whether to process files together.
+    pub fn group_invocations(mut self, group_invocations: bool) -> Self {
+        self.group_invocations = group_invocations;
+        self
+    }
+
+    /// Set whether the changes to every file are counted (see [`FileResult::diff_stat`]). Disabled
+    /// by default.
+    ///
+    /// The content of every file is read before it is processed. Files that are checked and not
+    /// formatted correctly are formatted in memory as well, to count the changes that formatting
+    /// would make. The changes of the files of child modules are not counted.
+    ///
+    /// # Arguments
+    ///
+    /// * `diff_stats`: Whether to count the changes.
+    #[cfg(feature = "diff")]
+    pub fn diff_stats(mut self, diff_stats: bool) -> Self {
+        self.diff_stats = diff_stats;
+        self
+    }
+
+    /// Set whether the state of processed files is recorded in stamp files, so that later runs
+    /// skip files that did not change since. Disabled by default.
+    ///
+    /// After a file was formatted or found to be formatted correctly, the hash of its content, the
+    /// [fingerprint](Options::fingerprint) of its options, the version of the formatting tool and
+    /// the hash of the `rustfmt.toml` file that the tool discovers for it are written to a stamp
+    /// file next to it, e.g. `lib.rs.rustfmt-stamp` for `lib.rs`. A file whose stamp still matches
+    /// is not passed to the tool again and is reported as formatted, or as clean when it is
+    /// checked. Unlike modification times, the stamps remain valid when other tools, e.g. a build
+    /// system, touch the files without changing them.
+    ///
+    /// # Arguments
+    ///
+    /// * `stamps`: Whether to use stamp files.
+    pub fn stamps(mut self, stamps: bool) -> Self {
+        self.stamps = stamps;
+        self
+    }
+
+    /// Determine whether the state of processed files is recorded in stamp files.
+    pub fn get_stamps(&self) -> bool {
+        self.stamps
+    }
+
+    /// Determine whether the changes to every file are counted.
+    fn counts_changes(&self) -> bool {
+        #[cfg(feature = "diff")]
+        return self.diff_stats;
+        #[cfg(not(feature = "diff"))]
+        false
+    }
+
+    /// Override the edition for files matching a glob pattern.
+    ///
+    /// # Arguments
+    ///
+    /// * `glob`: The glob pattern.
+    /// * `edition`: The edition.
+    pub fn edition_for(mut self, glob: impl Into<String>, edition: Edition) -> Self {
+        self.rules.push(Rule {
+            glob: Glob::new(glob),
+            config: None,
+            check: None,
+            edition: Some(edition),
+        });
+        self
+    }
+
+    /// Set the file system through which files are read and written. The file system of the
+    /// operating system is used by default.
+    ///
+    /// With another file system, files are formatted in memory, so they never have to exist on
+    /// disk. Configuration files are not discovered, child modules are not formatted and no
+    /// backups are created in that case.
+    ///
+    /// # Arguments
+    ///
+    /// * `file_system`: The file system.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rust_format::batch::{format_files_with, BatchOptions};
+    /// use rust_format::file_system::MemoryFileSystem;
+    /// use std::sync::Arc;
+    ///
+    /// let file_system = Arc::new(MemoryFileSystem::new());
+    /// file_system.insert("gen/lib.rs", "fn main(){}");
+    /// let options = BatchOptions::new().file_system(file_system.clone());
+    /// format_files_with(["gen/lib.rs"], &options);
+    /// assert_eq!(Some(b"fn main() {}\n".to_vec()), file_system.get("gen/lib.rs"));
+    /// ```
+    pub fn file_system(mut self, file_system: Arc<dyn FileSystem>) -> Self {
+        self.file_system = Some(file_system);
+        self
+    }
+
+    /// Retrieve the file system through which files are read and written.
+    pub fn get_file_system(&self) -> &dyn FileSystem {
+        match &self.file_system {
+            Some(file_system) => file_system.as_ref(),
+            None => &StdFileSystem,
+        }
+    }
+
+    /// Create a walk over the files below a directory of the file system.
+    #[cfg(feature = "walk")]
+    fn walk(&self, dir: &Path) -> crate::walk::Walk {
+        let walk = crate::walk::Walk::new(dir).verbosity(self.options.get_verbosity());
+        match &self.file_system {
+            Some(file_system) => walk.file_system(file_system.clone()),
+            None => walk,
+        }
+    }
+
+    /// Create options whose base options are those of the global formatter.
+    pub(crate) fn global() -> Self {
+        Self::new().options(crate::global().options().clone())
+    }
+
+    /// Determine the options and check mode for a file.
+    pub(crate) fn resolve(&self, path: &Path) -> (Options, bool) {
+        let relative = self
+            .root
+            .as_ref()
+            .and_then(|root| path.strip_prefix(root).ok())
+            .unwrap_or(path);
+
+        let mut options = self.options.clone();
+        if self.detect_edition {
+            if let Some(edition) = self.detected_edition(path) {
+                options = options.edition(edition);
+            }
+        }
+        let mut check = self.check;
+        for rule in self.rules.iter().filter(|rule| rule.glob.matches(relative)) {
+            if let Some(edition) = rule.edition {
+                options = options.edition(edition);
+            }
+            if let Some(config) = &rule.config {
+                let merged = options.get_config().clone().merge(config);
+                options = options.config(merged);
+            }
+            if let Some(rule_check) = rule.check {
+                check = rule_check;
+            }
+        }
+        (options, check)
+    }
+
+    /// Derive the options for fixing the files of a check, which format every file in place.
+    pub(crate) fn fixing(&self) -> Self {
+        let mut options = self.clone().check(false);
+        options.rules.retain(|rule| rule.check.is_none());
+        options.options = options.options.expect_changes(true);
+        options
+    }
+
+    /// Detect the edition of a file, reusing the editions of directories that were seen before.
+    fn detected_edition(&self, path: &Path) -> Option<Edition> {
+        let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+        let mut editions = self.editions.lock().expect("edition lock poisoned");
+        *editions
+            .entry(dir)
+            .or_insert_with_key(|dir| crate::manifest::detect_edition(dir))
+    }
+}
+
+/// Format multiple Rust source files.
+///
+/// The files are formatted in parallel with the options of the
+/// [global formatter](crate::set_global). The results are returned in the order of the input paths.
+///
+/// # Arguments
+///
+/// * `paths`: The paths to the target files.
+pub fn format_files<P: Into<PathBuf>>(paths: impl IntoIterator<Item = P>) -> Vec<FileResult> {
+    format_files_with(paths, &BatchOptions::global())
+}
+
+/// Format multiple Rust source files using the provided options.
+///
+/// The files are formatted in parallel by [`crate::available_parallelism_hint`] threads. The results
+/// are returned in the order of the input paths.
+///
+/// Paths are canonicalized, so that a file that is reached through several paths (e.g. through
+/// `..` or a symbolic link) is only processed once. The result for the other paths is marked with
+/// [`Duplicate::SameFile`].
+///
+/// # Arguments
+///
+/// * `paths`: The paths to the target files.
+/// * `options`: The options.
+pub fn format_files_with<P: Into<PathBuf>>(
+    paths: impl IntoIterator<Item = P>,
+    options: &BatchOptions,
+) -> Vec<FileResult> {
+    let (sender, receiver) = std::sync::mpsc::channel();
+    spawn_workers(paths, options, move |index, result| {
+        sender.send((index, result)).is_ok()
+    });
+    let mut results: Vec<(usize, FileResult)> = receiver.into_iter().collect();
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// Format all Rust source files below a directory.
+///
+/// The files are formatted in parallel with the options of the
+/// [global formatter](crate::set_global). See [`format_dir_with`].
+///
+/// # Arguments
+///
+/// * `dir`: The directory.
+#[cfg(feature = "walk")]
+pub fn format_dir(dir: impl AsRef<Path>) -> Result<Vec<FileResult>, Error> {
+    format_dir_with(dir, &BatchOptions::global())
+}
+
+/// Format all Rust source files below a directory using the provided options.
+///
+/// The files are discovered with [`crate::walk::Walk`]. The results are ordered by path,
+/// regardless of the order in which the files were processed, so reports are stable from run to
+/// run.
+///
+/// # Arguments
+///
+/// * `dir`: The directory.
+/// * `options`: The options.
+///
+/// # Returns
+///
+/// The results of the files. An error is only returned if the files could not be determined.
+#[cfg(feature = "walk")]
+pub fn format_dir_with(
+    dir: impl AsRef<Path>,
+    options: &BatchOptions,
+) -> Result<Vec<FileResult>, Error> {
+    let files = options.walk(dir.as_ref()).files()?;
+    Ok(format_files_with(files, options))
+}
+
+/// Format the Rust source files of all targets of a crate.
+///
+/// The files are formatted in parallel with the options of the
+/// [global formatter](crate::set_global). See [`format_crate_with`].
+///
+/// # Arguments
+///
+/// * `root`: The root directory of the crate.
+#[cfg(feature = "walk")]
+pub fn format_crate(root: impl AsRef<Path>) -> Result<Vec<FileResult>, Error> {
+    format_crate_with(root, &BatchOptions::global())
+}
+
+/// Format the Rust source files of all targets of a crate using the provided options.
+///
+/// The targets are located with [`crate::targets::find_targets`], through the file system of the
+/// options, and the files of their directories are discovered with [`crate::walk::Walk`]. The
+/// glob patterns of rules, including those of [`BatchOptions::config_for_target`], are matched
+/// relative to the root of the crate, replacing any root directory of the options.
+///
+/// # Arguments
+///
+/// * `root`: The root directory of the crate, which contains its `Cargo.toml`.
+/// * `options`: The options.
+///
+/// # Returns
+///
+/// The results of the files, ordered by target as in [`TargetKind::ALL`] and by path within
+/// targets, followed by the module files outside the target directories that the
+/// [module resolver](BatchOptions::module_resolver) found, if any. An error is only returned if the
+/// files could not be determined.
+///
+/// # Examples
+///
+/// ```no_run
+/// use rust_format::batch::{format_crate_with, BatchOptions};
+/// use rust_format::targets::TargetKind;
+/// use rust_format::Config;
+///
+/// let options =
+///     BatchOptions::new().config_for_target(TargetKind::Tests, Config::new().max_width(120));
+/// let results = format_crate_with(".", &options)?;
+/// # Ok::<(), rust_format::Error>(())
+/// ```
+#[cfg(feature = "walk")]
+pub fn format_crate_with(
+    root: impl AsRef<Path>,
+    options: &BatchOptions,
+) -> Result<Vec<FileResult>, Error> {
+    let root = root.as_ref();
+    let options = options.clone().root(root);
+    let mut files = Vec::new();
+    for target in crate::targets::find_targets_in(root, options.get_file_system()) {
+        match target.kind {
+            TargetKind::BuildScript => files.push(target.path),
+            _ => files.extend(options.walk(&target.path).files()?),
+        }
+    }
+    if let Some(resolver) = &options.module_resolver {
+        let resolver = match &options.file_system {
+            Some(file_system) => resolver.clone().file_system(file_system.clone()),
+            None => resolver.clone(),
+        };
+        let mut seen: std::collections::HashSet<PathBuf> = files
+            .iter()
+            .map(|path| crate::modules::normalize(path))
+            .collect();
+        for module in resolver.resolve(root)? {
+            if seen.insert(module.path.clone()) {
+                files.push(module.path);
+            }
+        }
+    }
+    Ok(format_files_with(files, &options))
+}
+
+/// Format the Rust source files of all packages of a workspace.
+///
+/// The files are formatted in parallel with the options of the
+/// [global formatter](crate::set_global). See [`format_workspace_with`].
+///
+/// # Arguments
+///
+/// * `root`: The root directory of the workspace.
+#[cfg(feature = "walk")]
+pub fn format_workspace(root: impl AsRef<Path>) -> Result<Vec<FileResult>, Error> {
+    format_workspace_with(root, &BatchOptions::global())
+}
+
+/// Format the Rust source files of all packages of a workspace using the provided options.
+///
+/// The packages are located with [`crate::targets::find_packages`] and formatted with
+/// [`format_packages_with`].
+///
+/// # Arguments
+///
+/// * `root`: The root directory of the workspace, which contains its `Cargo.toml`.
+/// * `options`: The options.
+#[cfg(feature = "walk")]
+pub fn format_workspace_with(
+    root: impl AsRef<Path>,
+    options: &BatchOptions,
+) -> Result<Vec<FileResult>, Error> {
+    format_packages_with(&crate::targets::find_packages(root)?, options)
+}
+
+/// Format the Rust source files of the targets of packages using the provided options.
+///
+/// Every package is formatted like a crate with [`format_crate_with`], so the glob patterns of
+/// rules are matched relative to the root of each package.
+///
+/// # Arguments
+///
+/// * `packages`: The packages.
+/// * `options`: The options.
+///
+/// # Returns
+///
+/// The results of the files, ordered by package. An error is only returned if the files could
+/// not be determined.
+#[cfg(feature = "walk")]
+pub fn format_packages_with(
+    packages: &[crate::targets::Package],
+    options: &BatchOptions,
+) -> Result<Vec<FileResult>, Error> {
+    let mut results = Vec::new();
+    for package in packages {
+        results.extend(format_crate_with(&package.root, options)?);
+    }
+    Ok(results)
+}
+
+/// Check whether multiple Rust source files are formatted correctly.
+///
+/// The files are checked with the options of the [global formatter](crate::set_global). See
+/// [`check_files_with`].
+///
+/// # Arguments
+///
+/// * `paths`: The paths to the target files.
+pub fn check_files<P: Into<PathBuf>>(paths: impl IntoIterator<Item = P>) -> CheckResults {
+    check_files_with(paths, &BatchOptions::global())
+}
+
+/// Check whether multiple Rust source files are formatted correctly using the provided options.
+///
+/// This is [`format_files_with`] with [`BatchOptions::check`] enabled, but the results can be
+/// evaluated without matching on them, and the files that are not formatted correctly can be
+/// fixed with the same options afterwards.
+///
+/// # Arguments
+///
+/// * `paths`: The paths to the target files.
+/// * `options`: The options.
+///
+/// # Examples
+///
+/// ```no_run
+/// use rust_format::batch::{check_files_with, BatchOptions};
+///
+/// let results = check_files_with(["src/lib.rs", "src/main.rs"], &BatchOptions::new());
+/// for path in results.unformatted_files() {
+///     eprintln!("Unformatted: {}", path.display());
+/// }
+/// results.into_error_if_dirty()?;
+/// # Ok::<(), rust_format::Error>(())
+/// ```
+pub fn check_files_with<P: Into<PathBuf>>(
+    paths: impl IntoIterator<Item = P>,
+    options: &BatchOptions,
+) -> CheckResults {
+    let options = options.clone().check(true);
+    CheckResults::new(format_files_with(paths, &options), options)
+}
+
+/// Check whether all Rust source files below a directory are formatted correctly.
+///
+/// The files are checked with the options of the [global formatter](crate::set_global). See
+/// [`check_dir_with`].
+///
+/// # Arguments
+///
+/// * `dir`: The directory.
+#[cfg(feature = "walk")]
+pub fn check_dir(dir: impl AsRef<Path>) -> Result<CheckResults, Error> {
+    check_dir_with(dir, &BatchOptions::global())
+}
+
+/// Check whether all Rust source files below a directory are formatted correctly using the
+/// provided options. See [`format_dir_with`] and [`check_files_with`].
+///
+/// # Arguments
+///
+/// * `dir`: The directory.
+/// * `options`: The options.
+///
+/// # Examples
+///
+/// ```no_run
+/// use rust_format::batch::check_dir;
+/// use rust_format::report::{ReportExt, Verbosity};
+///
+/// let results = check_dir("src")?;
+/// eprint!("{}", results.render(Verbosity::Normal));
+/// results.into_error_if_dirty()?;
+/// # Ok::<(), rust_format::Error>(())
+/// ```
+#[cfg(feature = "walk")]
+pub fn check_dir_with(
+    dir: impl AsRef<Path>,
+    options: &BatchOptions,
+) -> Result<CheckResults, Error> {
+    let options = options.clone().check(true);
+    let files = options.walk(dir.as_ref()).files()?;
+    Ok(CheckResults::new(
+        format_files_with(files, &options),
+        options,
+    ))
+}
+
+/// What a batch operation would do with a file.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Action {
+    /// The file would be formatted in place.
+    Format,
+    /// The file would only be checked.
+    Check,
+    /// The file would be left untouched, because it is read-only (see
+    /// [`crate::ReadOnlyPolicy::Skip`]) or it has a file-level `#![rustfmt::skip]` attribute.
+    Skip(SkipReason),
+}
+
+/// A file of a batch operation, along with the settings that would be used for it.
+#[derive(Debug, Clone)]
+pub struct PlannedFile {
+    /// The path of the file.
+    pub path: PathBuf,
+    /// What would be done with the file.
+    pub action: Action,
+    /// The edition of the file, if one is set.
+    pub edition: Option<Edition>,
+    /// The effective configuration of the file after applying all matching rules (see
+    /// [`Options::effective_config`]). Configuration files are discovered by the formatting tool
+    /// and are not part of it.
+    pub config: Config,
+    /// The backend that would format the file, in its debug representation. For
+    /// [`crate::backend::Rustfmt`], this includes the selected toolchain.
+    pub backend: String,
+    /// The file whose result would be reused for this file, if any.
+    pub duplicate: Option<Duplicate>,
+}
+
+/// Determine what formatting multiple files would do, without formatting or checking any of them.
+///
+/// This is useful to debug why a file is or is not formatted, or with which settings. The files
+/// are only inspected for their permissions and, with [`BatchOptions::dedup`], their content.
+///
+/// # Arguments
+///
+/// * `paths`: The paths to the target files.
+/// * `options`: The options.
+///
+/// # Returns
+///
+/// The planned files, in the order of the input paths.
+///
+/// # Examples
+///
+/// ```
+/// use rust_format::batch::{plan, Action, BatchOptions};
+///
+/// let options = BatchOptions::new().check_for("tests/**", true);
+/// let plan = plan(["src/lib.rs", "tests/api.rs"], &options);
+/// assert_eq!(Action::Format, plan[0].action);
+/// assert_eq!(Action::Check, plan[1].action);
+/// ```
+pub fn plan<P: Into<PathBuf>>(
+    paths: impl IntoIterator<Item = P>,
+    options: &BatchOptions,
+) -> Vec<PlannedFile> {
+    let paths: Vec<(usize, PathBuf)> = paths.into_iter().map(Into::into).enumerate().collect();
+    let mut planned = Vec::new();
+    for group in group(paths, options) {
+        let primary = group[0].path.clone();
+        for (position, member) in group.into_iter().enumerate() {
+            let (resolved, check) = options.resolve(&member.path);
+            let file_system = options.get_file_system();
+            let action = if file_system.read(&member.path).is_ok_and(|source| {
+                crate::lexer::has_file_level_skip(&String::from_utf8_lossy(&source))
+            }) {
+                Action::Skip(SkipReason::FileLevelSkip)
+            } else if check {
+                Action::Check
+            } else if resolved.get_read_only() == ReadOnlyPolicy::Skip
+                && file_system
+                    .metadata(&member.path)
+                    .is_ok_and(|metadata| metadata.read_only)
+            {
+                Action::Skip(SkipReason::ReadOnly)
+            } else {
+                Action::Format
+            };
+            let duplicate = match (position, member.same_file) {
+                (0, _) => None,
+                (_, true) => Some(Duplicate::SameFile(primary.clone())),
+                (_, false) => Some(Duplicate::SameContent(primary.clone())),
+            };
+            planned.push((
+                member.index,
+                PlannedFile {
+                    action,
+                    edition: resolved.get_edition(),
+                    config: resolved.effective_config(),
+                    backend: format!("{:?}", resolved.get_backend()),
+                    duplicate,
+                    path: member.path,
+                },
+            ));
+        }
+    }
+    planned.sort_by_key(|(index, _)| *index);
+    planned.into_iter().map(|(_, file)| file).collect()
+}
+
+/// Determine what formatting all Rust source files below a directory would do, without formatting
+/// or checking any of them. See [`plan`] and [`format_dir_with`].
+///
+/// # Arguments
+///
+/// * `dir`: The directory.
+/// * `options`: The options.
+#[cfg(feature = "walk")]
+pub fn plan_dir(dir: impl AsRef<Path>, options: &BatchOptions) -> Result<Vec<PlannedFile>, Error> {
+    let files = options.walk(dir.as_ref()).files()?;
+    Ok(plan(files, options))
+}
+
+/// Format multiple Rust source files, yielding results as they complete.
+///
+/// The files are formatted in parallel on background threads with the options of the
+/// [global formatter](crate::set_global). Dropping the iterator stops the processing of files that
+/// have not been started yet.
+///
+/// # Arguments
+///
+/// * `paths`: The paths to the target files.
+///
+/// # Examples
+///
+/// ```no_run
+/// for file in rust_format::batch::format_files_stream(["a.rs", "b.rs"]) {
+///     println!("{}: {:?}", file.path.display(), file.result);
+/// }
+/// ```
+pub fn format_files_stream<P: Into<PathBuf>>(
+    paths: impl IntoIterator<Item = P>,
+) -> impl Iterator<Item = FileResult> {
+    format_files_stream_with(paths, &BatchOptions::global())
+}
+
+/// Format multiple Rust source files using the provided options, yielding results as they
+/// complete.
+///
+/// # Arguments
+///
+/// * `paths`: The paths to the target files.
+/// * `options`: The options.
+pub fn format_files_stream_with<P: Into<PathBuf>>(
+    paths: impl IntoIterator<Item = P>,
+    options: &BatchOptions,
+) -> impl Iterator<Item = FileResult> {
+    let (sender, receiver) = std::sync::mpsc::channel();
+    spawn_workers(paths, options, move |_, result| sender.send(result).is_ok());
+    receiver.into_iter()
+}
+
+/// Format multiple Rust source files, producing an asynchronous stream of results.
+///
+/// This is the asynchronous counterpart of [`format_files_stream_with`]. The formatting itself
+/// still takes place on background threads, so the stream can be polled from any executor.
+///
+/// # Arguments
+///
+/// * `paths`: The paths to the target files.
+/// * `options`: The options.
+#[cfg(feature = "async")]
+pub fn format_files_stream_async<P: Into<PathBuf>>(
+    paths: impl IntoIterator<Item = P>,
+    options: &BatchOptions,
+) -> impl futures_core::Stream<Item = FileResult> {
+    let (sender, receiver) = futures_channel::mpsc::unbounded();
+    spawn_workers(paths, options, move |_, result| {
+        sender.unbounded_send(result).is_ok()
+    });
+    receiver
+}
+
+/// Format multiple sources of any kind.
+///
+/// The sources are formatted in parallel. Files are not modified; the rules of the options are
+/// applied to them based on their path, while all other sources use the base options. The results
+/// are returned in the order of the input sources.
+///
+/// # Arguments
+///
+/// * `sources`: The sources.
+/// * `options`: The options.
+///
+/// # Examples
+///
+/// ```
+/// use rust_format::batch::{format_sources, BatchOptions};
+/// use rust_format::Source;
+///
+/// let sources = [Source::from("fn a(){}".to_string()), Source::from("fn b(){}".to_string())];
+/// let results = format_sources(sources, &BatchOptions::new());
+/// assert_eq!("fn b() {}\n", results[1].as_ref().unwrap().text);
+/// ```
+pub fn format_sources<S: Into<Source>>(
+    sources: impl IntoIterator<Item = S>,
+    options: &BatchOptions,
+) -> Vec<Result<Output, Error>> {
+    // Sources that are bound to the current thread are converted before distributing the work.
+    let sources: VecDeque<(usize, Result<Sendable, Error>)> = sources
+        .into_iter()
+        .map(|source| source.into().into_sendable(&options.options))
+        .enumerate()
+        .collect();
+    let count = sources.len();
+    let workers = crate::available_parallelism_hint().min(count);
+    let sources = Mutex::new(sources);
+    let results = Mutex::new(Vec::with_capacity(count));
+
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let (index, source) = match sources.lock().expect("batch lock poisoned").pop_front()
+                {
+                    Some(next) => next,
+                    None => break,
+                };
+                let result = source.and_then(|source| match source {
+                    Sendable::Path(path) => {
+                        let (resolved, _) = options.resolve(&path);
+                        crate::format(Source::Path(path), &resolved)
+                    }
+                    text => crate::format(text.into(), &options.options),
+                });
+                results
+                    .lock()
+                    .expect("batch lock poisoned")
+                    .push((index, result));
+            });
+        }
+    });
+
+    let mut results = results.into_inner().expect("batch lock poisoned");
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// Process a single file of a batch and return its outcome along with the modified files of its
+/// child modules.
+fn process(path: &Path, options: &BatchOptions) -> Result<(Outcome, Vec<PathBuf>), Error> {
+    let source = options.get_file_system().read(path)?;
+    if crate::lexer::has_file_level_skip(&String::from_utf8_lossy(&source)) {
+        return Ok((Outcome::Skipped(SkipReason::FileLevelSkip), Vec::new()));
+    }
+    let file_system = options.file_system.clone();
+    let cache = options.cache.clone();
+    let (options, check) = options.resolve(path);
+    if let Some(file_system) = file_system {
+        return process_in_memory(
+            file_system.as_ref(),
+            path,
+            &options,
+            check,
+            cache.as_deref(),
+        )
+        .map(|outcome| (outcome, Vec::new()));
+    }
+    if let Some(cache) = cache {
+        return process_cached(&cache, path, &options, check).map(|outcome| (outcome, Vec::new()));
+    }
+    if check {
+        match crate::check_file_with(path, &options)? {
+            true => Ok((Outcome::Clean, Vec::new())),
+            false => Ok((Outcome::Unformatted, Vec::new())),
+        }
+    } else if options.get_read_only() == ReadOnlyPolicy::Skip
+        && crate::permissions::read_only(path)?.is_some()
+    {
+        Ok((Outcome::Skipped(SkipReason::ReadOnly), Vec::new()))
+    } else {
+        let mut children = crate::format::format_file_reporting(path, &options)?;
+        children.sort();
+        Ok((Outcome::Formatted, children))
+    }
+}
+
+/// Process a single file of a batch and count the changes to it if requested.
+fn process_counting(
+    path: &Path,
+    options: &BatchOptions,
+) -> Result<(Outcome, Vec<PathBuf>, Option<DiffStat>), Error> {
+    #[cfg(feature = "diff")]
+    if options.diff_stats {
+        let file_system = options.get_file_system();
+        let original = String::from_utf8_lossy(&file_system.read(path)?).into_owned();
+        let (outcome, children) = process(path, options)?;
+        let changed = match outcome {
+            Outcome::Formatted => String::from_utf8_lossy(&file_system.read(path)?).into_owned(),
+            Outcome::Unformatted => {
+                let (resolved, _) = options.resolve(path);
+                let dir = match options.file_system {
+                    Some(_) => None,
+                    None => Some(crate::format::parent_dir(path)),
+                };
+                match &options.cache {
+                    Some(cache) => cache.format_source(&original, dir, &resolved)?,
+                    None => crate::format::format_source(&original, dir, &resolved)?,
+                }
+            }
+            Outcome::Clean | Outcome::Skipped(_) => return Ok((outcome, children, None)),
+        };
+        let diff_stat = DiffStat::between(&original, &changed);
+        if let (Some(events), Some(diff_stat)) = (options.options.get_events(), &diff_stat) {
+            events.emit(EventKind::DiffComputed {
+                path: path.to_path_buf(),
+                insertions: diff_stat.insertions,
+                deletions: diff_stat.deletions,
+            });
+        }
+        return Ok((outcome, children, diff_stat));
+    }
+    process(path, options).map(|(outcome, children)| (outcome, children, None))
+}
+
+/// Process a single file of a batch, unless its stamp shows that it did not change since it was
+/// last processed, and update its stamp if stamps are enabled.
+fn process_stamped(
+    path: &Path,
+    options: &BatchOptions,
+) -> Result<(Outcome, Vec<PathBuf>, Option<DiffStat>), Error> {
+    if !options.stamps {
+        return process_counting(path, options);
+    }
+    let file_system = options.get_file_system();
+    let (resolved, check) = options.resolve(path);
+    let config = config_file(path, options);
+    if crate::stamp::is_stamped(file_system, path, &resolved, config.as_deref()) {
+        let outcome = match check {
+            true => Outcome::Clean,
+            false => Outcome::Formatted,
+        };
+        return Ok((outcome, Vec::new(), None));
+    }
+    let processed = process_counting(path, options)?;
+    if matches!(processed.0, Outcome::Formatted | Outcome::Clean) {
+        crate::stamp::write_stamp(file_system, path, &resolved, config.as_deref())?;
+    }
+    Ok(processed)
+}
+
+/// Process a single file of a batch with a cache of formatted code, writing it like formatting
+/// it in place does.
+fn process_cached(
+    cache: &CleanCache,
+    path: &Path,
+    options: &Options,
+    check: bool,
+) -> Result<Outcome, Error> {
+    let original = std::fs::read_to_string(path).at(path)?;
+    let formatted =
+        cache.format_source(&original, Some(crate::format::parent_dir(path)), options)?;
+    match (check, formatted == original) {
+        (true, true) => Ok(Outcome::Clean),
+        (true, false) => Ok(Outcome::Unformatted),
+        (false, true) => Ok(Outcome::Formatted),
+        (false, false) => write_checked(path, &original, &formatted, options),
+    }
+}
+
+/// Write the formatted content of a file of a batch, applying the policy for read-only files.
+fn write_checked(
+    path: &Path,
+    original: &str,
+    formatted: &str,
+    options: &Options,
+) -> Result<Outcome, Error> {
+    match (
+        crate::permissions::read_only(path)?,
+        options.get_read_only(),
+    ) {
+        (None, _) => crate::format::write_formatted(path, original, formatted, options)?,
+        (Some(_), ReadOnlyPolicy::Error) => {
+            return Err(Error::ReadOnly {
+                path: path.to_path_buf(),
+            })
+        }
+        (Some(_), ReadOnlyPolicy::Skip) => return Ok(Outcome::Skipped(SkipReason::ReadOnly)),
+        (Some(permissions), ReadOnlyPolicy::MakeWritable) => {
+            crate::permissions::make_writable(path, &permissions)?;
+            let result = crate::format::write_formatted(path, original, formatted, options);
+            std::fs::set_permissions(path, permissions).at(path)?;
+            result?;
+        }
+    }
+    Ok(Outcome::Formatted)
+}
+
+/// Process a single file of a batch in memory, reading and writing it through a file system.
+fn process_in_memory(
+    file_system: &dyn FileSystem,
+    path: &Path,
+    options: &Options,
+    check: bool,
+    cache: Option<&CleanCache>,
+) -> Result<Outcome, Error> {
+    let original = String::from_utf8(file_system.read(path)?).map_err(|_| Error::FileAccess {
+        path: path.to_path_buf(),
+        source: std::io::ErrorKind::InvalidData.into(),
+    })?;
+    if !check && file_system.metadata(path)?.read_only {
+        match options.get_read_only() {
+            ReadOnlyPolicy::Error => {
+                return Err(Error::ReadOnly {
+                    path: path.to_path_buf(),
+                })
+            }
+            ReadOnlyPolicy::Skip => return Ok(Outcome::Skipped(SkipReason::ReadOnly)),
+            ReadOnlyPolicy::MakeWritable => {}
+        }
+    }
+    let formatted = match cache {
+        Some(cache) => cache.format_source(&original, None, options)?,
+        None => crate::format::format_source(&original, None, options)?,
+    };
+    match (check, formatted == original) {
+        (true, true) => Ok(Outcome::Clean),
+        (true, false) => Ok(Outcome::Unformatted),
+        (false, true) => Ok(Outcome::Formatted),
+        (false, false) => {
+            options.get_hooks().write(path, &original, &formatted, || {
+                file_system.write(path, formatted.as_bytes())
+            })?;
+            if let Some(log) = options.get_audit_log() {
+                log.record_change(path, original.as_bytes(), formatted.as_bytes(), options)?;
+            }
+            Ok(Outcome::Formatted)
+        }
+    }
+}
+
+/// Apply the outcome of a file to a duplicate of it.
+///
+/// The duplicate is written with the checks that processing it on its own applies: files with a
+/// file-level skip attribute are skipped, and the policies for read-only files and for concurrent
+/// modifications are applied.
+fn fan_out(
+    primary: &Path,
+    path: &Path,
+    outcome: Outcome,
+    options: &BatchOptions,
+) -> Result<Outcome, Error> {
+    if outcome != Outcome::Formatted {
+        return Ok(outcome);
+    }
+    let file_system = options.get_file_system();
+    let original = file_system.read(path)?;
+    let formatted = file_system.read(primary)?;
+    let (old, new) = (
+        String::from_utf8_lossy(&original),
+        String::from_utf8_lossy(&formatted),
+    );
+    // The duplicate may have changed since it was grouped.
+    if crate::lexer::has_file_level_skip(&old) {
+        return Ok(Outcome::Skipped(SkipReason::FileLevelSkip));
+    }
+    if original == formatted {
+        return Ok(outcome);
+    }
+    let in_memory = options.file_system.is_some();
+    let (options, _) = options.resolve(path);
+    if !in_memory {
+        return write_checked(path, &old, &new, &options);
+    }
+    if file_system.metadata(path)?.read_only {
+        match options.get_read_only() {
+            ReadOnlyPolicy::Error => {
+                return Err(Error::ReadOnly {
+                    path: path.to_path_buf(),
+                })
+            }
+            ReadOnlyPolicy::Skip => return Ok(Outcome::Skipped(SkipReason::ReadOnly)),
+            ReadOnlyPolicy::MakeWritable => {}
+        }
+    }
+    options
+        .get_hooks()
+        .write(path, &old, &new, || file_system.write(path, &formatted))?;
+    if let Some(log) = options.get_audit_log() {
+        log.record_change(path, &original, &formatted, &options)?;
+    }
+    Ok(outcome)
+}
+
+/// A file of a batch.
+#[derive(Debug)]
+struct Member {
+    /// The index of the path in the input.
+    index: usize,
+    path: PathBuf,
+    /// Whether the path refers to the same file as the first member of its group.
+    same_file: bool,
+}
+
+/// Split the files of a batch into groups of duplicates.
+///
+/// Paths that refer to the same file always end up in the same group. With duplicate detection,
+/// files with identical content do as well. The first file of each group is the one that is
+/// processed. Files that cannot be canonicalized or read form a group of their own, so that the
+/// error is reported when they are processed.
+fn group(paths: Vec<(usize, PathBuf)>, options: &BatchOptions) -> VecDeque<Vec<Member>> {
+    use std::collections::HashMap;
+
+    let mut groups: Vec<Vec<Member>> = Vec::new();
+    let mut files: HashMap<PathBuf, usize> = HashMap::new();
+    let mut contents = HashMap::new();
+    for (index, path) in paths {
+        // Paths of other file systems are taken as they are.
+        let canonical = match options.file_system {
+            Some(_) => None,
+            None => std::fs::canonicalize(&path).ok(),
+        };
+        if let Some(&group) = canonical
+            .as_ref()
+            .and_then(|canonical| files.get(canonical))
+        {
+            groups[group].push(Member {
+                index,
+                path,
+                same_file: true,
+            });
+            continue;
+        }
+
+        let key = match options.dedup {
+            true => options.get_file_system().read(&path).ok().map(|content| {
+                let (resolved, check) = options.resolve(&path);
+                (
+                    crate::hash::sha256(&content),
+                    resolved.get_edition(),
+                    resolved.effective_config(),
+                    check,
+                    config_file(&path, options),
+                )
+            }),
+            false => None,
+        };
+        let group = match key.map(|key| *contents.entry(key).or_insert(groups.len())) {
+            Some(group) if group < groups.len() => group,
+            _ => {
+                groups.push(Vec::new());
+                groups.len() - 1
+            }
+        };
+        groups[group].push(Member {
+            index,
+            path,
+            same_file: false,
+        });
+        if let Some(canonical) = canonical {
+            files.insert(canonical, group);
+        }
+    }
+    groups.into()
+}
+
+/// Find the configuration file that the formatting tool discovers for a file itself, starting at
+/// the directory of the file. Files of other file systems are formatted without one.
+pub(crate) fn config_file(path: &Path, options: &BatchOptions) -> Option<PathBuf> {
+    if options.file_system.is_some() {
+        return None;
+    }
+    let dir = std::fs::canonicalize(crate::format::parent_dir(path)).ok()?;
+    dir.ancestors()
+        .flat_map(|dir| [dir.join(".rustfmt.toml"), dir.join("rustfmt.toml")])
+        .find(|config| config.is_file())
+}
+
+/// The maximum number of files that are passed to the backend together.
+const MAX_RUN_FILES: usize = 64;
+
+/// Combine the groups of duplicates into runs of files that are passed to the backend together.
+///
+/// Without [`BatchOptions::group_invocations`], every group is a run of its own. Otherwise, groups
+/// whose first files share their options, check mode and configuration file are combined into
+/// runs of at most [`MAX_RUN_FILES`] groups, which are distributed evenly between the workers. Runs
+/// are ordered by their first group. Groups whose first file has a file-level
+/// `#![rustfmt::skip]` attribute remain runs of their own, so that they are skipped without running
+/// the tool.
+fn runs(groups: VecDeque<Vec<Member>>, options: &BatchOptions) -> VecDeque<Vec<Vec<Member>>> {
+    if !options.group_invocations
+        || options.file_system.is_some()
+        || options.counts_changes()
+        || options.stamps
+        || options.cache.is_some()
+    {
+        return groups.into_iter().map(|group| vec![group]).collect();
+    }
+
+    let mut runs: Vec<Vec<Vec<Member>>> = Vec::new();
+    let mut keys: HashMap<_, usize> = HashMap::new();
+    for group in groups {
+        let path = &group[0].path;
+        let (resolved, check) = options.resolve(path);
+        let skipped = std::fs::read(path).is_ok_and(|source| {
+            crate::lexer::has_file_level_skip(&String::from_utf8_lossy(&source))
+        });
+        if skipped || !resolved.supports_grouping() {
+            runs.push(vec![group]);
+            continue;
+        }
+        let key = (
+            resolved.effective_options(),
+            format!("{:?}", resolved.get_backup()),
+            check,
+            config_file(path, options),
+        );
+        match keys.get(&key) {
+            Some(&run) => runs[run].push(group),
+            None => {
+                keys.insert(key, runs.len());
+                runs.push(vec![group]);
+            }
+        }
+    }
+
+    let workers = crate::available_parallelism_hint().max(1);
+    let mut split = VecDeque::new();
+    for run in runs {
+        let size = run.len().div_ceil(workers).clamp(1, MAX_RUN_FILES);
+        let mut run = run.into_iter().peekable();
+        while run.peek().is_some() {
+            split.push_back(run.by_ref().take(size).collect());
+        }
+    }
+    split
+}
+
+/// Process the first files of the groups of a run together.
+///
+/// # Returns
+///
+/// The outcomes of the files, or `None` if they have to be processed individually.
+fn process_together(paths: &[PathBuf], options: &BatchOptions) -> Option<Vec<Outcome>> {
+    let (resolved, check) = options.resolve(&paths[0]);
+    resolved.check_config().ok()?;
+    let backend = resolved.get_backend();
+    if check {
+        let clean = backend.check_paths(paths, &resolved).ok()?;
+        return Some(
+            clean
+                .into_iter()
+                .map(|clean| match clean {
+                    true => Outcome::Clean,
+                    false => Outcome::Unformatted,
+                })
+                .collect(),
+        );
+    }
+    for path in paths {
+        if crate::permissions::read_only(path).ok()?.is_some() {
+            return None;
+        }
+    }
+    backend.format_paths(paths, &resolved).ok()?;
+    Some(vec![Outcome::Formatted; paths.len()])
+}
+
+/// Process a group of duplicates and deliver the results of its files.
+///
+/// # Arguments
+///
+/// * `group`: The files of the group.
+/// * `processed`: The outcome of the first file, the time spent on it and the operation that
+///   processed it, if it was processed already.
+/// * `options`: The options.
+/// * `deliver`: The function that delivers the results.
+///
+/// # Returns
+///
+/// `false` if the results are no longer of interest.
+fn process_group(
+    group: Vec<Member>,
+    processed: Option<(Outcome, Duration, RequestId)>,
+    options: &BatchOptions,
+    deliver: &impl Fn(usize, FileResult) -> bool,
+) -> bool {
+    let mut group = group.into_iter();
+    let primary = group.next().expect("groups are never empty");
+    let start = Instant::now();
+    let (result, children, diff_stat, elapsed, request_id) = match processed {
+        Some((outcome, elapsed, request)) => (Ok(outcome), Vec::new(), None, elapsed, request),
+        None => {
+            file_started(&primary.path, options);
+            crate::request::scope(|request| match process_stamped(&primary.path, options) {
+                Ok((outcome, children, diff_stat)) => {
+                    (Ok(outcome), children, diff_stat, start.elapsed(), request)
+                }
+                Err(error) => (Err(error), Vec::new(), None, start.elapsed(), request),
+            })
+        }
+    };
+    let outcome = result.as_ref().ok().copied();
+    let result = FileResult {
+        path: primary.path.clone(),
+        result,
+        duplicate: None,
+        children,
+        elapsed,
+        diff_stat,
+        request_id: Some(request_id),
+    };
+    file_finished(&result, options);
+    if !deliver(primary.index, result) {
+        return false;
+    }
+
+    for member in group {
+        // If the first file failed, every other file is processed on its own to report its own
+        // error. Files of the same group are never processed concurrently.
+        let start = Instant::now();
+        file_started(&member.path, options);
+        // Duplicates have the same content as the first file, so they change in the same way.
+        // Whether a file is read-only depends on the file instead, so a duplicate of a skipped
+        // read-only file is processed on its own.
+        let shared = match (outcome, member.same_file) {
+            (Some(Outcome::Skipped(SkipReason::ReadOnly)), false) => None,
+            (outcome, _) => outcome,
+        };
+        let (result, duplicate, children, diff_stat, request_id) = match (shared, member.same_file)
+        {
+            (Some(outcome), true) => (
+                Ok(outcome),
+                Some(Duplicate::SameFile(primary.path.clone())),
+                Vec::new(),
+                diff_stat,
+                request_id,
+            ),
+            (Some(outcome), false) => (
+                fan_out(&primary.path, &member.path, outcome, options),
+                Some(Duplicate::SameContent(primary.path.clone())),
+                Vec::new(),
+                diff_stat,
+                request_id,
+            ),
+            (None, _) => {
+                crate::request::scope(|request| match process_stamped(&member.path, options) {
+                    Ok((outcome, children, diff_stat)) => {
+                        (Ok(outcome), None, children, diff_stat, request)
+                    }
+                    Err(error) => (Err(error), None, Vec::new(), None, request),
+                })
+            }
+        };
+        let result = FileResult {
+            path: member.path,
+            result,
+            duplicate,
+            children,
+            diff_stat,
+            request_id: Some(request_id),
+            elapsed: match member.same_file && outcome.is_some() {
+                true => Duration::ZERO,
+                false => start.elapsed(),
+            },
+        };
+        file_finished(&result, options);
+        if !deliver(member.index, result) {
+            return false;
+        }
+    }
+    true
+}
+
+/// Emit the event that processing a file started, if the options have an event sink.
+fn file_started(path: &Path, options: &BatchOptions) {
+    if let Some(events) = options.options.get_events() {
+        events.emit(EventKind::FileStarted {
+            path: path.to_path_buf(),
+        });
+    }
+}
+
+/// Emit the event that processing a file finished, if the options have an event sink.
+fn file_finished(result: &FileResult, options: &BatchOptions) {
+    if let Some(events) = options.options.get_events() {
+        events.file_finished(
+            &result.path,
+            result.result.as_ref().copied(),
+            result.elapsed,
+        );
+    }
+    #[cfg(feature = "tracing")]
+    if let Some(report) = crate::report::render_file(result, options.options.get_verbosity()) {
+        match result.result {
+            Ok(_) => tracing::info!("{}", report.trim_end()),
+            Err(_) => tracing::warn!("{}", report.trim_end()),
+        }
+    }
+}
+
+/// Spawn worker threads that format the provided files.
+///
+/// # Arguments
+///
+/// * `paths`: The paths to the target files.
+/// * `options`: The options.
+/// * `deliver`: The function that delivers a result along with the index of its path. Returns
+///   `false` if the results are no longer of interest, in which case the workers stop.
+fn spawn_workers<P: Into<PathBuf>>(
+    paths: impl IntoIterator<Item = P>,
+    options: &BatchOptions,
+    deliver: impl Fn(usize, FileResult) -> bool + Clone + Send + 'static,
+) {
+    let mut pending = Vec::new();
+    for (index, path) in paths.into_iter().map(Into::into).enumerate() {
+        let restored = options
+            .checkpoint
+            .as_ref()
+            .and_then(|checkpoint| checkpoint.restore(&path));
+        match restored {
+            Some(result) => {
+                if !deliver(index, result) {
+                    return;
+                }
+            }
+            None => pending.push((index, path)),
+        }
+    }
+    let paths = pending;
+    let checkpoint = options.checkpoint.clone();
+    let deliver = move |index, result: FileResult| {
+        if let Some(checkpoint) = &checkpoint {
+            checkpoint.record(&result);
+        }
+        deliver(index, result)
+    };
+    let runs = runs(group(paths, options), options);
+    let workers = crate::available_parallelism_hint().min(runs.len());
+    let runs = Arc::new(Mutex::new(runs));
+    let options = Arc::new(options.clone());
+
+    for _ in 0..workers {
+        let runs = runs.clone();
+        let options = options.clone();
+        let deliver = deliver.clone();
+        std::thread::spawn(move || loop {
+            let run = match runs.lock().expect("batch lock poisoned").pop_front() {
+                Some(next) => next,
+                None => break,
+            };
+            let outcomes = match run.len() {
+                1 => None,
+                count => {
+                    let paths: Vec<PathBuf> =
+                        run.iter().map(|group| group[0].path.clone()).collect();
+                    paths.iter().for_each(|path| file_started(path, &options));
+                    let start = Instant::now();
+                    crate::request::scope(|request| {
+                        process_together(&paths, &options).map(|outcomes| {
+                            let elapsed = start.elapsed() / count as u32;
+                            outcomes
+                                .into_iter()
+                                .map(|outcome| (outcome, elapsed, request))
+                                .collect::<Vec<_>>()
+                        })
+                    })
+                }
+            };
+            let mut outcomes = outcomes.map(Vec::into_iter);
+            for group in run {
+                let processed = outcomes.as_mut().and_then(Iterator::next);
+                if !process_group(group, processed, &options, &deliver) {
+                    return;
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs::read_to_string;
+    use std::path::PathBuf;
+
+    fn prepare(name: &str) -> PathBuf {
+        let path = PathBuf::from(format!("target/{}", name));
+        std::fs::copy("resources/test/sample_code.rs", &path).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_format_files_stream() {
+        let paths = [prepare("batch_stream_1.rs"), prepare("batch_stream_2.rs")];
+        let results: Vec<_> = super::format_files_stream(paths.clone()).collect();
+
+        assert_eq!(2, results.len());
+        let expected = read_to_string("resources/test/expected.rs").unwrap();
+        for result in results {
+            assert_eq!(super::Outcome::Formatted, result.result.unwrap());
+            assert!(paths.contains(&result.path));
+            assert_eq!(expected, read_to_string(&result.path).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_children() {
+        use crate::report::ReportExt;
+
+        let dir = PathBuf::from("target/batch_children");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("main.rs"),
+            "mod clean;\nmod dirty;\nfn main() {}\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("clean.rs"), "fn clean() {}\n").unwrap();
+        std::fs::write(dir.join("dirty.rs"), "fn dirty(){}\n").unwrap();
+
+        let results = super::format_files([dir.join("main.rs")]);
+
+        assert_eq!(1, results[0].children.len());
+        assert!(results[0].children[0].ends_with("dirty.rs"));
+        assert_eq!(2, results.formatted_files().len());
+        assert_eq!(
+            "fn dirty() {}\n",
+            read_to_string(dir.join("dirty.rs")).unwrap()
+        );
+    }
+
+    #[cfg(feature = "walk")]
+    #[test]
+    fn test_format_dir() {
+        let dir = PathBuf::from("target/batch_dir");
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut expected = Vec::new();
+        for name in ["b", "a/z", "a/b", "c", "a", "aa"] {
+            let path = dir.join(format!("{}.rs", name));
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, "fn main(){}").unwrap();
+            expected.push(path);
+        }
+        expected.sort();
+
+        let results = super::format_dir(&dir).unwrap();
+        let paths: Vec<PathBuf> = results.into_iter().map(|result| result.path).collect();
+        assert_eq!(expected, paths);
+    }
+
+    #[test]
+    fn test_plan() {
+        use super::{Action, BatchOptions, Duplicate, SkipReason};
+        use crate::Config;
+
+        let paths = [prepare("batch_plan_1.rs"), prepare("batch_plan_2.rs")];
+        let original = read_to_string(&paths[0]).unwrap();
+        let options = BatchOptions::new()
+            .dedup(true)
+            .config_for("**/batch_plan_2.rs", Config::new().max_width(80));
+
+        let plan = super::plan(paths.clone(), &options);
+
+        assert_eq!(2, plan.len());
+        assert_eq!(Action::Format, plan[0].action);
+        assert_eq!(None, plan[0].duplicate);
+        assert_eq!(None, plan[1].duplicate);
+        assert_eq!(Some("80"), plan[1].config.get("max_width"));
+        assert!(plan[0].backend.contains("Rustfmt"));
+        assert_eq!(original, read_to_string(&paths[0]).unwrap());
+
+        let plan = super::plan([paths[0].clone(), paths[0].clone()], &options);
+        assert_eq!(
+            Some(Duplicate::SameFile(paths[0].clone())),
+            plan[1].duplicate
+        );
+
+        std::fs::write(&paths[1], "#![rustfmt::skip]\nfn s(){}\n").unwrap();
+        let plan = super::plan(paths.clone(), &options.check(true));
+        assert_eq!(Action::Skip(SkipReason::FileLevelSkip), plan[1].action);
+        let results = super::check_files_with(paths, &BatchOptions::new());
+        assert_eq!(
+            super::Outcome::Skipped(SkipReason::FileLevelSkip),
+            *results[1].result.as_ref().unwrap()
+        );
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_format_files_stream_async() {
+        use futures::StreamExt;
+
+        let paths = [prepare("batch_async_1.rs"), prepare("batch_async_2.rs")];
+        let results: Vec<_> = futures::executor::block_on(
+            super::format_files_stream_async(paths, &Default::default()).collect(),
+        );
+
+        assert_eq!(2, results.len());
+        assert!(results.iter().all(|result| result.result.is_ok()));
+    }
+
+    #[test]
+    fn test_check_for() {
+        use super::{BatchOptions, Outcome};
+        use crate::Config;
+
+        let options = BatchOptions::new()
+            .root("target")
+            .check_for("batch_check_*.rs", true)
+            .config_for("batch_check_2.rs", Config::new().max_width(20));
+        let paths = [prepare("batch_check_1.rs"), prepare("batch_check_2.rs")];
+        std::fs::copy("resources/test/expected.rs", &paths[1]).unwrap();
+        let results = super::format_files_with(paths.clone(), &options);
+
+        assert_eq!(paths[0], results[0].path);
+        assert_eq!(Outcome::Unformatted, *results[0].result.as_ref().unwrap());
+        // The narrow width turns the formatted file into an unformatted one.
+        assert_eq!(Outcome::Unformatted, *results[1].result.as_ref().unwrap());
+        assert_eq!(
+            read_to_string("resources/test/sample_code.rs").unwrap(),
+            read_to_string(&paths[0]).unwrap()
+        );
+
+        let options = BatchOptions::new().check(true);
+        let results = super::format_files_with(paths, &options);
+        assert_eq!(Outcome::Clean, *results[1].result.as_ref().unwrap());
+    }
+
+    #[test]
+    fn test_dedup() {
+        use super::{BatchOptions, Duplicate};
+        use crate::report::{DedupStats, ReportExt};
+
+        let paths = [
+            prepare("batch_dedup_1.rs"),
+            prepare("batch_dedup_2.rs"),
+            prepare("batch_dedup_3.rs"),
+        ];
+        std::fs::write(&paths[2], "fn other(){}").unwrap();
+        let results = super::format_files_with(paths.clone(), &BatchOptions::new().dedup(true));
+
+        assert_eq!(None, results[0].duplicate);
+        assert_eq!(
+            Some(Duplicate::SameContent(paths[0].clone())),
+            results[1].duplicate
+        );
+        assert_eq!(None, results[2].duplicate);
+        assert_eq!(
+            DedupStats {
+                unique: 2,
+                duplicates: 1,
+                same_file: 0,
+            },
+            results.dedup_stats()
+        );
+        let expected = read_to_string("resources/test/expected.rs").unwrap();
+        assert_eq!(expected, read_to_string(&paths[1]).unwrap());
+    }
+
+    #[test]
+    fn test_dedup_config_file() {
+        use super::{BatchOptions, Outcome};
+
+        let dir = PathBuf::from("target/batch_dedup_config");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("a")).unwrap();
+        std::fs::create_dir_all(dir.join("b")).unwrap();
+        std::fs::write(dir.join("b/rustfmt.toml"), "hard_tabs = true\n").unwrap();
+        let paths = [dir.join("a/x.rs"), dir.join("b/x.rs")];
+        for path in &paths {
+            std::fs::write(path, "fn f(){\nx();}").unwrap();
+        }
+
+        let results = super::format_files_with(paths.clone(), &BatchOptions::new().dedup(true));
+        assert_eq!(None, results[1].duplicate);
+        assert_eq!(
+            "fn f() {\n    x();\n}\n",
+            read_to_string(&paths[0]).unwrap()
+        );
+        assert_eq!("fn f() {\n\tx();\n}\n", read_to_string(&paths[1]).unwrap());
+        let results = super::format_files_with(paths, &BatchOptions::new().check(true));
+        assert_eq!(Outcome::Clean, *results[1].result.as_ref().unwrap());
+    }
+
+    #[test]
+    fn test_dedup_read_only() {
+        use super::{BatchOptions, Outcome, SkipReason};
+        use crate::{Error, Options, ReadOnlyPolicy};
+
+        let set_read_only = |path: &std::path::Path, read_only: bool| {
+            let mut permissions = std::fs::metadata(path).unwrap().permissions();
+            #[allow(clippy::permissions_set_readonly_false)]
+            permissions.set_readonly(read_only);
+            std::fs::set_permissions(path, permissions).unwrap();
+        };
+        let duplicate = PathBuf::from("target/batch_dedup_read_only_2.rs");
+        for name in ["batch_dedup_read_only_1.rs", "batch_dedup_read_only_2.rs"] {
+            let path = PathBuf::from(format!("target/{}", name));
+            if path.exists() {
+                set_read_only(&path, false);
+            }
+        }
+        let paths = [
+            prepare("batch_dedup_read_only_1.rs"),
+            prepare("batch_dedup_read_only_2.rs"),
+        ];
+        set_read_only(&duplicate, true);
+
+        let skip = BatchOptions::new()
+            .dedup(true)
+            .options(Options::new().read_only(ReadOnlyPolicy::Skip));
+        let results = super::format_files_with(paths.clone(), &skip);
+        assert_eq!(Outcome::Formatted, *results[0].result.as_ref().unwrap());
+        assert_eq!(
+            Outcome::Skipped(SkipReason::ReadOnly),
+            *results[1].result.as_ref().unwrap()
+        );
+        assert_eq!(
+            read_to_string("resources/test/sample_code.rs").unwrap(),
+            read_to_string(&duplicate).unwrap()
+        );
+
+        prepare("batch_dedup_read_only_1.rs");
+        let results = super::format_files_with(paths.clone(), &BatchOptions::new().dedup(true));
+        assert!(matches!(results[1].result, Err(Error::ReadOnly { .. })));
+        set_read_only(&duplicate, false);
+
+        // A read-only first file does not keep its duplicates from being formatted.
+        set_read_only(&paths[0], false);
+        prepare("batch_dedup_read_only_1.rs");
+        prepare("batch_dedup_read_only_2.rs");
+        set_read_only(&paths[0], true);
+        let results = super::format_files_with(paths.clone(), &skip);
+        assert_eq!(
+            Outcome::Skipped(SkipReason::ReadOnly),
+            *results[0].result.as_ref().unwrap()
+        );
+        assert_eq!(Outcome::Formatted, *results[1].result.as_ref().unwrap());
+        assert_eq!(None, results[1].duplicate);
+        assert_eq!(
+            read_to_string("resources/test/expected.rs").unwrap(),
+            read_to_string(&duplicate).unwrap()
+        );
+        set_read_only(&paths[0], false);
+    }
+
+    #[test]
+    fn test_group_invocations() {
+        use super::{BatchOptions, Outcome};
+
+        let dir = PathBuf::from("target/batch_group");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.rs"), "fn a(){}").unwrap();
+        std::fs::write(dir.join("b.rs"), "fn b() {}\n").unwrap();
+        std::fs::write(dir.join("c.rs"), "fn c(){}").unwrap();
+        let paths = [dir.join("a.rs"), dir.join("b.rs"), dir.join("c.rs")];
+        let options = BatchOptions::new().group_invocations(true);
+
+        let outcomes = |options: &BatchOptions| -> Vec<Outcome> {
+            super::format_files_with(paths.clone(), options)
+                .into_iter()
+                .map(|result| result.result.unwrap())
+                .collect()
+        };
+        assert_eq!(
+            vec![Outcome::Unformatted, Outcome::Clean, Outcome::Unformatted],
+            outcomes(&options.clone().check(true))
+        );
+        assert_eq!(vec![Outcome::Formatted; 3], outcomes(&options));
+        assert_eq!("fn c() {}\n", read_to_string(&paths[2]).unwrap());
+
+        // A run with a file that cannot be formatted fails, so that its files are processed
+        // individually.
+        std::fs::write(&paths[0], "fn a(){}").unwrap();
+        let check = options.check(true);
+        assert_eq!(
+            Some(vec![Outcome::Unformatted, Outcome::Clean, Outcome::Clean]),
+            super::process_together(&paths, &check)
+        );
+        std::fs::write(&paths[1], "fn b(").unwrap();
+        assert_eq!(None, super::process_together(&paths, &check));
+        std::fs::write(&paths[0], "fn a() {}\n").unwrap();
+        let results = super::format_files_with(paths.clone(), &check);
+        assert_eq!(Outcome::Clean, *results[0].result.as_ref().unwrap());
+        assert!(results[1].result.is_err());
+        assert_eq!(Outcome::Clean, *results[2].result.as_ref().unwrap());
+    }
+
+    #[test]
+    fn test_group_invocations_file_level_skip() {
+        use super::{BatchOptions, Outcome, SkipReason};
+        use crate::report::ReportExt;
+
+        let dir = PathBuf::from("target/batch_group_skip");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let skipped = dir.join("skipped.rs");
+        std::fs::write(&skipped, "#![rustfmt::skip]\nfn s(){}\n").unwrap();
+        // Twice as many files as workers, so that the other files are grouped in pairs.
+        let mut paths = vec![skipped.clone()];
+        for index in 0..2 * crate::available_parallelism_hint().max(1) {
+            let path = dir.join(format!("f{}.rs", index));
+            std::fs::write(&path, "fn f(){}").unwrap();
+            paths.push(path);
+        }
+        let options = BatchOptions::new().group_invocations(true);
+
+        let indexed = paths.iter().cloned().enumerate().collect();
+        let runs = super::runs(super::group(indexed, &options), &options);
+        assert_eq!(vec![1, 2], {
+            let mut sizes: Vec<usize> = runs.iter().map(Vec::len).collect();
+            sizes.dedup();
+            sizes
+        });
+        assert_eq!(skipped, runs[0][0][0].path);
+
+        let results = super::format_files_with(paths.clone(), &options);
+        assert!(matches!(
+            results[0].result,
+            Ok(Outcome::Skipped(SkipReason::FileLevelSkip))
+        ));
+        assert!(results[1..]
+            .iter()
+            .all(|result| matches!(result.result, Ok(Outcome::Formatted))));
+        assert_eq!(1, results.skip_stats().file_level_skip);
+        assert_eq!(
+            "#![rustfmt::skip]\nfn s(){}\n",
+            read_to_string(&skipped).unwrap()
+        );
+        assert_eq!("fn f() {}\n", read_to_string(&paths[1]).unwrap());
+    }
+
+    #[test]
+    fn test_group_invocations_verify_applied() {
+        use super::BatchOptions;
+        use crate::Options;
+
+        let dir = PathBuf::from("target/batch_group_verify");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        // Twice as many files as workers, so that they would be grouped in pairs.
+        let count = 2 * crate::available_parallelism_hint().max(1);
+        let paths: Vec<(usize, PathBuf)> = (0..count)
+            .map(|index| {
+                let path = dir.join(format!("f{}.rs", index));
+                std::fs::write(&path, "fn f(){}").unwrap();
+                (index, path)
+            })
+            .collect();
+        let options = BatchOptions::new()
+            .group_invocations(true)
+            .options(Options::new().verify_applied(true));
+
+        // The files are hashed before and after they are formatted, which runs do not do.
+        let runs = super::runs(super::group(paths, &options), &options);
+        assert_eq!(count, runs.len());
+        assert!(runs.iter().all(|run| run.len() == 1));
+    }
+
+    #[test]
+    fn test_same_file() {
+        use super::{Duplicate, Outcome};
+        use crate::report::ReportExt;
+
+        let path = prepare("batch_same_file.rs");
+        let alias = PathBuf::from("target/../target/batch_same_file.rs");
+        let results = super::format_files([path.clone(), alias.clone(), path.clone()]);
+
+        assert_eq!(3, results.len());
+        assert_eq!(alias, results[1].path);
+        assert_eq!(
+            Some(Duplicate::SameFile(path.clone())),
+            results[1].duplicate
+        );
+        assert_eq!(Some(Duplicate::SameFile(path)), results[2].duplicate);
+        assert!(matches!(results[2].result, Ok(Outcome::Formatted)));
+        assert_eq!(2, results.dedup_stats().same_file);
+    }
+
+    #[test]
+    fn test_file_level_skip() {
+        use crate::backend::{Call, Mock};
+        use crate::file_system::MemoryFileSystem;
+        use crate::report::{ReportExt, SkipStats, Verbosity};
+        use std::sync::Arc;
+
+        let file_system = Arc::new(MemoryFileSystem::new());
+        file_system.insert("gen/a.rs", "//! Generated.\n#![rustfmt::skip]\nfn a(){}\n");
+        file_system.insert("gen/b.rs", "fn b(){}\n");
+        let mock = Arc::new(Mock::new());
+        let options = super::BatchOptions::new()
+            .options(crate::Options::new().backend(mock.clone()))
+            .file_system(file_system.clone());
+        let results = super::format_files_with(["gen/a.rs", "gen/b.rs"], &options);
+
+        assert!(matches!(
+            results[0].result,
+            Ok(super::Outcome::Skipped(super::SkipReason::FileLevelSkip))
+        ));
+        assert_eq!(
+            1,
+            mock.calls()
+                .iter()
+                .filter(|call| matches!(call, Call::FormatSource(_)))
+                .count()
+        );
+        assert_eq!(
+            SkipStats {
+                read_only: 0,
+                file_level_skip: 1
+            },
+            results.skip_stats()
+        );
+        assert!(results
+            .render(Verbosity::Verbose)
+            .starts_with("Skipped 'gen/a.rs' (file-level #![rustfmt::skip])"));
+    }
+}
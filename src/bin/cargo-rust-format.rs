@@ -0,0 +1,193 @@
+//! A cargo subcommand that formats the packages of a workspace, e.g. `cargo rust-format --check`.
+
+use rust_format::batch::{format_packages_with, BatchOptions};
+use rust_format::report::{ExitCodePolicy, ReportExt, Verbosity};
+use rust_format::targets::{find_packages, Package};
+use rust_format::Options;
+use std::path::{Path, PathBuf};
+
+const USAGE: &str = "Format the Rust source files of the packages of a workspace.
+
+Usage: cargo rust-format [OPTIONS]
+
+Options:
+  -p, --package <SPEC>     Format only the given package (may be repeated)
+      --workspace          Format all packages of the workspace
+      --exclude <SPEC>     Exclude a package from --workspace (may be repeated)
+      --manifest-path <PATH>
+                           The path to Cargo.toml
+      --check              Check whether the files are formatted instead of formatting them
+  -v, --verbose            Report all files
+  -q, --quiet              Report errors only
+  -h, --help               Print this help
+";
+
+/// The parsed command line.
+#[derive(Debug, Default)]
+struct Args {
+    packages: Vec<String>,
+    workspace: bool,
+    exclude: Vec<String>,
+    manifest_path: Option<PathBuf>,
+    check: bool,
+    verbosity: Verbosity,
+}
+
+/// Parse the command line, without the name of the binary and the name of the subcommand.
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut parsed = Args::default();
+    while let Some(arg) = args.next() {
+        let (name, inline) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name.to_string(), Some(value.into())),
+            _ => (arg, None),
+        };
+        let mut value = || {
+            inline
+                .clone()
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("'{}' requires a value", name))
+        };
+        match name.as_str() {
+            "-p" | "--package" => parsed.packages.push(value()?),
+            "--workspace" | "--all" => parsed.workspace = true,
+            "--exclude" => parsed.exclude.push(value()?),
+            "--manifest-path" => parsed.manifest_path = Some(PathBuf::from(value()?)),
+            "--check" => parsed.check = true,
+            "-v" | "--verbose" => parsed.verbosity = Verbosity::Verbose,
+            "-q" | "--quiet" => parsed.verbosity = Verbosity::Quiet,
+            _ => return Err(format!("unexpected argument '{}'", name)),
+        }
+    }
+    if !parsed.exclude.is_empty() && !parsed.workspace {
+        return Err("'--exclude' can only be used together with '--workspace'".to_string());
+    }
+    Ok(parsed)
+}
+
+/// Find the packages of the workspace that contains a directory, along with the root of the
+/// package that the directory belongs to, if any.
+///
+/// As for cargo, the nearest manifest determines the package, and the nearest manifest above it
+/// whose workspace contains that package determines the workspace.
+fn locate(dir: &Path) -> Result<(Vec<Package>, Option<PathBuf>), String> {
+    let nearest = dir
+        .ancestors()
+        .find(|dir| dir.join("Cargo.toml").is_file())
+        .ok_or_else(|| {
+            format!(
+                "could not find 'Cargo.toml' in '{}' or above",
+                dir.display()
+            )
+        })?;
+    let packages = find_packages(nearest).map_err(describe)?;
+    let current = packages
+        .iter()
+        .find(|package| package.root == nearest)
+        .map(|package| package.root.clone());
+    let Some(root) = &current else {
+        // A virtual manifest is the root of its workspace.
+        return Ok((packages, None));
+    };
+    for ancestor in nearest.ancestors().skip(1) {
+        if !ancestor.join("Cargo.toml").is_file() {
+            continue;
+        }
+        if let Ok(workspace) = find_packages(ancestor) {
+            if workspace
+                .iter()
+                .any(|package| same_dir(&package.root, root))
+            {
+                return Ok((workspace, current));
+            }
+        }
+    }
+    Ok((packages, current))
+}
+
+/// Determine whether two paths refer to the same directory.
+fn same_dir(a: &Path, b: &Path) -> bool {
+    match (std::fs::canonicalize(a), std::fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Select the packages to format.
+fn select(
+    args: &Args,
+    packages: Vec<Package>,
+    current: Option<PathBuf>,
+) -> Result<Vec<Package>, String> {
+    if let Some(unknown) = args
+        .packages
+        .iter()
+        .chain(&args.exclude)
+        .find(|name| !packages.iter().any(|package| &package.name == *name))
+    {
+        return Err(format!(
+            "package '{}' is not a member of the workspace",
+            unknown
+        ));
+    }
+    Ok(packages
+        .into_iter()
+        .filter(|package| {
+            if args.workspace {
+                !args.exclude.contains(&package.name)
+            } else if !args.packages.is_empty() {
+                args.packages.contains(&package.name)
+            } else {
+                current
+                    .as_ref()
+                    .is_none_or(|current| same_dir(&package.root, current))
+            }
+        })
+        .collect())
+}
+
+/// Describe an error along with the suggestions for recovering from it.
+fn describe(error: rust_format::Error) -> String {
+    let mut message = error.to_string();
+    for suggestion in error.suggestions() {
+        message.push_str("\nhelp: ");
+        message.push_str(&suggestion);
+    }
+    message
+}
+
+fn run() -> Result<i32, String> {
+    // Cargo passes the name of the subcommand as the first argument.
+    let mut args = std::env::args().skip(1).peekable();
+    if args.peek().map(String::as_str) == Some("rust-format") {
+        args.next();
+    }
+    let args: Vec<String> = args.collect();
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        print!("{}", USAGE);
+        return Ok(0);
+    }
+    let args = parse_args(args.into_iter())?;
+
+    let dir = match &args.manifest_path {
+        Some(path) => path.parent().unwrap_or(Path::new(".")).to_path_buf(),
+        None => std::env::current_dir().map_err(|error| error.to_string())?,
+    };
+    let (packages, current) = locate(&dir)?;
+    let packages = select(&args, packages, current)?;
+
+    let base = Options::new().verbosity(args.verbosity);
+    let options = BatchOptions::new().check(args.check).options(base.clone());
+    let results = format_packages_with(&packages, &options).map_err(describe)?;
+    eprint!("{}", results.render_with(&base));
+    Ok(results.exit_code(&ExitCodePolicy::default()))
+}
+
+fn main() {
+    match run() {
+        Ok(code) => std::process::exit(code),
+        Err(message) => {
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            std::process::exit(ExitCodePolicy::default().tool_error);
+        }
+    }
+}
@@ -0,0 +1,235 @@
+//! Normalization of the blank lines between top-level items.
+
+use crate::lexer::{tokenize, Token, TokenKind};
+
+/// The kind of a top-level item, as far as it matters for the spacing between items.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum ItemKind {
+    /// A `use` declaration.
+    Use,
+    /// An `extern crate` declaration.
+    ExternCrate,
+    /// A `mod` declaration or module.
+    Mod,
+    /// A function.
+    Fn,
+    /// A struct, enum or union.
+    Type,
+    /// A type alias.
+    TypeAlias,
+    /// A trait or trait alias.
+    Trait,
+    /// An `impl` block.
+    Impl,
+    /// A constant or static.
+    Const,
+    /// A macro definition or invocation.
+    Macro,
+    /// Any other item, e.g. an `extern` block.
+    Other,
+}
+
+/// The policy for the number of blank lines between top-level items.
+///
+/// The formatting tool preserves blank lines between items (up to `blank_lines_upper_bound`), so
+/// the spacing of code that was concatenated from generated fragments is often inconsistent.
+/// Completely blank gaps between items are normalized after formatting; comments between items
+/// stay attached to the item that follows them.
+///
+/// # Examples
+///
+/// ```
+/// use rust_format::{BlankLines, ItemKind, Options};
+///
+/// // Group `use` declarations, separate everything else by a blank line.
+/// fn spacing(previous: ItemKind, next: ItemKind) -> usize {
+///     match (previous, next) {
+///         (ItemKind::Use, ItemKind::Use) => 0,
+///         _ => 1,
+///     }
+/// }
+///
+/// let options = Options::new().blank_lines(BlankLines::Custom(spacing));
+/// let formatted = rust_format::format_str_with("use a::b;\n\nuse c::d;\nfn main() {}", &options);
+/// assert_eq!("use a::b;\nuse c::d;\n\nfn main() {}\n", formatted.unwrap());
+/// ```
+#[derive(Debug, Copy, Clone, Default)]
+pub enum BlankLines {
+    /// Keep the blank lines as produced by the formatting tool.
+    #[default]
+    Preserve,
+    /// Put exactly this number of blank lines between items.
+    Exactly(usize),
+    /// Determine the number of blank lines from the kinds of the previous and the next item.
+    Custom(fn(ItemKind, ItemKind) -> usize),
+}
+
+impl BlankLines {
+    /// Apply the policy to formatted source code.
+    pub(crate) fn apply(self, input: String) -> String {
+        if let BlankLines::Preserve = self {
+            return input;
+        }
+        let source = input.as_str();
+
+        let tokens = tokenize(source);
+        let mut output = crate::pool::take_string(source.len());
+        let mut copied = 0;
+        let mut depth = 0usize;
+        let mut current: Option<ItemKind> = None;
+        for (index, token) in tokens.iter().enumerate() {
+            let text = token.text(source);
+            if token.kind == TokenKind::Whitespace || token.kind.is_comment() {
+                continue;
+            }
+            if current.is_none() {
+                current = Some(item_kind(source, &tokens[index..]));
+            }
+            if token.kind == TokenKind::Punct {
+                match text {
+                    "(" | "[" | "{" => depth += 1,
+                    ")" | "]" | "}" => depth = depth.saturating_sub(1),
+                    _ => {}
+                }
+            }
+            if depth != 0 || !matches!(text, ";" | "}") {
+                continue;
+            }
+            // A block that is followed by more code, e.g. the `;` of a constant, ends no item.
+            if text == "}"
+                && tokens.get(index + 1).is_some_and(|next| {
+                    next.kind != TokenKind::Whitespace && !next.kind.is_comment()
+                })
+            {
+                continue;
+            }
+            let previous = current.take().unwrap_or(ItemKind::Other);
+
+            // A comment on the same line belongs to the item that ends here.
+            let mut end = index;
+            if let (Some(space), Some(comment)) = (tokens.get(index + 1), tokens.get(index + 2)) {
+                if space.kind == TokenKind::Whitespace
+                    && !space.text(source).contains('\n')
+                    && comment.kind.is_comment()
+                {
+                    end = index + 2;
+                }
+            }
+
+            // Only gaps that span several lines and are followed by another item (or the comments
+            // in front of it) are normalized.
+            let (gap, next) = match (tokens.get(end + 1), tokens.get(end + 2)) {
+                (Some(gap), Some(next))
+                    if gap.kind == TokenKind::Whitespace && gap.text(source).contains('\n') =>
+                {
+                    (gap, next)
+                }
+                _ => continue,
+            };
+            let blank_lines = match self {
+                BlankLines::Preserve => unreachable!("handled above"),
+                BlankLines::Exactly(count) => count,
+                BlankLines::Custom(spacing) => {
+                    spacing(previous, item_kind(source, &tokens[end + 2..]))
+                }
+            };
+            output.push_str(&source[copied..gap.start]);
+            output.extend(std::iter::repeat_n('\n', blank_lines + 1));
+            copied = next.start;
+        }
+        output.push_str(&source[copied..]);
+        // Whichever buffer is not returned goes back to the pool.
+        match output == source {
+            true => {
+                crate::pool::give_string(output);
+                input
+            }
+            false => {
+                crate::pool::give_string(input);
+                output
+            }
+        }
+    }
+
+    /// Add the policy to a fingerprint. Custom functions cannot be told apart by their content, so
+    /// they all share the same fingerprint.
+    pub(crate) fn fingerprint(self, fingerprint: &mut crate::options::Fingerprint) {
+        match self {
+            BlankLines::Preserve => fingerprint.value("blank_lines", "preserve"),
+            BlankLines::Exactly(count) => {
+                fingerprint.value("blank_lines", format_args!("exactly {}", count))
+            }
+            BlankLines::Custom(_) => fingerprint.value("blank_lines", "custom"),
+        }
+    }
+}
+
+/// Determine the kind of the item that starts with the provided tokens.
+fn item_kind(source: &str, tokens: &[Token]) -> ItemKind {
+    let mut depth = 0usize;
+    let mut words = tokens.iter().filter_map(|token| {
+        let text = token.text(source);
+        match (token.kind, text) {
+            // Skip attributes and the path of `pub(...)`.
+            (TokenKind::Punct, "(" | "[") => depth += 1,
+            (TokenKind::Punct, ")" | "]") => depth = depth.saturating_sub(1),
+            (TokenKind::Ident, _) if depth == 0 => return Some(text),
+            _ => {}
+        }
+        None
+    });
+    loop {
+        return match words.next() {
+            Some("pub" | "unsafe" | "async" | "default" | "safe") => continue,
+            Some("const") => match words.next() {
+                Some("fn" | "unsafe" | "async" | "extern") => ItemKind::Fn,
+                _ => ItemKind::Const,
+            },
+            Some("extern") => match words.next() {
+                Some("crate") => ItemKind::ExternCrate,
+                Some("fn") => ItemKind::Fn,
+                _ => ItemKind::Other,
+            },
+            Some("use") => ItemKind::Use,
+            Some("mod") => ItemKind::Mod,
+            Some("fn") => ItemKind::Fn,
+            Some("struct" | "enum" | "union") => ItemKind::Type,
+            Some("type") => ItemKind::TypeAlias,
+            Some("trait" | "auto") => ItemKind::Trait,
+            Some("impl") => ItemKind::Impl,
+            Some("static") => ItemKind::Const,
+            Some("macro_rules") => ItemKind::Macro,
+            Some(_) if is_macro_invocation(source, tokens) => ItemKind::Macro,
+            _ => ItemKind::Other,
+        };
+    }
+}
+
+/// Determine whether the item that starts with the provided tokens is a macro invocation.
+fn is_macro_invocation(source: &str, tokens: &[Token]) -> bool {
+    let mut depth = 0usize;
+    for token in tokens.iter().filter(|token| !token.kind.is_comment()) {
+        match token.text(source) {
+            "(" | "[" => depth += 1,
+            ")" | "]" => depth = depth.saturating_sub(1),
+            "!" if depth == 0 => return true,
+            "{" | ";" if depth == 0 => return false,
+            _ => {}
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod test {
+    use super::BlankLines;
+
+    #[test]
+    fn test_exactly() {
+        let source = "use a;\nuse b;\n\n\n// Comment\nstruct A {\n    a: u32,\n}\n\n\nfn f() {\n    g();\n\n    h();\n}\nfn g() {} // Trailing\nfn h() {}\n";
+        assert_eq!(
+            "use a;\n\nuse b;\n\n// Comment\nstruct A {\n    a: u32,\n}\n\nfn f() {\n    g();\n\n    h();\n}\n\nfn g() {} // Trailing\n\nfn h() {}\n",
+            BlankLines::Exactly(1).apply(source.to_string())
+        );
+    }
+}
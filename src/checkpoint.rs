@@ -0,0 +1,264 @@
+//! Checkpoints of batch runs, so that interrupted runs can be resumed.
+
+use crate::batch::{FileResult, Outcome, SkipReason};
+use crate::error::IoContext;
+use crate::report::DiffStat;
+use crate::Error;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// The first line of every checkpoint file.
+const HEADER: &str = "rust-format checkpoint 1\n";
+
+/// The result of a file that was completed before a checkpoint was opened.
+#[derive(Debug, Copy, Clone)]
+struct Completed {
+    outcome: Outcome,
+    elapsed: Duration,
+    diff_stat: Option<DiffStat>,
+}
+
+/// A file that records the progress of a batch run (see
+/// [`crate::batch::BatchOptions::resume_from`]).
+///
+/// Every file that is processed successfully is appended to the file as soon as its result is
+/// available, so that the progress survives an interruption of the run, e.g. by a crash or a
+/// signal. Files that fail are not recorded and are processed again on resumption. The results of
+/// completed files are restored from the checkpoint, without their children and their request.
+///
+/// The checkpoint does not record the options of the run; resume it with the same options only.
+/// Remove the file once the run is complete, or the next run skips all of its files.
+///
+/// # Examples
+///
+/// ```no_run
+/// use rust_format::batch::{format_files_with, BatchOptions, Checkpoint};
+///
+/// let checkpoint = Checkpoint::open("target/format.checkpoint")?;
+/// let options = BatchOptions::new().resume_from(checkpoint);
+/// let results = format_files_with(["src/a.rs", "src/b.rs"], &options);
+/// std::fs::remove_file("target/format.checkpoint")?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    path: PathBuf,
+    completed: Arc<HashMap<PathBuf, Completed>>,
+    file: Arc<Mutex<File>>,
+}
+
+impl Checkpoint {
+    /// Open a checkpoint file, creating it if it does not exist.
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: The path of the file.
+    ///
+    /// # Returns
+    ///
+    /// The checkpoint with the files that an earlier run completed, if the file exists. Returns
+    /// [`Error::InvalidCheckpoint`] if the file exists but is not a checkpoint.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, Error> {
+        let path = path.into();
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .at(&path)?;
+        let text = std::fs::read_to_string(&path).at(&path)?;
+        let completed = match text.strip_prefix(HEADER) {
+            Some(entries) => parse(entries),
+            None if text.is_empty() => {
+                file.write_all(HEADER.as_bytes()).at(&path)?;
+                HashMap::new()
+            }
+            None => return Err(Error::InvalidCheckpoint { path }),
+        };
+        Ok(Self {
+            path,
+            completed: Arc::new(completed),
+            file: Arc::new(Mutex::new(file)),
+        })
+    }
+
+    /// Retrieve the path of the checkpoint file.
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Retrieve the number of files that an earlier run completed.
+    pub fn len(&self) -> usize {
+        self.completed.len()
+    }
+
+    /// Determine whether no files were completed by an earlier run.
+    pub fn is_empty(&self) -> bool {
+        self.completed.is_empty()
+    }
+
+    /// Determine whether an earlier run completed a file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: The path of the file, as passed to the run.
+    pub fn contains(&self, path: &Path) -> bool {
+        self.completed.contains_key(path)
+    }
+
+    /// Restore the result of a file that an earlier run completed.
+    pub(crate) fn restore(&self, path: &Path) -> Option<FileResult> {
+        self.completed.get(path).map(|completed| FileResult {
+            path: path.to_path_buf(),
+            result: Ok(completed.outcome),
+            duplicate: None,
+            children: Vec::new(),
+            elapsed: completed.elapsed,
+            diff_stat: completed.diff_stat,
+            request_id: None,
+        })
+    }
+
+    /// Record the result of a file if it was processed successfully.
+    ///
+    /// Failures to write the file are ignored: the file is merely processed again on resumption.
+    /// Paths that are not valid UTF-8 or contain line breaks are not recorded either.
+    pub(crate) fn record(&self, result: &FileResult) {
+        let (Ok(outcome), Some(path)) = (&result.result, result.path.to_str()) else {
+            return;
+        };
+        if path.contains(['\n', '\r']) {
+            return;
+        }
+        let diff_stat = match result.diff_stat {
+            Some(stat) => format!(
+                "{},{},{}",
+                stat.files_changed, stat.insertions, stat.deletions
+            ),
+            None => "-".to_string(),
+        };
+        let line = format!(
+            "{}\t{}\t{}\t{}\n",
+            outcome_name(*outcome),
+            result.elapsed.as_micros(),
+            diff_stat,
+            path
+        );
+        let mut file = self.file.lock().expect("checkpoint lock poisoned");
+        let _ = file.write_all(line.as_bytes());
+    }
+}
+
+/// Retrieve the name of an outcome in a checkpoint file.
+pub(crate) fn outcome_name(outcome: Outcome) -> &'static str {
+    match outcome {
+        Outcome::Formatted => "formatted",
+        Outcome::Clean => "clean",
+        Outcome::Unformatted => "unformatted",
+        Outcome::Skipped(SkipReason::ReadOnly) => "skipped",
+        Outcome::Skipped(SkipReason::FileLevelSkip) => "skipped-file-level",
+    }
+}
+
+/// Parse the entries of a checkpoint file.
+///
+/// Lines that are incomplete, e.g. because the run was interrupted while writing them, are ignored.
+fn parse(entries: &str) -> HashMap<PathBuf, Completed> {
+    let mut completed = HashMap::new();
+    for line in entries.split_inclusive('\n') {
+        let Some(line) = line.strip_suffix('\n') else {
+            continue;
+        };
+        let mut fields = line.splitn(4, '\t');
+        let (Some(outcome), Some(elapsed), Some(diff_stat), Some(path)) =
+            (fields.next(), fields.next(), fields.next(), fields.next())
+        else {
+            continue;
+        };
+        let outcome = match outcome {
+            "formatted" => Outcome::Formatted,
+            "clean" => Outcome::Clean,
+            "unformatted" => Outcome::Unformatted,
+            "skipped" => Outcome::Skipped(SkipReason::ReadOnly),
+            "skipped-file-level" => Outcome::Skipped(SkipReason::FileLevelSkip),
+            _ => continue,
+        };
+        let Ok(elapsed) = elapsed.parse() else {
+            continue;
+        };
+        let diff_stat = match diff_stat {
+            "-" => None,
+            stat => {
+                let counts: Vec<usize> = stat.split(',').filter_map(|n| n.parse().ok()).collect();
+                let [files_changed, insertions, deletions] = counts[..] else {
+                    continue;
+                };
+                Some(DiffStat {
+                    files_changed,
+                    insertions,
+                    deletions,
+                })
+            }
+        };
+        completed.insert(
+            PathBuf::from(path),
+            Completed {
+                outcome,
+                elapsed: Duration::from_micros(elapsed),
+                diff_stat,
+            },
+        );
+    }
+    completed
+}
+
+#[cfg(test)]
+mod test {
+    use super::Checkpoint;
+    use crate::batch::{format_files_with, BatchOptions, Outcome};
+    use crate::Error;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_resume() {
+        let dir = PathBuf::from("target/checkpoint");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let (a, b, broken) = (dir.join("a.rs"), dir.join("b.rs"), dir.join("broken.rs"));
+        for path in [&a, &b] {
+            std::fs::write(path, "fn main(){}").unwrap();
+        }
+        std::fs::write(&broken, "fn main() {").unwrap();
+        let path = dir.join("run.checkpoint");
+
+        let checkpoint = Checkpoint::open(&path).unwrap();
+        assert!(checkpoint.is_empty());
+        let options = BatchOptions::new().resume_from(checkpoint);
+        let results = format_files_with([&a, &broken], &options);
+        assert!(results[1].result.is_err());
+
+        // The interrupted run left the first file unformatted again, which shows that it is
+        // skipped.
+        std::fs::write(&a, "fn main(){}").unwrap();
+        let checkpoint = Checkpoint::open(&path).unwrap();
+        assert_eq!(1, checkpoint.len());
+        assert!(checkpoint.contains(&a));
+        let options = BatchOptions::new().resume_from(checkpoint);
+        let results = format_files_with([&a, &b, &broken], &options);
+        assert!(matches!(results[0].result, Ok(Outcome::Formatted)));
+        assert_eq!(None, results[0].request_id);
+        assert!(results[1].request_id.is_some());
+        assert!(results[2].result.is_err());
+        assert_eq!("fn main(){}", std::fs::read_to_string(&a).unwrap());
+        assert_eq!("fn main() {}\n", std::fs::read_to_string(&b).unwrap());
+        assert_eq!(2, Checkpoint::open(&path).unwrap().len());
+
+        assert!(matches!(
+            Checkpoint::open(&a),
+            Err(Error::InvalidCheckpoint { .. })
+        ));
+    }
+}
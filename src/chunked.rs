@@ -0,0 +1,204 @@
+//! Formatting of enormous files in chunks of top-level items.
+
+use crate::error::IoContext;
+use crate::temp::write_atomic;
+use crate::{Error, Options};
+use std::path::Path;
+use syn::spanned::Spanned;
+
+/// Settings for formatting source code in chunks.
+///
+/// Formatting very large generated files can be extremely slow or exhaust memory. Chunked
+/// formatting splits the source code at top-level item boundaries, formats the chunks
+/// independently and stitches the results back together.
+///
+/// # Examples
+///
+/// ```
+/// use rust_format::{Chunking, Options};
+///
+/// let chunking = Chunking::new().lines_per_chunk(1);
+/// let formatted =
+///     rust_format::format_str_chunked("fn a(){}\n\nfn b(){}", &Options::new(), &chunking).unwrap();
+/// assert_eq!("fn a() {}\n\nfn b() {}\n", formatted);
+/// ```
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Chunking {
+    lines_per_chunk: usize,
+    parallel: bool,
+}
+
+impl Default for Chunking {
+    fn default() -> Self {
+        Self {
+            lines_per_chunk: 10_000,
+            parallel: true,
+        }
+    }
+}
+
+impl Chunking {
+    /// Create settings with default values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the number of lines after which a chunk is completed at the next item boundary. The
+    /// default is 10000.
+    ///
+    /// # Arguments
+    ///
+    /// * `lines_per_chunk`: The number of lines.
+    pub fn lines_per_chunk(mut self, lines_per_chunk: usize) -> Self {
+        self.lines_per_chunk = lines_per_chunk.max(1);
+        self
+    }
+
+    /// Set whether chunks are formatted in parallel. Enabled by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `parallel`: Whether to format in parallel.
+    pub fn parallel(mut self, parallel: bool) -> Self {
+        self.parallel = parallel;
+        self
+    }
+}
+
+/// Format Rust source code in chunks of top-level items.
+///
+/// The source code must be syntactically valid. Each chunk is formatted with
+/// [`crate::format_str_with`], so all verifications of the options apply per chunk. Comments
+/// between items stay with the following item and comments at the end of an item's last line stay
+/// with that item.
+///
+/// # Arguments
+///
+/// * `source`: The source code.
+/// * `options`: The options.
+/// * `chunking`: The chunk settings.
+pub fn format_str_chunked(
+    source: impl AsRef<str>,
+    options: &Options,
+    chunking: &Chunking,
+) -> Result<String, Error> {
+    let source = source.as_ref();
+    options.check_input_size(source.len() as u64)?;
+    let chunks = split(source, chunking.lines_per_chunk)?;
+
+    let formatted: Vec<Result<String, Error>> = if chunking.parallel && chunks.len() > 1 {
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = chunks
+                .iter()
+                .map(|chunk| scope.spawn(move || crate::format_str_with(chunk, options)))
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().expect("chunk formatting panicked"))
+                .collect()
+        })
+    } else {
+        chunks
+            .iter()
+            .map(|chunk| crate::format_str_with(chunk, options))
+            .collect()
+    };
+
+    let mut result = String::with_capacity(source.len());
+    let mut previous: Option<&str> = None;
+    for (chunk, formatted) in chunks.iter().zip(formatted) {
+        let formatted = formatted?;
+        // The tool drops leading blank lines, so the separation from the previous chunk is restored.
+        if let Some(previous) = previous {
+            let trailing = &previous[previous.trim_end().len()..];
+            let leading = &chunk[..chunk.len() - chunk.trim_start().len()];
+            if trailing.matches('\n').count() + leading.matches('\n').count() > 1 {
+                result.push('\n');
+            }
+        }
+        result.push_str(&formatted);
+        previous = Some(chunk);
+    }
+    Ok(result)
+}
+
+/// Format a Rust source file in place in chunks of top-level items.
+///
+/// See [`format_str_chunked`].
+///
+/// # Arguments
+///
+/// * `path`: The path to the target file.
+/// * `options`: The options.
+/// * `chunking`: The chunk settings.
+pub fn format_file_chunked(
+    path: impl AsRef<Path>,
+    options: &Options,
+    chunking: &Chunking,
+) -> Result<(), Error> {
+    let path = path.as_ref();
+    let source = std::fs::read_to_string(path).at(path)?;
+    let formatted = format_str_chunked(&source, options, chunking)?;
+    if formatted != source {
+        write_atomic(path, formatted)?;
+    }
+    Ok(())
+}
+
+/// Split source code into chunks of complete top-level items.
+fn split(source: &str, lines_per_chunk: usize) -> Result<Vec<&str>, Error> {
+    let file = syn::parse_file(source)?;
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    let mut lines = 0;
+    for item in &file.items {
+        let end = item_end(source, item.span().byte_range().end);
+        lines += source[start..end].matches('\n').count().max(1);
+        if lines >= lines_per_chunk {
+            chunks.push(&source[start..end]);
+            start = end;
+            lines = 0;
+        }
+    }
+    if start < source.len() {
+        chunks.push(&source[start..]);
+    }
+    Ok(chunks)
+}
+
+/// Extend the end of an item to the end of its line, if the rest of the line holds only a comment.
+fn item_end(source: &str, end: usize) -> usize {
+    let rest = &source[end..];
+    let line_end = rest
+        .find('\n')
+        .map_or(source.len(), |index| end + index + 1);
+    let line = source[end..line_end].trim();
+    match line.is_empty() || line.starts_with("//") {
+        true => line_end,
+        false => end,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{split, Chunking};
+    use crate::Options;
+
+    #[test]
+    fn test_format_str_chunked() {
+        let source = "#![allow(dead_code)]\n// a\nfn a(){} // end of a\n\n\n/// b\nstruct B{x:u32}\nfn c(){}\n";
+        assert_eq!(
+            vec![
+                "#![allow(dead_code)]\n// a\nfn a(){} // end of a\n",
+                "\n\n/// b\nstruct B{x:u32}\n",
+                "fn c(){}\n"
+            ],
+            split(source, 1).unwrap()
+        );
+
+        let formatted =
+            super::format_str_chunked(source, &Options::new(), &Chunking::new().lines_per_chunk(1))
+                .unwrap();
+        assert_eq!(crate::format_str(source).unwrap(), formatted);
+    }
+}
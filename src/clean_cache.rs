@@ -0,0 +1,283 @@
+//! Caching of sources that are known to be formatted, so that they skip the formatting tool.
+
+use crate::events::{EventKind, EventSink};
+use crate::{Error, Options};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// The default maximum number of sources that a [`CleanCache`] remembers.
+const DEFAULT_MAX_ENTRIES: usize = 4096;
+
+/// Statistics about the lookups of a [`CleanCache`].
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct CacheStats {
+    /// The number of sources that were found in the cache.
+    pub hits: u64,
+    /// The number of sources that were passed to the formatting tool.
+    pub misses: u64,
+}
+
+/// A cached source.
+#[derive(Debug, Clone)]
+enum Entry {
+    /// The source is formatted.
+    Clean,
+    /// The source is not formatted; this is the formatted code.
+    Formatted(String),
+}
+
+/// A cache of the sources that are already formatted with a set of options, for formatting many
+/// small sources in hot loops.
+///
+/// A source that was found to be formatted before is returned as it is, without starting the
+/// formatting tool and without allocating. Sources are identified by a 64-bit hash of their
+/// content, so the chance that a changed source is mistaken for a cached one is negligible.
+/// Sources that need formatting are only cached if [`CleanCache::cache_formatted`] is enabled.
+///
+/// The cache can be shared between threads. When it is full, all entries are discarded. With the
+/// `tracing` feature, every hit is logged at the trace level.
+///
+/// # Examples
+///
+/// ```
+/// use rust_format::{CleanCache, Options};
+/// use std::borrow::Cow;
+///
+/// let cache = CleanCache::new(Options::new());
+/// assert!(matches!(cache.format("fn main(){}").unwrap(), Cow::Owned(_)));
+/// assert!(matches!(cache.format("fn main() {}\n").unwrap(), Cow::Borrowed(_)));
+/// assert_eq!(1, cache.len());
+/// ```
+#[derive(Debug)]
+pub struct CleanCache {
+    options: Options,
+    max_entries: usize,
+    cache_formatted: bool,
+    entries: Mutex<HashMap<u64, Entry>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl CleanCache {
+    /// Create an empty cache.
+    ///
+    /// # Arguments
+    ///
+    /// * `options`: The options with which the sources are formatted.
+    pub fn new(options: Options) -> Self {
+        Self {
+            options,
+            max_entries: DEFAULT_MAX_ENTRIES,
+            cache_formatted: false,
+            entries: Mutex::new(HashMap::new()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Set the maximum number of sources that the cache remembers. Defaults to 4096.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_entries`: The maximum number of sources.
+    pub fn max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    /// Set whether the formatted code of sources that need formatting is cached as well. Disabled
+    /// by default.
+    ///
+    /// This serves check-then-fix workflows: a batch that shares the cache (see
+    /// [`crate::batch::BatchOptions::cache`]) computes the formatted code of every file while
+    /// checking it, and fixing the files afterwards only writes them instead of running the
+    /// formatting tool again.
+    ///
+    /// # Arguments
+    ///
+    /// * `cache_formatted`: Whether to cache the formatted code.
+    pub fn cache_formatted(mut self, cache_formatted: bool) -> Self {
+        self.cache_formatted = cache_formatted;
+        self
+    }
+
+    /// Format Rust source code, unless it is known to be formatted.
+    ///
+    /// # Arguments
+    ///
+    /// * `source`: The source code.
+    ///
+    /// # Returns
+    ///
+    /// The source itself if it is formatted, otherwise the formatted code.
+    pub fn format<'a>(&self, source: &'a str) -> Result<Cow<'a, str>, Error> {
+        let formatted =
+            self.get_or_format(key(source), source, self.options.get_events(), || {
+                crate::format_str_with(source, &self.options)
+            })?;
+        Ok(match formatted {
+            Some(formatted) => Cow::Owned(formatted),
+            None => Cow::Borrowed(source),
+        })
+    }
+
+    /// Format the source code of a file with the options of the file, unless the result is cached.
+    ///
+    /// The options of the cache do not apply; the [fingerprint](Options::fingerprint) of the
+    /// options of the file and the directory in which configuration files are discovered are part
+    /// of the key instead.
+    pub(crate) fn format_source(
+        &self,
+        source: &str,
+        dir: Option<&Path>,
+        options: &Options,
+    ) -> Result<String, Error> {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        (source, options.fingerprint()?, dir).hash(&mut hasher);
+        let formatted =
+            self.get_or_format(hasher.finish(), source, options.get_events(), || {
+                crate::format::format_source(source, dir, options)
+            })?;
+        Ok(formatted.unwrap_or_else(|| source.to_string()))
+    }
+
+    /// Look up the entry of a source, or format the source and cache the result.
+    ///
+    /// # Returns
+    ///
+    /// `None` if the source is formatted, otherwise the formatted code.
+    fn get_or_format(
+        &self,
+        key: u64,
+        source: &str,
+        events: Option<&EventSink>,
+        format: impl FnOnce() -> Result<String, Error>,
+    ) -> Result<Option<String>, Error> {
+        let cached = self.lock().get(&key).cloned();
+        if let Some(entry) = cached {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            #[cfg(feature = "tracing")]
+            tracing::trace!(key, "cache hit");
+            if let Some(events) = events {
+                events.emit(EventKind::CacheHit { key });
+            }
+            return Ok(match entry {
+                Entry::Clean => None,
+                Entry::Formatted(formatted) => Some(formatted),
+            });
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let formatted = format()?;
+        let entry = match formatted == source {
+            true => Entry::Clean,
+            false if self.cache_formatted => Entry::Formatted(formatted.clone()),
+            false => return Ok(Some(formatted)),
+        };
+        let mut entries = self.lock();
+        if entries.len() >= self.max_entries {
+            entries.clear();
+        }
+        entries.insert(key, entry);
+        Ok((formatted != source).then_some(formatted))
+    }
+
+    /// Retrieve the number of hits and misses since the cache was created.
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Retrieve the number of sources that the cache remembers.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Determine whether the cache remembers no sources.
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// Discard all entries, e.g. after the formatting tool was updated.
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<u64, Entry>> {
+        self.entries.lock().expect("cache lock poisoned")
+    }
+}
+
+/// Compute the key of a source.
+fn key(source: &str) -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    source.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CacheStats, CleanCache};
+    use crate::backend::{Call, Mock};
+    use crate::Options;
+    use std::borrow::Cow;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_clean_cache() {
+        let mock = Arc::new(Mock::new());
+        mock.respond(Ok("fn a() {}\n".to_string()));
+        let cache = CleanCache::new(Options::new().backend(mock.clone())).max_entries(2);
+
+        assert_eq!("fn a() {}\n", cache.format("fn a(){}").unwrap());
+        assert!(cache.is_empty());
+        for _ in 0..3 {
+            assert!(matches!(cache.format("fn b() {}\n"), Ok(Cow::Borrowed(_))));
+        }
+        let formats = |calls: Vec<Call>| {
+            calls
+                .iter()
+                .filter(|call| matches!(call, Call::FormatSource(_)))
+                .count()
+        };
+        assert_eq!(2, formats(mock.calls()));
+
+        cache.format("fn c() {}\n").unwrap();
+        cache.format("fn d() {}\n").unwrap();
+        assert_eq!(1, cache.len());
+        cache.format("fn b() {}\n").unwrap();
+        assert_eq!(5, formats(mock.calls()));
+        assert_eq!(CacheStats { hits: 2, misses: 5 }, cache.stats());
+    }
+
+    #[test]
+    fn test_check_then_fix() {
+        use crate::batch::{check_files_with, BatchOptions, Outcome};
+        use crate::file_system::MemoryFileSystem;
+
+        let file_system = Arc::new(MemoryFileSystem::new());
+        file_system.insert("gen/a.rs", "fn a(){}");
+        let mock = Arc::new(Mock::new());
+        mock.respond(Ok("fn a() {}\n".to_string()));
+        let cache = Arc::new(CleanCache::new(Options::new()).cache_formatted(true));
+        let options = BatchOptions::new()
+            .options(Options::new().backend(mock.clone()))
+            .file_system(file_system.clone())
+            .cache(cache.clone());
+
+        let results = check_files_with(["gen/a.rs"], &options);
+        assert_eq!(
+            vec![std::path::Path::new("gen/a.rs")],
+            results.unformatted_files()
+        );
+        let fixes = results.write_all_fixes();
+        assert_eq!(Outcome::Formatted, *fixes[0].result.as_ref().unwrap());
+        assert_eq!(Some(b"fn a() {}\n".to_vec()), file_system.get("gen/a.rs"));
+        assert_eq!(CacheStats { hits: 1, misses: 1 }, cache.stats());
+    }
+}
@@ -0,0 +1,84 @@
+//! Conversion of errors into compiler diagnostics for procedural macros.
+
+use crate::Error;
+use proc_macro2::{Delimiter, Group, Ident, Literal, Punct, Spacing, Span, TokenStream, TokenTree};
+
+impl Error {
+    /// Convert the error into a `compile_error!` invocation.
+    ///
+    /// Procedural macros that format their output can return the result instead of panicking, so
+    /// that the failure is reported as a regular compiler diagnostic. Use `.into()` to obtain a
+    /// `proc_macro::TokenStream`.
+    ///
+    /// # Arguments
+    ///
+    /// * `span`: The span to which the diagnostic is attached.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proc_macro2::Span;
+    /// use rust_format::Error;
+    ///
+    /// let tokens = Error::NoTargetPath.to_compile_error(Span::call_site());
+    /// assert!(tokens.to_string().contains("compile_error"));
+    /// ```
+    pub fn to_compile_error(&self, span: Span) -> TokenStream {
+        let mut message = Literal::string(&self.to_string());
+        message.set_span(span);
+        let tokens = [
+            punct(':', Spacing::Joint, span),
+            punct(':', Spacing::Alone, span),
+            TokenTree::Ident(Ident::new("core", span)),
+            punct(':', Spacing::Joint, span),
+            punct(':', Spacing::Alone, span),
+            TokenTree::Ident(Ident::new("compile_error", span)),
+            punct('!', Spacing::Alone, span),
+            group(Delimiter::Brace, TokenTree::Literal(message).into(), span),
+        ];
+        tokens.into_iter().collect()
+    }
+}
+
+/// Convert the error into a [`syn::Error`] at the call site.
+///
+/// Syntax errors retain their original span.
+#[cfg(feature = "syn")]
+impl From<Error> for syn::Error {
+    fn from(error: Error) -> Self {
+        match error {
+            Error::Syntax { source: error } => error,
+            other => syn::Error::new(Span::call_site(), other),
+        }
+    }
+}
+
+fn punct(ch: char, spacing: Spacing, span: Span) -> TokenTree {
+    let mut punct = Punct::new(ch, spacing);
+    punct.set_span(span);
+    TokenTree::Punct(punct)
+}
+
+fn group(delimiter: Delimiter, stream: TokenStream, span: Span) -> TokenTree {
+    let mut group = Group::new(delimiter, stream);
+    group.set_span(span);
+    TokenTree::Group(group)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Error;
+    use proc_macro2::Span;
+
+    #[test]
+    fn test_to_compile_error() {
+        let tokens = Error::ToolMissing { tool: "rustfmt" }.to_compile_error(Span::call_site());
+        assert_eq!(
+            format!(
+                ":: core :: compile_error ! {{ {:?} }}",
+                Error::ToolMissing { tool: "rustfmt" }.to_string()
+            ),
+            tokens.to_string()
+        );
+    }
+}
@@ -0,0 +1,118 @@
+//! Formatting of compressed sources.
+
+use crate::{Error, Options};
+use std::io::{Read, Write};
+
+/// A compression format.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Compression {
+    /// The gzip format.
+    #[cfg(feature = "gzip")]
+    Gzip,
+    /// The zstd format.
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+impl Compression {
+    /// Decompress source code from a reader, honoring the maximum input size.
+    fn decompress(self, reader: impl Read, options: &Options) -> Result<String, Error> {
+        match self {
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => crate::source::read(flate2::read::GzDecoder::new(reader), options),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => crate::source::read(zstd::Decoder::new(reader)?, options),
+        }
+    }
+
+    /// Compress data.
+    fn compress(self, data: &[u8]) -> Result<Vec<u8>, Error> {
+        match self {
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(data)?;
+                Ok(encoder.finish()?)
+            }
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => {
+                let mut encoder = zstd::Encoder::new(Vec::new(), 0)?;
+                encoder.write_all(data)?;
+                Ok(encoder.finish()?)
+            }
+        }
+    }
+}
+
+/// Format compressed Rust source code.
+///
+/// The source code is formatted with the options of the [global formatter](crate::set_global).
+///
+/// # Arguments
+///
+/// * `reader`: The reader that provides the compressed source code.
+/// * `compression`: The compression format of the input and the output.
+///
+/// # Returns
+///
+/// The formatted source code, compressed with the same format.
+pub fn format_compressed(reader: impl Read, compression: Compression) -> Result<Vec<u8>, Error> {
+    format_compressed_with(reader, compression, crate::global().options())
+}
+
+/// Format compressed Rust source code using the provided options.
+///
+/// The source code is decompressed in memory, so no temporary files are needed. The maximum input
+/// size (see [`Options::max_input_bytes`]) applies to the decompressed source code.
+///
+/// # Arguments
+///
+/// * `reader`: The reader that provides the compressed source code.
+/// * `compression`: The compression format of the input and the output.
+/// * `options`: The options.
+///
+/// # Returns
+///
+/// The formatted source code, compressed with the same format.
+pub fn format_compressed_with(
+    reader: impl Read,
+    compression: Compression,
+    options: &Options,
+) -> Result<Vec<u8>, Error> {
+    let original = compression.decompress(reader, options)?;
+    let formatted = crate::format::format_source(&original, None, options)?;
+    compression.compress(formatted.as_bytes())
+}
+
+#[cfg(test)]
+mod test {
+    use super::Compression;
+    use std::io::Read;
+
+    fn roundtrip(compression: Compression) {
+        let compressed = compression.compress(b"fn main(){}").unwrap();
+        let formatted = super::format_compressed(compressed.as_slice(), compression).unwrap();
+        let mut decompressed = String::new();
+        match compression {
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => flate2::read::GzDecoder::new(formatted.as_slice())
+                .read_to_string(&mut decompressed)
+                .unwrap(),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => zstd::Decoder::new(formatted.as_slice())
+                .unwrap()
+                .read_to_string(&mut decompressed)
+                .unwrap(),
+        };
+        assert_eq!("fn main() {}\n", decompressed);
+    }
+
+    #[test]
+    fn test_format_compressed() {
+        #[cfg(feature = "gzip")]
+        roundtrip(Compression::Gzip);
+        #[cfg(feature = "zstd")]
+        roundtrip(Compression::Zstd);
+    }
+}
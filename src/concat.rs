@@ -0,0 +1,227 @@
+//! Composition of a single file from several generated fragments.
+
+use crate::error::IoContext;
+use crate::lexer::{inner_attribute_end, tokenize, Token, TokenKind};
+use crate::source::Sendable;
+use crate::{Error, Options, Source};
+use std::collections::HashSet;
+
+/// The policy for joining the fragments of [`concat_and_format`].
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub enum SeparatorPolicy {
+    /// Separate the fragments by exactly one blank line and keep their content as it is.
+    #[default]
+    BlankLine,
+    /// Separate the fragments by exactly one blank line, move the file-level attributes
+    /// (`#![...]`) of all fragments to the start of the file and remove duplicates of them and of
+    /// top-level `use` declarations.
+    Merge,
+}
+
+/// Join several fragments of source code into a single file and format it.
+///
+/// The fragments are joined with the options of the [global formatter](crate::set_global). See
+/// [`concat_and_format_with`].
+///
+/// # Arguments
+///
+/// * `parts`: The fragments.
+/// * `separator`: The policy for joining the fragments.
+pub fn concat_and_format(
+    parts: impl IntoIterator<Item = Source>,
+    separator: SeparatorPolicy,
+) -> Result<String, Error> {
+    concat_and_format_with(parts, separator, crate::global().options())
+}
+
+/// Join several fragments of source code into a single file and format it, using the provided
+/// options.
+///
+/// This is the typical flow of assembling a file from templates. Leading and trailing blank lines
+/// of the fragments are removed, so that exactly one blank line separates them. File-level
+/// attributes and `use` declarations are compared by their tokens, regardless of whitespace;
+/// declarations with attributes are never removed.
+///
+/// # Arguments
+///
+/// * `parts`: The fragments.
+/// * `separator`: The policy for joining the fragments.
+/// * `options`: The options.
+///
+/// # Examples
+///
+/// ```
+/// use rust_format::{concat_and_format_with, Options, SeparatorPolicy, Source};
+///
+/// let parts = [
+///     Source::from("#![allow(dead_code)]\nuse std::fmt;\nstruct A;\n\n".to_string()),
+///     Source::from("#![allow(dead_code)]\nuse std::fmt;\nstruct B;".to_string()),
+/// ];
+/// let formatted = concat_and_format_with(parts, SeparatorPolicy::Merge, &Options::new())?;
+/// assert_eq!(
+///     "#![allow(dead_code)]\n\nuse std::fmt;\nstruct A;\n\nstruct B;\n",
+///     formatted
+/// );
+/// # Ok::<(), rust_format::Error>(())
+/// ```
+pub fn concat_and_format_with(
+    parts: impl IntoIterator<Item = Source>,
+    separator: SeparatorPolicy,
+    options: &Options,
+) -> Result<String, Error> {
+    let mut fragments = Vec::new();
+    for part in parts {
+        fragments.push(match part.into_sendable(options)? {
+            Sendable::Path(path) => std::fs::read_to_string(&path).at(&path)?,
+            Sendable::Text(text) => text,
+        });
+    }
+    if separator == SeparatorPolicy::Merge {
+        fragments = merge(fragments);
+    }
+    let joined = fragments
+        .iter()
+        .map(|fragment| fragment.trim_matches(['\n', '\r']).trim_end())
+        .filter(|fragment| !fragment.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    crate::format_str_with(joined, options)
+}
+
+/// Move the file-level attributes of the fragments to a fragment of their own at the start, and
+/// remove duplicate attributes and `use` declarations.
+fn merge(fragments: Vec<String>) -> Vec<String> {
+    let mut attributes = String::new();
+    let mut seen_attributes = HashSet::new();
+    let mut seen_imports = HashSet::new();
+    let mut merged = Vec::with_capacity(fragments.len() + 1);
+    for fragment in fragments {
+        let tokens: Vec<Token> = tokenize(&fragment)
+            .into_iter()
+            .filter(|token| token.kind != TokenKind::Whitespace)
+            .collect();
+        let mut removed = Vec::new();
+        let mut index = 0;
+        // File-level attributes can only be preceded by comments.
+        loop {
+            while tokens
+                .get(index)
+                .is_some_and(|token| token.kind.is_comment())
+            {
+                index += 1;
+            }
+            let Some(end) = inner_attribute_end(&fragment, &tokens, index) else {
+                break;
+            };
+            let (start, stop) = (tokens[index].start, tokens[end].end);
+            if seen_attributes.insert(normalize(&fragment, &tokens[index..=end])) {
+                attributes.push_str(&fragment[start..stop]);
+                attributes.push('\n');
+            }
+            let stop = match fragment[stop..].starts_with('\n') {
+                true => stop + 1,
+                false => stop,
+            };
+            removed.push((start, stop));
+            index = end + 1;
+        }
+
+        let mut depth = 0usize;
+        let mut previous: Option<&str> = None;
+        while index < tokens.len() {
+            let token = tokens[index];
+            if token.kind.is_comment() {
+                index += 1;
+                continue;
+            }
+            let text = token.text(&fragment);
+            let item_start = depth == 0 && matches!(previous, None | Some(";" | "}"));
+            match text {
+                "{" | "(" | "[" => depth += 1,
+                "}" | ")" | "]" => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            if item_start && token.kind == TokenKind::Ident && (text == "use" || text == "pub") {
+                if let Some(end) = use_end(&fragment, &tokens, index) {
+                    if !seen_imports.insert(normalize(&fragment, &tokens[index..=end])) {
+                        removed.push((token.start, tokens[end].end));
+                    }
+                    previous = Some(";");
+                    index = end + 1;
+                    continue;
+                }
+            }
+            previous = Some(text);
+            index += 1;
+        }
+
+        let mut kept = String::with_capacity(fragment.len());
+        let mut copied = 0;
+        for (start, stop) in removed {
+            kept.push_str(&fragment[copied..start]);
+            copied = stop;
+        }
+        kept.push_str(&fragment[copied..]);
+        merged.push(kept);
+    }
+    merged.insert(0, attributes);
+    merged
+}
+
+/// Determine the index of the terminating `;` of the `use` declaration that starts at a token,
+/// optionally with a visibility, if it is one.
+fn use_end(source: &str, tokens: &[Token], start: usize) -> Option<usize> {
+    let mut index = start;
+    if tokens[index].text(source) == "pub" {
+        index += 1;
+        if tokens.get(index)?.text(source) == "(" {
+            index = (index..tokens.len()).find(|&index| tokens[index].text(source) == ")")? + 1;
+        }
+    }
+    if tokens.get(index)?.text(source) != "use" {
+        return None;
+    }
+    (index..tokens.len()).find(|&index| tokens[index].text(source) == ";")
+}
+
+/// Join the text of tokens, ignoring comments, for comparing code regardless of whitespace.
+fn normalize(source: &str, tokens: &[Token]) -> String {
+    tokens
+        .iter()
+        .filter(|token| !token.kind.is_comment())
+        .map(|token| token.text(source))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod test {
+    use super::SeparatorPolicy;
+    use crate::{Options, Source};
+
+    #[test]
+    fn test_concat_and_format() {
+        let parts = |parts: [&str; 4]| parts.map(|part| Source::from(part.to_string()));
+
+        assert_eq!(
+            "fn a() {}\n\nfn b() {}\n\nuse x;\n",
+            super::concat_and_format_with(
+                parts(["fn a(){}\n\n\n", "\n\nfn b() {}", "", "use x;"]),
+                SeparatorPolicy::BlankLine,
+                &Options::new()
+            )
+            .unwrap()
+        );
+        let parts = parts([
+            "//! Generated.\n#![allow(unused)]\n\nuse a::b;\nfn f() {\n    use a::b;\n}\n\n\n",
+            "\n#![allow( unused )]\n#![deny(missing_docs)]\nuse a :: b;\n#[cfg(test)]\nuse a::b;\npub use c::D;\nfn g(){}",
+            "",
+            "pub use c::D;\nconst X: u8 = 1;",
+        ]);
+        assert_eq!(
+            "#![allow(unused)]\n#![deny(missing_docs)]\n\n//! Generated.\n\nuse a::b;\nfn f() {\n    \
+             use a::b;\n}\n\n#[cfg(test)]\nuse a::b;\npub use c::D;\nfn g() {}\n\nconst X: u8 = 1;\n",
+            super::concat_and_format_with(parts, SeparatorPolicy::Merge, &Options::new()).unwrap()
+        );
+    }
+}
@@ -0,0 +1,232 @@
+//! Configuration of the formatting tool.
+
+use std::collections::BTreeMap;
+
+/// Configuration settings that are passed to the formatting tool.
+///
+/// The settings correspond to the options of a `rustfmt.toml` file and are passed to the tool
+/// through its `--config` argument. Settings that are not covered by a dedicated method can be
+/// provided through [`Config::set`].
+///
+/// # Examples
+///
+/// ```
+/// use rust_format::Config;
+///
+/// let config = Config::new().max_width(120).set("hard_tabs", true);
+/// assert_eq!(Some("120"), config.get("max_width"));
+/// ```
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub struct Config {
+    entries: BTreeMap<String, String>,
+}
+
+impl Config {
+    /// Create an empty configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set a configuration setting.
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: The name of the setting, e.g. `max_width`.
+    /// * `value`: The value of the setting.
+    pub fn set(mut self, key: impl Into<String>, value: impl ToString) -> Self {
+        self.entries.insert(key.into(), value.to_string());
+        self
+    }
+
+    /// Retrieve the value of a configuration setting.
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: The name of the setting.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries.get(key).map(String::as_str)
+    }
+
+    /// Remove a configuration setting.
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: The name of the setting.
+    pub fn remove(mut self, key: &str) -> Self {
+        self.entries.remove(key);
+        self
+    }
+
+    /// Iterate over all settings in alphabetical order of their names.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    /// Determine whether the configuration contains no settings.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Combine this configuration with another one.
+    ///
+    /// Settings of `other` take precedence over the settings of this configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `other`: The overriding configuration.
+    pub fn merge(mut self, other: &Config) -> Self {
+        self.entries.extend(
+            other
+                .entries
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone())),
+        );
+        self
+    }
+
+    /// Set the maximum width of each line (`max_width`).
+    pub fn max_width(self, max_width: u32) -> Self {
+        self.set("max_width", max_width)
+    }
+
+    /// Set the number of spaces per indentation level (`tab_spaces`).
+    pub fn tab_spaces(self, tab_spaces: u32) -> Self {
+        self.set("tab_spaces", tab_spaces)
+    }
+
+    /// Set whether to indent with tabs instead of spaces (`hard_tabs`).
+    pub fn hard_tabs(self, hard_tabs: bool) -> Self {
+        self.set("hard_tabs", hard_tabs)
+    }
+
+    /// Set the width heuristics (`use_small_heuristics`), e.g. `Default`, `Off` or `Max`.
+    pub fn use_small_heuristics(self, value: &str) -> Self {
+        self.set("use_small_heuristics", value)
+    }
+
+    /// Set the layout of function parameters (`fn_params_layout`), e.g. `Tall`, `Compressed` or
+    /// `Vertical`.
+    pub fn fn_params_layout(self, value: &str) -> Self {
+        self.set("fn_params_layout", value)
+    }
+
+    /// Set whether to put single-expression functions on a single line (`fn_single_line`).
+    ///
+    /// This setting is only supported by nightly versions of the formatting tool.
+    pub fn fn_single_line(self, fn_single_line: bool) -> Self {
+        self.set("fn_single_line", fn_single_line)
+    }
+
+    /// Set whether to format the matchers of `macro_rules!` definitions
+    /// (`format_macro_matchers`).
+    ///
+    /// This setting is only supported by nightly versions of the formatting tool. See
+    /// [`crate::Options::degrade_unsupported_config`] for using it with any channel.
+    pub fn format_macro_matchers(self, format_macro_matchers: bool) -> Self {
+        self.set("format_macro_matchers", format_macro_matchers)
+    }
+
+    /// Set whether to format the bodies of `macro_rules!` definitions and other declarative
+    /// macros (`format_macro_bodies`).
+    ///
+    /// This setting is only supported by nightly versions of the formatting tool. See
+    /// [`crate::Options::degrade_unsupported_config`] for using it with any channel.
+    pub fn format_macro_bodies(self, format_macro_bodies: bool) -> Self {
+        self.set("format_macro_bodies", format_macro_bodies)
+    }
+
+    /// Set the layout of imports (`imports_layout`), e.g. `Mixed`, `Horizontal`,
+    /// `HorizontalVertical` or `Vertical`.
+    ///
+    /// This setting is only supported by nightly versions of the formatting tool.
+    pub fn imports_layout(self, value: &str) -> Self {
+        self.set("imports_layout", value)
+    }
+
+    /// Set whether to reorder import statements alphabetically (`reorder_imports`).
+    pub fn reorder_imports(self, reorder_imports: bool) -> Self {
+        self.set("reorder_imports", reorder_imports)
+    }
+
+    /// Create the value of the `--config` argument of the formatting tool, if there are any
+    /// settings.
+    pub(crate) fn to_arg(&self) -> Option<String> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        let pairs: Vec<String> = self
+            .entries
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect();
+        Some(pairs.join(","))
+    }
+}
+
+/// A named set of configuration settings for a consistent code style.
+///
+/// A profile only provides defaults: settings in [`crate::Options::config`] take precedence over
+/// the settings of the profile.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub enum Profile {
+    /// Dense code that puts as much on a single line as possible.
+    Compact,
+    /// The default style of the formatting tool.
+    #[default]
+    Default,
+    /// Spacious code that favors one element per line.
+    Expanded,
+}
+
+impl Profile {
+    /// Retrieve the configuration settings of the profile.
+    ///
+    /// Some settings are only supported by nightly versions of the formatting tool. Stable
+    /// versions ignore these with a warning.
+    pub fn config(self) -> Config {
+        match self {
+            Profile::Compact => Config::new()
+                .max_width(120)
+                .use_small_heuristics("Max")
+                .fn_params_layout("Compressed")
+                .fn_single_line(true)
+                .imports_layout("Horizontal")
+                .set("where_single_line", true),
+            Profile::Default => Config::new(),
+            Profile::Expanded => Config::new()
+                .max_width(100)
+                .use_small_heuristics("Off")
+                .fn_params_layout("Vertical")
+                .imports_layout("Vertical")
+                .set("struct_lit_single_line", false),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Config, Profile};
+
+    #[test]
+    fn test_profile_overrides() {
+        let config = Profile::Compact
+            .config()
+            .merge(&Config::new().max_width(80));
+
+        assert_eq!(Some("80"), config.get("max_width"));
+        assert_eq!(Some("Max"), config.get("use_small_heuristics"));
+        assert!(Profile::Default.config().to_arg().is_none());
+    }
+
+    #[test]
+    fn test_format_with_profile() {
+        let source = "fn f(a: u32, b: u32) -> u32 { a + b }";
+        let expanded = crate::Options::new().profile(Profile::Expanded);
+        assert_eq!(
+            "fn f(\n    a: u32,\n    b: u32,\n) -> u32 {\n    a + b\n}\n",
+            crate::format_str_with(source, &expanded).unwrap()
+        );
+    }
+}
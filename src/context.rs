@@ -0,0 +1,110 @@
+//! Source context for errors reported by the formatting tool.
+
+use std::fmt::{Display, Formatter};
+
+/// The number of lines shown before and after the reported line.
+const CONTEXT_LINES: usize = 5;
+
+/// An excerpt of the source code around a location reported by the formatting tool.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SourceContext {
+    /// The reported line (1-based).
+    pub line: usize,
+    /// The reported column (1-based).
+    pub column: usize,
+    /// The line number of the first line in `lines` (1-based).
+    pub first_line: usize,
+    /// The source lines around the reported line.
+    pub lines: Vec<String>,
+}
+
+impl SourceContext {
+    /// Create a context for the first location reported in the stderr output of the formatting
+    /// tool.
+    ///
+    /// # Arguments
+    ///
+    /// * `stderr`: The stderr output of the formatting tool.
+    /// * `source`: The source code that was passed to the formatting tool.
+    pub(crate) fn from_stderr(stderr: &str, source: &str) -> Option<Self> {
+        let (line, column) = stderr.lines().find_map(parse_location)?;
+        let first_line = line.saturating_sub(CONTEXT_LINES).max(1);
+        let lines: Vec<String> = source
+            .lines()
+            .skip(first_line - 1)
+            .take(line + CONTEXT_LINES + 1 - first_line)
+            .map(str::to_string)
+            .collect();
+        if lines.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            line,
+            column,
+            first_line,
+            lines,
+        })
+    }
+}
+
+impl Display for SourceContext {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let width = (self.first_line + self.lines.len()).to_string().len();
+        for (index, text) in self.lines.iter().enumerate() {
+            let number = self.first_line + index;
+            let marker = if number == self.line { '>' } else { ' ' };
+            writeln!(f, "{} {:>width$} | {}", marker, number, text, width = width)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse a location line of the form ` --> <file>:<line>:<column>`.
+fn parse_location(text: &str) -> Option<(usize, usize)> {
+    let location = text.trim_start().strip_prefix("--> ")?;
+    let mut parts = location.rsplitn(3, ':');
+    let column = parts.next()?.trim().parse().ok()?;
+    let line = parts.next()?.trim().parse().ok()?;
+    Some((line, column))
+}
+
+/// Attach source context to a tool execution error, if the location can be determined.
+///
+/// Other errors are returned unchanged.
+pub(crate) fn attach(error: crate::Error, source: &str) -> crate::Error {
+    let mut error = error;
+    if let crate::Error::ToolExecutionError {
+        stderr,
+        context: context @ None,
+        ..
+    } = &mut error
+    {
+        *context = match stderr {
+            crate::IoStream::Text(text) => SourceContext::from_stderr(text, source).map(Box::new),
+            crate::IoStream::InvalidUtf8 => None,
+        };
+    }
+    error
+}
+
+#[cfg(test)]
+mod test {
+    use super::SourceContext;
+
+    #[test]
+    fn test_from_stderr() {
+        let stderr = "error: expected expression, found `;`\n --> <stdin>:3:13\n  |\n";
+        let source = "fn a() {}\nfn main() {\n    let x = ;\n}\n";
+        let context = SourceContext::from_stderr(stderr, source).unwrap();
+
+        assert_eq!(3, context.line);
+        assert_eq!(13, context.column);
+        assert_eq!(1, context.first_line);
+        assert_eq!(4, context.lines.len());
+        assert_eq!(
+            "  1 | fn a() {}\n  2 | fn main() {\n> 3 |     let x = ;\n  4 | }\n",
+            context.to_string()
+        );
+    }
+}
@@ -0,0 +1,122 @@
+//! A unified representation of the places that formatting results can be written to.
+
+use crate::batch::Outcome;
+use crate::temp::write_atomic;
+use crate::{Error, Options, Source};
+use std::fmt::{Debug, Formatter};
+use std::io::Write;
+use std::path::PathBuf;
+
+/// The destination of a formatting result.
+///
+/// # Examples
+///
+/// ```
+/// use rust_format::batch::Outcome;
+/// use rust_format::{Destination, Options, Source};
+///
+/// let mut formatted = String::new();
+/// let source = Source::from("fn main(){}".to_string());
+/// rust_format::format_to(source, Destination::String(&mut formatted), &Options::new()).unwrap();
+/// assert_eq!("fn main() {}\n", formatted);
+///
+/// let source = Source::from("fn main() {}\n".to_string());
+/// let outcome = rust_format::format_to(source, Destination::Check, &Options::new()).unwrap();
+/// assert_eq!(Outcome::Clean, outcome);
+/// ```
+pub enum Destination<'a> {
+    /// The file that the source was read from. Only valid for [`Source::Path`]. Backups, audit
+    /// logging and restoration on failed verification apply as for [`crate::format_file_with`].
+    InPlace,
+    /// Another file, which is created or overwritten.
+    Path(PathBuf),
+    /// A string, which is replaced with the result.
+    String(&'a mut String),
+    /// A writer.
+    Writer(&'a mut dyn Write),
+    /// Nothing is written. The outcome tells whether the source is formatted correctly.
+    Check,
+    /// Nothing is written. This is useful to validate that the source can be formatted.
+    Discard,
+}
+
+impl Debug for Destination<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Destination::InPlace => f.write_str("InPlace"),
+            Destination::Path(path) => f.debug_tuple("Path").field(path).finish(),
+            Destination::String(_) => f.write_str("String"),
+            Destination::Writer(_) => f.write_str("Writer"),
+            Destination::Check => f.write_str("Check"),
+            Destination::Discard => f.write_str("Discard"),
+        }
+    }
+}
+
+/// Format a source and write the result to a destination.
+///
+/// # Arguments
+///
+/// * `source`: The source.
+/// * `destination`: The destination.
+/// * `options`: The options.
+///
+/// # Returns
+///
+/// [`Outcome::Clean`] or [`Outcome::Unformatted`] for [`Destination::Check`] and
+/// [`Outcome::Formatted`] otherwise.
+pub fn format_to(
+    source: Source,
+    destination: Destination<'_>,
+    options: &Options,
+) -> Result<Outcome, Error> {
+    if let Destination::InPlace = destination {
+        return match source {
+            Source::Path(path) => {
+                crate::format_file_with(path, options).map(|_| Outcome::Formatted)
+            }
+            _ => Err(Error::NoTargetPath),
+        };
+    }
+
+    let output = crate::format(source, options)?;
+    match destination {
+        Destination::InPlace => unreachable!("handled above"),
+        Destination::Path(path) => write_atomic(&path, output.text)?,
+        Destination::String(string) => *string = output.text,
+        Destination::Writer(writer) => writer.write_all(output.text.as_bytes())?,
+        Destination::Check if output.changed => return Ok(Outcome::Unformatted),
+        Destination::Check => return Ok(Outcome::Clean),
+        Destination::Discard => {}
+    }
+    Ok(Outcome::Formatted)
+}
+
+#[cfg(test)]
+mod test {
+    use super::Destination;
+    use crate::{Error, Options, Source};
+    use std::fs::read_to_string;
+
+    #[test]
+    fn test_format_to() {
+        let target = "target/destination_sample_code.rs";
+        let source = Source::from(std::path::PathBuf::from("resources/test/sample_code.rs"));
+        super::format_to(source, Destination::Path(target.into()), &Options::new()).unwrap();
+        assert_eq!(
+            read_to_string("resources/test/expected.rs").unwrap(),
+            read_to_string(target).unwrap()
+        );
+
+        let mut written = Vec::new();
+        let source = Source::from("fn main(){}".to_string());
+        super::format_to(source, Destination::Writer(&mut written), &Options::new()).unwrap();
+        assert_eq!(b"fn main() {}\n".to_vec(), written);
+
+        let source = Source::from("fn main(){}".to_string());
+        assert!(matches!(
+            super::format_to(source, Destination::InPlace, &Options::new()),
+            Err(Error::NoTargetPath)
+        ));
+    }
+}
@@ -0,0 +1,234 @@
+//! A sequence diff based on the Myers algorithm.
+
+/// The number of unchanged lines that surround each hunk of a unified diff.
+const CONTEXT_LINES: usize = 3;
+
+/// A single edit in a diff.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) enum Edit {
+    /// An item that is present in both sequences, at the given indices of the old and new
+    /// sequence.
+    Equal(usize, usize),
+    /// An item of the old sequence that is not present in the new sequence.
+    Delete(usize),
+    /// An item of the new sequence that is not present in the old sequence.
+    Insert(usize),
+}
+
+impl Edit {
+    fn shifted(self, offset: usize) -> Self {
+        match self {
+            Edit::Equal(old, new) => Edit::Equal(old + offset, new + offset),
+            Edit::Delete(old) => Edit::Delete(old + offset),
+            Edit::Insert(new) => Edit::Insert(new + offset),
+        }
+    }
+}
+
+/// Compute the shortest edit script that transforms `old` into `new`.
+pub(crate) fn diff<T: PartialEq>(old: &[T], new: &[T]) -> Vec<Edit> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut edits: Vec<Edit> = (0..prefix).map(|index| Edit::Equal(index, index)).collect();
+    edits.extend(
+        myers(
+            &old[prefix..old.len() - suffix],
+            &new[prefix..new.len() - suffix],
+        )
+        .into_iter()
+        .map(|edit| edit.shifted(prefix)),
+    );
+    edits.extend(
+        (0..suffix)
+            .map(|index| Edit::Equal(old.len() - suffix + index, new.len() - suffix + index)),
+    );
+    edits
+}
+
+fn myers<T: PartialEq>(old: &[T], new: &[T]) -> Vec<Edit> {
+    let n = old.len() as isize;
+    let m = new.len() as isize;
+    let max = n + m;
+    let offset = max + 1;
+    let mut v = vec![0isize; (2 * max + 3) as usize];
+    // Every entry holds the furthest reaching x for the diagonals -d..=d before step d.
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    'search: for d in 0..=max {
+        trace.push(v[(offset - d) as usize..=(offset + d) as usize].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let index = (offset + k) as usize;
+            let mut x = if k == -d || (k != d && v[index - 1] < v[index + 1]) {
+                v[index + 1]
+            } else {
+                v[index - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[index] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    let mut edits = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let (prev_x, prev_y) = if d == 0 {
+            (0, 0)
+        } else {
+            let get = |k: isize| v[(k + d) as usize];
+            let k = x - y;
+            let prev_k = if k == -d || (k != d && get(k - 1) < get(k + 1)) {
+                k + 1
+            } else {
+                k - 1
+            };
+            let prev_x = get(prev_k);
+            (prev_x, prev_x - prev_k)
+        };
+
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            edits.push(Edit::Equal(x as usize, y as usize));
+        }
+        if d > 0 {
+            if x == prev_x {
+                edits.push(Edit::Insert(prev_y as usize));
+            } else {
+                edits.push(Edit::Delete(prev_x as usize));
+            }
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+    edits.reverse();
+    edits
+}
+
+/// Render the hunks of a unified diff between two texts.
+pub(crate) fn unified_diff(old: &str, new: &str) -> String {
+    let old_lines: Vec<&str> = old.split_inclusive('\n').collect();
+    let new_lines: Vec<&str> = new.split_inclusive('\n').collect();
+    let edits = diff(&old_lines, &new_lines);
+
+    // The number of old and new lines before each edit.
+    let mut positions = Vec::with_capacity(edits.len() + 1);
+    let (mut old_pos, mut new_pos) = (0, 0);
+    for edit in &edits {
+        positions.push((old_pos, new_pos));
+        match edit {
+            Edit::Equal(..) => {
+                old_pos += 1;
+                new_pos += 1;
+            }
+            Edit::Delete(_) => old_pos += 1,
+            Edit::Insert(_) => new_pos += 1,
+        }
+    }
+    positions.push((old_pos, new_pos));
+
+    let changes: Vec<usize> = (0..edits.len())
+        .filter(|&index| !matches!(edits[index], Edit::Equal(..)))
+        .collect();
+    let mut output = String::new();
+    let mut remaining = changes.as_slice();
+    while let Some(&first) = remaining.first() {
+        let mut last = first;
+        let mut count = 1;
+        while let Some(&next) = remaining.get(count) {
+            if next - last - 1 > 2 * CONTEXT_LINES {
+                break;
+            }
+            last = next;
+            count += 1;
+        }
+        remaining = &remaining[count..];
+
+        let start = first.saturating_sub(CONTEXT_LINES);
+        let end = (last + 1 + CONTEXT_LINES).min(edits.len());
+        let (old_start, new_start) = positions[start];
+        let (old_end, new_end) = positions[end];
+        output.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(old_start, old_end - old_start),
+            hunk_range(new_start, new_end - new_start)
+        ));
+        for edit in &edits[start..end] {
+            let (prefix, line) = match *edit {
+                Edit::Equal(old, _) => (' ', old_lines[old]),
+                Edit::Delete(old) => ('-', old_lines[old]),
+                Edit::Insert(new) => ('+', new_lines[new]),
+            };
+            output.push(prefix);
+            output.push_str(line);
+            if !line.ends_with('\n') {
+                output.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+    }
+    output
+}
+
+/// Render the line range of a hunk, where `start` is the number of lines before the hunk.
+fn hunk_range(start: usize, count: usize) -> String {
+    match count {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, count),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{diff, Edit};
+
+    #[test]
+    fn test_diff() {
+        let old: Vec<char> = "abcabba".chars().collect();
+        let new: Vec<char> = "cbabac".chars().collect();
+        let edits = diff(&old, &new);
+
+        let mut rebuilt = Vec::new();
+        for edit in &edits {
+            match *edit {
+                Edit::Equal(a, b) => {
+                    assert_eq!(old[a], new[b]);
+                    rebuilt.push(new[b]);
+                }
+                Edit::Insert(b) => rebuilt.push(new[b]),
+                Edit::Delete(_) => {}
+            }
+        }
+        assert_eq!(new, rebuilt);
+        assert_eq!(
+            5,
+            edits
+                .iter()
+                .filter(|edit| !matches!(edit, Edit::Equal(..)))
+                .count()
+        );
+    }
+
+    #[test]
+    fn test_unified_diff() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+        let new = "a\nB\nc\nd\ne\nf\ng\nh\ni\nj";
+        assert_eq!(
+            "@@ -1,5 +1,5 @@\n a\n-b\n+B\n c\n d\n e\n@@ -7,4 +7,4 @@\n g\n h\n i\n-j\n+j\n\\ No newline at end of file\n",
+            super::unified_diff(old, new)
+        );
+    }
+}
@@ -0,0 +1,260 @@
+//! Comparison of the formatting results of two configurations or versions of the formatting tool,
+//! e.g. to quantify the churn of a toolchain upgrade.
+
+use crate::batch::BatchOptions;
+use crate::diff::Edit;
+use crate::{Error, Options, Source};
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+
+/// The difference between the formatting results of a file.
+#[derive(Debug)]
+pub enum Drift {
+    /// Both configurations produce the same output.
+    Unchanged,
+    /// The outputs differ.
+    Changed {
+        /// The number of lines of the baseline output that the candidate output does not contain.
+        removed: usize,
+        /// The number of lines of the candidate output that the baseline output does not contain.
+        added: usize,
+        /// The unified diff from the baseline output to the candidate output.
+        diff: String,
+    },
+    /// Formatting fails with at least one of the configurations.
+    Failed {
+        /// The error of the baseline, if any.
+        baseline: Option<Box<Error>>,
+        /// The error of the candidate, if any.
+        candidate: Option<Box<Error>>,
+    },
+}
+
+/// The comparison of a single file.
+#[derive(Debug)]
+pub struct FileDrift {
+    /// The path of the file.
+    pub path: PathBuf,
+    /// The difference between the results.
+    pub drift: Drift,
+}
+
+/// The differences between the formatting results of two configurations for a set of files.
+///
+/// The [`Display`] implementation renders a summary that is suitable for a changelog or a pull
+/// request description.
+#[derive(Debug)]
+pub struct DriftReport {
+    /// The version of the baseline backend, if it could be determined.
+    pub baseline_version: Option<String>,
+    /// The version of the candidate backend, if it could be determined.
+    pub candidate_version: Option<String>,
+    /// The comparisons of the files, in the order of the input paths.
+    pub files: Vec<FileDrift>,
+}
+
+impl DriftReport {
+    /// Iterate over the files whose outputs differ.
+    pub fn changed_files(&self) -> impl Iterator<Item = &FileDrift> {
+        self.files
+            .iter()
+            .filter(|file| matches!(file.drift, Drift::Changed { .. }))
+    }
+
+    /// Iterate over the files that could not be formatted with at least one of the configurations.
+    pub fn failed_files(&self) -> impl Iterator<Item = &FileDrift> {
+        self.files
+            .iter()
+            .filter(|file| matches!(file.drift, Drift::Failed { .. }))
+    }
+
+    /// Count the removed and added lines of all files.
+    ///
+    /// # Returns
+    ///
+    /// The number of removed lines and the number of added lines.
+    pub fn changed_lines(&self) -> (usize, usize) {
+        self.files
+            .iter()
+            .fold((0, 0), |(removed, added), file| match file.drift {
+                Drift::Changed {
+                    removed: file_removed,
+                    added: file_added,
+                    ..
+                } => (removed + file_removed, added + file_added),
+                _ => (removed, added),
+            })
+    }
+}
+
+impl Display for DriftReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let version = |version: &Option<String>| match version {
+            Some(version) => version.clone(),
+            None => "an unknown version".to_string(),
+        };
+        let (removed, added) = self.changed_lines();
+        writeln!(
+            f,
+            "Formatting drift from {} to {}: {} of {} file(s) changed (+{} -{} lines), {} failed.",
+            version(&self.baseline_version),
+            version(&self.candidate_version),
+            self.changed_files().count(),
+            self.files.len(),
+            added,
+            removed,
+            self.failed_files().count()
+        )?;
+        for file in &self.files {
+            let path = file.path.display();
+            match &file.drift {
+                Drift::Unchanged => {}
+                Drift::Changed { removed, added, .. } => {
+                    writeln!(f, "* {}: +{} -{}", path, added, removed)?
+                }
+                Drift::Failed {
+                    baseline,
+                    candidate,
+                } => match (baseline, candidate) {
+                    (Some(_), Some(error)) => {
+                        writeln!(f, "* {}: fails with both: {}", path, error)?
+                    }
+                    (Some(error), None) => {
+                        writeln!(f, "* {}: fails with the baseline: {}", path, error)?
+                    }
+                    (None, Some(error)) => {
+                        writeln!(f, "* {}: fails with the candidate: {}", path, error)?
+                    }
+                    (None, None) => {}
+                },
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Format a set of files with two configurations and compare the results.
+///
+/// The files are not modified. Either the configurations or the backends of the options can
+/// differ, e.g. two toolchains with different versions of the formatting tool (see
+/// [`Options::toolchain`]). The files are formatted in parallel.
+///
+/// # Arguments
+///
+/// * `paths`: The paths of the files.
+/// * `baseline`: The options of the current configuration.
+/// * `candidate`: The options of the configuration to evaluate.
+///
+/// # Examples
+///
+/// ```no_run
+/// use rust_format::drift::compare_backends;
+/// use rust_format::Options;
+///
+/// let report = compare_backends(
+///     ["src/lib.rs", "src/main.rs"],
+///     &Options::new(),
+///     &Options::new().toolchain("nightly"),
+/// );
+/// print!("{}", report);
+/// ```
+pub fn compare_backends<P: Into<PathBuf>>(
+    paths: impl IntoIterator<Item = P>,
+    baseline: &Options,
+    candidate: &Options,
+) -> DriftReport {
+    let paths: Vec<PathBuf> = paths.into_iter().map(Into::into).collect();
+    let format = |options: &Options| {
+        crate::batch::format_sources(
+            paths.iter().cloned().map(Source::Path),
+            &BatchOptions::new().options(options.clone()),
+        )
+    };
+    let files = paths
+        .iter()
+        .cloned()
+        .zip(format(baseline).into_iter().zip(format(candidate)))
+        .map(|(path, results)| {
+            let drift = match results {
+                (Ok(old), Ok(new)) if old.text == new.text => Drift::Unchanged,
+                (Ok(old), Ok(new)) => changed(&old.text, &new.text),
+                (old, new) => Drift::Failed {
+                    baseline: old.err().map(Box::new),
+                    candidate: new.err().map(Box::new),
+                },
+            };
+            FileDrift { path, drift }
+        })
+        .collect();
+    DriftReport {
+        baseline_version: baseline.get_backend().version().ok(),
+        candidate_version: candidate.get_backend().version().ok(),
+        files,
+    }
+}
+
+/// Describe the difference between two outputs.
+fn changed(old: &str, new: &str) -> Drift {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let (mut removed, mut added) = (0, 0);
+    for edit in crate::diff::diff(&old_lines, &new_lines) {
+        match edit {
+            Edit::Delete(_) => removed += 1,
+            Edit::Insert(_) => added += 1,
+            Edit::Equal(..) => {}
+        }
+    }
+    Drift::Changed {
+        removed,
+        added,
+        diff: crate::diff::unified_diff(old, new),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Drift;
+    use crate::{Config, Options};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_compare_backends() {
+        let dir = PathBuf::from("target/drift");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("short.rs"), "fn a() {}\n").unwrap();
+        std::fs::write(
+            dir.join("long.rs"),
+            "fn long(first: u32, second: u32) -> u32 { first + second }\n",
+        )
+        .unwrap();
+        let paths = [
+            dir.join("short.rs"),
+            dir.join("long.rs"),
+            dir.join("missing.rs"),
+        ];
+
+        let narrow = Options::new().config(Config::new().max_width(40));
+        let report = super::compare_backends(&paths, &Options::new(), &narrow);
+        assert!(matches!(report.files[0].drift, Drift::Unchanged));
+        assert!(matches!(
+            report.files[1].drift,
+            Drift::Changed {
+                removed: 1,
+                added: 4,
+                ..
+            }
+        ));
+        assert!(matches!(
+            report.files[2].drift,
+            Drift::Failed {
+                baseline: Some(_),
+                candidate: Some(_)
+            }
+        ));
+        let summary = report.to_string();
+        assert!(summary.contains(": 1 of 3 file(s) changed (+4 -1 lines), 1 failed.\n"));
+        assert!(summary.contains("long.rs: +4 -1\n"));
+    }
+}
@@ -0,0 +1,210 @@
+//! Formatting of Rust code that is embedded in other files, e.g. templates or documentation.
+
+use crate::{Error, Options};
+
+/// How the lines that open and close a region are recognized.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+enum Matching {
+    /// The line consists of the delimiter, apart from surrounding whitespace.
+    Line,
+    /// The line contains the marker anywhere, e.g. inside a comment of the surrounding language.
+    Contains,
+}
+
+/// The lines that enclose the Rust code in a file.
+///
+/// The lines that open and close a region are left untouched; only the lines between them are
+/// formatted. The code may be indented as a whole, by the indentation of the opening line.
+///
+/// # Examples
+///
+/// ```
+/// use rust_format::Region;
+///
+/// let fence = Region::delimited("```rust", "```");
+/// let template = Region::markers("{# rust #}", "{# endrust #}");
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Region {
+    start: String,
+    end: String,
+    matching: Matching,
+}
+
+impl Region {
+    /// Create a region that is enclosed by lines that consist of a delimiter, e.g. the code fences
+    /// of Markdown.
+    ///
+    /// # Arguments
+    ///
+    /// * `start`: The delimiter that opens the region.
+    /// * `end`: The delimiter that closes the region.
+    pub fn delimited(start: impl Into<String>, end: impl Into<String>) -> Self {
+        Self {
+            start: start.into(),
+            end: end.into(),
+            matching: Matching::Line,
+        }
+    }
+
+    /// Create a region that is enclosed by lines that contain a marker, e.g. in a comment of a
+    /// template language.
+    ///
+    /// # Arguments
+    ///
+    /// * `start`: The marker that opens the region.
+    /// * `end`: The marker that closes the region.
+    pub fn markers(start: impl Into<String>, end: impl Into<String>) -> Self {
+        Self {
+            start: start.into(),
+            end: end.into(),
+            matching: Matching::Contains,
+        }
+    }
+
+    /// Determine whether a line matches a delimiter or marker.
+    fn matches(&self, line: &str, delimiter: &str) -> bool {
+        match self.matching {
+            Matching::Line => line.trim() == delimiter,
+            Matching::Contains => line.contains(delimiter),
+        }
+    }
+}
+
+/// Format the Rust regions of a text.
+///
+/// The text is formatted with the options of the [global formatter](crate::set_global). See
+/// [`format_embedded_with`].
+///
+/// # Arguments
+///
+/// * `text`: The text.
+/// * `region`: The lines that enclose the Rust code.
+///
+/// # Examples
+///
+/// ```
+/// use rust_format::{format_embedded, Region};
+///
+/// let text = "# Usage\n```rust\nfn main(){}\n```\n";
+/// let formatted = format_embedded(text, Region::delimited("```rust", "```")).unwrap();
+/// assert_eq!("# Usage\n```rust\nfn main() {}\n```\n", formatted);
+/// ```
+pub fn format_embedded(text: impl AsRef<str>, region: Region) -> Result<String, Error> {
+    format_embedded_with(text, region, crate::global().options())
+}
+
+/// Format the Rust regions of a text using the provided options.
+///
+/// Every region is formatted on its own. The text outside of the regions, including the lines that
+/// open and close them, is left untouched, as are empty regions and a region that is not closed.
+///
+/// # Arguments
+///
+/// * `text`: The text.
+/// * `region`: The lines that enclose the Rust code.
+/// * `options`: The options.
+///
+/// # Returns
+///
+/// The text with formatted regions. If any region cannot be formatted, the error of the first of
+/// them is returned.
+pub fn format_embedded_with(
+    text: impl AsRef<str>,
+    region: Region,
+    options: &Options,
+) -> Result<String, Error> {
+    let text = text.as_ref();
+    let mut result = String::with_capacity(text.len());
+    // The indentation of the opening line and the code of the open region, if any.
+    let mut open: Option<(&str, String)> = None;
+    for line in text.split_inclusive('\n') {
+        match open.take() {
+            None => {
+                if region.matches(line, &region.start) {
+                    let indentation = &line[..line.len() - line.trim_start().len()];
+                    open = Some((indentation, String::new()));
+                }
+                result.push_str(line);
+            }
+            Some((indentation, code)) if region.matches(line, &region.end) => {
+                result.push_str(&format_region(&code, indentation, options)?);
+                result.push_str(line);
+            }
+            Some((indentation, mut code)) => {
+                code.push_str(line.strip_prefix(indentation).unwrap_or(line));
+                open = Some((indentation, code));
+            }
+        }
+    }
+    if let Some((indentation, code)) = open {
+        // Put back the code of a region that is not closed as it was.
+        for line in code.split_inclusive('\n') {
+            if !line.trim().is_empty() {
+                result.push_str(indentation);
+            }
+            result.push_str(line);
+        }
+    }
+    Ok(result)
+}
+
+/// Format the code of a region and indent it.
+fn format_region(code: &str, indentation: &str, options: &Options) -> Result<String, Error> {
+    if code.trim().is_empty() {
+        return Ok(code.to_string());
+    }
+    let formatted = crate::format_str_with(code, options)?;
+    let mut indented = String::with_capacity(formatted.len());
+    for line in formatted.split_inclusive('\n') {
+        if line != "\n" {
+            indented.push_str(indentation);
+        }
+        indented.push_str(line);
+    }
+    Ok(indented)
+}
+
+#[cfg(test)]
+mod test {
+    use super::Region;
+    use crate::Options;
+
+    #[test]
+    fn test_format_embedded() {
+        let text = concat!(
+            "<ul>\n",
+            "  {# rust #}\n",
+            "  fn item(){\n",
+            "      render();}\n",
+            "  {# endrust #}\n",
+            "</ul>\n",
+            "{# rust #}\n",
+            "\n",
+            "{# endrust #}\n",
+            "{# rust #}\n",
+            "fn unclosed(){}\n",
+        );
+        let region = Region::markers("{# rust #}", "{# endrust #}");
+        assert_eq!(
+            concat!(
+                "<ul>\n",
+                "  {# rust #}\n",
+                "  fn item() {\n",
+                "      render();\n",
+                "  }\n",
+                "  {# endrust #}\n",
+                "</ul>\n",
+                "{# rust #}\n",
+                "\n",
+                "{# endrust #}\n",
+                "{# rust #}\n",
+                "fn unclosed(){}\n",
+            ),
+            super::format_embedded_with(text, region.clone(), &Options::new()).unwrap()
+        );
+
+        let broken = "{# rust #}\nfn main(){\n{# endrust #}\n";
+        assert!(super::format_embedded_with(broken, region, &Options::new()).is_err());
+    }
+}
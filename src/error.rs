@@ -0,0 +1,574 @@
+//! The error type of the crate.
+
+use crate::{SourceContext, Violation};
+use std::fmt::Display;
+
+/// A textual I/O stream.
+#[derive(Debug)]
+pub enum IoStream {
+    /// The stream does not contain valid UTF-8.
+    InvalidUtf8,
+    /// The text.
+    Text(String),
+}
+
+impl Display for IoStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IoStream::InvalidUtf8 => f.write_str("(Invalid UTF-8)"),
+            IoStream::Text(string) => f.write_str(string),
+        }
+    }
+}
+
+impl From<Result<String, std::string::FromUtf8Error>> for IoStream {
+    fn from(result: Result<String, std::string::FromUtf8Error>) -> Self {
+        match result {
+            Ok(string) => IoStream::Text(string),
+            Err(_) => IoStream::InvalidUtf8,
+        }
+    }
+}
+
+/// A formatting error.
+///
+/// Every variant has a stable code, see [`Error::code`]. The type is non-exhaustive, so that new
+/// kinds of failures can be added without breaking matches. The underlying cause of an error, if
+/// any, is available through [`std::error::Error::source`].
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The 'rustfmt' tool is missing from the Rust toolchain.
+    #[error("Formatting tool '{tool}' not available on toolchain.")]
+    ToolMissing {
+        /// The name of the tool.
+        tool: &'static str,
+    },
+    /// The formatting tool cannot be run, as determined by [`crate::ensure_available`].
+    #[error("Formatting tool '{tool}' is not available. {hint}")]
+    ToolUnavailable {
+        /// The name of the tool.
+        tool: &'static str,
+        /// Instructions for installing the tool.
+        hint: String,
+    },
+    /// The formatting tool does not support a configuration setting (see
+    /// [`crate::Options::validate_config`]).
+    #[error(
+        "Configuration setting '{key}' is not supported by the formatting tool{}.",
+        .required_channel.as_ref().map(|channel| format!(" (requires the {} channel)", channel)).unwrap_or_default()
+    )]
+    UnsupportedOption {
+        /// The name of the setting.
+        key: String,
+        /// The channel of the tool that supports the setting, e.g. `nightly`, or `None` if the
+        /// setting is unknown.
+        required_channel: Option<String>,
+    },
+    /// The formatting tool terminated with a failure exit code.
+    #[error(
+        "Error executing formatting tool (code {code}{}).\nStdout:\n{stdout}\nStderr:{stderr}{}",
+        .request.map(|request| format!(", request {}", request)).unwrap_or_default(),
+        .context.as_ref().map(|context| format!("\nSource:\n{}", context)).unwrap_or_default()
+    )]
+    ToolExecutionError {
+        /// The backend that ran the tool, as a [specification](crate::backend::Registry::create),
+        /// e.g. `rustfmt:nightly`.
+        backend: String,
+        /// The file that the tool was run on, or `None` for source code on its standard input or a
+        /// run on multiple files.
+        path: Option<std::path::PathBuf>,
+        /// The exit code.
+        code: i32,
+        /// The stdout stream.
+        stdout: IoStream,
+        /// The stderr stream.
+        stderr: IoStream,
+        /// The source code around the location reported by the tool, if it could be determined.
+        context: Option<Box<SourceContext>>,
+        /// The operation during which the tool was run.
+        request: Option<crate::RequestId>,
+    },
+    /// No backup exists for the file.
+    #[error("No backup found for '{}'.", .path.display())]
+    NoBackup {
+        /// The path of the file.
+        path: std::path::PathBuf,
+    },
+    /// The file is read-only (see [`crate::ReadOnlyPolicy`]).
+    #[error("File '{}' is read-only.", .path.display())]
+    ReadOnly {
+        /// The path of the file.
+        path: std::path::PathBuf,
+    },
+    /// An I/O error occurred that is not related to a specific file.
+    #[error(transparent)]
+    IoError {
+        /// The underlying error.
+        #[from]
+        source: std::io::Error,
+    },
+    /// An I/O error occurred while accessing a file or directory.
+    #[error("Failed to access '{}'.", .path.display())]
+    FileAccess {
+        /// The path of the file or directory.
+        path: std::path::PathBuf,
+        /// The underlying error.
+        source: std::io::Error,
+    },
+    /// No result code was obtained. This can happen on Unix systems when the process is terminated
+    /// by a signal.
+    #[error("No result code received from formatting tool process.")]
+    NoResultCode {
+        /// The backend that ran the tool, as a [specification](crate::backend::Registry::create).
+        backend: String,
+        /// The file that the tool was run on, if it was run on a single file.
+        path: Option<std::path::PathBuf>,
+    },
+    /// The formatting tool crashed, e.g. due to a stack overflow.
+    #[error(
+        "Formatting tool crashed{}{}{}.\nStderr:{stderr}",
+        .signal.map(|signal| format!(" with signal {}", signal)).unwrap_or_default(),
+        if *.stack_overflow { " (stack overflow)" } else { "" },
+        .request.map(|request| format!(" in request {}", request)).unwrap_or_default()
+    )]
+    ToolCrashed {
+        /// The backend that ran the tool, as a [specification](crate::backend::Registry::create).
+        backend: String,
+        /// The file that the tool was run on, if it was run on a single file.
+        path: Option<std::path::PathBuf>,
+        /// The signal that terminated the process, if known.
+        signal: Option<i32>,
+        /// Whether the crash was caused by a stack overflow.
+        stack_overflow: bool,
+        /// The stderr stream.
+        stderr: IoStream,
+        /// The operation during which the tool was run.
+        request: Option<crate::RequestId>,
+    },
+    /// The input violates the configured [`crate::Limits`].
+    #[error("Input rejected: {violation}.")]
+    InputRejected {
+        /// The violated limit.
+        violation: Violation,
+    },
+    /// The formatting tool produced output that is not valid UTF-8.
+    #[error("Formatting tool produced invalid UTF-8 output.")]
+    InvalidUtf8Output {
+        /// The backend that ran the tool, as a [specification](crate::backend::Registry::create).
+        backend: String,
+        /// The file that the tool was run on, if it was run on a single file.
+        path: Option<std::path::PathBuf>,
+    },
+    /// The input is not valid UTF-8 (see [`crate::format_bytes`]).
+    #[error("Input is not valid UTF-8 at byte {offset}: invalid sequence {snippet}.")]
+    InvalidUtf8Input {
+        /// The offset of the first invalid byte.
+        offset: usize,
+        /// The invalid bytes in hexadecimal notation, e.g. `ff fe`.
+        snippet: String,
+    },
+    /// No backend of the name is registered (see [`crate::backend::Registry::create`]).
+    #[error("Unknown backend '{name}' (available: {}).", available.join(", "))]
+    UnknownBackend {
+        /// The name of the backend.
+        name: String,
+        /// The names of the registered backends.
+        available: Vec<String>,
+    },
+    /// No post-processor or metrics sink of the name is registered (see
+    /// [`crate::backend::Registry`]).
+    #[error("Unknown {kind} '{name}' (available: {}).", available.join(", "))]
+    UnknownPlugin {
+        /// The kind of the plugin, i.e. `post-processor` or `metrics sink`.
+        kind: &'static str,
+        /// The name of the plugin.
+        name: String,
+        /// The names of the registered plugins of the kind.
+        available: Vec<String>,
+    },
+    /// Git failed to list the changed files of a repository (see `git::changed_files`).
+    #[cfg(feature = "git")]
+    #[error(
+        "Git failed{}: {stderr}",
+        .code.map(|code| format!(" (code {})", code)).unwrap_or_default()
+    )]
+    GitFailed {
+        /// The exit code, or `None` if git was terminated by a signal.
+        code: Option<i32>,
+        /// The stderr output of git.
+        stderr: String,
+    },
+    /// Lines of the formatted code exceed the maximum width (see
+    /// [`crate::Options::reject_line_overflow`]).
+    #[error(
+        "{} line(s) exceed the maximum width of {} characters after formatting: line(s) {}.",
+        lines.len(),
+        lines.first().map_or(0, |line| line.max_width),
+        lines.iter().map(|line| line.line.to_string()).collect::<Vec<_>>().join(", ")
+    )]
+    LineOverflow {
+        /// The lines that exceed the maximum width.
+        lines: Vec<crate::LineOverflow>,
+    },
+    /// The input exceeds the configured maximum size.
+    #[error("Input of {size} bytes exceeds the maximum of {limit} bytes.")]
+    InputTooLarge {
+        /// The size of the input in bytes.
+        size: u64,
+        /// The maximum size in bytes.
+        limit: u64,
+    },
+    /// The output of the formatting tool exceeds the configured maximum size (see
+    /// [`crate::Options::max_stdout_bytes`]).
+    #[error("Output of {size} bytes exceeds the maximum of {limit} bytes.")]
+    OutputTooLarge {
+        /// The size of the output in bytes.
+        size: u64,
+        /// The maximum size in bytes.
+        limit: u64,
+    },
+    /// The source code is not syntactically valid Rust.
+    #[cfg(feature = "syn")]
+    #[error("Invalid Rust syntax: {source}")]
+    Syntax {
+        /// The parse error.
+        #[from]
+        source: syn::Error,
+    },
+    /// The destination requires a file source, but the source is not a file.
+    #[error("Formatting in place requires a file source.")]
+    NoTargetPath,
+    /// The formatted output does not compile, although the input does.
+    #[error("Formatted code does not compile:\n{diagnostics}")]
+    CompileCheckFailed {
+        /// The diagnostics of the compiler.
+        diagnostics: IoStream,
+    },
+    /// Comments of the input are missing from the formatted output.
+    #[error("Formatting lost {} comment(s): {missing:?}", missing.len())]
+    CommentsLost {
+        /// The missing comments, with normalized whitespace.
+        missing: Vec<String>,
+    },
+    /// String literals of the input are missing from the formatted output.
+    #[error("Formatting altered {} string literal(s): {missing:?}", missing.len())]
+    LiteralsAltered {
+        /// The missing literals, as they appear in the input.
+        missing: Vec<String>,
+    },
+    /// The file was modified by another process while it was being formatted (see
+    /// [`crate::ModificationPolicy::Refuse`]).
+    #[error("File '{}' was modified while it was being formatted.", .path.display())]
+    ConcurrentModification {
+        /// The path of the file.
+        path: std::path::PathBuf,
+    },
+    /// The declared tree of a [`crate::module_tree::ModuleTree`] is inconsistent, or does not match
+    /// the files in its directories.
+    #[error("Invalid module tree: {reason}.")]
+    InvalidModuleTree {
+        /// What is inconsistent.
+        reason: String,
+    },
+    /// A file is not a checkpoint of a batch run (see [`crate::batch::Checkpoint`]).
+    #[error("'{}' is not a checkpoint of a batch run.", .path.display())]
+    InvalidCheckpoint {
+        /// The path of the file.
+        path: std::path::PathBuf,
+    },
+    /// The formatting tool did not emit its result as requested with its `--emit` argument, e.g.
+    /// because its version handles the argument differently.
+    #[error(
+        "The formatting tool did not emit its result to {emit} as requested.\nStdout:\n{stdout}"
+    )]
+    UnexpectedEmitBehavior {
+        /// The backend that ran the tool, as a [specification](crate::backend::Registry::create).
+        backend: String,
+        /// The file that the tool was run on, if it was run on a single file.
+        path: Option<std::path::PathBuf>,
+        /// The requested destination, `stdout` or `files`.
+        emit: &'static str,
+        /// The stdout stream.
+        stdout: IoStream,
+    },
+    /// The formatting tool exited successfully, but left a file that is not formatted correctly
+    /// unchanged (see [`crate::Options::verify_applied`]).
+    #[error(
+        "The formatting tool reported success, but did not change '{}', which is not formatted \
+         correctly.",
+        .path.display()
+    )]
+    ChangesNotApplied {
+        /// The path of the file.
+        path: std::path::PathBuf,
+    },
+    /// Files of a check are not formatted correctly (see
+    /// [`crate::report::CheckResults::into_error_if_dirty`]).
+    #[error(
+        "{} file(s) are not formatted correctly: {}.",
+        files.len(),
+        files.iter().map(|path| path.display().to_string()).collect::<Vec<_>>().join(", ")
+    )]
+    Unformatted {
+        /// The files that are not formatted correctly.
+        files: Vec<std::path::PathBuf>,
+    },
+    /// The formatted output does not consist of the same tokens as the input.
+    #[error("Formatting altered the code at line {line}: expected '{expected}', found '{found}'.")]
+    SemanticDrift {
+        /// The line in the input at which the difference starts (1-based).
+        line: usize,
+        /// The token in the input.
+        expected: String,
+        /// The token in the output.
+        found: String,
+    },
+}
+
+/// Attach the path of a file to I/O errors.
+pub(crate) trait IoContext<T> {
+    /// Convert an I/O error into an [`Error::FileAccess`] for a path.
+    fn at(self, path: &std::path::Path) -> Result<T, Error>;
+}
+
+impl<T> IoContext<T> for std::io::Result<T> {
+    fn at(self, path: &std::path::Path) -> Result<T, Error> {
+        self.map_err(|source| Error::FileAccess {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+}
+
+impl Error {
+    /// Attach the backend that ran the formatting tool and the file that it was run on to the
+    /// errors of the tool. A file that is attached already is retained.
+    ///
+    /// # Arguments
+    ///
+    /// * `spec`: The specification of the backend.
+    /// * `file`: The file, if the tool was run on a single file.
+    #[cfg(feature = "process-backend")]
+    pub(crate) fn with_backend(mut self, spec: &str, file: Option<&std::path::Path>) -> Self {
+        if let Error::ToolExecutionError { backend, path, .. }
+        | Error::ToolCrashed { backend, path, .. }
+        | Error::NoResultCode { backend, path }
+        | Error::InvalidUtf8Output { backend, path }
+        | Error::UnexpectedEmitBehavior { backend, path, .. } = &mut self
+        {
+            *backend = spec.to_string();
+            if path.is_none() {
+                *path = file.map(std::path::Path::to_path_buf);
+            }
+        }
+        self
+    }
+
+    /// Retrieve the stable code of the error, e.g. `RF0001`.
+    ///
+    /// Unlike the message, the code never changes between releases, so frontends can use it to
+    /// look up localized messages or documentation. Codes of removed variants are not reused.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rust_format::Error;
+    ///
+    /// assert_eq!("RF0012", Error::NoTargetPath.code());
+    /// ```
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::ToolMissing { .. } => "RF0001",
+            Error::ToolExecutionError { .. } => "RF0002",
+            Error::NoBackup { .. } => "RF0003",
+            Error::ReadOnly { .. } => "RF0004",
+            Error::IoError { .. } => "RF0005",
+            Error::NoResultCode { .. } => "RF0006",
+            Error::ToolCrashed { .. } => "RF0007",
+            Error::InputRejected { .. } => "RF0008",
+            Error::InvalidUtf8Output { .. } => "RF0009",
+            Error::InputTooLarge { .. } => "RF0010",
+            #[cfg(feature = "syn")]
+            Error::Syntax { .. } => "RF0011",
+            Error::NoTargetPath => "RF0012",
+            Error::CompileCheckFailed { .. } => "RF0013",
+            Error::CommentsLost { .. } => "RF0014",
+            Error::LiteralsAltered { .. } => "RF0015",
+            Error::SemanticDrift { .. } => "RF0016",
+            Error::FileAccess { .. } => "RF0017",
+            Error::ConcurrentModification { .. } => "RF0018",
+            Error::OutputTooLarge { .. } => "RF0019",
+            Error::InvalidModuleTree { .. } => "RF0020",
+            Error::ToolUnavailable { .. } => "RF0021",
+            Error::UnsupportedOption { .. } => "RF0022",
+            Error::Unformatted { .. } => "RF0023",
+            Error::InvalidUtf8Input { .. } => "RF0024",
+            Error::UnknownBackend { .. } => "RF0025",
+            Error::LineOverflow { .. } => "RF0026",
+            Error::InvalidCheckpoint { .. } => "RF0027",
+            Error::UnexpectedEmitBehavior { .. } => "RF0028",
+            Error::ChangesNotApplied { .. } => "RF0029",
+            Error::UnknownPlugin { .. } => "RF0030",
+            #[cfg(feature = "git")]
+            Error::GitFailed { .. } => "RF0031",
+        }
+    }
+
+    /// Suggest how to recover from the error, e.g. for printing helpful next steps in command line
+    /// tools.
+    ///
+    /// # Returns
+    ///
+    /// The suggestions, each a sentence, in order of relevance. Errors without known remedies have
+    /// no suggestions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rust_format::Error;
+    ///
+    /// let error = Error::ToolMissing { tool: "rustfmt" };
+    /// assert!(error.suggestions()[0].contains("rustup component add rustfmt"));
+    /// ```
+    pub fn suggestions(&self) -> Vec<String> {
+        match self {
+            Error::ToolMissing { tool } => {
+                let toolchain = std::env::var("RUSTUP_TOOLCHAIN")
+                    .map(|toolchain| format!(" --toolchain {}", toolchain))
+                    .unwrap_or_default();
+                vec![format!(
+                    "Install the tool with 'rustup component add {}{}'.",
+                    tool, toolchain
+                )]
+            }
+            Error::ToolUnavailable { hint, .. } => vec![hint.clone()],
+            Error::UnsupportedOption {
+                key,
+                required_channel: Some(channel),
+            } => vec![
+                format!(
+                    "Use a {} toolchain for '{}', e.g. with 'Rustfmt::with_toolchain(\"{}\")'.",
+                    channel, key, channel
+                ),
+                "Enable 'Options::degrade_unsupported_config' to ignore the setting on other \
+                 channels."
+                    .to_string(),
+            ],
+            Error::UnsupportedOption {
+                key,
+                required_channel: None,
+            } => vec![format!(
+                "Check the spelling of '{}'; 'rust_format::rustfmt_config_schema' lists the \
+                 supported settings.",
+                key
+            )],
+            Error::ToolExecutionError { stderr, .. }
+                if ["Rust 2015", "Rust 2018 or later", "--edition"]
+                    .iter()
+                    .any(|hint| stderr.to_string().contains(hint)) =>
+            {
+                vec![
+                    "Set the edition of the code, e.g. with 'Options::edition(Edition::E2021)'."
+                        .to_string(),
+                ]
+            }
+            Error::ToolCrashed {
+                stack_overflow: true,
+                ..
+            } => vec![
+                "Split the input, e.g. with 'format_str_chunked', or reduce its nesting depth."
+                    .to_string(),
+            ],
+            Error::ReadOnly { .. } => vec![
+                "Make the file writable, or choose another 'ReadOnlyPolicy' to skip it or make it \
+                 writable."
+                    .to_string(),
+            ],
+            Error::InputTooLarge { .. } => vec![
+                "Raise the limit with 'Options::max_input_bytes', or split the input with \
+                 'format_str_chunked'."
+                    .to_string(),
+            ],
+            Error::OutputTooLarge { .. } => {
+                vec!["Raise the limit with 'Options::max_stdout_bytes'.".to_string()]
+            }
+            Error::LineOverflow { .. } => vec![
+                "Shorten the lines, e.g. by splitting long string literals, or raise 'max_width'."
+                    .to_string(),
+                "Disable 'Options::reject_line_overflow' to accept long lines.".to_string(),
+            ],
+            Error::ConcurrentModification { .. } => {
+                vec!["Retry once no other process modifies the file.".to_string()]
+            }
+            Error::InvalidCheckpoint { .. } => {
+                vec!["Remove the file or choose another path for the checkpoint.".to_string()]
+            }
+            Error::UnexpectedEmitBehavior { .. } => {
+                vec!["Update the formatting tool, or select another one with 'Rustfmt::with_toolchain'.".to_string()]
+            }
+            Error::ChangesNotApplied { .. } => vec![
+                "Make sure that no other process, e.g. an editor or a file synchronization tool, \
+                 restores the file."
+                    .to_string(),
+                "Let the crate write the file with 'Options::write_policy(WritePolicy::Replace)'."
+                    .to_string(),
+            ],
+            Error::Unformatted { .. } => {
+                vec!["Format the files, e.g. with 'CheckResults::write_all_fixes'.".to_string()]
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Error, IoStream};
+    #[cfg(feature = "process-backend")]
+    use std::path::Path;
+
+    #[test]
+    fn test_suggestions() {
+        let error = Error::ToolExecutionError {
+            backend: "rustfmt".to_string(),
+            path: None,
+            code: 1,
+            stdout: IoStream::Text(String::new()),
+            stderr: IoStream::Text("error: `async fn` is not permitted in Rust 2015".to_string()),
+            context: None,
+            request: None,
+        };
+        assert!(error.suggestions()[0].contains("Options::edition(Edition::E2021)"));
+        let error = Error::UnsupportedOption {
+            key: "imports_granularity".to_string(),
+            required_channel: Some("nightly".to_string()),
+        };
+        assert_eq!(2, error.suggestions().len());
+        assert!(Error::NoTargetPath.suggestions().is_empty());
+    }
+
+    #[cfg(feature = "process-backend")]
+    #[test]
+    fn test_with_backend() {
+        let error = Error::NoResultCode {
+            backend: "rustfmt".to_string(),
+            path: None,
+        }
+        .with_backend("rustfmt:nightly", Some(Path::new("src/lib.rs")));
+        assert!(matches!(
+            &error,
+            Error::NoResultCode { backend, path: Some(path) }
+                if backend == "rustfmt:nightly" && path == Path::new("src/lib.rs")
+        ));
+        let error = error.with_backend("rustfmt", Some(Path::new("src/main.rs")));
+        assert!(matches!(
+            error,
+            Error::NoResultCode { path: Some(path), .. } if path == Path::new("src/lib.rs")
+        ));
+        assert!(matches!(
+            Error::NoTargetPath.with_backend("rustfmt", Some(Path::new("src/lib.rs"))),
+            Error::NoTargetPath
+        ));
+    }
+}
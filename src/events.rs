@@ -0,0 +1,313 @@
+//! A machine-readable stream of the activity of batch runs and formatting queues.
+
+use crate::batch::Outcome;
+use crate::json::JsonObject;
+use crate::Error;
+use std::fmt::{Debug, Formatter};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+/// What happened.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum EventKind {
+    /// Formatting or checking a file started.
+    FileStarted {
+        /// The path of the file.
+        path: PathBuf,
+    },
+    /// Formatting or checking a file finished.
+    FileFinished {
+        /// The path of the file.
+        path: PathBuf,
+        /// The outcome, or `None` if the file failed.
+        outcome: Option<Outcome>,
+        /// The code of the error (see [`Error::code`]), if the file failed.
+        error_code: Option<&'static str>,
+        /// The message of the error, if the file failed.
+        error: Option<String>,
+        /// The time spent on the file.
+        elapsed: Duration,
+    },
+    /// A result was reused from a [`crate::CleanCache`] instead of running the formatting tool.
+    CacheHit {
+        /// The key of the cache entry.
+        key: u64,
+    },
+    /// The changes to a file were counted (see [`crate::batch::BatchOptions::diff_stats`]).
+    DiffComputed {
+        /// The path of the file.
+        path: PathBuf,
+        /// The number of inserted lines.
+        insertions: usize,
+        /// The number of deleted lines.
+        deletions: usize,
+    },
+}
+
+impl EventKind {
+    /// Retrieve the name of the kind, e.g. `file-started`, which is the `event` member of the JSON
+    /// encoding.
+    pub fn name(&self) -> &'static str {
+        match self {
+            EventKind::FileStarted { .. } => "file-started",
+            EventKind::FileFinished { .. } => "file-finished",
+            EventKind::CacheHit { .. } => "cache-hit",
+            EventKind::DiffComputed { .. } => "diff-computed",
+        }
+    }
+}
+
+/// An event of the activity of the crate.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Event {
+    /// What happened.
+    pub kind: EventKind,
+    /// The time of the event.
+    pub timestamp: SystemTime,
+}
+
+impl Event {
+    /// Encode the event as a single-line JSON object, with the name of its kind in the `event`
+    /// member, the time in the `timestamp` member and the data of the kind in further members.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rust_format::events::{Event, EventKind};
+    /// use std::time::SystemTime;
+    ///
+    /// let event = Event {
+    ///     kind: EventKind::CacheHit { key: 42 },
+    ///     timestamp: SystemTime::UNIX_EPOCH,
+    /// };
+    /// assert_eq!(
+    ///     r#"{"event":"cache-hit","timestamp":"1970-01-01T00:00:00.000Z","key":42}"#,
+    ///     event.to_json()
+    /// );
+    /// ```
+    pub fn to_json(&self) -> String {
+        let object = JsonObject::new()
+            .string("event", self.kind.name())
+            .string("timestamp", &crate::json::timestamp(self.timestamp));
+        let path = |path: &Path| path.to_string_lossy().into_owned();
+        match &self.kind {
+            EventKind::FileStarted { path: file } => object.string("path", &path(file)),
+            EventKind::FileFinished {
+                path: file,
+                outcome,
+                error_code,
+                error,
+                elapsed,
+            } => object
+                .string("path", &path(file))
+                .optional_string("outcome", outcome.map(crate::checkpoint::outcome_name))
+                .optional_string("error_code", *error_code)
+                .optional_string("error", error.as_deref())
+                .number("elapsed_ms", elapsed.as_millis() as u64),
+            EventKind::CacheHit { key } => object.number("key", *key),
+            EventKind::DiffComputed {
+                path: file,
+                insertions,
+                deletions,
+            } => object
+                .string("path", &path(file))
+                .number("insertions", *insertions as u64)
+                .number("deletions", *deletions as u64),
+        }
+        .build()
+    }
+}
+
+/// A receiver of events that aggregates them, e.g. into the counters and histograms of a
+/// monitoring system.
+///
+/// The trait is object safe, so that sinks can be registered by name in a
+/// [`crate::backend::Registry`] and chosen by configuration files. A sink is used through an
+/// [`EventSink`] (see [`EventSink::to_metrics_sink`]), which serializes the calls.
+///
+/// # Examples
+///
+/// ```
+/// use rust_format::events::{Event, EventKind, EventSink, MetricsSink};
+///
+/// #[derive(Default)]
+/// struct CacheHits(u64);
+///
+/// impl MetricsSink for CacheHits {
+///     fn record(&mut self, event: &Event) {
+///         if let EventKind::CacheHit { .. } = event.kind {
+///             self.0 += 1;
+///         }
+///     }
+/// }
+///
+/// let events = EventSink::to_metrics_sink(Box::new(CacheHits::default()));
+/// ```
+pub trait MetricsSink: Send {
+    /// Record an event.
+    ///
+    /// # Arguments
+    ///
+    /// * `event`: The event.
+    fn record(&mut self, event: &Event);
+}
+
+enum Sink {
+    Writer(Box<dyn Write + Send>),
+    Callback(Box<dyn FnMut(&Event) + Send>),
+    Metrics(Box<dyn MetricsSink>),
+}
+
+/// A sink for the events of batch runs, caches and formatting queues, e.g. for monitoring
+/// long-running formatting services with standard log tooling.
+///
+/// Writers and files receive one JSON object per line (see [`Event::to_json`]). Failures to write
+/// events are ignored, so that monitoring never fails formatting. The sink can be cloned cheaply;
+/// clones write to the same destination.
+///
+/// Events are emitted for the options that the sink is set on (see [`crate::Options::events`]):
+/// the base options of a batch run, the options of a cache, or the options of a
+/// [`crate::queue::FormatQueue`].
+///
+/// # Examples
+///
+/// ```no_run
+/// use rust_format::batch::{format_files_with, BatchOptions};
+/// use rust_format::events::EventSink;
+/// use rust_format::Options;
+///
+/// let events = EventSink::to_writer(std::io::stderr());
+/// let options = BatchOptions::new().options(Options::new().events(events));
+/// format_files_with(["src/lib.rs"], &options);
+/// ```
+#[derive(Clone)]
+pub struct EventSink {
+    sink: Arc<Mutex<Sink>>,
+}
+
+impl Debug for EventSink {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventSink").finish_non_exhaustive()
+    }
+}
+
+impl EventSink {
+    /// Create a sink that appends events as JSON lines to a file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: The path of the file. The file is created if it does not exist.
+    pub fn to_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        Ok(Self::to_writer(file))
+    }
+
+    /// Create a sink that writes events as JSON lines, e.g. to the standard error stream.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer`: The writer. Every line is flushed once it is written.
+    pub fn to_writer(writer: impl Write + Send + 'static) -> Self {
+        Self::with_sink(Sink::Writer(Box::new(writer)))
+    }
+
+    /// Create a sink that passes events to a callback.
+    ///
+    /// # Arguments
+    ///
+    /// * `callback`: The callback.
+    pub fn with_callback(callback: impl FnMut(&Event) + Send + 'static) -> Self {
+        Self::with_sink(Sink::Callback(Box::new(callback)))
+    }
+
+    /// Create a sink that passes events to a metrics sink.
+    ///
+    /// # Arguments
+    ///
+    /// * `sink`: The metrics sink.
+    pub fn to_metrics_sink(sink: Box<dyn MetricsSink>) -> Self {
+        Self::with_sink(Sink::Metrics(sink))
+    }
+
+    fn with_sink(sink: Sink) -> Self {
+        Self {
+            sink: Arc::new(Mutex::new(sink)),
+        }
+    }
+
+    /// Emit an event that happens now.
+    pub(crate) fn emit(&self, kind: EventKind) {
+        let event = Event {
+            kind,
+            timestamp: SystemTime::now(),
+        };
+        let mut sink = self.sink.lock().expect("event sink lock poisoned");
+        match &mut *sink {
+            Sink::Writer(writer) => {
+                let _ = writeln!(writer, "{}", event.to_json()).and_then(|_| writer.flush());
+            }
+            Sink::Callback(callback) => callback(&event),
+            Sink::Metrics(sink) => sink.record(&event),
+        }
+    }
+
+    /// Emit the event that formatting or checking a file finished.
+    pub(crate) fn file_finished(
+        &self,
+        path: &Path,
+        result: Result<Outcome, &Error>,
+        elapsed: Duration,
+    ) {
+        self.emit(EventKind::FileFinished {
+            path: path.to_path_buf(),
+            outcome: result.ok(),
+            error_code: result.err().map(Error::code),
+            error: result.err().map(ToString::to_string),
+            elapsed,
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::EventSink;
+    use crate::batch::{check_files_with, BatchOptions};
+    use crate::{CleanCache, Options};
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_events() {
+        let path = PathBuf::from("target/events.rs");
+        let log = PathBuf::from("target/events.jsonl");
+        std::fs::write(&path, "fn main(){}").unwrap();
+        let _ = std::fs::remove_file(&log);
+        let options = Options::new().events(EventSink::to_file(&log).unwrap());
+        let cache = Arc::new(CleanCache::new(options.clone()).cache_formatted(true));
+        let options = BatchOptions::new().options(options).cache(cache);
+        let _ = check_files_with([&path], &options);
+        let _ = check_files_with([&path], &options);
+
+        let events = std::fs::read_to_string(&log).unwrap();
+        let events: Vec<&str> = events.lines().collect();
+        let name = |line: &str| line.split('"').nth(3).unwrap_or_default().to_string();
+        assert_eq!(
+            vec![
+                "file-started",
+                "file-finished",
+                "file-started",
+                "cache-hit",
+                "file-finished"
+            ],
+            events.iter().map(|line| name(line)).collect::<Vec<_>>()
+        );
+        assert!(events[1].contains(r#""path":"target/events.rs","outcome":"unformatted""#));
+        assert!(events[1].contains(r#""error_code":null,"error":null,"elapsed_ms":"#));
+    }
+}
@@ -0,0 +1,227 @@
+//! File systems that batch operations and walks read and write files through.
+
+use crate::error::IoContext;
+use crate::Error;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Debug;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// The metadata of a file or directory.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct Metadata {
+    /// Whether the entry is a directory.
+    pub is_dir: bool,
+    /// The size of a file in bytes.
+    pub len: u64,
+    /// Whether the file is read-only.
+    pub read_only: bool,
+}
+
+/// A file system, e.g. the one of the operating system or an in-memory one for generated code.
+///
+/// Batch operations with a file system other than the default one (see
+/// [`crate::batch::BatchOptions::file_system`]) format files in memory: the content is read
+/// through the file system, passed to the backend and written back through the file system.
+pub trait FileSystem: Debug + Send + Sync {
+    /// Read the content of a file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: The path to the file.
+    fn read(&self, path: &Path) -> Result<Vec<u8>, Error>;
+
+    /// Replace the content of a file, creating it if it does not exist.
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: The path to the file.
+    /// * `contents`: The new content.
+    fn write(&self, path: &Path, contents: &[u8]) -> Result<(), Error>;
+
+    /// List the paths of the entries of a directory, which are used to walk directory trees.
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: The path to the directory.
+    fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>, Error>;
+
+    /// Retrieve the metadata of a file or directory, following symbolic links.
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: The path to the entry.
+    fn metadata(&self, path: &Path) -> Result<Metadata, Error>;
+}
+
+/// The file system of the operating system, which is the default.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct StdFileSystem;
+
+impl FileSystem for StdFileSystem {
+    fn read(&self, path: &Path) -> Result<Vec<u8>, Error> {
+        std::fs::read(path).at(path)
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> Result<(), Error> {
+        crate::temp::write_atomic(path, contents)
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>, Error> {
+        let mut entries = Vec::new();
+        for entry in std::fs::read_dir(path).at(path)? {
+            entries.push(entry.at(path)?.path());
+        }
+        Ok(entries)
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata, Error> {
+        let metadata = std::fs::metadata(path).at(path)?;
+        Ok(Metadata {
+            is_dir: metadata.is_dir(),
+            len: metadata.len(),
+            read_only: metadata.permissions().readonly(),
+        })
+    }
+}
+
+/// A file system that keeps files in memory. Directories exist implicitly when they contain files.
+///
+/// # Examples
+///
+/// ```
+/// use rust_format::file_system::{FileSystem, MemoryFileSystem};
+/// use std::path::Path;
+///
+/// let file_system = MemoryFileSystem::new();
+/// file_system.insert("gen/lib.rs", "fn main(){}");
+/// assert_eq!(vec![Path::new("gen/lib.rs")], file_system.read_dir("gen".as_ref()).unwrap());
+/// ```
+#[derive(Debug, Default)]
+pub struct MemoryFileSystem {
+    files: Mutex<BTreeMap<PathBuf, Vec<u8>>>,
+}
+
+impl MemoryFileSystem {
+    /// Create an empty file system.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a file, replacing any file at the same path.
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: The path to the file.
+    /// * `contents`: The content.
+    pub fn insert(&self, path: impl Into<PathBuf>, contents: impl Into<Vec<u8>>) {
+        self.lock().insert(path.into(), contents.into());
+    }
+
+    /// Retrieve the content of a file, if it exists.
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: The path to the file.
+    pub fn get(&self, path: impl AsRef<Path>) -> Option<Vec<u8>> {
+        self.lock().get(path.as_ref()).cloned()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<PathBuf, Vec<u8>>> {
+        self.files.lock().expect("file system lock poisoned")
+    }
+}
+
+/// Create the error for a missing entry.
+fn not_found(path: &Path) -> Error {
+    Error::FileAccess {
+        path: path.to_path_buf(),
+        source: std::io::ErrorKind::NotFound.into(),
+    }
+}
+
+impl FileSystem for MemoryFileSystem {
+    fn read(&self, path: &Path) -> Result<Vec<u8>, Error> {
+        self.get(path).ok_or_else(|| not_found(path))
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> Result<(), Error> {
+        self.insert(path, contents);
+        Ok(())
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>, Error> {
+        let entries: BTreeSet<PathBuf> = self
+            .lock()
+            .keys()
+            .filter_map(|file| file.strip_prefix(path).ok())
+            .filter_map(|relative| relative.components().next())
+            .map(|name| path.join(name))
+            .collect();
+        match entries.is_empty() {
+            true => Err(not_found(path)),
+            false => Ok(entries.into_iter().collect()),
+        }
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata, Error> {
+        let files = self.lock();
+        if let Some(contents) = files.get(path) {
+            return Ok(Metadata {
+                is_dir: false,
+                len: contents.len() as u64,
+                read_only: false,
+            });
+        }
+        match files.keys().any(|file| file.starts_with(path)) {
+            true => Ok(Metadata {
+                is_dir: true,
+                ..Metadata::default()
+            }),
+            false => Err(not_found(path)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{FileSystem, MemoryFileSystem};
+    use crate::batch::{BatchOptions, Outcome};
+    use std::path::Path;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_memory_file_system() {
+        let file_system = Arc::new(MemoryFileSystem::new());
+        file_system.insert("gen/a.rs", "fn a(){}");
+        file_system.insert("gen/nested/b.rs", "fn b() {}\n");
+        file_system.insert("gen/notes.md", "");
+        assert!(
+            file_system
+                .metadata(Path::new("gen/nested"))
+                .unwrap()
+                .is_dir
+        );
+        assert!(file_system.read(Path::new("gen/c.rs")).is_err());
+
+        let options = BatchOptions::new().file_system(file_system.clone());
+        #[cfg(feature = "walk")]
+        {
+            use std::path::PathBuf;
+
+            let results =
+                crate::batch::format_dir_with("gen", &options.clone().check(true)).unwrap();
+            let paths: Vec<PathBuf> = results.iter().map(|result| result.path.clone()).collect();
+            assert_eq!(
+                vec![PathBuf::from("gen/a.rs"), PathBuf::from("gen/nested/b.rs")],
+                paths
+            );
+            assert_eq!(Outcome::Unformatted, *results[0].result.as_ref().unwrap());
+            assert_eq!(Outcome::Clean, *results[1].result.as_ref().unwrap());
+        }
+
+        let results = crate::batch::format_files_with(["gen/a.rs"], &options);
+        assert_eq!(Outcome::Formatted, *results[0].result.as_ref().unwrap());
+        assert_eq!(Some(b"fn a() {}\n".to_vec()), file_system.get("gen/a.rs"));
+    }
+}
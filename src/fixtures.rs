@@ -0,0 +1,203 @@
+//! Formatting of test fixture pairs, as used with `include_str!` in the tests of macro crates.
+
+use crate::batch::Outcome;
+use crate::error::IoContext;
+use crate::format::{format_source, parent_dir, write_formatted};
+use crate::walk::Walk;
+use crate::{Error, Options};
+use std::path::{Path, PathBuf};
+
+/// The suffix of fixture files that contain the input of a test.
+const INPUT_SUFFIX: &str = ".rs.in";
+/// The suffix of fixture files that contain the expected output of a test.
+const EXPECTED_SUFFIX: &str = ".rs.expected";
+
+/// Options for formatting fixture directories.
+#[derive(Debug, Clone, Default)]
+pub struct FixtureOptions {
+    options: Options,
+    check: bool,
+}
+
+impl FixtureOptions {
+    /// Create a new instance with default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the options for formatting the fixtures.
+    ///
+    /// # Arguments
+    ///
+    /// * `options`: The options.
+    pub fn options(mut self, options: Options) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Set whether fixtures are only checked instead of formatted in place. Disabled by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `check`: Whether to only check the fixtures.
+    pub fn check(mut self, check: bool) -> Self {
+        self.check = check;
+        self
+    }
+}
+
+/// The result of formatting a pair of fixture files.
+#[derive(Debug)]
+pub struct PairResult {
+    /// The path of the input file (`*.rs.in`).
+    pub input: PathBuf,
+    /// The path of the expected output file (`*.rs.expected`).
+    pub expected: PathBuf,
+    /// The result. If formatting either file fails, neither file is modified.
+    pub result: Result<Outcome, Error>,
+}
+
+/// A fixture file whose counterpart is missing.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum Drift {
+    /// An input file without an expected output file.
+    MissingExpected(PathBuf),
+    /// An expected output file without an input file.
+    MissingInput(PathBuf),
+}
+
+/// The results of formatting a fixture directory.
+#[derive(Debug, Default)]
+pub struct FixtureReport {
+    /// The results of all complete pairs, ordered by the path of the input file.
+    pub pairs: Vec<PairResult>,
+    /// The fixture files without a counterpart, ordered by path. These are left untouched.
+    pub drift: Vec<Drift>,
+}
+
+/// Format the test fixtures below a directory.
+///
+/// Fixtures are pairs of an input file (`name.rs.in`) and an expected output file
+/// (`name.rs.expected`) next to each other. Both files of a pair are formatted together: they are
+/// only written if both can be formatted, so that a pair never ends up half formatted. In check
+/// mode, a pair is reported as [`Outcome::Unformatted`] if either file is not formatted correctly.
+/// Fixture files without a counterpart are reported as drift.
+///
+/// Hidden directories and temporary files of editors are skipped, as for
+/// [`crate::walk::Walk`].
+///
+/// # Arguments
+///
+/// * `dir`: The fixture directory.
+/// * `options`: The options.
+///
+/// # Examples
+///
+/// ```no_run
+/// use rust_format::fixtures::{format_fixtures, FixtureOptions};
+///
+/// let report = format_fixtures("tests/fixtures", FixtureOptions::new().check(true))?;
+/// for drift in &report.drift {
+///     eprintln!("incomplete fixture: {:?}", drift);
+/// }
+/// # Ok::<(), rust_format::Error>(())
+/// ```
+pub fn format_fixtures(
+    dir: impl AsRef<Path>,
+    options: FixtureOptions,
+) -> Result<FixtureReport, Error> {
+    let mut files = Walk::new(dir.as_ref()).files_matching(|path| {
+        let name = path.to_string_lossy();
+        name.ends_with(INPUT_SUFFIX) || name.ends_with(EXPECTED_SUFFIX)
+    })?;
+    files.sort();
+
+    let mut report = FixtureReport::default();
+    for path in &files {
+        let name = path.to_string_lossy();
+        if let Some(stem) = name.strip_suffix(INPUT_SUFFIX) {
+            let expected = PathBuf::from(format!("{}{}", stem, EXPECTED_SUFFIX));
+            if files.binary_search(&expected).is_ok() {
+                let result = format_pair(path, &expected, &options);
+                report.pairs.push(PairResult {
+                    input: path.clone(),
+                    expected,
+                    result,
+                });
+            } else {
+                report.drift.push(Drift::MissingExpected(path.clone()));
+            }
+        } else if let Some(stem) = name.strip_suffix(EXPECTED_SUFFIX) {
+            let input = PathBuf::from(format!("{}{}", stem, INPUT_SUFFIX));
+            if files.binary_search(&input).is_err() {
+                report.drift.push(Drift::MissingInput(path.clone()));
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// Format both files of a fixture pair, or neither.
+fn format_pair(input: &Path, expected: &Path, options: &FixtureOptions) -> Result<Outcome, Error> {
+    let mut contents = Vec::new();
+    for path in [input, expected] {
+        let original = std::fs::read_to_string(path).at(path)?;
+        let formatted = format_source(&original, Some(parent_dir(path)), &options.options)?;
+        contents.push((path, original, formatted));
+    }
+
+    if contents
+        .iter()
+        .all(|(_, original, formatted)| original == formatted)
+    {
+        return Ok(Outcome::Clean);
+    }
+    if options.check {
+        return Ok(Outcome::Unformatted);
+    }
+    for (path, original, formatted) in contents {
+        if original != formatted {
+            write_formatted(path, &original, &formatted, &options.options)?;
+        }
+    }
+    Ok(Outcome::Formatted)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Drift, FixtureOptions, Outcome};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_format_fixtures() {
+        let root = PathBuf::from("target/fixtures");
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("valid.rs.in"), "fn main(){}").unwrap();
+        std::fs::write(root.join("valid.rs.expected"), "fn main() {}\n").unwrap();
+        std::fs::write(root.join("broken.rs.in"), "fn main(){}").unwrap();
+        std::fs::write(root.join("broken.rs.expected"), "fn main() {").unwrap();
+        std::fs::write(root.join("orphan.rs.expected"), "").unwrap();
+
+        let report = super::format_fixtures(&root, FixtureOptions::new()).unwrap();
+
+        assert_eq!(2, report.pairs.len());
+        assert!(report.pairs[0].result.is_err());
+        assert_eq!(
+            "fn main(){}",
+            std::fs::read_to_string(root.join("broken.rs.in")).unwrap()
+        );
+        assert_eq!(
+            Outcome::Formatted,
+            *report.pairs[1].result.as_ref().unwrap()
+        );
+        assert_eq!(
+            "fn main() {}\n",
+            std::fs::read_to_string(root.join("valid.rs.in")).unwrap()
+        );
+        assert_eq!(
+            vec![Drift::MissingInput(root.join("orphan.rs.expected"))],
+            report.drift
+        );
+    }
+}
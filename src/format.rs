@@ -0,0 +1,844 @@
+//! Formatting of files and source code.
+
+use crate::error::IoContext;
+use crate::permissions::{make_writable, read_only};
+use crate::{
+    backup, context, global, verify, Error, IoStream, ModificationPolicy, Options, ReadOnlyPolicy,
+};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Format a Rust source file.
+///
+/// The file is formatted with the options of the [global formatter](crate::set_global).
+///
+/// # Arguments
+///
+/// * `path`: The path to the target file.
+///
+/// # Examples
+///
+/// ```
+/// pub fn format_lib() {
+///     use std::path::PathBuf;
+///     rust_format::format_file(PathBuf::from("lib.rs")).unwrap();
+/// }
+/// ```
+pub fn format_file(path: impl AsRef<Path>) -> Result<(), Error> {
+    global().format_file(path)
+}
+
+/// Format a Rust source file using the provided options.
+///
+/// Read-only files are handled according to [`Options::read_only`].
+///
+/// # Arguments
+///
+/// * `path`: The path to the target file.
+/// * `options`: The options.
+pub fn format_file_with(path: impl AsRef<Path>, options: &Options) -> Result<(), Error> {
+    format_file_reporting(path.as_ref(), options).map(|_| ())
+}
+
+/// Format a Rust source file using the provided options and return the paths of the files of its
+/// child modules that were modified along with it.
+pub(crate) fn format_file_reporting(path: &Path, options: &Options) -> Result<Vec<PathBuf>, Error> {
+    crate::request::scope(|_| format_file_reporting_in_scope(path, options))
+}
+
+fn format_file_reporting_in_scope(path: &Path, options: &Options) -> Result<Vec<PathBuf>, Error> {
+    options.check_config()?;
+    let permissions = match read_only(path)? {
+        Some(permissions) => permissions,
+        None => return format_writable_file(path, options),
+    };
+    match options.get_read_only() {
+        ReadOnlyPolicy::Error => Err(Error::ReadOnly {
+            path: path.to_path_buf(),
+        }),
+        ReadOnlyPolicy::Skip => Ok(Vec::new()),
+        ReadOnlyPolicy::MakeWritable => {
+            make_writable(path, &permissions)?;
+            let result = format_writable_file(path, options);
+            std::fs::set_permissions(path, permissions).at(path)?;
+            result
+        }
+    }
+}
+
+/// Format a Rust source file and return its previous content if formatting changed it.
+///
+/// The file is formatted with the options of the [global formatter](crate::set_global).
+///
+/// # Arguments
+///
+/// * `path`: The path to the target file.
+///
+/// # Returns
+///
+/// The content before formatting, or `None` if the file was already formatted correctly.
+pub fn format_file_returning_original(path: impl AsRef<Path>) -> Result<Option<String>, Error> {
+    global().format_file_returning_original(path)
+}
+
+/// Format a Rust source file using the provided options and return its previous content if
+/// formatting changed it.
+///
+/// This allows tooling to offer an undo or to detect changes without reading the file itself.
+///
+/// # Arguments
+///
+/// * `path`: The path to the target file.
+/// * `options`: The options.
+pub fn format_file_returning_original_with(
+    path: impl AsRef<Path>,
+    options: &Options,
+) -> Result<Option<String>, Error> {
+    let path = path.as_ref();
+    let original = std::fs::read_to_string(path).at(path)?;
+    format_file_with(path, options)?;
+    let formatted = std::fs::read_to_string(path).at(path)?;
+    Ok((formatted != original).then_some(original))
+}
+
+/// Format a Rust source file that is known to be writable.
+fn format_writable_file(path: &Path, options: &Options) -> Result<Vec<PathBuf>, Error> {
+    if options.get_max_input_bytes().is_some() {
+        options.check_input_size(std::fs::metadata(path).at(path)?.len())?;
+    }
+    let original = if options.requires_source() {
+        Some(crate::network::read_to_string(path, options)?)
+    } else {
+        None
+    };
+    if let Some(original) = &original {
+        options
+            .get_limits()
+            .check(original)
+            .map_err(|violation| Error::InputRejected { violation })?;
+    }
+    #[cfg(feature = "syn")]
+    if let (true, Some(original)) = (options.get_validate_syntax(), &original) {
+        crate::validate_syntax(original)?;
+    }
+    let in_memory = options.get_modification() == ModificationPolicy::Refuse
+        || options.get_write_policy().in_memory()
+        || options.get_hooks().is_enabled();
+    if let (true, Some(original)) = (in_memory, &original) {
+        let formatted = format_source(original, Some(parent_dir(path)), options)?;
+        if &formatted != original {
+            write_formatted(path, original, &formatted, options)?;
+        }
+        return Ok(Vec::new());
+    }
+
+    // A hash tells whether the tool changed the file, without keeping its content around.
+    let hash_before = match (options.get_verify_applied(), &original) {
+        (false, _) => None,
+        (true, Some(original)) => Some(crate::hash::sha256(original.as_bytes())),
+        (true, None) => Some(crate::hash::sha256(&std::fs::read(path).at(path)?)),
+    };
+    let backup = options.get_backup().create(path)?;
+    let children = options
+        .get_backend()
+        .format_path_with_children(path, options)
+        .map_err(|error| match &error {
+            Error::ToolExecutionError { .. } => match std::fs::read_to_string(path) {
+                Ok(source) => context::attach(error, &source),
+                Err(_) => error,
+            },
+            _ => error,
+        })?;
+    if options.requires_post_processing() {
+        let formatted = std::fs::read_to_string(path).at(path)?;
+        let processed = post_process(formatted.clone(), options)?;
+        if processed != formatted {
+            crate::network::write(path, processed.as_bytes(), options)?;
+        }
+    }
+    if let Some(hash_before) = hash_before {
+        verify_applied(path, hash_before, &children, options)?;
+    }
+    if let Some(backup) = backup {
+        backup::Backup::discard_if_unchanged(&backup, path)?;
+    }
+
+    if let Some(original) = original {
+        let formatted = std::fs::read_to_string(path).at(path)?;
+        if let Err(error) = verify::verify(options, &original, &formatted) {
+            // Do not leave a result behind that failed verification.
+            crate::network::write(path, original.as_bytes(), options)?;
+            return Err(error);
+        }
+        if let Some(log) = options.get_audit_log() {
+            log.record_change(path, original.as_bytes(), formatted.as_bytes(), options)?;
+        }
+    }
+    Ok(children)
+}
+
+/// Verify that a run of the formatting tool that left a file unchanged had nothing to change.
+fn verify_applied(
+    path: &Path,
+    hash_before: [u8; 32],
+    children: &[PathBuf],
+    options: &Options,
+) -> Result<(), Error> {
+    let hash_after = crate::hash::sha256(&std::fs::read(path).at(path)?);
+    // Changes of child modules may be why a check found the file not formatted correctly.
+    if hash_after != hash_before || !children.is_empty() {
+        return Ok(());
+    }
+    if options.expects_changes() || !check_file_in_scope(path, options)? {
+        return Err(Error::ChangesNotApplied {
+            path: path.to_path_buf(),
+        });
+    }
+    Ok(())
+}
+
+/// Check whether a Rust source file is formatted correctly.
+///
+/// The file is not modified. It is checked with the options of the
+/// [global formatter](crate::set_global).
+///
+/// # Arguments
+///
+/// * `path`: The path to the target file.
+///
+/// # Returns
+///
+/// `true` if the file is formatted correctly, `false` otherwise.
+pub fn check_file(path: impl AsRef<Path>) -> Result<bool, Error> {
+    global().check_file(path)
+}
+
+/// Check whether a Rust source file is formatted correctly using the provided options.
+///
+/// # Arguments
+///
+/// * `path`: The path to the target file.
+/// * `options`: The options.
+pub fn check_file_with(path: impl AsRef<Path>, options: &Options) -> Result<bool, Error> {
+    let path = path.as_ref();
+    crate::request::scope(|_| check_file_in_scope(path, options))
+}
+
+fn check_file_in_scope(path: &Path, options: &Options) -> Result<bool, Error> {
+    options.check_config()?;
+    if options.get_max_input_bytes().is_some() {
+        options.check_input_size(std::fs::metadata(path).at(path)?.len())?;
+    }
+
+    if options.requires_post_processing() {
+        // The formatting tool is not aware of the post-processing steps.
+        let source = std::fs::read_to_string(path).at(path)?;
+        return Ok(format_source(&source, Some(parent_dir(path)), options)? == source);
+    }
+    options.get_backend().check_path(path, options)
+}
+
+/// Format Rust source code.
+///
+/// The source is passed to the formatting tool through its standard input and the formatted code
+/// is returned. The source is formatted with the options of the [global formatter](crate::set_global).
+///
+/// # Arguments
+///
+/// * `source`: The source code.
+///
+/// # Examples
+///
+/// ```
+/// let formatted = rust_format::format_str("fn main(){}").unwrap();
+/// assert_eq!("fn main() {}\n", formatted);
+/// ```
+pub fn format_str(source: impl AsRef<str>) -> Result<String, Error> {
+    global().format_str(source)
+}
+
+/// Format Rust source code using the provided options.
+///
+/// # Arguments
+///
+/// * `source`: The source code.
+/// * `options`: The options.
+pub fn format_str_with(source: impl AsRef<str>, options: &Options) -> Result<String, Error> {
+    format_source(source.as_ref(), None, options)
+}
+
+/// Format the contents of an editor buffer as if they were the content of a file.
+///
+/// The contents are formatted with the options of the [global formatter](crate::set_global). See
+/// [`format_buffer_with`].
+///
+/// # Arguments
+///
+/// * `path_hint`: The path of the file of the buffer, which does not need to exist.
+/// * `contents`: The contents of the buffer.
+pub fn format_buffer(
+    path_hint: impl AsRef<Path>,
+    contents: impl AsRef<str>,
+) -> Result<String, Error> {
+    global().format_buffer(path_hint, contents)
+}
+
+/// Format the contents of an editor buffer as if they were the content of a file, using the
+/// provided options.
+///
+/// The file is neither read nor written, so unsaved changes can be formatted. The path is used
+/// for everything else:
+///
+/// * The configuration files of the formatting tool are discovered from the directory of the path,
+///   or its nearest existing ancestor for new files.
+/// * Unless the options specify an edition, the edition is taken from the `Cargo.toml` of the
+///   package of the path.
+/// * Errors of the formatting tool refer to the path instead of its standard input.
+///
+/// # Arguments
+///
+/// * `path_hint`: The path of the file of the buffer, which does not need to exist.
+/// * `contents`: The contents of the buffer.
+/// * `options`: The options.
+///
+/// # Examples
+///
+/// ```
+/// let formatted = rust_format::format_buffer_with(
+///     "src/lib.rs",
+///     "async fn main(){}",
+///     &rust_format::Options::new(),
+/// );
+/// assert_eq!("async fn main() {}\n", formatted.unwrap());
+/// ```
+pub fn format_buffer_with(
+    path_hint: impl AsRef<Path>,
+    contents: impl AsRef<str>,
+    options: &Options,
+) -> Result<String, Error> {
+    let path = path_hint.as_ref();
+    let dir = parent_dir(path)
+        .ancestors()
+        .find(|dir| dir.is_dir())
+        .unwrap_or(Path::new("."));
+    let detected;
+    let options = match options.get_edition() {
+        Some(_) => options,
+        None => match crate::manifest::detect_edition(dir) {
+            Some(edition) => {
+                detected = options.clone().edition(edition);
+                &detected
+            }
+            None => options,
+        },
+    };
+    format_source(contents.as_ref(), Some(dir), options).map_err(|error| attribute(error, path))
+}
+
+/// Replace the references to the standard input of the formatting tool in an error with a path.
+fn attribute(mut error: Error, path: &Path) -> Error {
+    let replace = |stream: IoStream| match stream {
+        IoStream::Text(text) => {
+            IoStream::Text(text.replace("<stdin>", &path.display().to_string()))
+        }
+        stream => stream,
+    };
+    match &mut error {
+        Error::ToolExecutionError {
+            path: file,
+            stdout,
+            stderr,
+            ..
+        } => {
+            *stdout = replace(std::mem::replace(stdout, IoStream::InvalidUtf8));
+            *stderr = replace(std::mem::replace(stderr, IoStream::InvalidUtf8));
+            file.get_or_insert_with(|| path.to_path_buf());
+        }
+        Error::ToolCrashed {
+            path: file, stderr, ..
+        } => {
+            *stderr = replace(std::mem::replace(stderr, IoStream::InvalidUtf8));
+            file.get_or_insert_with(|| path.to_path_buf());
+        }
+        _ => {}
+    }
+    error
+}
+
+/// Format Rust source code that is provided as bytes.
+///
+/// The source is formatted with the options of the [global formatter](crate::set_global). See
+/// [`format_bytes_with`].
+///
+/// # Arguments
+///
+/// * `source`: The source code, which must be valid UTF-8.
+///
+/// # Examples
+///
+/// ```
+/// use rust_format::Error;
+///
+/// assert_eq!("fn main() {}\n", rust_format::format_bytes(b"fn main(){}").unwrap());
+/// match rust_format::format_bytes(b"fn main() { \"\xff\" }") {
+///     Err(Error::InvalidUtf8Input { offset, snippet }) => assert_eq!((13, "ff"), (offset, &*snippet)),
+///     other => panic!("Unexpected result: {:?}", other),
+/// }
+/// ```
+pub fn format_bytes(source: impl AsRef<[u8]>) -> Result<String, Error> {
+    global().format_bytes(source)
+}
+
+/// Format Rust source code that is provided as bytes using the provided options.
+///
+/// # Arguments
+///
+/// * `source`: The source code, which must be valid UTF-8.
+/// * `options`: The options.
+///
+/// # Returns
+///
+/// The formatted code. If the source is not valid UTF-8, an [`Error::InvalidUtf8Input`] with the
+/// position of the first invalid sequence is returned.
+pub fn format_bytes_with(source: impl AsRef<[u8]>, options: &Options) -> Result<String, Error> {
+    format_source(decode_utf8(source.as_ref())?, None, options)
+}
+
+/// Decode UTF-8 input, reporting the position of the first invalid sequence.
+pub(crate) fn decode_utf8(bytes: &[u8]) -> Result<&str, Error> {
+    std::str::from_utf8(bytes).map_err(|error| {
+        let offset = error.valid_up_to();
+        // An incomplete sequence at the end of the input extends to the end.
+        let length = error.error_len().unwrap_or(bytes.len() - offset);
+        let snippet: Vec<String> = bytes[offset..offset + length]
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        Error::InvalidUtf8Input {
+            offset,
+            snippet: snippet.join(" "),
+        }
+    })
+}
+
+/// Format a Rust source file and return the result, without modifying the file.
+///
+/// The file is formatted with the options of the [global formatter](crate::set_global).
+///
+/// # Arguments
+///
+/// * `path`: The path to the target file.
+pub fn format_file_to_string(path: impl AsRef<Path>) -> Result<String, Error> {
+    global().format_file_to_string(path)
+}
+
+/// Format a Rust source file using the provided options and return the result, without modifying
+/// the file.
+///
+/// The content is passed to the formatting tool through its standard input, so the file is never
+/// rewritten. Configuration files are discovered starting at the directory of the file.
+///
+/// # Arguments
+///
+/// * `path`: The path to the target file.
+/// * `options`: The options.
+pub fn format_file_to_string_with(
+    path: impl AsRef<Path>,
+    options: &Options,
+) -> Result<String, Error> {
+    let path = path.as_ref();
+    if options.get_max_input_bytes().is_some() {
+        options.check_input_size(std::fs::metadata(path).at(path)?.len())?;
+    }
+    let source = std::fs::read_to_string(path).at(path)?;
+    format_source(&source, Some(parent_dir(path)), options)
+}
+
+/// Write the formatted content of a file, creating a backup and an audit log entry as configured.
+pub(crate) fn write_formatted(
+    path: &Path,
+    original: &str,
+    formatted: &str,
+    options: &Options,
+) -> Result<(), Error> {
+    crate::modification::check_unmodified(path, original, options.get_modification())?;
+    options.get_hooks().write(path, original, formatted, || {
+        match options.get_backup() {
+            // The formatting tool creates these backups itself when it writes the file.
+            backup::Backup::Adjacent => {
+                let backup = path.with_extension("bk");
+                std::fs::write(&backup, original).at(&backup)?;
+            }
+            policy => {
+                policy.create(path)?;
+            }
+        }
+        crate::network::write(path, formatted.as_bytes(), options)
+    })?;
+    if let Some(log) = options.get_audit_log() {
+        log.record_change(path, original.as_bytes(), formatted.as_bytes(), options)?;
+    }
+    Ok(())
+}
+
+/// Determine the directory that contains a file.
+pub(crate) fn parent_dir(path: &Path) -> &Path {
+    path.parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."))
+}
+
+/// Format source code, optionally as if it were located in a directory.
+pub(crate) fn format_source(
+    source: &str,
+    dir: Option<&Path>,
+    options: &Options,
+) -> Result<String, Error> {
+    crate::request::scope(|_| format_source_in_scope(source, dir, options))
+}
+
+fn format_source_in_scope(
+    source: &str,
+    dir: Option<&Path>,
+    options: &Options,
+) -> Result<String, Error> {
+    options.check_config()?;
+    options.check_input_size(source.len() as u64)?;
+    options
+        .get_limits()
+        .check(source)
+        .map_err(|violation| Error::InputRejected { violation })?;
+    #[cfg(feature = "syn")]
+    if options.get_validate_syntax() {
+        crate::validate_syntax(source)?;
+    }
+
+    let backend = options.get_backend();
+    let formatted = match dir {
+        Some(dir) => backend.format_source_in(source, dir, options),
+        None => backend.format_source(source, options),
+    }
+    .map_err(|error| context::attach(error, source))?;
+    let formatted = post_process(formatted, options)?;
+    verify::verify(options, source, &formatted)?;
+    Ok(formatted)
+}
+
+/// Apply the post-processing steps of the options to the output of the formatting tool.
+fn post_process(formatted: String, options: &Options) -> Result<String, Error> {
+    let formatted = options.get_blank_lines().apply(formatted);
+    let formatted = match options.get_trim_trailing_whitespace() {
+        true => crate::whitespace::trim_trailing_whitespace(formatted),
+        false => formatted,
+    };
+    let formatted = match options.get_generated_file_profile() {
+        Some(profile) => profile.apply(formatted),
+        None => formatted,
+    };
+    let mut formatted = match options.get_banner() {
+        Some(banner) => banner.apply(formatted, options)?,
+        None => formatted,
+    };
+    if let Some(shebang) = options.get_shebang() {
+        if crate::lexer::shebang_end(&formatted).is_none() {
+            formatted.insert_str(0, &format!("{}\n", shebang));
+        }
+    }
+    options
+        .get_post_processors()
+        .iter()
+        .try_fold(formatted, |formatted, processor| {
+            processor.process(formatted, options)
+        })
+}
+
+/// Retrieve the complete configuration of the formatting tool that applies to a file.
+///
+/// The configuration is determined with the options of the [global formatter](crate::set_global).
+/// See [`effective_rustfmt_config_with`].
+///
+/// # Arguments
+///
+/// * `path`: The path to the file, which does not need to exist.
+pub fn effective_rustfmt_config(path: impl AsRef<Path>) -> Result<BTreeMap<String, String>, Error> {
+    global().effective_rustfmt_config(path)
+}
+
+/// Retrieve the complete configuration of the formatting tool that applies to a file using the
+/// provided options.
+///
+/// For the `rustfmt` tool, this is the output of `rustfmt --print-config current`: every setting,
+/// including the defaults, the `rustfmt.toml` files that apply to the file and the settings of the
+/// options. Tools can record it along with generated artifacts, to reproduce the style later.
+///
+/// # Arguments
+///
+/// * `path`: The path to the file, which does not need to exist. Its directory must exist.
+/// * `options`: The options.
+///
+/// # Returns
+///
+/// The value of every setting by its name (see [`crate::backend::FormatBackend::print_config`]).
+///
+/// # Examples
+///
+/// ```
+/// use rust_format::{Config, Options};
+///
+/// let options = Options::new().config(Config::new().max_width(80));
+/// let config = rust_format::effective_rustfmt_config_with("src/lib.rs", &options).unwrap();
+/// assert_eq!("80", config["max_width"]);
+/// ```
+pub fn effective_rustfmt_config_with(
+    path: impl AsRef<Path>,
+    options: &Options,
+) -> Result<BTreeMap<String, String>, Error> {
+    let path = path.as_ref();
+    options.get_backend().print_config(path, options)
+}
+
+/// Retrieve the version of the formatting tool, as reported by `rustfmt --version`.
+///
+/// The version of the tool of the current toolchain is determined once and cached for the lifetime
+/// of the process.
+#[cfg(feature = "process-backend")]
+pub fn tool_version() -> Result<String, Error> {
+    use crate::backend::FormatBackend;
+    crate::backend::Rustfmt::new().version()
+}
+
+/// Determine whether the formatting tool of the current toolchain can be run.
+///
+/// See [`ensure_available`].
+#[cfg(feature = "process-backend")]
+pub fn is_available() -> bool {
+    crate::backend::Rustfmt::new().is_available()
+}
+
+/// Verify that the formatting tool of the current toolchain can be run and describe it.
+///
+/// Applications can call this at startup to report a missing tool, along with instructions for
+/// installing it, before they start working. Use
+/// [`crate::backend::Rustfmt::ensure_available`] for the tool of a specific toolchain.
+///
+/// # Examples
+///
+/// ```no_run
+/// match rust_format::ensure_available() {
+///     Ok(tool) => println!("Using rustfmt {} at '{}'.", tool.version, tool.path.display()),
+///     Err(error) => {
+///         eprintln!("{}", error);
+///         std::process::exit(1);
+///     }
+/// }
+/// ```
+#[cfg(feature = "process-backend")]
+pub fn ensure_available() -> Result<crate::backend::ToolInfo, Error> {
+    crate::backend::Rustfmt::new().ensure_available()
+}
+
+/// Install the formatting tool with `rustup component add rustfmt`, unless it is available
+/// already.
+///
+/// # Arguments
+///
+/// * `toolchain`: The toolchain to install the tool for, or `None` for the current toolchain.
+///
+/// # Examples
+///
+/// ```no_run
+/// rust_format::install_rustfmt(Some("nightly"))?;
+/// # Ok::<(), rust_format::Error>(())
+/// ```
+#[cfg(feature = "install")]
+pub fn install_rustfmt(toolchain: Option<&str>) -> Result<(), Error> {
+    match toolchain {
+        Some(toolchain) => crate::backend::Rustfmt::with_toolchain(toolchain).install(),
+        None => crate::backend::Rustfmt::new().install(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_format_buffer() {
+        use crate::{Error, IoStream, Options};
+
+        let dir = PathBuf::from("target/format_buffer");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(dir.join("rustfmt.toml"), "tab_spaces = 2\n").unwrap();
+        std::fs::write(
+            dir.join("Cargo.toml"),
+            "[package]\nname = \"buffer\"\nedition = \"2018\"\n",
+        )
+        .unwrap();
+
+        let path = dir.join("src/new/unsaved.rs");
+        let formatted =
+            super::format_buffer_with(&path, "async fn f(){\nx();}", &Options::new()).unwrap();
+        assert_eq!("async fn f() {\n  x();\n}\n", formatted);
+        assert!(!path.exists());
+
+        match super::format_buffer_with(&path, "fn f( {", &Options::new()) {
+            Err(Error::ToolExecutionError {
+                backend,
+                path: Some(failed),
+                stderr: IoStream::Text(stderr),
+                ..
+            }) => {
+                assert_eq!(("rustfmt", &path), (backend.as_str(), &failed));
+                assert!(stderr.contains(&format!("{}:1:", path.display())));
+            }
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_decode_utf8() {
+        use crate::Error;
+
+        assert_eq!(
+            "fn ä() {}",
+            super::decode_utf8("fn ä() {}".as_bytes()).unwrap()
+        );
+        match super::decode_utf8(b"// \xe2\x82") {
+            Err(Error::InvalidUtf8Input { offset, snippet }) => {
+                assert_eq!((3, "e2 82"), (offset, snippet.as_str()))
+            }
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_format_file() {
+        const TARGET_PATH: &str = "target/sample_code.rs";
+        std::fs::copy("resources/test/sample_code.rs", TARGET_PATH).unwrap();
+        super::format_file(PathBuf::from(TARGET_PATH)).unwrap();
+
+        use std::fs::read_to_string;
+        assert_eq!(
+            read_to_string("resources/test/expected.rs").unwrap(),
+            read_to_string(TARGET_PATH).unwrap(),
+        );
+    }
+
+    #[test]
+    fn test_format_str() {
+        use std::fs::read_to_string;
+        let source = read_to_string("resources/test/sample_code.rs").unwrap();
+        assert_eq!(
+            read_to_string("resources/test/expected.rs").unwrap(),
+            super::format_str(source).unwrap(),
+        );
+    }
+
+    #[test]
+    fn test_shebang() {
+        let options = crate::Options::new()
+            .blank_lines(crate::BlankLines::Exactly(1))
+            .banner(crate::Banner::new().header("// Generated."));
+        let source = "#!/usr/bin/env rust-script\n#![allow(dead_code)]\nfn main(){}\nfn f(){}";
+        let formatted = super::format_str_with(source, &options).unwrap();
+        assert_eq!(
+            "#!/usr/bin/env rust-script\n// Generated.\n#![allow(dead_code)]\nfn main() {}\n\nfn f() {}\n",
+            formatted
+        );
+        assert_eq!(
+            formatted,
+            super::format_str_with(&formatted, &options).unwrap()
+        );
+
+        let options = crate::Options::new().shebang("/usr/bin/env rust-script");
+        assert_eq!(
+            "#!/usr/bin/env rust-script\n#![allow(dead_code)]\n",
+            super::format_str_with("#![allow(dead_code)]", &options).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_format_file_to_string() {
+        use std::fs::read_to_string;
+        let dir = PathBuf::from("target/to_string");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("rustfmt.toml"), "tab_spaces = 2\n").unwrap();
+        let path = dir.join("sample_code.rs");
+        std::fs::copy("resources/test/sample_code.rs", &path).unwrap();
+
+        let formatted = super::format_file_to_string(&path).unwrap();
+        assert_eq!(
+            read_to_string("resources/test/expected.rs")
+                .unwrap()
+                .replace("    ", "  "),
+            formatted
+        );
+        assert_eq!(
+            read_to_string("resources/test/sample_code.rs").unwrap(),
+            read_to_string(&path).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_format_file_returning_original() {
+        const TARGET_PATH: &str = "target/returning_original_sample_code.rs";
+        std::fs::copy("resources/test/sample_code.rs", TARGET_PATH).unwrap();
+
+        use std::fs::read_to_string;
+        assert_eq!(
+            Some(read_to_string("resources/test/sample_code.rs").unwrap()),
+            super::format_file_returning_original(TARGET_PATH).unwrap()
+        );
+        assert_eq!(
+            None,
+            super::format_file_returning_original(TARGET_PATH).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_missing_file() {
+        use std::error::Error as _;
+        let error = super::format_file_to_string("target/missing_file.rs").unwrap_err();
+        assert!(
+            matches!(&error, crate::Error::FileAccess { path, .. } if path.ends_with("missing_file.rs"))
+        );
+        assert!(error.source().is_some());
+    }
+
+    #[test]
+    fn test_verify_applied() {
+        use crate::backend::{Call, Mock};
+        use crate::{Error, Options};
+        use std::sync::Arc;
+
+        let path = PathBuf::from("target/verify_applied.rs");
+        std::fs::write(&path, "fn main(){}").unwrap();
+        // The mock leaves the file unchanged, but a check finds it not formatted correctly.
+        let mock = Arc::new(Mock::new());
+        mock.respond(Ok("fn main(){}".to_string()));
+        mock.respond(Ok("fn main() {}\n".to_string()));
+        let options = Options::new().backend(mock.clone()).verify_applied(true);
+        assert!(matches!(
+            super::format_file_with(&path, &options),
+            Err(Error::ChangesNotApplied { path: failed }) if failed == path
+        ));
+        assert_eq!(
+            vec![
+                Call::FormatPath(path.clone()),
+                Call::CheckPath(path.clone())
+            ],
+            mock.calls()
+        );
+
+        // Files that are formatted correctly have nothing to change.
+        super::format_file_with(&path, &options).unwrap();
+        // Files that a prior check found not formatted correctly are not checked again.
+        let expected = options.clone().expect_changes(true);
+        assert!(super::format_file_with(&path, &expected).is_err());
+        assert_eq!(5, mock.calls().len());
+        mock.respond(Ok("fn main() {}\n".to_string()));
+        super::format_file_with(&path, &expected).unwrap();
+        assert_eq!("fn main() {}\n", std::fs::read_to_string(&path).unwrap());
+    }
+}
@@ -0,0 +1,70 @@
+//! Conversion of code models into formatted source code.
+
+use crate::{Error, Options};
+
+/// A representation of Rust source code that can be converted into formatted source code.
+///
+/// # Examples
+///
+/// ```
+/// use rust_format::{Options, ToFormattedString};
+///
+/// let source = "fn main(){}".to_string();
+/// assert_eq!("fn main() {}\n", source.to_formatted_string(&Options::new()).unwrap());
+/// ```
+pub trait ToFormattedString {
+    /// Format the source code.
+    ///
+    /// # Arguments
+    ///
+    /// * `options`: The options.
+    fn to_formatted_string(&self, options: &Options) -> Result<String, Error>;
+}
+
+impl ToFormattedString for str {
+    fn to_formatted_string(&self, options: &Options) -> Result<String, Error> {
+        crate::format_str_with(self, options)
+    }
+}
+
+impl ToFormattedString for String {
+    fn to_formatted_string(&self, options: &Options) -> Result<String, Error> {
+        self.as_str().to_formatted_string(options)
+    }
+}
+
+#[cfg(feature = "tokens")]
+impl ToFormattedString for proc_macro2::TokenStream {
+    fn to_formatted_string(&self, options: &Options) -> Result<String, Error> {
+        self.to_string().to_formatted_string(options)
+    }
+}
+
+#[cfg(feature = "syn")]
+impl ToFormattedString for syn::File {
+    fn to_formatted_string(&self, options: &Options) -> Result<String, Error> {
+        quote::ToTokens::to_token_stream(self).to_formatted_string(options)
+    }
+}
+
+#[cfg(feature = "syn")]
+impl ToFormattedString for syn::Item {
+    fn to_formatted_string(&self, options: &Options) -> Result<String, Error> {
+        quote::ToTokens::to_token_stream(self).to_formatted_string(options)
+    }
+}
+
+#[cfg(all(test, feature = "syn"))]
+mod test {
+    use super::ToFormattedString;
+    use crate::Options;
+
+    #[test]
+    fn test_syn_to_formatted_string() {
+        let item: syn::Item = syn::parse_str("struct Hello{world:u32}").unwrap();
+        assert_eq!(
+            "struct Hello {\n    world: u32,\n}\n",
+            item.to_formatted_string(&Options::new()).unwrap()
+        );
+    }
+}
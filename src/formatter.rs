@@ -0,0 +1,231 @@
+//! A formatter with a fixed set of options and the global default formatter.
+
+use crate::{Error, Options};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::RwLock;
+
+/// A formatter that applies a fixed set of options.
+///
+/// # Examples
+///
+/// ```
+/// use rust_format::{Edition, Formatter, Options};
+///
+/// let formatter = Formatter::new(Options::new().edition(Edition::E2021));
+/// assert_eq!("async fn f() {}\n", formatter.format_str("async fn f(){}").unwrap());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Formatter {
+    options: Options,
+}
+
+impl Formatter {
+    /// Create a new formatter.
+    ///
+    /// # Arguments
+    ///
+    /// * `options`: The options to apply.
+    pub fn new(options: Options) -> Self {
+        Self { options }
+    }
+
+    /// Retrieve the options of the formatter.
+    pub fn options(&self) -> &Options {
+        &self.options
+    }
+
+    /// Resolve the settings that the formatter runs with. See [`Options::effective_options`].
+    pub fn effective_options(&self) -> crate::ResolvedOptions {
+        self.options.effective_options()
+    }
+
+    /// Format a Rust source file. See [`crate::format_file_with`].
+    pub fn format_file(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        crate::format_file_with(path, &self.options)
+    }
+
+    /// Format a Rust source file and return its previous content if formatting changed it. See
+    /// [`crate::format_file_returning_original_with`].
+    pub fn format_file_returning_original(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> Result<Option<String>, Error> {
+        crate::format_file_returning_original_with(path, &self.options)
+    }
+
+    /// Check whether a Rust source file is formatted correctly. See [`crate::check_file_with`].
+    pub fn check_file(&self, path: impl AsRef<Path>) -> Result<bool, Error> {
+        crate::check_file_with(path, &self.options)
+    }
+
+    /// Format a Rust source file and return the result. See [`crate::format_file_to_string_with`].
+    pub fn format_file_to_string(&self, path: impl AsRef<Path>) -> Result<String, Error> {
+        crate::format_file_to_string_with(path, &self.options)
+    }
+
+    /// Format the contents of an editor buffer as if they were the content of a file. See
+    /// [`crate::format_buffer_with`].
+    pub fn format_buffer(
+        &self,
+        path_hint: impl AsRef<Path>,
+        contents: impl AsRef<str>,
+    ) -> Result<String, Error> {
+        crate::format_buffer_with(path_hint, contents, &self.options)
+    }
+
+    /// Retrieve the complete configuration of the formatting tool that applies to a file. See
+    /// [`crate::effective_rustfmt_config_with`].
+    pub fn effective_rustfmt_config(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> Result<BTreeMap<String, String>, Error> {
+        crate::effective_rustfmt_config_with(path, &self.options)
+    }
+
+    /// Format Rust source code that is provided as bytes. See [`crate::format_bytes_with`].
+    pub fn format_bytes(&self, source: impl AsRef<[u8]>) -> Result<String, Error> {
+        crate::format_bytes_with(source, &self.options)
+    }
+
+    /// Format Rust source code. See [`crate::format_str_with`].
+    pub fn format_str(&self, source: impl AsRef<str>) -> Result<String, Error> {
+        crate::format_str_with(source, &self.options)
+    }
+}
+
+static GLOBAL: RwLock<Option<Formatter>> = RwLock::new(None);
+
+thread_local! {
+    static SCOPED: RefCell<Option<Formatter>> = const { RefCell::new(None) };
+}
+
+/// Set the global formatter.
+///
+/// The free functions of the crate that do not take options, such as [`crate::format_file`] and
+/// [`crate::format_str`], use the global formatter. This allows an application to configure the
+/// formatting for all libraries that it uses in one place.
+///
+/// # Arguments
+///
+/// * `formatter`: The formatter.
+///
+/// # Examples
+///
+/// ```
+/// use rust_format::backend::{Call, Mock};
+/// use rust_format::{Formatter, Options};
+/// use std::sync::Arc;
+///
+/// let mock = Arc::new(Mock::new());
+/// rust_format::set_global(Formatter::new(Options::new().backend(mock.clone())));
+///
+/// rust_format::format_str("fn main(){}").unwrap();
+/// assert_eq!(vec![Call::FormatSource("fn main(){}".to_string())], mock.calls());
+/// ```
+pub fn set_global(formatter: Formatter) {
+    *GLOBAL.write().unwrap_or_else(|error| error.into_inner()) = Some(formatter);
+}
+
+/// Retrieve the global formatter.
+///
+/// A formatter that was installed for the current thread with [`scoped`] or [`with_options`] takes
+/// precedence. If no global formatter was set, a formatter with default options is returned.
+pub fn global() -> Formatter {
+    if let Some(formatter) = SCOPED.with(|scoped| scoped.borrow().clone()) {
+        return formatter;
+    }
+    GLOBAL
+        .read()
+        .unwrap_or_else(|error| error.into_inner())
+        .clone()
+        .unwrap_or_default()
+}
+
+/// A guard that overrides the global formatter for the current thread until it is dropped.
+///
+/// Created by [`scoped`]. Guards can be nested; dropping a guard restores the formatter that was in
+/// effect when it was created.
+#[derive(Debug)]
+#[must_use = "the override ends when the guard is dropped"]
+pub struct ScopeGuard {
+    previous: Option<Formatter>,
+    // The guard must be dropped on the thread that created it.
+    _not_send: std::marker::PhantomData<*const ()>,
+}
+
+impl Drop for ScopeGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        SCOPED.with(|scoped| *scoped.borrow_mut() = previous);
+    }
+}
+
+/// Override the global formatter for the current thread.
+///
+/// The free functions of the crate use the provided options until the returned guard is dropped.
+/// Other threads are not affected.
+///
+/// # Arguments
+///
+/// * `options`: The options.
+///
+/// # Examples
+///
+/// ```
+/// use rust_format::{Edition, Options};
+///
+/// let _guard = rust_format::scoped(Options::new().edition(Edition::E2021));
+/// assert_eq!("async fn f() {}\n", rust_format::format_str("async fn f(){}").unwrap());
+/// ```
+pub fn scoped(options: Options) -> ScopeGuard {
+    let previous = SCOPED.with(|scoped| scoped.replace(Some(Formatter::new(options))));
+    ScopeGuard {
+        previous,
+        _not_send: std::marker::PhantomData,
+    }
+}
+
+/// Run a function while the global formatter is overridden for the current thread.
+///
+/// See [`scoped`].
+///
+/// # Arguments
+///
+/// * `options`: The options.
+/// * `f`: The function.
+pub fn with_options<R>(options: Options, f: impl FnOnce() -> R) -> R {
+    let _guard = scoped(options);
+    f()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::backend::{Call, Mock};
+    use crate::{Formatter, Options};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_formatter() {
+        let mock = Arc::new(Mock::new());
+        let formatter = Formatter::new(Options::new().backend(mock.clone()));
+
+        assert_eq!("source", formatter.format_str("source").unwrap());
+        assert_eq!(vec![Call::FormatSource("source".to_string())], mock.calls());
+    }
+
+    #[test]
+    fn test_with_options() {
+        let outer = Arc::new(Mock::new());
+        let inner = Arc::new(Mock::new());
+        let _guard = crate::scoped(Options::new().backend(outer.clone()));
+        crate::with_options(Options::new().backend(inner.clone()), || {
+            crate::format_str("inner").unwrap();
+        });
+        crate::format_str("outer").unwrap();
+
+        assert_eq!(vec![Call::FormatSource("inner".to_string())], inner.calls());
+        assert_eq!(vec![Call::FormatSource("outer".to_string())], outer.calls());
+    }
+}
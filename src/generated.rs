@@ -0,0 +1,200 @@
+//! File-level lint attributes and markers for generated code.
+
+/// The marker of [`GeneratedFileProfile::new`].
+const DEFAULT_MARKER: &str = "// @generated";
+
+/// The lints that [`GeneratedFileProfile::new`] allows.
+const DEFAULT_ALLOWS: [&str; 7] = [
+    "clippy::all",
+    "clippy::pedantic",
+    "dead_code",
+    "missing_docs",
+    "non_camel_case_types",
+    "non_snake_case",
+    "unused",
+];
+
+/// A block of a generated-code marker and file-level `#![allow(...)]` attributes that is added to
+/// formatted source code, so that lints do not report code that nobody edits by hand.
+///
+/// The block consists of the marker line, followed by one attribute per lint. It is added at the
+/// start of the code, after the shebang line of scripts, if any. If the code already contains the
+/// marker line, the attributes that directly follow it are replaced instead, so the block is never
+/// duplicated, and changing the lints updates it. Every attribute is on a line of its own, which
+/// the formatting tool leaves as it is.
+///
+/// The attributes are inner attributes, so the profile suits crate roots and module files, but not
+/// files that are included with `include!`.
+///
+/// # Examples
+///
+/// ```
+/// use rust_format::{GeneratedFileProfile, Options};
+///
+/// let profile = GeneratedFileProfile::new().allows(["clippy::all", "dead_code"]);
+/// let options = Options::new().generated_file_profile(profile);
+/// let formatted = rust_format::format_str_with("fn main(){}", &options).unwrap();
+/// assert_eq!(
+///     "// @generated\n#![allow(clippy::all)]\n#![allow(dead_code)]\nfn main() {}\n",
+///     formatted
+/// );
+/// assert_eq!(formatted, rust_format::format_str_with(&formatted, &options).unwrap());
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct GeneratedFileProfile {
+    marker: String,
+    allows: Vec<String>,
+}
+
+impl GeneratedFileProfile {
+    /// Create the curated profile, which is marked with `// @generated` and allows the lint groups
+    /// `clippy::all`, `clippy::pedantic` and `unused` as well as the lints `dead_code`,
+    /// `missing_docs`, `non_camel_case_types` and `non_snake_case`.
+    pub fn new() -> Self {
+        Self {
+            marker: DEFAULT_MARKER.to_string(),
+            allows: DEFAULT_ALLOWS.map(String::from).to_vec(),
+        }
+    }
+
+    /// Set the marker line, e.g. `// Code generated by protoc. DO NOT EDIT.`.
+    ///
+    /// # Arguments
+    ///
+    /// * `marker`: The marker. The `// ` prefix is added if it is missing.
+    pub fn marker(mut self, marker: impl Into<String>) -> Self {
+        let marker = marker.into();
+        self.marker = match marker.starts_with("//") {
+            true => marker,
+            false => format!("// {}", marker),
+        };
+        self
+    }
+
+    /// Retrieve the marker line.
+    pub fn get_marker(&self) -> &str {
+        &self.marker
+    }
+
+    /// Allow a lint in addition to the lints of the profile, unless it is allowed already.
+    ///
+    /// # Arguments
+    ///
+    /// * `lint`: The lint or lint group, e.g. `clippy::redundant_closure`.
+    pub fn allow(mut self, lint: impl Into<String>) -> Self {
+        let lint = lint.into();
+        if !self.allows.contains(&lint) {
+            self.allows.push(lint);
+        }
+        self
+    }
+
+    /// Replace the lints of the profile.
+    ///
+    /// # Arguments
+    ///
+    /// * `lints`: The lints or lint groups.
+    pub fn allows(mut self, lints: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.allows.clear();
+        lints
+            .into_iter()
+            .fold(self, |profile, lint| profile.allow(lint))
+    }
+
+    /// Retrieve the allowed lints, in the order of their attributes.
+    pub fn get_allows(&self) -> &[String] {
+        &self.allows
+    }
+
+    /// Add the block to formatted source code, or update the block that it contains.
+    pub(crate) fn apply(&self, source: String) -> String {
+        let mut block = format!("{}\n", self.marker);
+        for lint in &self.allows {
+            block.push_str(&format!("#![allow({})]\n", lint));
+        }
+
+        let mut offset = 0;
+        let mut existing = None;
+        for line in source.split_inclusive('\n') {
+            let end = offset + line.len();
+            match existing {
+                None if line.trim_end() == self.marker => existing = Some((offset, end)),
+                Some((start, _)) if is_allow(line) => existing = Some((start, end)),
+                Some(_) => break,
+                None => {}
+            }
+            offset = end;
+        }
+
+        let mut output = source;
+        match existing {
+            Some((start, end)) => output.replace_range(start..end, &block),
+            None => {
+                // The block goes after a shebang line, which must remain the first line.
+                let start = match crate::lexer::shebang_end(&output) {
+                    Some(end) if end < output.len() => end + 1,
+                    Some(end) => {
+                        output.push('\n');
+                        end + 1
+                    }
+                    None => 0,
+                };
+                output.insert_str(start, &block);
+            }
+        }
+        output
+    }
+
+    /// Add the profile to a fingerprint.
+    pub(crate) fn fingerprint(&self, fingerprint: &mut crate::options::Fingerprint) {
+        fingerprint.text("generated_file_profile.marker", Some(&self.marker));
+        fingerprint.value("generated_file_profile.allows", self.allows.len());
+        for allow in &self.allows {
+            fingerprint.text("generated_file_profile.allow", Some(allow));
+        }
+    }
+}
+
+impl Default for GeneratedFileProfile {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Determine whether a line consists of a single `#![allow(...)]` attribute.
+fn is_allow(line: &str) -> bool {
+    let line = line.trim();
+    line.starts_with("#![allow(") && line.ends_with(")]") && line.matches(']').count() == 1
+}
+
+#[cfg(test)]
+mod test {
+    use super::GeneratedFileProfile;
+
+    #[test]
+    fn test_apply() {
+        let profile = GeneratedFileProfile::new()
+            .marker("Generated by codegen.")
+            .allows(["dead_code", "unused"]);
+        let applied = profile.apply("#!/usr/bin/env run\nfn main() {}\n".to_string());
+        assert_eq!(
+            "#!/usr/bin/env run\n// Generated by codegen.\n#![allow(dead_code)]\n\
+             #![allow(unused)]\nfn main() {}\n",
+            applied
+        );
+        assert_eq!(applied, profile.apply(applied.clone()));
+
+        let updated = profile.clone().allow("clippy::all").apply(applied);
+        assert_eq!(
+            "#!/usr/bin/env run\n// Generated by codegen.\n#![allow(dead_code)]\n\
+             #![allow(unused)]\n#![allow(clippy::all)]\nfn main() {}\n",
+            updated
+        );
+        let banner = "// Header.\n// Generated by codegen.\n#![allow(unused)]\n\n#![allow(x)]\n";
+        assert_eq!(
+            "// Header.\n// Generated by codegen.\n#![allow(dead_code)]\n#![allow(unused)]\n\n\
+             #![allow(x)]\n",
+            profile.apply(banner.to_string())
+        );
+    }
+}
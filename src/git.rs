@@ -0,0 +1,120 @@
+//! Selection of the Rust source files that changed in a git repository, e.g. for formatting only
+//! the files of a commit in a pre-commit hook.
+
+use crate::Error;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// List the Rust source files below a directory of a git repository that differ from a revision,
+/// including files that are not tracked yet. Deleted files and ignored files are not listed.
+///
+/// `git` is taken from the `PATH`.
+///
+/// # Arguments
+///
+/// * `dir`: The directory within the repository.
+/// * `revision`: The revision to compare with, e.g. `main`, or `None` for `HEAD`.
+///
+/// # Returns
+///
+/// The paths of the files, joined to `dir` and in sorted order.
+///
+/// # Examples
+///
+/// ```no_run
+/// use rust_format::batch::{format_files_with, BatchOptions};
+///
+/// let files = rust_format::git::changed_files(".", None)?;
+/// let results = format_files_with(files, &BatchOptions::new());
+/// # Ok::<(), rust_format::Error>(())
+/// ```
+pub fn changed_files(dir: impl AsRef<Path>, revision: Option<&str>) -> Result<Vec<PathBuf>, Error> {
+    let dir = dir.as_ref();
+    let mut diff = git(dir);
+    diff.args(["diff", "--name-only", "--relative", "--diff-filter=ACMR"])
+        .arg(revision.unwrap_or("HEAD"))
+        .arg("--");
+    let mut untracked = git(dir);
+    untracked.args(["ls-files", "--others", "--exclude-standard"]);
+
+    let mut files: Vec<PathBuf> = [diff, untracked]
+        .into_iter()
+        .map(run)
+        .collect::<Result<Vec<String>, Error>>()?
+        .iter()
+        .flat_map(|stdout| stdout.lines())
+        .filter(|line| line.ends_with(".rs"))
+        .map(|line| dir.join(line))
+        .collect();
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+/// Create a command that runs git in a directory.
+fn git(dir: &Path) -> Command {
+    let mut command = Command::new("git");
+    command.arg("-C").arg(dir);
+    command
+}
+
+/// Run git and retrieve its output.
+fn run(mut command: Command) -> Result<String, Error> {
+    let output = command.output()?;
+    if !output.status.success() {
+        return Err(Error::GitFailed {
+            code: output.status.code(),
+            stderr: String::from_utf8_lossy(&output.stderr)
+                .trim_end()
+                .to_string(),
+        });
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Error;
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+
+    #[test]
+    fn test_changed_files() {
+        let dir = PathBuf::from("target/git_changed");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        for name in ["src/lib.rs", "src/old.rs", "src/gone.rs", "README.md"] {
+            std::fs::write(dir.join(name), "fn f(){}").unwrap();
+        }
+        git(&dir, &["init", "-q"]);
+        git(&dir, &["add", "."]);
+        git(&dir, &["commit", "-q", "-m", "Initial"]);
+
+        std::fs::write(dir.join("src/lib.rs"), "fn g(){}").unwrap();
+        std::fs::write(dir.join("src/new.rs"), "fn h(){}").unwrap();
+        std::fs::write(dir.join("README.md"), "Changed").unwrap();
+        std::fs::remove_file(dir.join("src/gone.rs")).unwrap();
+        let changed = vec![dir.join("src/lib.rs"), dir.join("src/new.rs")];
+        assert_eq!(changed, super::changed_files(&dir, None).unwrap());
+        assert_eq!(
+            changed,
+            super::changed_files(dir.join("src"), None).unwrap()
+        );
+
+        assert!(matches!(
+            super::changed_files(&dir, Some("unknown-revision")),
+            Err(Error::GitFailed { code: Some(_), .. })
+        ));
+    }
+}
@@ -0,0 +1,82 @@
+//! Matching of paths against glob patterns.
+
+use std::path::Path;
+
+/// A glob pattern for paths.
+///
+/// Patterns consist of `/`-separated segments. Within a segment, `*` matches any sequence of
+/// characters and `?` matches a single character. A segment consisting of `**` matches any number
+/// of segments, including none.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub(crate) struct Glob {
+    pattern: String,
+}
+
+impl Glob {
+    pub(crate) fn new(pattern: impl Into<String>) -> Self {
+        Self {
+            pattern: pattern.into(),
+        }
+    }
+
+    /// Determine whether a path matches the pattern.
+    pub(crate) fn matches(&self, path: &Path) -> bool {
+        let path = path.to_string_lossy().replace('\\', "/");
+        let path: Vec<&str> = path
+            .split('/')
+            .filter(|segment| !segment.is_empty() && *segment != ".")
+            .collect();
+        let pattern: Vec<&str> = self
+            .pattern
+            .split('/')
+            .filter(|segment| !segment.is_empty() && *segment != ".")
+            .collect();
+        match_segments(&pattern, &path)
+    }
+}
+
+fn match_segments(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| match_segments(rest, &path[skip..])),
+        Some((segment, rest)) => match path.split_first() {
+            Some((name, path)) => {
+                let segment: Vec<char> = segment.chars().collect();
+                let name: Vec<char> = name.chars().collect();
+                match_segment(&segment, &name) && match_segments(rest, path)
+            }
+            None => false,
+        },
+    }
+}
+
+fn match_segment(pattern: &[char], name: &[char]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some(('*', rest)) => (0..=name.len()).any(|skip| match_segment(rest, &name[skip..])),
+        Some(('?', rest)) => !name.is_empty() && match_segment(rest, &name[1..]),
+        Some((c, rest)) => name.first() == Some(c) && match_segment(rest, &name[1..]),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Glob;
+    use std::path::Path;
+
+    #[test]
+    fn test_matches() {
+        let glob = Glob::new("tests/fixtures/**");
+        assert!(glob.matches(Path::new("tests/fixtures/a.rs")));
+        assert!(glob.matches(Path::new("./tests/fixtures/a/b/c.rs")));
+        assert!(!glob.matches(Path::new("src/tests/fixtures/a.rs")));
+
+        let glob = Glob::new("**/gen_?.rs");
+        assert!(glob.matches(Path::new("gen_1.rs")));
+        assert!(glob.matches(Path::new("src/deep/gen_x.rs")));
+        assert!(!glob.matches(Path::new("src/gen_10.rs")));
+
+        assert!(Glob::new("src/*.rs").matches(Path::new("src/lib.rs")));
+        assert!(!Glob::new("src/*.rs").matches(Path::new("src/a/lib.rs")));
+    }
+}
@@ -0,0 +1,162 @@
+//! Golden tests, which compare formatted inputs with expected output files.
+
+use crate::report::Verbosity;
+use crate::temp::write_atomic;
+use crate::walk::Walk;
+use crate::{Error, Options};
+use std::path::{Path, PathBuf};
+
+/// The name of input files of fixture directories.
+const INPUT: &str = "input.rs";
+/// The name of expected output files of fixture directories.
+const EXPECTED: &str = "expected.rs";
+
+/// The environment variable that enables bless mode.
+pub const BLESS_VAR: &str = "RUST_FORMAT_BLESS";
+
+/// Determine whether bless mode is enabled.
+///
+/// Bless mode is enabled by setting the [`BLESS_VAR`] environment variable to any value other
+/// than an empty string or `0`.
+pub fn is_blessing() -> bool {
+    std::env::var_os(BLESS_VAR).is_some_and(|value| !value.is_empty() && value != "0")
+}
+
+/// Assert that formatting an input file produces the content of an expected output file.
+///
+/// The input file is not modified; configuration files are discovered starting at its directory.
+/// In bless mode (see [`is_blessing`]), the expected output file is written instead, which is
+/// useful after the formatting tool was upgraded.
+///
+/// # Arguments
+///
+/// * `input`: The path to the input file.
+/// * `expected`: The path to the expected output file.
+/// * `options`: The options.
+///
+/// # Panics
+///
+/// Panics with a diff if the formatted input does not match the expected output, or if formatting
+/// fails.
+///
+/// # Examples
+///
+/// ```no_run
+/// use rust_format::Options;
+///
+/// rust_format::golden::assert_matches("tests/input.rs", "tests/expected.rs", &Options::new());
+/// ```
+#[track_caller]
+pub fn assert_matches(input: impl AsRef<Path>, expected: impl AsRef<Path>, options: &Options) {
+    let (input, expected) = (input.as_ref(), expected.as_ref());
+    let formatted = crate::format_file_to_string_with(input, options)
+        .unwrap_or_else(|error| panic!("Failed to format '{}': {}", input.display(), error));
+
+    if is_blessing() {
+        std::fs::write(expected, &formatted)
+            .unwrap_or_else(|error| panic!("Failed to write '{}': {}", expected.display(), error));
+        return;
+    }
+
+    let content = std::fs::read_to_string(expected)
+        .unwrap_or_else(|error| panic!("Failed to read '{}': {}", expected.display(), error));
+    if content != formatted {
+        panic!(
+            "Formatted '{}' does not match '{}' (set {}=1 to update it):\n{}",
+            input.display(),
+            expected.display(),
+            BLESS_VAR,
+            crate::diff::unified_diff(&content, &formatted)
+        );
+    }
+}
+
+/// Regenerate the expected output files of all fixtures below a directory.
+///
+/// A fixture is a directory that contains an `input.rs` and an `expected.rs` file. The expected
+/// output file is rewritten with the formatted input if it differs. Every changed file is logged
+/// to stderr, unless the verbosity of the options is [`crate::report::Verbosity::Quiet`]. With
+/// [`crate::report::Verbosity::Verbose`], unchanged files are logged as well.
+///
+/// # Arguments
+///
+/// * `dir`: The directory.
+/// * `options`: The options.
+///
+/// # Returns
+///
+/// The paths of the expected output files that changed, ordered by path.
+pub fn bless(dir: impl AsRef<Path>, options: &Options) -> Result<Vec<PathBuf>, Error> {
+    let mut expected_files: Vec<PathBuf> = Walk::new(dir.as_ref())
+        .files()?
+        .into_iter()
+        .filter(|path| path.file_name().is_some_and(|name| name == EXPECTED))
+        .collect();
+    expected_files.sort();
+
+    let mut changed = Vec::new();
+    for expected in expected_files {
+        let input = expected.with_file_name(INPUT);
+        if !input.is_file() {
+            continue;
+        }
+        let formatted = crate::format_file_to_string_with(&input, options)?;
+        if std::fs::read_to_string(&expected).ok().as_ref() != Some(&formatted) {
+            write_atomic(&expected, formatted)?;
+            if options.get_verbosity() >= Verbosity::Normal {
+                eprintln!("Blessed '{}'.", expected.display());
+            }
+            changed.push(expected);
+        } else if options.get_verbosity() == Verbosity::Verbose {
+            eprintln!("Unchanged '{}'.", expected.display());
+        }
+    }
+    Ok(changed)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Options;
+
+    #[test]
+    fn test_assert_matches() {
+        // Blessing would overwrite the resources of the crate.
+        if super::is_blessing() {
+            return;
+        }
+        super::assert_matches(
+            "resources/test/sample_code.rs",
+            "resources/test/expected.rs",
+            &Options::new(),
+        );
+
+        let result = std::panic::catch_unwind(|| {
+            super::assert_matches(
+                "resources/test/sample_code.rs",
+                "resources/test/sample_code.rs",
+                &Options::new(),
+            )
+        });
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_bless() {
+        let root = std::path::PathBuf::from("target/golden");
+        let _ = std::fs::remove_dir_all(&root);
+        for fixture in ["clean", "stale"] {
+            std::fs::create_dir_all(root.join(fixture)).unwrap();
+            std::fs::write(root.join(fixture).join("input.rs"), "fn main(){}").unwrap();
+        }
+        std::fs::write(root.join("clean/expected.rs"), "fn main() {}\n").unwrap();
+        std::fs::write(root.join("stale/expected.rs"), "").unwrap();
+
+        let changed = super::bless(&root, &Options::new()).unwrap();
+
+        assert_eq!(vec![root.join("stale/expected.rs")], changed);
+        assert_eq!(
+            "fn main() {}\n",
+            std::fs::read_to_string(root.join("stale/expected.rs")).unwrap()
+        );
+    }
+}
@@ -0,0 +1,166 @@
+//! Callbacks of embedders that are invoked around the writes of formatted files.
+
+use crate::error::IoContext;
+use crate::Error;
+use std::fmt::{Debug, Formatter};
+use std::path::Path;
+use std::sync::Arc;
+
+/// A callback that receives the path, the old content and the new content of a file.
+type WriteHook = dyn Fn(&Path, &str, &str) -> std::io::Result<()> + Send + Sync;
+
+/// Callbacks that are invoked before and after a file that is formatted in place is written.
+///
+/// This integrates the writes of the crate with the environment of an embedder, e.g. for checking
+/// out files that version control locks, for custom backup schemes or for notifying other
+/// systems. A file with hooks is formatted in memory and written by the crate, so that its old and
+/// new content are known; child modules of a file are not formatted along with it. Files whose
+/// content does not change are not written, and the hooks are not invoked for them.
+///
+/// The [read-only policy](crate::Options::read_only) is applied before the hooks are invoked.
+///
+/// # Examples
+///
+/// ```
+/// use rust_format::{Hooks, Options};
+///
+/// let hooks = Hooks::new()
+///     .before_write(|path, _old, _new| {
+///         eprintln!("Checking out '{}'.", path.display());
+///         Ok(())
+///     })
+///     .after_write(|path, old, new| {
+///         eprintln!("Wrote '{}' ({} -> {} bytes).", path.display(), old.len(), new.len());
+///         Ok(())
+///     });
+/// let options = Options::new().hooks(hooks);
+/// ```
+#[derive(Clone, Default)]
+pub struct Hooks {
+    before_write: Option<Arc<WriteHook>>,
+    after_write: Option<Arc<WriteHook>>,
+}
+
+impl Hooks {
+    /// Create a new instance without callbacks.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the callback that is invoked before a file is written.
+    ///
+    /// If the callback fails, the file is not written and formatting it fails with an
+    /// [`Error::FileAccess`] for the file.
+    ///
+    /// # Arguments
+    ///
+    /// * `hook`: The callback, which receives the path, the old content and the new content of the
+    ///   file.
+    pub fn before_write(
+        mut self,
+        hook: impl Fn(&Path, &str, &str) -> std::io::Result<()> + Send + Sync + 'static,
+    ) -> Self {
+        self.before_write = Some(Arc::new(hook));
+        self
+    }
+
+    /// Set the callback that is invoked after a file was written.
+    ///
+    /// If the callback fails, formatting the file fails with an [`Error::FileAccess`] for the
+    /// file, although it was written.
+    ///
+    /// # Arguments
+    ///
+    /// * `hook`: The callback, which receives the path, the old content and the new content of the
+    ///   file.
+    pub fn after_write(
+        mut self,
+        hook: impl Fn(&Path, &str, &str) -> std::io::Result<()> + Send + Sync + 'static,
+    ) -> Self {
+        self.after_write = Some(Arc::new(hook));
+        self
+    }
+
+    /// Determine whether any callback is set.
+    pub fn is_enabled(&self) -> bool {
+        self.before_write.is_some() || self.after_write.is_some()
+    }
+
+    /// Write a file, invoking the callbacks around the write.
+    pub(crate) fn write(
+        &self,
+        path: &Path,
+        old: &str,
+        new: &str,
+        write: impl FnOnce() -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        if let Some(hook) = &self.before_write {
+            hook(path, old, new).at(path)?;
+        }
+        write()?;
+        if let Some(hook) = &self.after_write {
+            hook(path, old, new).at(path)?;
+        }
+        Ok(())
+    }
+}
+
+impl Debug for Hooks {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Hooks")
+            .field("before_write", &self.before_write.is_some())
+            .field("after_write", &self.after_write.is_some())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Hooks;
+    use crate::{Error, Options};
+    use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_hooks() {
+        let path = PathBuf::from("target/hooks.rs");
+        std::fs::write(&path, "fn main(){}").unwrap();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let (before, after) = (events.clone(), events.clone());
+        let hooks = Hooks::new()
+            .before_write(move |path, old, new| {
+                let on_disk = std::fs::read_to_string(path)?;
+                before
+                    .lock()
+                    .unwrap()
+                    .push(format!("before {} {} {}", old, new, on_disk));
+                Ok(())
+            })
+            .after_write(move |path, _old, _new| {
+                let on_disk = std::fs::read_to_string(path)?;
+                after
+                    .lock()
+                    .unwrap()
+                    .push(format!("after {}", on_disk.trim_end()));
+                Ok(())
+            });
+        crate::format_file_with(&path, &Options::new().hooks(hooks.clone())).unwrap();
+        crate::format_file_with(&path, &Options::new().hooks(hooks)).unwrap();
+        assert_eq!(
+            vec![
+                "before fn main(){} fn main() {}\n fn main(){}".to_string(),
+                "after fn main() {}".to_string()
+            ],
+            *events.lock().unwrap()
+        );
+
+        std::fs::write(&path, "fn main(){}").unwrap();
+        let locked =
+            Hooks::new().before_write(|_, _, _| Err(std::io::ErrorKind::PermissionDenied.into()));
+        assert!(matches!(
+            crate::format_file_with(&path, &Options::new().hooks(locked)),
+            Err(Error::FileAccess { .. })
+        ));
+        assert_eq!("fn main(){}", std::fs::read_to_string(&path).unwrap());
+    }
+}
@@ -0,0 +1,163 @@
+//! Minimal JSON output for machine-readable records.
+
+use std::fmt::Write;
+
+/// A builder for a single-line JSON object.
+#[derive(Debug, Default)]
+pub(crate) struct JsonObject {
+    text: String,
+}
+
+impl JsonObject {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    fn key(&mut self, key: &str) {
+        self.text.push(if self.text.is_empty() { '{' } else { ',' });
+        write_string(&mut self.text, key);
+        self.text.push(':');
+    }
+
+    /// Add a string member.
+    pub(crate) fn string(mut self, key: &str, value: &str) -> Self {
+        self.key(key);
+        write_string(&mut self.text, value);
+        self
+    }
+
+    /// Add a string member, or a `null` member if there is no value.
+    pub(crate) fn optional_string(mut self, key: &str, value: Option<&str>) -> Self {
+        self.key(key);
+        match value {
+            Some(value) => write_string(&mut self.text, value),
+            None => self.text.push_str("null"),
+        }
+        self
+    }
+
+    /// Add a number member.
+    pub(crate) fn number(mut self, key: &str, value: u64) -> Self {
+        self.key(key);
+        let _ = write!(self.text, "{}", value);
+        self
+    }
+
+    /// Add a number member, or a `null` member if there is no value.
+    pub(crate) fn optional_number(mut self, key: &str, value: Option<u64>) -> Self {
+        self.key(key);
+        match value {
+            Some(value) => {
+                let _ = write!(self.text, "{}", value);
+            }
+            None => self.text.push_str("null"),
+        }
+        self
+    }
+
+    /// Add a boolean member.
+    pub(crate) fn bool(mut self, key: &str, value: bool) -> Self {
+        self.key(key);
+        let _ = write!(self.text, "{}", value);
+        self
+    }
+
+    /// Add a member that is an array of strings.
+    pub(crate) fn strings(mut self, key: &str, values: &[impl AsRef<str>]) -> Self {
+        self.key(key);
+        self.text.push('[');
+        for (index, value) in values.iter().enumerate() {
+            if index > 0 {
+                self.text.push(',');
+            }
+            write_string(&mut self.text, value.as_ref());
+        }
+        self.text.push(']');
+        self
+    }
+
+    /// Add a member that is a nested object.
+    pub(crate) fn object(mut self, key: &str, value: JsonObject) -> Self {
+        self.key(key);
+        self.text.push_str(&value.build());
+        self
+    }
+
+    /// Finish the object.
+    pub(crate) fn build(mut self) -> String {
+        if self.text.is_empty() {
+            self.text.push('{');
+        }
+        self.text.push('}');
+        self.text
+    }
+}
+
+/// Write a JSON string literal.
+pub(crate) fn write_string(out: &mut String, value: &str) {
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Format a point in time as an RFC 3339 timestamp in UTC, e.g. `2021-03-04T05:06:07.123Z`.
+pub(crate) fn timestamp(time: std::time::SystemTime) -> String {
+    let duration = time
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    let seconds = duration.as_secs();
+    let (days, rest) = ((seconds / 86400) as i64, seconds % 86400);
+
+    // Convert days since the epoch to a civil date (proleptic Gregorian calendar).
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        rest / 3600,
+        rest % 3600 / 60,
+        rest % 60,
+        duration.subsec_millis()
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::JsonObject;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn test_json_object() {
+        let json = JsonObject::new()
+            .string("path", "a \"b\"\n")
+            .string("kind", "\u{1}")
+            .build();
+        assert_eq!(r#"{"path":"a \"b\"\n","kind":"\u0001"}"#, json);
+        assert_eq!(
+            "2021-03-04T05:06:07.089Z",
+            super::timestamp(UNIX_EPOCH + Duration::from_millis(1614834367089))
+        );
+    }
+}
@@ -0,0 +1,264 @@
+//! Layering of configuration settings from several sources with a fixed precedence.
+
+use crate::error::IoContext;
+use crate::{Config, Error, Options};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// The environment variable with configuration settings for [`ConfigLayers::environment`].
+///
+/// The value has the form of the `--config` argument of the formatting tool, e.g.
+/// `max_width=80,hard_tabs=true`.
+pub const CONFIG_ENV_VAR: &str = "RUST_FORMAT_CONFIG";
+
+/// A source of configuration settings, in increasing order of precedence.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Layer {
+    /// The settings of the [profile](Options::profile).
+    Defaults,
+    /// The `rustfmt.toml` file of the crate.
+    File,
+    /// The [`CONFIG_ENV_VAR`] environment variable.
+    Environment,
+    /// The [configuration](Options::config) that was set programmatically.
+    Builder,
+    /// The settings for a single call.
+    Override,
+}
+
+/// Configuration settings from several sources, which are combined with a fixed precedence:
+/// built-in defaults < crate config file < environment < programmatic builder < per-call override.
+///
+/// Settings of a layer replace the settings of the same name from all layers below it, regardless
+/// of the order in which the layers were provided. The layer that set a setting can be inspected
+/// with [`ConfigLayers::source`].
+///
+/// # Examples
+///
+/// ```
+/// use rust_format::{Config, ConfigLayers, Layer, Options, Profile};
+///
+/// let options = Options::new()
+///     .profile(Profile::Compact)
+///     .config(Config::new().tab_spaces(2));
+/// let layers = ConfigLayers::of(&options).override_config(Config::new().max_width(80));
+/// assert_eq!(Some(Layer::Override), layers.source("max_width"));
+/// assert_eq!(Some(Layer::Builder), layers.source("tab_spaces"));
+/// assert_eq!(Some(Layer::Defaults), layers.source("use_small_heuristics"));
+/// assert_eq!(Some("80"), layers.options().effective_config().get("max_width"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ConfigLayers {
+    base: Options,
+    layers: BTreeMap<Layer, Config>,
+}
+
+impl ConfigLayers {
+    /// Create the layers of options: the profile provides the defaults and the configuration is
+    /// the builder layer.
+    ///
+    /// # Arguments
+    ///
+    /// * `options`: The options, whose other settings are kept by [`ConfigLayers::options`].
+    pub fn of(options: &Options) -> Self {
+        Self {
+            base: options.clone(),
+            layers: BTreeMap::new(),
+        }
+        .layer(Layer::Defaults, options.get_profile().config())
+        .layer(Layer::Builder, options.get_config().clone())
+    }
+
+    /// Replace the settings of a layer.
+    ///
+    /// # Arguments
+    ///
+    /// * `layer`: The layer.
+    /// * `config`: The settings.
+    pub fn layer(mut self, layer: Layer, config: Config) -> Self {
+        self.layers.insert(layer, config);
+        self
+    }
+
+    /// Read the file layer from the `rustfmt.toml` or `.rustfmt.toml` file of a crate.
+    ///
+    /// Only settings with simple values are read; arrays and tables, e.g. `ignore`, are skipped.
+    /// The file layer is left empty if the crate has no configuration file.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir`: The root directory of the crate.
+    pub fn file(self, dir: impl AsRef<Path>) -> Result<Self, Error> {
+        let dir = dir.as_ref();
+        let path = match ["rustfmt.toml", ".rustfmt.toml"]
+            .into_iter()
+            .map(|name| dir.join(name))
+            .find(|path| path.is_file())
+        {
+            Some(path) => path,
+            None => return Ok(self.layer(Layer::File, Config::new())),
+        };
+        let text = std::fs::read_to_string(&path).at(&path)?;
+        Ok(self.layer(Layer::File, parse_file(&text)))
+    }
+
+    /// Read the environment layer from the [`CONFIG_ENV_VAR`] environment variable. The layer is
+    /// left empty if the variable is not set.
+    pub fn environment(self) -> Self {
+        let value = std::env::var(CONFIG_ENV_VAR).unwrap_or_default();
+        self.layer(Layer::Environment, parse_arg(&value))
+    }
+
+    /// Set the per-call override layer.
+    ///
+    /// # Arguments
+    ///
+    /// * `config`: The settings.
+    pub fn override_config(self, config: Config) -> Self {
+        self.layer(Layer::Override, config)
+    }
+
+    /// Retrieve the settings of a layer.
+    ///
+    /// # Arguments
+    ///
+    /// * `layer`: The layer.
+    pub fn get_layer(&self, layer: Layer) -> Option<&Config> {
+        self.layers.get(&layer)
+    }
+
+    /// Determine which layer set a setting.
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: The name of the setting.
+    ///
+    /// # Returns
+    ///
+    /// The layer with the highest precedence that contains the setting, or `None` if no layer
+    /// does.
+    pub fn source(&self, key: &str) -> Option<Layer> {
+        self.layers
+            .iter()
+            .rev()
+            .find(|(_, config)| config.get(key).is_some())
+            .map(|(layer, _)| *layer)
+    }
+
+    /// List the combined settings along with the layer that set them, in alphabetical order of
+    /// their names.
+    pub fn sources(&self) -> Vec<(String, String, Layer)> {
+        let mut sources: BTreeMap<&str, (&str, Layer)> = BTreeMap::new();
+        for (layer, config) in &self.layers {
+            for (key, value) in config.iter() {
+                sources.insert(key, (value, *layer));
+            }
+        }
+        sources
+            .into_iter()
+            .map(|(key, (value, layer))| (key.to_string(), value.to_string(), layer))
+            .collect()
+    }
+
+    /// Combine the layers into a single configuration.
+    pub fn resolve(&self) -> Config {
+        self.layers
+            .values()
+            .fold(Config::new(), |combined, config| combined.merge(config))
+    }
+
+    /// Create the options with the combined settings.
+    ///
+    /// The settings of all layers above the defaults become the configuration of the options, so
+    /// that the profile still provides the defaults. Other settings are those of the options that
+    /// the layers were created of.
+    pub fn options(&self) -> Options {
+        let config = self
+            .layers
+            .iter()
+            .filter(|(layer, _)| **layer != Layer::Defaults)
+            .fold(Config::new(), |combined, (_, config)| {
+                combined.merge(config)
+            });
+        self.base.clone().config(config)
+    }
+}
+
+/// Parse the simple settings of a `rustfmt.toml` file.
+fn parse_file(text: &str) -> Config {
+    let mut config = Config::new();
+    for line in text.lines() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let value = value.trim();
+        if value.starts_with('[') || value.starts_with('{') {
+            continue;
+        }
+        config = config.set(key.trim(), value.trim_matches('"'));
+    }
+    config
+}
+
+/// Parse settings in the form of the `--config` argument of the formatting tool.
+fn parse_arg(value: &str) -> Config {
+    value
+        .split(',')
+        .filter_map(|pair| pair.split_once('='))
+        .fold(Config::new(), |config, (key, value)| {
+            config.set(key.trim(), value.trim())
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ConfigLayers, Layer};
+    use crate::{Config, Options};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_layers() {
+        let dir = PathBuf::from("target/layers");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("rustfmt.toml"),
+            "max_width = 90 # narrow\nimports_granularity = \"Crate\"\nignore = [\n  \"gen\",\n]\n",
+        )
+        .unwrap();
+
+        let options = Options::new().config(Config::new().tab_spaces(2));
+        let layers = ConfigLayers::of(&options)
+            .override_config(Config::new().hard_tabs(true))
+            .layer(
+                Layer::Environment,
+                super::parse_arg("max_width=80, tab_spaces=8"),
+            )
+            .file(&dir)
+            .unwrap();
+
+        assert_eq!(Some("Crate"), layers.resolve().get("imports_granularity"));
+        assert_eq!(None, layers.source("ignore"));
+        assert_eq!(Some(Layer::Environment), layers.source("max_width"));
+        assert_eq!(Some(Layer::Builder), layers.source("tab_spaces"));
+        assert_eq!(
+            vec![
+                ("hard_tabs".to_string(), "true".to_string(), Layer::Override),
+                (
+                    "imports_granularity".to_string(),
+                    "Crate".to_string(),
+                    Layer::File
+                ),
+                (
+                    "max_width".to_string(),
+                    "80".to_string(),
+                    Layer::Environment
+                ),
+                ("tab_spaces".to_string(), "2".to_string(), Layer::Builder),
+            ],
+            layers.sources()
+        );
+        assert_eq!(layers.resolve(), layers.options().effective_config());
+    }
+}
@@ -0,0 +1,411 @@
+//! A lightweight lexer for Rust source code.
+//!
+//! The lexer does not validate the source code. It only splits it into tokens that are precise
+//! enough to tell code, comments and literals apart. It never fails; unterminated constructs extend
+//! to the end of the input.
+
+/// The kind of a token.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) enum TokenKind {
+    /// A run of whitespace.
+    Whitespace,
+    /// A `#!` line at the very start of the input that is not an inner attribute.
+    Shebang,
+    /// A `//` comment, excluding the terminating newline.
+    LineComment,
+    /// A `/* */` comment, including nested comments.
+    BlockComment,
+    /// A string, byte string, C string or raw variant of these, including prefix and quotes.
+    Str,
+    /// A character or byte literal.
+    Char,
+    /// A lifetime or label.
+    Lifetime,
+    /// An identifier or keyword, including raw identifiers.
+    Ident,
+    /// A numeric literal, including suffix.
+    Number,
+    /// A single punctuation character.
+    Punct,
+}
+
+impl TokenKind {
+    /// Determine whether the token is a comment.
+    pub(crate) fn is_comment(self) -> bool {
+        matches!(self, TokenKind::LineComment | TokenKind::BlockComment)
+    }
+
+    /// Determine whether the token is trivia, i.e. whitespace or a comment.
+    #[cfg(feature = "diff")]
+    pub(crate) fn is_trivia(self) -> bool {
+        self == TokenKind::Whitespace || self.is_comment()
+    }
+}
+
+/// A token, referring to a byte range of the input.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) struct Token {
+    pub(crate) kind: TokenKind,
+    pub(crate) start: usize,
+    pub(crate) end: usize,
+}
+
+impl Token {
+    /// Retrieve the text of the token.
+    pub(crate) fn text(self, source: &str) -> &str {
+        &source[self.start..self.end]
+    }
+}
+
+/// Split source code into tokens.
+pub(crate) fn tokenize(source: &str) -> Vec<Token> {
+    let mut lexer = Lexer {
+        source,
+        bytes: source.as_bytes(),
+        pos: 0,
+    };
+    let mut tokens = Vec::new();
+    if let Some(end) = shebang_end(source) {
+        tokens.push(Token {
+            kind: TokenKind::Shebang,
+            start: 0,
+            end,
+        });
+        lexer.pos = end;
+    }
+    while lexer.pos < lexer.bytes.len() {
+        let start = lexer.pos;
+        let kind = lexer.next_kind();
+        tokens.push(Token {
+            kind,
+            start,
+            end: lexer.pos,
+        });
+    }
+    tokens
+}
+
+/// Determine the end of a shebang line at the start of the source code, if any.
+///
+/// Like rustc, `#!` followed by `[` (ignoring whitespace and comments) is treated as an inner
+/// attribute instead.
+pub(crate) fn shebang_end(source: &str) -> Option<usize> {
+    let rest = source.strip_prefix("#!")?;
+    let after = rest.trim_start();
+    if after.starts_with('[') {
+        return None;
+    }
+    Some(source.find('\n').unwrap_or(source.len()))
+}
+
+/// Determine the index of the closing bracket of the inner attribute (`#![...]`) that starts at a
+/// token, if any.
+///
+/// The tokens must not contain whitespace.
+pub(crate) fn inner_attribute_end(source: &str, tokens: &[Token], start: usize) -> Option<usize> {
+    let texts: Vec<&str> = tokens
+        .get(start..start + 3)?
+        .iter()
+        .map(|token| token.text(source))
+        .collect();
+    if texts != ["#", "!", "["] {
+        return None;
+    }
+    let mut depth = 0usize;
+    (start + 2..tokens.len()).find(|&index| {
+        match tokens[index].text(source) {
+            "(" | "[" | "{" => depth += 1,
+            ")" | "]" | "}" => depth -= 1,
+            _ => {}
+        }
+        depth == 0
+    })
+}
+
+/// Determine whether source code opts out of formatting as a whole with a file-level
+/// `#![rustfmt::skip]` attribute.
+///
+/// Like rustc, only inner attributes before the first item are considered; they can be preceded by
+/// a shebang and comments.
+pub(crate) fn has_file_level_skip(source: &str) -> bool {
+    let tokens: Vec<Token> = tokenize(source)
+        .into_iter()
+        .filter(|token| {
+            !token.kind.is_comment()
+                && !matches!(token.kind, TokenKind::Whitespace | TokenKind::Shebang)
+        })
+        .collect();
+    let mut index = 0;
+    while let Some(end) = inner_attribute_end(source, &tokens, index) {
+        let path: Vec<&str> = tokens[index + 3..end]
+            .iter()
+            .map(|token| token.text(source))
+            .collect();
+        if path == ["rustfmt", ":", ":", "skip"] {
+            return true;
+        }
+        index = end + 1;
+    }
+    false
+}
+
+struct Lexer<'a> {
+    source: &'a str,
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Lexer<'_> {
+    fn peek(&self, offset: usize) -> Option<u8> {
+        self.bytes.get(self.pos + offset).copied()
+    }
+
+    fn current_char(&self) -> char {
+        self.source[self.pos..].chars().next().expect("not at end")
+    }
+
+    fn next_kind(&mut self) -> TokenKind {
+        let c = self.current_char();
+        if c.is_whitespace() {
+            while self.pos < self.bytes.len() && self.current_char().is_whitespace() {
+                self.pos += self.current_char().len_utf8();
+            }
+            return TokenKind::Whitespace;
+        }
+
+        match (self.peek(0), self.peek(1), self.peek(2)) {
+            (Some(b'/'), Some(b'/'), _) => {
+                self.pos = self.source[self.pos..]
+                    .find('\n')
+                    .map_or(self.bytes.len(), |index| self.pos + index);
+                TokenKind::LineComment
+            }
+            (Some(b'/'), Some(b'*'), _) => {
+                self.block_comment();
+                TokenKind::BlockComment
+            }
+            (Some(b'r'), Some(b'#'), Some(c)) if is_ident_start(c as char) => {
+                self.pos += 2;
+                self.ident();
+                TokenKind::Ident
+            }
+            (Some(b'r'), Some(b'"' | b'#'), _) => self.raw_str(1),
+            (Some(b'b' | b'c'), Some(b'r'), Some(b'"' | b'#')) => self.raw_str(2),
+            (Some(b'b' | b'c'), Some(b'"'), _) => {
+                self.pos += 1;
+                self.quoted(b'"');
+                TokenKind::Str
+            }
+            (Some(b'b'), Some(b'\''), _) => {
+                self.pos += 1;
+                self.quoted(b'\'');
+                TokenKind::Char
+            }
+            (Some(b'"'), _, _) => {
+                self.quoted(b'"');
+                TokenKind::Str
+            }
+            (Some(b'\''), _, _) => self.char_or_lifetime(),
+            (Some(b'0'..=b'9'), _, _) => {
+                self.number();
+                TokenKind::Number
+            }
+            _ if is_ident_start(c) => {
+                self.ident();
+                TokenKind::Ident
+            }
+            _ => {
+                self.pos += c.len_utf8();
+                TokenKind::Punct
+            }
+        }
+    }
+
+    fn block_comment(&mut self) {
+        let mut depth = 0usize;
+        while self.pos < self.bytes.len() {
+            match (self.peek(0), self.peek(1)) {
+                (Some(b'/'), Some(b'*')) => {
+                    depth += 1;
+                    self.pos += 2;
+                }
+                (Some(b'*'), Some(b'/')) => {
+                    depth -= 1;
+                    self.pos += 2;
+                    if depth == 0 {
+                        return;
+                    }
+                }
+                _ => self.pos += 1,
+            }
+        }
+    }
+
+    /// Consume a raw string whose `r` is located `prefix_len - 1` bytes after the current position.
+    fn raw_str(&mut self, prefix_len: usize) -> TokenKind {
+        let start = self.pos;
+        self.pos += prefix_len;
+        let mut hashes = 0;
+        while self.peek(0) == Some(b'#') {
+            hashes += 1;
+            self.pos += 1;
+        }
+        if self.peek(0) != Some(b'"') {
+            // Not a raw string after all; treat the prefix as an identifier.
+            self.pos = start;
+            self.ident();
+            return TokenKind::Ident;
+        }
+        self.pos += 1;
+        let terminator: String = std::iter::once('"')
+            .chain(std::iter::repeat_n('#', hashes))
+            .collect();
+        self.pos = self.source[self.pos..]
+            .find(&terminator)
+            .map_or(self.bytes.len(), |index| {
+                self.pos + index + terminator.len()
+            });
+        TokenKind::Str
+    }
+
+    /// Consume a literal delimited by `quote`, honoring backslash escapes.
+    fn quoted(&mut self, quote: u8) {
+        self.pos += 1;
+        while let Some(byte) = self.peek(0) {
+            self.pos += 1;
+            match byte {
+                b'\\' => self.pos = (self.pos + 1).min(self.bytes.len()),
+                _ if byte == quote => break,
+                _ => {}
+            }
+        }
+        self.skip_suffix();
+    }
+
+    fn char_or_lifetime(&mut self) -> TokenKind {
+        let rest = &self.source[self.pos + 1..];
+        let mut chars = rest.chars();
+        match (chars.next(), chars.next()) {
+            (Some('\\'), _) => {
+                self.quoted(b'\'');
+                TokenKind::Char
+            }
+            (Some(c), Some('\'')) => {
+                self.pos += 1 + c.len_utf8() + 1;
+                self.skip_suffix();
+                TokenKind::Char
+            }
+            (Some(c), _) if is_ident_start(c) => {
+                self.pos += 1;
+                self.ident();
+                TokenKind::Lifetime
+            }
+            _ => {
+                self.pos += 1;
+                TokenKind::Punct
+            }
+        }
+    }
+
+    fn number(&mut self) {
+        let start = self.pos;
+        self.consume_while(|c| c.is_ascii_alphanumeric() || c == '_');
+        if self.peek(0) == Some(b'.') && self.peek(1).is_some_and(|b| b.is_ascii_digit()) {
+            self.pos += 1;
+            self.consume_while(|c| c.is_ascii_alphanumeric() || c == '_');
+        }
+        let previous = self.bytes[self.pos - 1];
+        let hex = self.source[start..].starts_with("0x");
+        if !hex
+            && matches!(previous, b'e' | b'E')
+            && matches!(self.peek(0), Some(b'+' | b'-'))
+            && self.peek(1).is_some_and(|b| b.is_ascii_digit())
+        {
+            self.pos += 1;
+            self.consume_while(|c| c.is_ascii_alphanumeric() || c == '_');
+        }
+    }
+
+    fn ident(&mut self) {
+        self.consume_while(is_ident_continue);
+    }
+
+    fn skip_suffix(&mut self) {
+        if self.pos < self.bytes.len() && is_ident_start(self.current_char()) {
+            self.ident();
+        }
+    }
+
+    fn consume_while(&mut self, predicate: impl Fn(char) -> bool) {
+        while self.pos < self.bytes.len() {
+            let c = self.current_char();
+            if !predicate(c) {
+                break;
+            }
+            self.pos += c.len_utf8();
+        }
+    }
+}
+
+fn is_ident_start(c: char) -> bool {
+    c == '_' || c.is_alphabetic()
+}
+
+fn is_ident_continue(c: char) -> bool {
+    c == '_' || c.is_alphanumeric()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{has_file_level_skip, tokenize, TokenKind};
+
+    #[test]
+    fn test_tokenize() {
+        let source = "#!/usr/bin/env run\nfn f<'a>() { /* a /* b */ */ let s = r#\"x\"y\"#; 'c'; b'\\n'; 1.5e-3 } // end";
+        let kinds: Vec<(TokenKind, &str)> = tokenize(source)
+            .into_iter()
+            .filter(|token| token.kind != TokenKind::Whitespace)
+            .map(|token| (token.kind, token.text(source)))
+            .collect();
+
+        assert_eq!(
+            vec![
+                (TokenKind::Shebang, "#!/usr/bin/env run"),
+                (TokenKind::Ident, "fn"),
+                (TokenKind::Ident, "f"),
+                (TokenKind::Punct, "<"),
+                (TokenKind::Lifetime, "'a"),
+                (TokenKind::Punct, ">"),
+                (TokenKind::Punct, "("),
+                (TokenKind::Punct, ")"),
+                (TokenKind::Punct, "{"),
+                (TokenKind::BlockComment, "/* a /* b */ */"),
+                (TokenKind::Ident, "let"),
+                (TokenKind::Ident, "s"),
+                (TokenKind::Punct, "="),
+                (TokenKind::Str, "r#\"x\"y\"#"),
+                (TokenKind::Punct, ";"),
+                (TokenKind::Char, "'c'"),
+                (TokenKind::Punct, ";"),
+                (TokenKind::Char, "b'\\n'"),
+                (TokenKind::Punct, ";"),
+                (TokenKind::Number, "1.5e-3"),
+                (TokenKind::Punct, "}"),
+                (TokenKind::LineComment, "// end"),
+            ],
+            kinds
+        );
+    }
+
+    #[test]
+    fn test_has_file_level_skip() {
+        assert!(has_file_level_skip("#![rustfmt::skip]\nfn f(){}"));
+        assert!(has_file_level_skip(
+            "#!/usr/bin/env run\n//! Generated.\n#![allow(unused)]\n#![ rustfmt :: skip ]\n"
+        ));
+        assert!(!has_file_level_skip("#[rustfmt::skip]\nfn f(){}"));
+        assert!(!has_file_level_skip("#![rustfmt::skip::macros(m)]\n"));
+        assert!(!has_file_level_skip("fn f() {}\n#![rustfmt::skip]\n"));
+        assert!(!has_file_level_skip("// #![rustfmt::skip]\nfn f() {}"));
+    }
+}
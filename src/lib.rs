@@ -1,111 +1,189 @@
-use std::fmt::{Display, Formatter};
-use std::path::Path;
+//! A wrapper around the 'rustfmt' tool for use in Rust code.
+//!
+//! # Features
+//!
+//! The crate is split into cargo features, so that embedders only compile what they use:
+//!
+//! * `process-backend` (default): The [`backend::Rustfmt`] backend, which runs the tool in a
+//!   subprocess. Without it, a backend must be provided through [`Options::backend`].
+//! * `diff` (default): Token verification ([`Options::verify_tokens`]), [`SourceMap`], interactive
+//!   review of changes ([`review`]), edits for language servers ([`lsp`]) and comparisons of
+//!   formatting results ([`drift`]).
+//! * `async`: Asynchronous batch streams.
+//! * `cli`: The `cargo-rust-format` binary, a cargo subcommand that formats the packages of a
+//!   workspace ([`batch::format_packages_with`]) and understands `--package`, `--workspace` and
+//!   `--exclude`. Implies `process-backend` and `walk`.
+//! * `fixtures`: Formatting of test fixture pairs ([`fixtures`]). Implies `walk`.
+//! * `git`: Selection of the files that changed in a git repository ([`git`]).
+//! * `gzip`: Formatting of gzip-compressed sources ([`format_compressed`]).
+//! * `golden`: Golden tests that compare formatted inputs with expected output files ([`golden`]).
+//!   Implies `diff` and `walk`.
+//! * `install`: Installation of the formatting tool with `rustup` ([`install_rustfmt`]). Implies
+//!   `process-backend`.
+//! * `macros`: The [`formatted_quote!`] macro, which formats code generated with `quote`. Implies
+//!   `tokens`.
+//! * `pipelines`: Formatting of the output of code generators. Implies `walk`.
+//! * `tokens`: Conversion of errors into compiler diagnostics using `proc-macro2`.
+//! * `tracing`: Spans for the runs of the formatting tool using `tracing`, which carry the
+//!   [`RequestId`] of the operation. Implies `process-backend`.
+//! * `syn`: Syntax validation and chunked formatting of enormous files using `syn`. Implies
+//!   `tokens`.
+//! * `serde`: Serialization of [`Edition`] with `serde`.
+//! * `service`: Request and response types for formatting services using `serde` ([`service`]).
+//!   Implies `serde`.
+//! * `signals`: Forwarding of interrupts to the formatting tool ([`Options::forward_interrupts`]).
+//!   Implies `process-backend`.
+//! * `tar`: Formatting of tar archives of source files ([`archive`]).
+//! * `testing`: Property-based testing helpers using `proptest`, and an assertion that a source
+//!   tree is formatted ([`testing::assert_source_tree_formatted`]). Implies `golden`.
+//! * `walk`: Discovery of Rust source files in directory trees and formatting of all targets of a
+//!   crate ([`batch::format_crate`]).
+//! * `zip`: Formatting of zip archives of source files ([`archive`]).
+//! * `zstd`: Formatting of zstd-compressed sources ([`format_compressed`]).
 
-/// A textual I/O stream.
-#[derive(Debug)]
-pub enum IoStream {
-    /// The stream does not contain valid UTF-8.
-    InvalidUtf8,
-    /// The text.
-    Text(String),
-}
-
-impl Display for IoStream {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self {
-            IoStream::InvalidUtf8 => f.write_str("(Invalid UTF-8)"),
-            IoStream::Text(string) => f.write_str(&string),
-        }
-    }
-}
-
-impl From<Result<String, std::string::FromUtf8Error>> for IoStream {
-    fn from(result: Result<String, std::string::FromUtf8Error>) -> Self {
-        match result {
-            Ok(string) => IoStream::Text(string),
-            Err(_) => IoStream::InvalidUtf8,
-        }
-    }
-}
-
-/// A formatting error.
-#[derive(thiserror::Error, Debug)]
-pub enum Error {
-    /// The 'rustfmt' tool is missing from the Rust toolchain.
-    #[error("Formatting tool '{0}' not available on toolchain.")]
-    ToolMissing(&'static str),
-    /// The 'rustfmt' tool terminated with a failure exit code.
-    #[error("Error executing formatting tool (code {code}).\nStdout:\n{stdout}\nStderr:{stderr}")]
-    ToolExecutionError {
-        /// The exit code.
-        code: i32,
-        /// The stdout stream.
-        stdout: IoStream,
-        /// The stderr stream.
-        stderr: IoStream,
-    },
-    /// An I/O error occurred.
-    #[error(transparent)]
-    IoError(#[from] std::io::Error),
-    /// No result code was obtained. This can happen on Unix systems when the process is terminated
-    /// by a signal.
-    #[error("No result code received from formatting tool process.")]
-    NoResultCode,
-}
-
-/// Format a Rust source file.
-///
-/// # Arguments
-///
-/// * `path`: The path to the target file.
-///
-/// # Examples
-///
-/// ```
-/// pub fn format_lib() {
-///     use std::path::PathBuf;
-///     rust_format::format_file(PathBuf::from("lib.rs")).unwrap();
-/// }
-/// ```
-pub fn format_file(path: impl AsRef<Path>) -> Result<(), Error> {
-    const TOOL_NAME: &'static str = "rustfmt";
-    let rustfmt =
-        toolchain_find::find_installed_component(TOOL_NAME).ok_or(Error::ToolMissing(TOOL_NAME))?;
-
-    let process = std::process::Command::new(&rustfmt)
-        .arg(path.as_ref().as_os_str())
-        .stdin(std::process::Stdio::piped())
-        .stdout(std::process::Stdio::piped())
-        .stderr(std::process::Stdio::piped())
-        .spawn()?;
-
-    let out = process.wait_with_output()?;
-    let code = out.status.code().ok_or(Error::NoResultCode)?;
-    if code != 0 {
-        Err(Error::ToolExecutionError {
-            code,
-            stdout: String::from_utf8(out.stdout).into(),
-            stderr: String::from_utf8(out.stderr).into(),
-        })
-    } else {
-        Ok(())
-    }
-}
+#[cfg(any(feature = "tar", feature = "zip"))]
+pub mod archive;
+pub mod audit;
+pub mod backend;
+pub mod backup;
+pub mod batch;
+#[cfg(feature = "diff")]
+pub mod drift;
+pub mod events;
+pub mod file_system;
+#[cfg(feature = "fixtures")]
+pub mod fixtures;
+#[cfg(feature = "git")]
+pub mod git;
+#[cfg(feature = "golden")]
+pub mod golden;
+#[cfg(feature = "diff")]
+pub mod lsp;
+pub mod module_tree;
+pub mod modules;
+#[cfg(feature = "pipelines")]
+pub mod pipelines;
+pub mod queue;
+pub mod report;
+#[cfg(feature = "diff")]
+pub mod review;
+#[cfg(feature = "service")]
+pub mod service;
+pub mod targets;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "walk")]
+pub mod walk;
 
-#[cfg(test)]
-mod test {
-    use std::path::PathBuf;
+mod banner;
+mod blank_lines;
+mod checkpoint;
+#[cfg(feature = "syn")]
+mod chunked;
+mod clean_cache;
+#[cfg(feature = "tokens")]
+mod compile_error;
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+mod compressed;
+mod concat;
+mod config;
+mod context;
+mod destination;
+#[cfg(feature = "diff")]
+mod diff;
+mod embedded;
+mod error;
+mod format;
+mod formatted;
+mod formatter;
+mod generated;
+mod glob;
+mod hash;
+mod hooks;
+mod json;
+mod layers;
+mod lexer;
+mod limits;
+#[cfg(feature = "macros")]
+mod macros;
+mod manifest;
+mod modification;
+mod network;
+mod options;
+mod overflow;
+mod parallelism;
+mod permissions;
+mod pool;
+mod post_processor;
+mod request;
+mod resolved;
+mod schema;
+mod session;
+mod source;
+#[cfg(feature = "diff")]
+mod source_map;
+mod stamp;
+#[cfg(feature = "syn")]
+mod syntax;
+mod temp;
+mod verify;
+mod whitespace;
 
-    #[test]
-    fn test_format_file() {
-        const TARGET_PATH: &str = "target/sample_code.rs";
-        std::fs::copy("resources/test/sample_code.rs", TARGET_PATH).unwrap();
-        super::format_file(&PathBuf::from(TARGET_PATH)).unwrap();
+#[cfg(feature = "process-backend")]
+pub use backend::ToolInfo;
+pub use banner::Banner;
+pub use blank_lines::{BlankLines, ItemKind};
+#[cfg(feature = "syn")]
+pub use chunked::{format_file_chunked, format_str_chunked, Chunking};
+pub use clean_cache::{CacheStats, CleanCache};
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+pub use compressed::{format_compressed, format_compressed_with, Compression};
+pub use concat::{concat_and_format, concat_and_format_with, SeparatorPolicy};
+pub use config::{Config, Profile};
+pub use context::SourceContext;
+pub use destination::{format_to, Destination};
+pub use embedded::{format_embedded, format_embedded_with, Region};
+pub use error::{Error, IoStream};
+#[cfg(feature = "install")]
+pub use format::install_rustfmt;
+pub use format::{
+    check_file, check_file_with, effective_rustfmt_config, effective_rustfmt_config_with,
+    format_buffer, format_buffer_with, format_bytes, format_bytes_with, format_file,
+    format_file_returning_original, format_file_returning_original_with, format_file_to_string,
+    format_file_to_string_with, format_file_with, format_str, format_str_with,
+};
+#[cfg(feature = "process-backend")]
+pub use format::{ensure_available, is_available, tool_version};
+pub use formatted::ToFormattedString;
+pub use formatter::{global, scoped, set_global, with_options, Formatter, ScopeGuard};
+pub use generated::GeneratedFileProfile;
+pub use hooks::Hooks;
+pub use layers::{ConfigLayers, Layer, CONFIG_ENV_VAR};
+pub use limits::{Limits, Violation};
+pub use modification::ModificationPolicy;
+pub use network::WritePolicy;
+pub use options::{Edition, Options};
+pub use overflow::{overflowing_lines, LineOverflow};
+pub use parallelism::available_parallelism_hint;
+pub use permissions::ReadOnlyPolicy;
+pub use post_processor::PostProcessor;
+pub use request::RequestId;
+pub use resolved::{ResolvedOptions, RESOLVED_OPTIONS_VERSION};
+pub use schema::{rustfmt_config_schema, ConfigKey, ConfigKind};
+pub use session::{Session, SessionMetrics};
+pub use source::{format, Output, Source};
+#[cfg(feature = "diff")]
+pub use source_map::{format_str_with_map, SourceMap};
+#[cfg(feature = "syn")]
+pub use syntax::{
+    format_syn_file, format_syn_file_with, parse_and_format, parse_and_format_with, validate_syntax,
+};
+pub use temp::TempPolicy;
+pub use verify::CompileCheck;
 
-        use std::fs::read_to_string;
-        assert_eq!(
-            read_to_string("resources/test/expected.rs").unwrap(),
-            read_to_string(TARGET_PATH).unwrap(),
-        );
-    }
+/// Re-exports for the expansion of the macros of the crate. Not part of the public API.
+#[cfg(feature = "macros")]
+#[doc(hidden)]
+pub mod __private {
+    pub use quote;
 }
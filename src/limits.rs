@@ -0,0 +1,181 @@
+//! Limits that protect against pathological inputs.
+
+use crate::lexer::{tokenize, TokenKind};
+use std::fmt::{Display, Formatter};
+
+/// Limits on the shape of the input that are enforced before the formatting tool is started.
+///
+/// Extremely long lines and deeply nested code can make the formatting tool run for a very long
+/// time or overflow its stack. No limits are enforced by default; [`Limits::recommended`] provides
+/// values that accept any reasonable code.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct Limits {
+    /// The maximum length of a line in characters.
+    pub max_line_length: Option<usize>,
+    /// The maximum nesting depth of parentheses, brackets and braces.
+    pub max_nesting_depth: Option<usize>,
+    /// Whether to reject inputs that contain NUL bytes.
+    pub reject_nul_bytes: bool,
+}
+
+impl Limits {
+    /// Create limits that accept any reasonable code, while rejecting pathological inputs.
+    pub fn recommended() -> Self {
+        Self {
+            max_line_length: Some(100_000),
+            max_nesting_depth: Some(256),
+            reject_nul_bytes: true,
+        }
+    }
+
+    /// Determine whether any limit is enforced.
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.max_line_length.is_some() || self.max_nesting_depth.is_some() || self.reject_nul_bytes
+    }
+
+    /// Verify that source code is within the limits.
+    pub(crate) fn check(&self, source: &str) -> Result<(), Violation> {
+        if self.reject_nul_bytes {
+            if let Some(offset) = source.find('\0') {
+                return Err(Violation::NulByte { offset });
+            }
+        }
+
+        if let Some(limit) = self.max_line_length {
+            for (index, line) in source.lines().enumerate() {
+                // Counting bytes first avoids decoding lines that are short anyway.
+                if line.len() > limit && line.chars().count() > limit {
+                    return Err(Violation::LineTooLong {
+                        line: index + 1,
+                        length: line.chars().count(),
+                        limit,
+                    });
+                }
+            }
+        }
+
+        if let Some(limit) = self.max_nesting_depth {
+            let mut depth = 0usize;
+            for token in tokenize(source) {
+                if token.kind != TokenKind::Punct {
+                    continue;
+                }
+                match token.text(source) {
+                    "(" | "[" | "{" => depth += 1,
+                    ")" | "]" | "}" => depth = depth.saturating_sub(1),
+                    _ => continue,
+                }
+                if depth > limit {
+                    return Err(Violation::NestingTooDeep {
+                        line: source[..token.start].matches('\n').count() + 1,
+                        limit,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Add the limits to a fingerprint.
+    pub(crate) fn fingerprint(&self, fingerprint: &mut crate::options::Fingerprint) {
+        let text = |limit: Option<usize>| limit.map(|limit| limit.to_string());
+        fingerprint.text(
+            "limits.max_line_length",
+            text(self.max_line_length).as_deref(),
+        );
+        fingerprint.text(
+            "limits.max_nesting_depth",
+            text(self.max_nesting_depth).as_deref(),
+        );
+        fingerprint.value("limits.reject_nul_bytes", self.reject_nul_bytes);
+    }
+}
+
+/// A violation of the input [`Limits`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Violation {
+    /// The input contains a NUL byte.
+    NulByte {
+        /// The byte offset of the first NUL byte.
+        offset: usize,
+    },
+    /// A line exceeds the maximum length.
+    LineTooLong {
+        /// The line number (1-based).
+        line: usize,
+        /// The length of the line in characters.
+        length: usize,
+        /// The maximum length.
+        limit: usize,
+    },
+    /// The code is nested too deeply.
+    NestingTooDeep {
+        /// The line at which the maximum depth is exceeded (1-based).
+        line: usize,
+        /// The maximum depth.
+        limit: usize,
+    },
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Violation::NulByte { offset } => write!(f, "NUL byte at offset {}", offset),
+            Violation::LineTooLong {
+                line,
+                length,
+                limit,
+            } => write!(
+                f,
+                "line {} has {} characters, exceeding the maximum of {}",
+                line, length, limit
+            ),
+            Violation::NestingTooDeep { line, limit } => write!(
+                f,
+                "nesting at line {} exceeds the maximum depth of {}",
+                line, limit
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Limits, Violation};
+    use crate::{Error, Options};
+
+    #[test]
+    fn test_limits() {
+        let limits = Limits::recommended();
+        assert!(limits.check("fn main() {}\n").is_ok());
+        assert_eq!(
+            Err(Violation::NulByte { offset: 3 }),
+            limits.check("fn \0main() {}")
+        );
+
+        let nested = format!("const X: u32 = {}1{};", "(".repeat(300), ")".repeat(300));
+        let options = Options::new().limits(limits);
+        assert!(matches!(
+            crate::format_str_with(nested, &options),
+            Err(Error::InputRejected {
+                violation: Violation::NestingTooDeep {
+                    line: 1,
+                    limit: 256
+                }
+            })
+        ));
+
+        let limits = Limits {
+            max_line_length: Some(10),
+            ..Limits::default()
+        };
+        assert_eq!(
+            Err(Violation::LineTooLong {
+                line: 2,
+                length: 14,
+                limit: 10
+            }),
+            limits.check("fn f() {\n    let x = 1;\n}")
+        );
+    }
+}
@@ -0,0 +1,222 @@
+//! Adapters for the formatting requests of the Language Server Protocol.
+//!
+//! The types mirror the corresponding types of the protocol, so language servers can convert them
+//! field by field. Positions are zero-based, and characters are counted in UTF-16 code units, which
+//! is the default encoding of the protocol.
+
+use crate::diff::{diff, Edit};
+use crate::{Error, Options};
+
+/// A position in a document.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Position {
+    /// The line (zero-based).
+    pub line: u32,
+    /// The offset within the line in UTF-16 code units (zero-based).
+    pub character: u32,
+}
+
+/// A range in a document. The end is exclusive.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct Range {
+    /// The start of the range.
+    pub start: Position,
+    /// The end of the range.
+    pub end: Position,
+}
+
+/// A replacement of a range of a document.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub struct TextEdit {
+    /// The range to replace.
+    pub range: Range,
+    /// The replacement text.
+    pub new_text: String,
+}
+
+/// Compute the edits for a `textDocument/formatting` request.
+///
+/// # Arguments
+///
+/// * `document`: The content of the document.
+/// * `options`: The options.
+///
+/// # Returns
+///
+/// The edits, which are empty if the document is formatted correctly. They do not overlap and are
+/// ordered by their position, as required by the protocol.
+///
+/// # Examples
+///
+/// ```
+/// use rust_format::lsp::{formatting_edits, Position};
+/// use rust_format::Options;
+///
+/// let edits = formatting_edits("fn main() {}\nfn f(){}\n", &Options::new()).unwrap();
+/// assert_eq!(1, edits.len());
+/// assert_eq!(Position { line: 1, character: 0 }, edits[0].range.start);
+/// assert_eq!("fn f() {}\n", edits[0].new_text);
+/// ```
+pub fn formatting_edits(document: &str, options: &Options) -> Result<Vec<TextEdit>, Error> {
+    let formatted = crate::format_str_with(document, options)?;
+    Ok(text_edits(document, &formatted))
+}
+
+/// Compute the edits for a `textDocument/rangeFormatting` request.
+///
+/// The whole document is formatted, because the formatting tool cannot format fragments of a file
+/// on its own. Only the edits that touch the lines of the range are returned.
+///
+/// # Arguments
+///
+/// * `document`: The content of the document.
+/// * `range`: The range to format.
+/// * `options`: The options.
+pub fn range_formatting_edits(
+    document: &str,
+    range: Range,
+    options: &Options,
+) -> Result<Vec<TextEdit>, Error> {
+    let mut edits = formatting_edits(document, options)?;
+    edits.retain(|edit| {
+        // An empty range at the beginning of a line is an insertion in front of that line.
+        let end = match edit.range.end.character {
+            0 if edit.range.end.line > edit.range.start.line => edit.range.end.line - 1,
+            _ => edit.range.end.line,
+        };
+        edit.range.start.line <= range.end.line && end >= range.start.line
+    });
+    Ok(edits)
+}
+
+/// Compute the line-based edits that transform one version of a document into another.
+///
+/// # Arguments
+///
+/// * `old`: The current content of the document.
+/// * `new`: The new content of the document.
+pub fn text_edits(old: &str, new: &str) -> Vec<TextEdit> {
+    let old_lines: Vec<&str> = old.split_inclusive('\n').collect();
+    let new_lines: Vec<&str> = new.split_inclusive('\n').collect();
+
+    let mut edits = Vec::new();
+    // The pending change: the range of replaced old lines and the inserted new lines.
+    let mut pending: Option<(usize, usize, String)> = None;
+    let mut next_old = 0;
+    for edit in diff(&old_lines, &new_lines) {
+        match edit {
+            Edit::Equal(old_index, _) => {
+                if let Some((start, end, text)) = pending.take() {
+                    edits.push(text_edit(&old_lines, start, end, text));
+                }
+                next_old = old_index + 1;
+            }
+            Edit::Delete(old_index) => {
+                let (_, end, _) =
+                    pending.get_or_insert_with(|| (old_index, old_index, String::new()));
+                *end = old_index + 1;
+                next_old = old_index + 1;
+            }
+            Edit::Insert(new_index) => {
+                let (_, _, text) =
+                    pending.get_or_insert_with(|| (next_old, next_old, String::new()));
+                text.push_str(new_lines[new_index]);
+            }
+        }
+    }
+    if let Some((start, end, text)) = pending {
+        edits.push(text_edit(&old_lines, start, end, text));
+    }
+    edits
+}
+
+/// Create an edit that replaces a range of lines.
+fn text_edit(lines: &[&str], start: usize, end: usize, new_text: String) -> TextEdit {
+    TextEdit {
+        range: Range {
+            start: line_start(lines, start),
+            end: line_start(lines, end),
+        },
+        new_text,
+    }
+}
+
+/// Determine the position of the start of a line. The line after the last line starts at the end
+/// of the document.
+fn line_start(lines: &[&str], index: usize) -> Position {
+    match lines.last() {
+        Some(last) if index == lines.len() && !last.ends_with('\n') => Position {
+            line: (index - 1) as u32,
+            character: last.encode_utf16().count() as u32,
+        },
+        _ => Position {
+            line: index as u32,
+            character: 0,
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Position, Range, TextEdit};
+
+    /// Apply edits to a document.
+    fn apply(document: &str, edits: &[TextEdit]) -> String {
+        let offset = |position: Position| {
+            let start: usize = document
+                .split_inclusive('\n')
+                .take(position.line as usize)
+                .map(str::len)
+                .sum();
+            let line = &document[start..];
+            let mut units = 0;
+            let within = line
+                .char_indices()
+                .find(|(_, c)| {
+                    let found = units >= position.character;
+                    units += c.len_utf16() as u32;
+                    found
+                })
+                .map_or(line.len(), |(index, _)| index);
+            start + within
+        };
+        let mut result = document.to_string();
+        for edit in edits.iter().rev() {
+            result.replace_range(
+                offset(edit.range.start)..offset(edit.range.end),
+                &edit.new_text,
+            );
+        }
+        result
+    }
+
+    #[test]
+    fn test_text_edits() {
+        for (old, new) in [
+            ("a\nb\nc\n", "a\nx\nc\n"),
+            ("a\nb\nc", "a\nb\nc\n"),
+            ("a\nb\n", "b\nc\nd\n"),
+            ("ä\n😀", "ä\n😀x"),
+            ("", "a\n"),
+            ("a\n", ""),
+        ] {
+            assert_eq!(new, apply(old, &super::text_edits(old, new)), "{:?}", old);
+        }
+        assert_eq!(
+            vec![TextEdit {
+                range: Range {
+                    start: Position {
+                        line: 1,
+                        character: 0
+                    },
+                    end: Position {
+                        line: 1,
+                        character: 1
+                    },
+                },
+                new_text: "b\n".to_string(),
+            }],
+            super::text_edits("a\nb", "a\nb\n")
+        );
+    }
+}
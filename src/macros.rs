@@ -0,0 +1,39 @@
+//! Macros for generating formatted source code.
+
+/// Generate Rust source code with [`quote::quote!`] and format it.
+///
+/// The macro accepts the same input as `quote!`, including interpolation of variables with `#`.
+/// The result is formatted with the options of the [global formatter](crate::set_global).
+///
+/// # Returns
+///
+/// The formatted source code as a `Result<String, rust_format::Error>`.
+///
+/// # Examples
+///
+/// ```
+/// use rust_format::formatted_quote;
+///
+/// let name = quote::format_ident!("Hello");
+/// let fields = [quote::quote!(world: u32)];
+/// let source = formatted_quote! { pub struct #name { #(#fields),* } }.unwrap();
+/// assert_eq!("pub struct Hello {\n    world: u32,\n}\n", source);
+/// ```
+#[macro_export]
+macro_rules! formatted_quote {
+    ($($tokens:tt)*) => {
+        $crate::format_str($crate::__private::quote::quote!($($tokens)*).to_string())
+    };
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn test_formatted_quote() {
+        let value = 42u32;
+        assert_eq!(
+            "fn answer() -> u32 {\n    42u32\n}\n",
+            formatted_quote! { fn answer() -> u32 { #value } }.unwrap()
+        );
+    }
+}
@@ -0,0 +1,200 @@
+//! Detection of the edition of source files and of the packages of workspaces from their
+//! manifests.
+
+use crate::Edition;
+use std::path::Path;
+
+/// The edition that the `[package]` section of a manifest declares.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Declared {
+    /// An explicit edition.
+    Edition(Edition),
+    /// The edition is inherited from the workspace (`edition.workspace = true`).
+    Workspace,
+}
+
+/// The relevant settings of a manifest.
+#[derive(Debug, Default)]
+struct Manifest {
+    /// Whether the manifest has a `[package]` section.
+    package: bool,
+    /// The edition of the package, if declared.
+    edition: Option<Declared>,
+    /// The edition in the `[workspace.package]` section, if any.
+    workspace_edition: Option<Edition>,
+}
+
+/// Parse the edition of a manifest.
+///
+/// This is not a complete TOML parser: it only understands the forms of the `edition` key that
+/// `cargo new` and `cargo` documentation use.
+fn parse(text: &str) -> Manifest {
+    let mut manifest = Manifest::default();
+    let mut section = String::new();
+    for line in text.lines() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if let Some(header) = line
+            .strip_prefix('[')
+            .and_then(|line| line.strip_suffix(']'))
+        {
+            section = header.trim().to_string();
+            manifest.package |= section == "package";
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let (key, value) = (key.trim(), value.trim());
+        match (section.as_str(), key) {
+            ("package", "edition") if value.contains("workspace") => {
+                manifest.edition = Some(Declared::Workspace)
+            }
+            ("package", "edition.workspace") => manifest.edition = Some(Declared::Workspace),
+            ("package", "edition") => {
+                manifest.edition = Edition::parse(value.trim_matches('"')).map(Declared::Edition)
+            }
+            ("workspace.package", "edition") => {
+                manifest.workspace_edition = Edition::parse(value.trim_matches('"'))
+            }
+            _ => {}
+        }
+    }
+    manifest
+}
+
+/// The package and workspace settings of a manifest.
+#[derive(Debug, Default, Eq, PartialEq)]
+pub(crate) struct Workspace {
+    /// The name of the package, if the manifest has a `[package]` section.
+    pub(crate) package: Option<String>,
+    /// The `members` of the `[workspace]` section, which may contain glob patterns.
+    pub(crate) members: Vec<String>,
+    /// The `exclude` of the `[workspace]` section.
+    pub(crate) exclude: Vec<String>,
+}
+
+/// Parse the package and workspace settings of a manifest.
+///
+/// Like [`parse`], this only understands the common forms of the settings, but arrays may span
+/// several lines.
+pub(crate) fn parse_workspace(text: &str) -> Workspace {
+    let mut workspace = Workspace::default();
+    let mut section = String::new();
+    let mut lines = text.lines();
+    while let Some(line) = lines.next() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if let Some(header) = line
+            .strip_prefix('[')
+            .and_then(|line| line.strip_suffix(']'))
+            .filter(|header| !header.starts_with('['))
+        {
+            section = header.trim().to_string();
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let (key, value) = (key.trim(), value.trim());
+        match (section.as_str(), key) {
+            ("package", "name") => workspace.package = Some(value.trim_matches('"').to_string()),
+            ("workspace", "members" | "exclude") => {
+                let mut array = value.to_string();
+                while !array.contains(']') {
+                    match lines.next() {
+                        Some(line) => array.push_str(line.split('#').next().unwrap_or_default()),
+                        None => break,
+                    }
+                }
+                let entries = array
+                    .split('"')
+                    .skip(1)
+                    .step_by(2)
+                    .map(str::to_string)
+                    .collect();
+                match key {
+                    "members" => workspace.members = entries,
+                    _ => workspace.exclude = entries,
+                }
+            }
+            _ => {}
+        }
+    }
+    workspace
+}
+
+/// Detect the edition of the files in a directory from the manifest of their package.
+///
+/// The nearest `Cargo.toml` with a `[package]` section is used. Packages that do not declare an
+/// edition use the 2015 edition, as for `cargo`. Editions that are inherited from the workspace
+/// are looked up in the manifests above the package.
+///
+/// # Returns
+///
+/// The edition, or `None` if the directory is not part of a package.
+pub(crate) fn detect_edition(dir: &Path) -> Option<Edition> {
+    let mut inherited = false;
+    for dir in dir.ancestors() {
+        let Ok(text) = std::fs::read_to_string(dir.join("Cargo.toml")) else {
+            continue;
+        };
+        let manifest = parse(&text);
+        if inherited {
+            if let Some(edition) = manifest.workspace_edition {
+                return Some(edition);
+            }
+            continue;
+        }
+        if !manifest.package {
+            continue;
+        }
+        match manifest.edition {
+            Some(Declared::Edition(edition)) => return Some(edition),
+            Some(Declared::Workspace) => match manifest.workspace_edition {
+                Some(edition) => return Some(edition),
+                None => inherited = true,
+            },
+            None => return Some(Edition::E2015),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Edition;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_detect_edition() {
+        let root = PathBuf::from("target/manifest");
+        let _ = std::fs::remove_dir_all(&root);
+        for dir in ["explicit/src", "inherited/src", "legacy/src"] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        std::fs::write(
+            root.join("Cargo.toml"),
+            "[workspace]\nmembers = [\"*\"]\n\n[workspace.package]\nedition = \"2018\"\n",
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("explicit/Cargo.toml"),
+            "[package]\nname = \"explicit\"\nedition = \"2024\" # latest\n",
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("inherited/Cargo.toml"),
+            "[package]\nname = \"inherited\"\nedition.workspace = true\n",
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("legacy/Cargo.toml"),
+            "[package]\nname = \"legacy\"\n",
+        )
+        .unwrap();
+
+        let detect = |dir: &str| super::detect_edition(&root.join(dir));
+        assert_eq!(Some(Edition::E2024), detect("explicit/src"));
+        assert_eq!(Some(Edition::E2018), detect("inherited/src"));
+        assert_eq!(Some(Edition::E2015), detect("legacy/src"));
+    }
+}
@@ -0,0 +1,79 @@
+//! Detection of files that are modified by other processes while they are being formatted.
+
+use crate::error::IoContext;
+use crate::Error;
+use std::path::Path;
+
+/// The policy for files that are modified by another process (e.g. an editor saving the file)
+/// between reading them and writing the formatted result.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub enum ModificationPolicy {
+    /// Write the formatted result regardless, which loses the newer content.
+    #[default]
+    Overwrite,
+    /// Fail with [`Error::ConcurrentModification`] and leave the newer content untouched.
+    ///
+    /// Files are formatted in memory and written by the crate, so that the content can be
+    /// re-checked right before writing. Child modules of a file are not formatted along with it.
+    Refuse,
+}
+
+/// Verify that a file still has the content that was formatted, if the policy requires it.
+pub(crate) fn check_unmodified(
+    path: &Path,
+    original: &str,
+    policy: ModificationPolicy,
+) -> Result<(), Error> {
+    if policy == ModificationPolicy::Refuse && std::fs::read(path).at(path)? != original.as_bytes()
+    {
+        return Err(Error::ConcurrentModification {
+            path: path.to_path_buf(),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::backend::FormatBackend;
+    use crate::{Error, ModificationPolicy, Options};
+    use std::path::{Path, PathBuf};
+
+    /// A backend that simulates an editor that saves the file while it is being formatted.
+    #[derive(Debug)]
+    struct Racing(PathBuf);
+
+    impl FormatBackend for Racing {
+        fn format_source(&self, source: &str, _options: &Options) -> Result<String, Error> {
+            std::fs::write(&self.0, "fn edited() {}\n")?;
+            Ok(format!("{}\n", source.trim_end()))
+        }
+
+        fn format_path(&self, _path: &Path, _options: &Options) -> Result<(), Error> {
+            unreachable!("files are formatted in memory")
+        }
+
+        fn check_path(&self, _path: &Path, _options: &Options) -> Result<bool, Error> {
+            unreachable!("files are not checked")
+        }
+
+        fn version(&self) -> Result<String, Error> {
+            Ok("racing".to_string())
+        }
+    }
+
+    #[test]
+    fn test_refuse() {
+        let path = PathBuf::from("target/concurrent_modification.rs");
+        std::fs::write(&path, "fn main() {}").unwrap();
+        let options = Options::new()
+            .backend(std::sync::Arc::new(Racing(path.clone())))
+            .modification(ModificationPolicy::Refuse);
+
+        assert!(matches!(
+            crate::format_file_with(&path, &options),
+            Err(Error::ConcurrentModification { .. })
+        ));
+        assert_eq!("fn edited() {}\n", std::fs::read_to_string(&path).unwrap());
+    }
+}
@@ -0,0 +1,578 @@
+//! Generation of module trees, which writes the files of generated modules along with the `mod`
+//! declarations and re-exports that tie them together.
+
+use crate::error::IoContext;
+use crate::lexer::{tokenize, TokenKind};
+use crate::temp::write_atomic;
+use crate::{Edition, Error, Options};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+/// A module of a [`ModuleTree`].
+///
+/// # Examples
+///
+/// ```
+/// use rust_format::module_tree::Module;
+///
+/// let module = Module::new("types")
+///     .public(true)
+///     .content("pub struct Message;")
+///     .reexport("Message")
+///     .child(Module::new("wire").content("pub fn encode() {}"));
+/// ```
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub struct Module {
+    name: String,
+    content: String,
+    public: bool,
+    path: Option<PathBuf>,
+    reexports: Vec<String>,
+    children: Vec<Module>,
+}
+
+impl Module {
+    /// Create an empty, private module.
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: The name of the module, which must be an identifier.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Set the code of the module, which follows the generated declarations.
+    ///
+    /// # Arguments
+    ///
+    /// * `content`: The code.
+    pub fn content(mut self, content: impl Into<String>) -> Self {
+        self.content = content.into();
+        self
+    }
+
+    /// Set whether the module is declared as `pub mod`. Disabled by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `public`: Whether the module is public.
+    pub fn public(mut self, public: bool) -> Self {
+        self.public = public;
+        self
+    }
+
+    /// Write the module to a custom file, which is declared with a `#[path]` attribute.
+    ///
+    /// As for the attribute, the path is relative to the directory of the file that declares the
+    /// module. Modules with a custom path cannot have child modules.
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: The path of the file.
+    pub fn path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    /// Re-export an item of the module from its parent with `pub use`.
+    ///
+    /// # Arguments
+    ///
+    /// * `item`: The path of the item relative to the module, e.g. `Message` or `wire::encode`.
+    pub fn reexport(mut self, item: impl Into<String>) -> Self {
+        self.reexports.push(item.into());
+        self
+    }
+
+    /// Add a child module.
+    ///
+    /// # Arguments
+    ///
+    /// * `child`: The child module.
+    pub fn child(mut self, child: Module) -> Self {
+        self.children.push(child);
+        self
+    }
+
+    /// Generate the declarations of the child modules and their re-exports.
+    fn declarations(&self) -> String {
+        let mut code = String::new();
+        for child in &self.children {
+            if let Some(path) = &child.path {
+                code.push_str(&format!("#[path = {:?}]\n", path.to_string_lossy()));
+            }
+            let visibility = match child.public {
+                true => "pub ",
+                false => "",
+            };
+            code.push_str(&format!("{}mod {};\n", visibility, child.name));
+        }
+        for child in &self.children {
+            for item in &child.reexports {
+                code.push_str(&format!("pub use {}::{};\n", child.name, item));
+            }
+        }
+        code
+    }
+}
+
+/// A tree of generated modules below a directory, e.g. the `src` directory of a generated crate or
+/// a directory below `OUT_DIR`.
+///
+/// The root module is written to `lib.rs` by default. Other modules are written to files named
+/// after them (`name.rs`), with the files of their child modules in a directory of the same name.
+/// Every file is formatted before it is written, which may reorder the declarations, and files
+/// whose content does not change are not written at all.
+///
+/// The tree is validated before anything is written: module names must be identifiers and must
+/// be unique among their siblings, and no two modules may share a file. After writing, the
+/// directories of the tree must not contain other Rust source files, e.g. the files of modules
+/// that an earlier version of the generator emitted.
+///
+/// # Examples
+///
+/// ```no_run
+/// use rust_format::module_tree::{Module, ModuleTree};
+///
+/// let files = ModuleTree::new("generated/src")
+///     .module(Module::new("types").public(true).content("pub struct Message;"))
+///     .module(Module::new("client").content("pub fn connect() {}").reexport("connect"))
+///     .write()?;
+/// # Ok::<(), rust_format::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct ModuleTree {
+    dir: PathBuf,
+    root: Module,
+    root_file: String,
+    options: Options,
+}
+
+impl ModuleTree {
+    /// Create an empty tree.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir`: The directory of the root module.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            root: Module::default(),
+            root_file: "lib.rs".to_string(),
+            options: Options::default(),
+        }
+    }
+
+    /// Set the name of the file of the root module, e.g. `mod.rs` or `main.rs`. Defaults to
+    /// `lib.rs`.
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: The file name.
+    pub fn root_file(mut self, name: impl Into<String>) -> Self {
+        self.root_file = name.into();
+        self
+    }
+
+    /// Set the code of the root module, which follows the generated declarations.
+    ///
+    /// # Arguments
+    ///
+    /// * `content`: The code.
+    pub fn content(mut self, content: impl Into<String>) -> Self {
+        self.root.content = content.into();
+        self
+    }
+
+    /// Add a top-level module.
+    ///
+    /// # Arguments
+    ///
+    /// * `module`: The module.
+    pub fn module(mut self, module: Module) -> Self {
+        self.root.children.push(module);
+        self
+    }
+
+    /// Set the options for formatting the files. The code is formatted as 2021 edition code,
+    /// unless the options specify an edition.
+    ///
+    /// # Arguments
+    ///
+    /// * `options`: The options.
+    pub fn options(mut self, options: Options) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Write the files of the tree.
+    ///
+    /// # Returns
+    ///
+    /// The paths of all files of the tree, starting with the root module, in declaration order.
+    pub fn write(&self) -> Result<Vec<PathBuf>, Error> {
+        let mut files = Vec::new();
+        collect(
+            &self.root,
+            self.dir.join(&self.root_file),
+            &self.dir,
+            &mut files,
+        )?;
+        let mut unique = BTreeSet::new();
+        if let Some((path, _)) = files
+            .iter()
+            .find(|(path, _)| !unique.insert(normalize(path)))
+        {
+            return Err(invalid(format!(
+                "more than one module is written to '{}'",
+                path.display()
+            )));
+        }
+
+        let options = match self.options.get_edition() {
+            Some(_) => self.options.clone(),
+            None => self.options.clone().edition(Edition::E2021),
+        };
+        for (path, module) in &files {
+            let code = format!("{}{}", module.declarations(), module.content);
+            let formatted = crate::format_str_with(code, &options)?;
+            if std::fs::read_to_string(path).ok().as_ref() != Some(&formatted) {
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent).at(parent)?;
+                }
+                write_atomic(path, formatted)?;
+            }
+        }
+
+        // Rust source files in the directories of the tree that are not part of it are stale.
+        let dirs: BTreeSet<PathBuf> = files
+            .iter()
+            .filter_map(|(path, _)| path.parent().map(normalize))
+            .collect();
+        for dir in dirs {
+            for entry in std::fs::read_dir(&dir).at(&dir)? {
+                let path = normalize(&entry.at(&dir)?.path());
+                if path.extension().is_some_and(|extension| extension == "rs")
+                    && !unique.contains(&path)
+                {
+                    return Err(invalid(format!(
+                        "'{}' is not part of the tree",
+                        path.display()
+                    )));
+                }
+            }
+        }
+        Ok(files.into_iter().map(|(path, _)| path).collect())
+    }
+}
+
+/// Format source code and split its inline modules into a file-per-module layout.
+///
+/// The modules are split with the [options](crate::global) of the global formatter. See
+/// [`split_by_modules_with`].
+///
+/// # Arguments
+///
+/// * `source`: The source code.
+pub fn split_by_modules(source: impl AsRef<str>) -> Result<Vec<(PathBuf, String)>, Error> {
+    split_by_modules_with(source, crate::global().options())
+}
+
+/// Format source code and split its inline modules into a file-per-module layout, using the
+/// provided options.
+///
+/// This lets generators that build a single token stream emit a tidy tree of files. Every inline
+/// module (`mod name { ... }`) is moved to a file of its own and its block is replaced with a
+/// declaration (`mod name;`), keeping its attributes and visibility. Modules within functions or
+/// other items stay inline. As for [`ModuleTree`], the source is formatted as 2021 edition code,
+/// unless the options specify an edition, and every file is formatted on its own, which may
+/// reorder the declarations.
+///
+/// # Arguments
+///
+/// * `source`: The source code.
+/// * `options`: The options.
+///
+/// # Returns
+///
+/// The files, starting with the root module, in the order of the inline modules. The root module is `lib.rs`;
+/// a module `name` is `name.rs`, with the files of its child modules in the directory `name`.
+/// Returns [`Error::InvalidModuleTree`] if a module is declared twice.
+///
+/// # Examples
+///
+/// ```
+/// use rust_format::module_tree::split_by_modules;
+/// use std::path::PathBuf;
+///
+/// let files = split_by_modules("pub mod types { pub struct Message; mod wire {} }")?;
+/// assert_eq!(
+///     vec![
+///         (PathBuf::from("lib.rs"), "pub mod types;\n".to_string()),
+///         (PathBuf::from("types.rs"), "pub struct Message;\nmod wire;\n".to_string()),
+///         (PathBuf::from("types/wire.rs"), "\n".to_string()),
+///     ],
+///     files
+/// );
+/// # Ok::<(), rust_format::Error>(())
+/// ```
+pub fn split_by_modules_with(
+    source: impl AsRef<str>,
+    options: &Options,
+) -> Result<Vec<(PathBuf, String)>, Error> {
+    let options = match options.get_edition() {
+        Some(_) => options.clone(),
+        None => options.clone().edition(Edition::E2021),
+    };
+    let mut files = Vec::new();
+    split(
+        crate::format_str_with(source, &options)?,
+        PathBuf::from("lib.rs"),
+        Path::new(""),
+        &options,
+        &mut files,
+    )?;
+    Ok(files)
+}
+
+/// Split the inline modules of formatted code into files, recursively.
+///
+/// # Arguments
+///
+/// * `code`: The formatted code of the module.
+/// * `path`: The path of the file of the module.
+/// * `dir`: The directory of the files of the child modules.
+/// * `options`: The options for formatting the files.
+/// * `files`: The collected files.
+fn split(
+    code: String,
+    path: PathBuf,
+    dir: &Path,
+    options: &Options,
+    files: &mut Vec<(PathBuf, String)>,
+) -> Result<(), Error> {
+    let tokens: Vec<_> = tokenize(&code)
+        .into_iter()
+        .filter(|token| token.kind != TokenKind::Whitespace && !token.kind.is_comment())
+        .collect();
+    // The byte ranges of the blocks of top-level modules, along with their names.
+    let mut blocks = Vec::new();
+    let mut depth = 0usize;
+    let mut index = 0;
+    while index < tokens.len() {
+        let text = tokens[index].text(&code);
+        match text {
+            "{" | "(" | "[" => depth += 1,
+            "}" | ")" | "]" => depth = depth.saturating_sub(1),
+            "mod"
+                if depth == 0
+                    && tokens[index].kind == TokenKind::Ident
+                    && tokens.get(index + 2).map(|token| token.text(&code)) == Some("{") =>
+            {
+                let name = tokens[index + 1].text(&code);
+                let open = index + 2;
+                let mut nesting = 0usize;
+                let close = (open..tokens.len()).find(|&at| {
+                    match tokens[at].text(&code) {
+                        "{" | "(" | "[" => nesting += 1,
+                        "}" | ")" | "]" => nesting -= 1,
+                        _ => {}
+                    }
+                    nesting == 0
+                });
+                if let Some(close) = close {
+                    blocks.push((name, tokens[open].start, tokens[close].end));
+                    index = close + 1;
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        index += 1;
+    }
+
+    let mut names = BTreeSet::new();
+    let mut parent = String::new();
+    let mut children = Vec::new();
+    let mut last = 0;
+    for (name, start, end) in blocks {
+        if !names.insert(name) {
+            return Err(invalid(format!("module '{}' is declared twice", name)));
+        }
+        parent.push_str(code[last..start].trim_end_matches([' ', '\t']));
+        parent.push(';');
+        last = end;
+        children.push((name, code[start + 1..end - 1].trim().to_string()));
+    }
+    parent.push_str(&code[last..]);
+    let parent = match children.is_empty() {
+        true => parent,
+        false => crate::format_str_with(parent, options)?,
+    };
+    files.push((path, parent));
+    for (name, body) in children {
+        let body = crate::format_str_with(body, options)?;
+        let child_path = dir.join(format!("{}.rs", name));
+        split(body, child_path, &dir.join(name), options, files)?;
+    }
+    Ok(())
+}
+
+/// Validate a module and collect the files of it and its descendants.
+///
+/// # Arguments
+///
+/// * `module`: The module.
+/// * `path`: The path of the file of the module.
+/// * `dir`: The directory of the files of the child modules.
+/// * `files`: The collected files.
+fn collect<'a>(
+    module: &'a Module,
+    path: PathBuf,
+    dir: &Path,
+    files: &mut Vec<(PathBuf, &'a Module)>,
+) -> Result<(), Error> {
+    if module.path.is_some() && !module.children.is_empty() {
+        return Err(invalid(format!(
+            "module '{}' has a custom path and child modules",
+            module.name
+        )));
+    }
+    let declaring_dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+    files.push((path, module));
+
+    let mut names = BTreeSet::new();
+    for child in &module.children {
+        if !is_identifier(&child.name) {
+            return Err(invalid(format!("'{}' is not a module name", child.name)));
+        }
+        if !names.insert(child.name.as_str()) {
+            return Err(invalid(format!(
+                "module '{}' is declared twice",
+                child.name
+            )));
+        }
+        let child_path = match &child.path {
+            Some(custom) => declaring_dir.join(custom),
+            None => dir.join(format!("{}.rs", child.name)),
+        };
+        collect(child, child_path, &dir.join(&child.name), files)?;
+    }
+    Ok(())
+}
+
+/// Determine whether a name is a (non-raw) identifier.
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|first| first == '_' || first.is_alphabetic())
+        && chars.all(|c| c == '_' || c.is_alphanumeric())
+        && name != "_"
+}
+
+/// Remove `.` components from a path, so that paths of the same file compare equal.
+fn normalize(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|component| *component != std::path::Component::CurDir)
+        .collect()
+}
+
+/// Create the error for an invalid tree.
+fn invalid(reason: String) -> Error {
+    Error::InvalidModuleTree { reason }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Module, ModuleTree};
+    use crate::Error;
+    use std::fs::read_to_string;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_write() {
+        let dir = PathBuf::from("target/module_tree");
+        let _ = std::fs::remove_dir_all(&dir);
+        let tree = ModuleTree::new(&dir)
+            .content("pub fn root(){}")
+            .module(
+                Module::new("types")
+                    .public(true)
+                    .content("pub struct Message;")
+                    .reexport("Message")
+                    .child(Module::new("wire").content("pub fn encode(){}")),
+            )
+            .module(Module::new("ffi").path("bindings.rs"));
+
+        let files = tree.write().unwrap();
+
+        assert_eq!(
+            vec![
+                dir.join("lib.rs"),
+                dir.join("types.rs"),
+                dir.join("types/wire.rs"),
+                dir.join("bindings.rs"),
+            ],
+            files
+        );
+        assert_eq!(
+            "#[path = \"bindings.rs\"]\nmod ffi;\npub mod types;\npub use types::Message;\npub fn root() {}\n",
+            read_to_string(dir.join("lib.rs")).unwrap()
+        );
+        assert_eq!(
+            "mod wire;\npub struct Message;\n",
+            read_to_string(dir.join("types.rs")).unwrap()
+        );
+
+        std::fs::write(dir.join("stale.rs"), "").unwrap();
+        assert!(matches!(tree.write(), Err(Error::InvalidModuleTree { .. })));
+        let duplicate = ModuleTree::new(&dir)
+            .module(Module::new("a"))
+            .module(Module::new("b").path("a.rs"));
+        assert!(matches!(
+            duplicate.write(),
+            Err(Error::InvalidModuleTree { .. })
+        ));
+    }
+
+    #[test]
+    fn test_split_by_modules() {
+        let source = "//! Generated.\n\
+            #[cfg(test)] mod tests { #[test] fn test() { mod local { } } }\n\
+            pub(crate) mod a { //! Module a.\n\
+                pub fn f() -> &'static str { \"mod x { }\" } pub mod b { pub struct B; } }\n\
+            fn main() {}";
+        let files = super::split_by_modules(source).unwrap();
+
+        assert_eq!(
+            vec![
+                PathBuf::from("lib.rs"),
+                PathBuf::from("tests.rs"),
+                PathBuf::from("a.rs"),
+                PathBuf::from("a/b.rs"),
+            ],
+            files
+                .iter()
+                .map(|(path, _)| path.clone())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            "//! Generated.\npub(crate) mod a;\n#[cfg(test)]\nmod tests;\nfn main() {}\n",
+            files[0].1
+        );
+        assert_eq!("#[test]\nfn test() {\n    mod local {}\n}\n", files[1].1);
+        assert_eq!(
+            "//! Module a.\npub fn f() -> &'static str {\n    \"mod x { }\"\n}\npub mod b;\n",
+            files[2].1
+        );
+        assert_eq!("pub struct B;\n", files[3].1);
+        assert!(matches!(
+            super::split_by_modules("mod a {} mod a {}"),
+            Err(Error::InvalidModuleTree { .. })
+        ));
+    }
+}
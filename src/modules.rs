@@ -0,0 +1,472 @@
+//! Resolution of the files of the modules of a crate, including modules with `#[path]` attributes.
+
+use crate::file_system::{FileSystem, StdFileSystem};
+use crate::lexer::{tokenize, Token, TokenKind};
+use crate::Error;
+use std::collections::{HashSet, VecDeque};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+/// The policy for `mod` declarations with a `#[path = "..."]` attribute.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub enum PathAttributePolicy {
+    /// Follow every attribute, wherever the file is located.
+    #[default]
+    Follow,
+    /// Follow only attributes whose file is below the root directory of the crate, so that files
+    /// of other crates or of the system are never formatted.
+    WithinCrate,
+    /// Ignore the modules that are declared with an attribute, along with their child modules.
+    Ignore,
+}
+
+/// A module of a crate and the file that it was resolved to.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct ResolvedModule {
+    /// The path of the module, e.g. `crate::ffi::types`.
+    pub name: String,
+    /// The file of the module.
+    pub path: PathBuf,
+    /// The root file of the crate target that contains the module, e.g. `src/lib.rs`.
+    pub crate_root: PathBuf,
+    /// The file that declares the module, or `None` for the root file of a target.
+    pub declared_in: Option<PathBuf>,
+    /// The value of the `#[path]` attribute of the declaration, if any.
+    pub path_attribute: Option<String>,
+}
+
+/// A resolver of the files of the modules of a crate, following the `mod` declarations of the
+/// files as rustc does.
+///
+/// The files of a crate are found by walking its target directories, which misses modules that a
+/// `#[path = "..."]` attribute locates elsewhere, e.g. bindings that a build step generates next
+/// to the sources. The resolver starts at the root files of the targets — `src/lib.rs`,
+/// `src/main.rs`, `src/bin/*.rs`, the files directly below `tests`, `benches` and `examples`, the
+/// `main.rs` files of their subdirectories and `build.rs` — and reports every module file that
+/// exists exactly once, even if several declarations refer to it. Declarations whose file does not
+/// exist, e.g. platform-specific modules, are ignored, and attributes inside `cfg_attr` are not
+/// evaluated.
+///
+/// Use [`crate::batch::BatchOptions::module_resolver`] to format the resolved files along with the
+/// files of the target directories.
+///
+/// # Examples
+///
+/// ```no_run
+/// use rust_format::modules::{ModuleResolver, PathAttributePolicy};
+///
+/// let resolver = ModuleResolver::new().path_attributes(PathAttributePolicy::WithinCrate);
+/// for module in resolver.resolve(".")? {
+///     println!("{} -> {}", module.name, module.path.display());
+/// }
+/// # Ok::<(), rust_format::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct ModuleResolver {
+    path_attributes: PathAttributePolicy,
+    file_system: Arc<dyn FileSystem>,
+}
+
+impl ModuleResolver {
+    /// Create a resolver that follows every `#[path]` attribute.
+    pub fn new() -> Self {
+        Self {
+            path_attributes: PathAttributePolicy::default(),
+            file_system: Arc::new(StdFileSystem),
+        }
+    }
+
+    /// Set the policy for `mod` declarations with a `#[path]` attribute.
+    ///
+    /// # Arguments
+    ///
+    /// * `policy`: The policy.
+    pub fn path_attributes(mut self, policy: PathAttributePolicy) -> Self {
+        self.path_attributes = policy;
+        self
+    }
+
+    /// Retrieve the policy for `mod` declarations with a `#[path]` attribute.
+    pub fn get_path_attributes(&self) -> PathAttributePolicy {
+        self.path_attributes
+    }
+
+    /// Set the file system to read. The file system of the operating system is used by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `file_system`: The file system.
+    pub fn file_system(mut self, file_system: Arc<dyn FileSystem>) -> Self {
+        self.file_system = file_system;
+        self
+    }
+
+    /// Resolve the modules of a crate.
+    ///
+    /// # Arguments
+    ///
+    /// * `root`: The root directory of the crate, which contains its `Cargo.toml`.
+    ///
+    /// # Returns
+    ///
+    /// The modules, ordered by target and then in the order in which they are reached, starting
+    /// with the root module of every target. The paths are joined to `root`, with `.` and `..`
+    /// components removed.
+    pub fn resolve(&self, root: impl AsRef<Path>) -> Result<Vec<ResolvedModule>, Error> {
+        let root = normalize(root.as_ref());
+        let mut modules = Vec::new();
+        let mut seen = HashSet::new();
+        for crate_root in self.crate_roots(&root) {
+            if !seen.insert(crate_root.clone()) {
+                continue;
+            }
+            let mut pending = VecDeque::from([(
+                ResolvedModule {
+                    name: "crate".to_string(),
+                    path: crate_root.clone(),
+                    crate_root: crate_root.clone(),
+                    declared_in: None,
+                    path_attribute: None,
+                },
+                true,
+            )]);
+            while let Some((module, mod_rs)) = pending.pop_front() {
+                let source = self.file_system.read(&module.path)?;
+                for declaration in declarations(&String::from_utf8_lossy(&source)) {
+                    let Some((path, mod_rs)) = self.locate(&module.path, mod_rs, &declaration)
+                    else {
+                        continue;
+                    };
+                    let within = path.starts_with(&root)
+                        && path.is_absolute() == root.is_absolute()
+                        && !path.components().any(|c| c == Component::ParentDir);
+                    if self.path_attributes == PathAttributePolicy::WithinCrate && !within {
+                        continue;
+                    }
+                    if !seen.insert(path.clone()) {
+                        continue;
+                    }
+                    let mut name = module.name.clone();
+                    for segment in declaration.inline.iter().chain([&declaration.module]) {
+                        name.push_str("::");
+                        name.push_str(&segment.name);
+                    }
+                    let child = ResolvedModule {
+                        name,
+                        path,
+                        crate_root: crate_root.clone(),
+                        declared_in: Some(module.path.clone()),
+                        path_attribute: declaration.module.path.clone(),
+                    };
+                    pending.push_back((child, mod_rs));
+                }
+                modules.push(module);
+            }
+        }
+        Ok(modules)
+    }
+
+    /// Collect the root files of the targets of a crate that exist.
+    fn crate_roots(&self, root: &Path) -> Vec<PathBuf> {
+        let mut roots = vec![root.join("src/lib.rs"), root.join("src/main.rs")];
+        for dir in ["src/bin", "tests", "benches", "examples"] {
+            let mut entries = self
+                .file_system
+                .read_dir(&root.join(dir))
+                .unwrap_or_default();
+            entries.sort();
+            for entry in entries {
+                match entry.extension().is_some_and(|extension| extension == "rs") {
+                    true => roots.push(entry),
+                    false => roots.push(entry.join("main.rs")),
+                }
+            }
+        }
+        roots.push(root.join("build.rs"));
+        roots.retain(|path| self.is_file(path));
+        roots
+    }
+
+    /// Determine the file of a declared module and whether it is a `mod.rs` file, whose child
+    /// modules are located in its directory, if the file exists and the policy allows it.
+    fn locate(
+        &self,
+        file: &Path,
+        mod_rs: bool,
+        declaration: &Declaration,
+    ) -> Option<(PathBuf, bool)> {
+        let has_attribute = declaration.module.path.is_some()
+            || declaration
+                .inline
+                .iter()
+                .any(|inline| inline.path.is_some());
+        if has_attribute && self.path_attributes == PathAttributePolicy::Ignore {
+            return None;
+        }
+        let dir = file.parent().unwrap_or(Path::new(""));
+        // The directory of the child modules of the file.
+        let mut base = match mod_rs {
+            true => dir.to_path_buf(),
+            false => dir.join(file.file_stem().unwrap_or_default()),
+        };
+        for inline in &declaration.inline {
+            base.push(inline.path.as_ref().unwrap_or(&inline.name));
+        }
+        let module = &declaration.module;
+        let (path, mod_rs) = match &module.path {
+            // Outside of inline modules, the attribute is relative to the directory of the file,
+            // even if that is not a `mod.rs` file. Files with an attribute behave like `mod.rs`
+            // files.
+            Some(path) if declaration.inline.is_empty() => (dir.join(path), true),
+            Some(path) => (base.join(path), true),
+            None => match base.join(format!("{}.rs", module.name)) {
+                path if self.is_file(&path) => (path, false),
+                _ => (base.join(&module.name).join("mod.rs"), true),
+            },
+        };
+        let path = normalize(&path);
+        self.is_file(&path).then_some((path, mod_rs))
+    }
+
+    /// Determine whether a path refers to a file.
+    fn is_file(&self, path: &Path) -> bool {
+        self.file_system
+            .metadata(path)
+            .is_ok_and(|metadata| !metadata.is_dir)
+    }
+}
+
+impl Default for ModuleResolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A module in a `mod` declaration, along with its `#[path]` attribute.
+#[derive(Debug, Clone)]
+struct Segment {
+    name: String,
+    path: Option<String>,
+}
+
+/// A `mod` declaration without a body.
+#[derive(Debug, Clone)]
+struct Declaration {
+    /// The inline modules (`mod name { ... }`) that contain the declaration, outermost first.
+    inline: Vec<Segment>,
+    /// The declared module.
+    module: Segment,
+}
+
+/// Find the `mod` declarations without a body in source code, skipping those inside functions,
+/// macros and other items, where rustc does not allow them.
+fn declarations(source: &str) -> Vec<Declaration> {
+    let tokens: Vec<Token> = tokenize(source)
+        .into_iter()
+        .filter(|token| token.kind != TokenKind::Whitespace && !token.kind.is_comment())
+        .collect();
+    let text = |index: usize| tokens.get(index).map_or("", |token| token.text(source));
+    let mut declarations = Vec::new();
+    // The open braces, along with the inline module that each one starts, if any.
+    let mut braces: Vec<Option<Segment>> = Vec::new();
+    let mut path = None;
+    let mut index = 0;
+    while index < tokens.len() {
+        match text(index) {
+            "#" if text(index + 1) == "[" => {
+                let close = attribute_end(source, &tokens, index + 1);
+                if close == index + 5
+                    && text(index + 2) == "path"
+                    && text(index + 3) == "="
+                    && tokens[index + 4].kind == TokenKind::Str
+                {
+                    path = Some(unquote(text(index + 4)));
+                }
+                index = close + 1;
+                continue;
+            }
+            "mod"
+                if matches!(text(index + 2), ";" | "{")
+                    && tokens[index + 1].kind == TokenKind::Ident =>
+            {
+                let in_modules = braces.iter().all(Option::is_some);
+                let segment = Segment {
+                    name: text(index + 1).trim_start_matches("r#").to_string(),
+                    path: path.take(),
+                };
+                match text(index + 2) {
+                    ";" if in_modules => declarations.push(Declaration {
+                        inline: braces.iter().flatten().cloned().collect(),
+                        module: segment,
+                    }),
+                    ";" => {}
+                    _ => braces.push(in_modules.then_some(segment)),
+                }
+                index += 3;
+                continue;
+            }
+            "{" => {
+                braces.push(None);
+                path = None;
+            }
+            "}" => {
+                braces.pop();
+                path = None;
+            }
+            ";" => path = None,
+            _ => {}
+        }
+        index += 1;
+    }
+    declarations
+}
+
+/// Determine the index of the closing bracket of an attribute whose opening bracket is at a token,
+/// or the index of the last token if it is not closed.
+fn attribute_end(source: &str, tokens: &[Token], open: usize) -> usize {
+    let mut depth = 0usize;
+    for (index, token) in tokens.iter().enumerate().skip(open) {
+        match token.text(source) {
+            "(" | "[" | "{" => depth += 1,
+            ")" | "]" | "}" => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+        if depth == 0 {
+            return index;
+        }
+    }
+    tokens.len() - 1
+}
+
+/// Determine the value of a string literal, handling raw strings and escaped quotes and
+/// backslashes.
+fn unquote(literal: &str) -> String {
+    match literal.strip_prefix('r') {
+        Some(raw) => raw.trim_matches('#').trim_matches('"').to_string(),
+        None => literal
+            .trim_matches('"')
+            .replace("\\\\", "\\")
+            .replace("\\\"", "\""),
+    }
+}
+
+/// Remove the `.` and `..` components of a path without accessing the file system.
+pub(crate) fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir
+                if normalized
+                    .components()
+                    .next_back()
+                    .is_some_and(|last| matches!(last, Component::Normal(_))) =>
+            {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ModuleResolver, PathAttributePolicy};
+    use crate::file_system::MemoryFileSystem;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_resolve() {
+        let file_system = Arc::new(MemoryFileSystem::new());
+        for (path, content) in [
+            (
+                "app/src/lib.rs",
+                "mod a;\n#[path = \"../gen/bindings.rs\"]\npub mod ffi;\n\
+                 mod inline {\n    mod nested;\n}\n#[path = \"a.rs\"]\nmod again;\n\
+                 fn f() {\n    mod local {}\n}\nmod missing;\n",
+            ),
+            (
+                "app/src/main.rs",
+                "mod a;\n#[path = \"/outside/x.rs\"]\nmod x;\n",
+            ),
+            ("app/src/a.rs", "// mod commented;\nmod b;\n"),
+            ("app/src/a/b.rs", ""),
+            ("app/src/inline/nested.rs", ""),
+            ("app/gen/bindings.rs", "mod sys;\n"),
+            ("app/gen/sys/mod.rs", ""),
+            ("/outside/x.rs", ""),
+        ] {
+            file_system.insert(path, content);
+        }
+        let resolve = |policy| {
+            ModuleResolver::new()
+                .path_attributes(policy)
+                .file_system(file_system.clone())
+                .resolve("app/./")
+                .unwrap()
+        };
+
+        let modules = resolve(PathAttributePolicy::Follow);
+        let mapping: Vec<(&str, &str)> = modules
+            .iter()
+            .map(|module| (module.name.as_str(), module.path.to_str().unwrap()))
+            .collect();
+        assert_eq!(
+            vec![
+                ("crate", "app/src/lib.rs"),
+                ("crate::a", "app/src/a.rs"),
+                ("crate::ffi", "app/gen/bindings.rs"),
+                ("crate::inline::nested", "app/src/inline/nested.rs"),
+                ("crate::a::b", "app/src/a/b.rs"),
+                ("crate::ffi::sys", "app/gen/sys/mod.rs"),
+                ("crate", "app/src/main.rs"),
+                ("crate::x", "/outside/x.rs"),
+            ],
+            mapping
+        );
+        assert_eq!(
+            Some("../gen/bindings.rs"),
+            modules[2].path_attribute.as_deref()
+        );
+        assert_eq!(
+            Some(PathBuf::from("app/src/lib.rs")),
+            modules[2].declared_in
+        );
+        assert_eq!(PathBuf::from("app/src/main.rs"), modules[7].crate_root);
+
+        let paths = |policy| -> Vec<PathBuf> {
+            resolve(policy)
+                .into_iter()
+                .map(|module| module.path)
+                .collect()
+        };
+        assert!(!paths(PathAttributePolicy::WithinCrate).contains(&PathBuf::from("/outside/x.rs")));
+        assert_eq!(7, paths(PathAttributePolicy::WithinCrate).len());
+        assert_eq!(5, paths(PathAttributePolicy::Ignore).len());
+
+        #[cfg(feature = "walk")]
+        {
+            use crate::batch::BatchOptions;
+
+            let options = BatchOptions::new()
+                .file_system(file_system.clone())
+                .module_resolver(
+                    ModuleResolver::new().path_attributes(PathAttributePolicy::WithinCrate),
+                );
+            let results = crate::batch::format_crate_with("app", &options).unwrap();
+            let paths: Vec<PathBuf> = results.into_iter().map(|file| file.path).collect();
+            let expected = [
+                "app/src/a/b.rs",
+                "app/src/a.rs",
+                "app/src/inline/nested.rs",
+                "app/src/lib.rs",
+                "app/src/main.rs",
+                "app/gen/bindings.rs",
+                "app/gen/sys/mod.rs",
+            ];
+            assert_eq!(expected.map(PathBuf::from).to_vec(), paths);
+        }
+    }
+}
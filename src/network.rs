@@ -0,0 +1,149 @@
+//! Formatting of files on network filesystems, e.g. NFS and SMB shares.
+
+use crate::error::IoContext;
+use crate::{Error, Options};
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
+
+/// The delay before the first retry of an operation whose file handle became stale. Every further
+/// retry waits one more delay.
+const RETRY_DELAY: Duration = Duration::from_millis(50);
+
+/// The policy for writing files that are formatted in place.
+///
+/// Network filesystems give weaker guarantees than local ones: the writes of the formatting tool
+/// may reach the server incompletely if the client fails, renaming a file over one that another
+/// client has open fails on SMB shares, and file handles become stale (`ESTALE`) if the server or
+/// another client replaces a file. See [`Options::network_filesystem`] for options that are safe on
+/// such filesystems.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub enum WritePolicy {
+    /// Let the formatting tool write the files itself. Results that the crate writes, e.g. after
+    /// post-processing, replace the file as with [`WritePolicy::Replace`].
+    #[default]
+    Tool,
+    /// Format the files in memory, write the result to a temporary file next to the file, flush it
+    /// to the storage and rename it over the file. Readers never observe a partially written file.
+    ///
+    /// Child modules of a file are not formatted along with it.
+    Replace,
+    /// Format the files in memory, overwrite the file with the result and flush it to the storage.
+    /// The file is never renamed, so it keeps its identity, e.g. its ownership, and can be written
+    /// while other clients of an SMB share have it open. Readers may observe a partially written
+    /// file.
+    ///
+    /// Child modules of a file are not formatted along with it.
+    Overwrite,
+}
+
+impl WritePolicy {
+    /// Determine whether files are formatted in memory and written by the crate.
+    pub(crate) fn in_memory(self) -> bool {
+        self != WritePolicy::Tool
+    }
+}
+
+/// Read a file, retrying as configured if its file handle is stale.
+pub(crate) fn read_to_string(path: &Path, options: &Options) -> Result<String, Error> {
+    retry_stale(options, || std::fs::read_to_string(path).at(path))
+}
+
+/// Write the formatted content of a file according to the write policy, retrying as configured if
+/// its file handle is stale.
+pub(crate) fn write(path: &Path, contents: &[u8], options: &Options) -> Result<(), Error> {
+    retry_stale(options, || match options.get_write_policy() {
+        WritePolicy::Tool | WritePolicy::Replace => crate::temp::write_atomic(path, contents),
+        WritePolicy::Overwrite => overwrite(path, contents),
+    })
+}
+
+/// Replace the content of a file in place and flush it to the storage.
+fn overwrite(path: &Path, contents: &[u8]) -> Result<(), Error> {
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .truncate(true)
+        .open(path)
+        .at(path)?;
+    file.write_all(contents).at(path)?;
+    file.sync_all().at(path)
+}
+
+/// Perform an operation, repeating it while it fails because a file handle is stale, up to
+/// [`Options::get_stale_retries`] times.
+fn retry_stale<T>(
+    options: &Options,
+    mut operation: impl FnMut() -> Result<T, Error>,
+) -> Result<T, Error> {
+    let mut retries = 0;
+    loop {
+        match operation() {
+            Err(error) if retries < options.get_stale_retries() && is_stale(&error) => {
+                retries += 1;
+                std::thread::sleep(RETRY_DELAY * retries);
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Determine whether an error is caused by a stale file handle.
+fn is_stale(error: &Error) -> bool {
+    match error {
+        Error::FileAccess { source, .. } | Error::IoError { source } => {
+            source.kind() == std::io::ErrorKind::StaleNetworkFileHandle
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::WritePolicy;
+    use crate::{Error, Options};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_network_filesystem() {
+        let stale = || Error::IoError {
+            source: std::io::ErrorKind::StaleNetworkFileHandle.into(),
+        };
+        let options = Options::new().network_filesystem();
+        let mut attempts = 0;
+        let result = super::retry_stale(&options, || {
+            attempts += 1;
+            match attempts {
+                1 | 2 => Err(stale()),
+                _ => Ok(attempts),
+            }
+        });
+        assert_eq!(3, result.unwrap());
+        let mut attempts = 0;
+        let result: Result<(), Error> = super::retry_stale(&Options::new(), || {
+            attempts += 1;
+            Err(stale())
+        });
+        assert!(result.is_err());
+        assert_eq!(1, attempts);
+
+        let dir = PathBuf::from("target/network");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        for policy in [WritePolicy::Replace, WritePolicy::Overwrite] {
+            let path = dir.join(format!("{:?}.rs", policy));
+            std::fs::write(&path, "fn main(){}").unwrap();
+            #[cfg(unix)]
+            let inode = std::os::unix::fs::MetadataExt::ino(&std::fs::metadata(&path).unwrap());
+            let options = options.clone().write_policy(policy);
+            crate::format_file_with(&path, &options).unwrap();
+            assert_eq!("fn main() {}\n", std::fs::read_to_string(&path).unwrap());
+            #[cfg(unix)]
+            assert_eq!(
+                policy == WritePolicy::Overwrite,
+                inode == std::os::unix::fs::MetadataExt::ino(&std::fs::metadata(&path).unwrap())
+            );
+        }
+        // Neither policy leaves temporary files behind.
+        assert_eq!(2, std::fs::read_dir(&dir).unwrap().count());
+    }
+}
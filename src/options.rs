@@ -0,0 +1,1252 @@
+//! Formatting options.
+
+use crate::audit::AuditLog;
+use crate::backend::FormatBackend;
+#[cfg(feature = "process-backend")]
+use crate::backend::Rustfmt;
+use crate::backup::Backup;
+use crate::events::EventSink;
+use crate::report::Verbosity;
+use crate::{
+    Banner, BlankLines, CompileCheck, Config, GeneratedFileProfile, Hooks, Limits,
+    ModificationPolicy, PostProcessor, Profile, ReadOnlyPolicy, ResolvedOptions, TempPolicy,
+    WritePolicy,
+};
+use std::sync::Arc;
+
+/// The settings of a [fingerprint](Options::fingerprint), in a format that does not depend on the
+/// `Debug` output of their types.
+#[derive(Debug, Default)]
+pub(crate) struct Fingerprint(String);
+
+impl Fingerprint {
+    /// Add a setting whose value never contains a line break, e.g. a number, a flag or a name.
+    pub(crate) fn value(&mut self, key: &str, value: impl std::fmt::Display) {
+        use std::fmt::Write;
+
+        let _ = writeln!(self.0, "{}={}", key, value);
+    }
+
+    /// Add a setting whose value may contain any text, or `None` if it is not set.
+    pub(crate) fn text(&mut self, key: &str, value: Option<&str>) {
+        match value {
+            Some(value) => self.value(key, format_args!("{}:{}", value.len(), value)),
+            None => self.value(key, "none"),
+        }
+    }
+
+    /// Compute the fingerprint of the settings.
+    fn finish(self) -> u64 {
+        let digest = crate::hash::sha256(self.0.as_bytes());
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(&digest[..8]);
+        u64::from_be_bytes(bytes)
+    }
+}
+
+/// A Rust edition.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Edition {
+    /// The 2015 edition.
+    #[cfg_attr(feature = "serde", serde(rename = "2015"))]
+    E2015,
+    /// The 2018 edition.
+    #[cfg_attr(feature = "serde", serde(rename = "2018"))]
+    E2018,
+    /// The 2021 edition.
+    #[cfg_attr(feature = "serde", serde(rename = "2021"))]
+    E2021,
+    /// The 2024 edition.
+    #[cfg_attr(feature = "serde", serde(rename = "2024"))]
+    E2024,
+}
+
+impl Edition {
+    /// Retrieve the edition as it is passed to the formatting tool, e.g. `2021`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Edition::E2015 => "2015",
+            Edition::E2018 => "2018",
+            Edition::E2021 => "2021",
+            Edition::E2024 => "2024",
+        }
+    }
+
+    /// Parse an edition, e.g. `2021`.
+    pub(crate) fn parse(value: &str) -> Option<Self> {
+        match value {
+            "2015" => Some(Edition::E2015),
+            "2018" => Some(Edition::E2018),
+            "2021" => Some(Edition::E2021),
+            "2024" => Some(Edition::E2024),
+            _ => None,
+        }
+    }
+}
+
+/// Options that control a formatting operation.
+///
+/// # Examples
+///
+/// ```
+/// use rust_format::Options;
+///
+/// let options = Options::new().max_input_bytes(1024 * 1024);
+/// let formatted = rust_format::format_str_with("fn main(){}", &options).unwrap();
+/// assert_eq!("fn main() {}\n", formatted);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    edition: Option<Edition>,
+    profile: Profile,
+    config: Config,
+    max_input_bytes: Option<u64>,
+    max_stdout_bytes: Option<u64>,
+    max_stderr_bytes: Option<u64>,
+    limits: Limits,
+    #[cfg(feature = "syn")]
+    validate_syntax: bool,
+    validate_config: bool,
+    degrade_unsupported_config: bool,
+    preserve_comments: bool,
+    preserve_literals: bool,
+    reject_line_overflow: bool,
+    #[cfg(feature = "diff")]
+    verify_tokens: bool,
+    check_compile: CompileCheck,
+    blank_lines: BlankLines,
+    trim_trailing_whitespace: bool,
+    banner: Option<Banner>,
+    generated_file_profile: Option<GeneratedFileProfile>,
+    shebang: Option<String>,
+    post_processors: Vec<Arc<dyn PostProcessor>>,
+    skip_children: bool,
+    #[cfg(feature = "install")]
+    auto_install: bool,
+    #[cfg(feature = "signals")]
+    forward_interrupts: bool,
+    verbosity: Verbosity,
+    backup: Backup,
+    read_only: ReadOnlyPolicy,
+    modification: ModificationPolicy,
+    write_policy: WritePolicy,
+    stale_retries: u32,
+    verify_applied: bool,
+    /// Whether the files are known not to be formatted correctly, e.g. from a prior check.
+    expect_changes: bool,
+    temp: TempPolicy,
+    deterministic_temp_names: bool,
+    audit_log: Option<AuditLog>,
+    events: Option<EventSink>,
+    hooks: Hooks,
+    backend: Option<Arc<dyn FormatBackend>>,
+}
+
+impl Options {
+    /// Create a new instance with default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the edition of the source code.
+    ///
+    /// By default, the formatting tool determines the edition itself. Source code passed through
+    /// standard input is treated as 2015 edition code in that case.
+    ///
+    /// # Arguments
+    ///
+    /// * `edition`: The edition.
+    pub fn edition(mut self, edition: Edition) -> Self {
+        self.edition = Some(edition);
+        self
+    }
+
+    /// Retrieve the edition of the source code, if set.
+    pub fn get_edition(&self) -> Option<Edition> {
+        self.edition
+    }
+
+    /// Set the profile that provides the base configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `profile`: The profile.
+    pub fn profile(mut self, profile: Profile) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    /// Retrieve the profile.
+    pub fn get_profile(&self) -> Profile {
+        self.profile
+    }
+
+    /// Set the configuration of the formatting tool.
+    ///
+    /// The settings take precedence over the settings of the [profile](Options::profile). See
+    /// [`crate::ConfigLayers`] for combining them with settings from other sources.
+    ///
+    /// # Arguments
+    ///
+    /// * `config`: The configuration.
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Retrieve the configuration of the formatting tool, excluding the profile settings.
+    pub fn get_config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Retrieve the configuration that is passed to the formatting tool, i.e. the profile settings
+    /// combined with the explicit configuration.
+    pub fn effective_config(&self) -> Config {
+        self.profile.config().merge(&self.config)
+    }
+
+    /// Resolve the settings that a formatting operation with these options runs with.
+    ///
+    /// This shows how the profile, the explicit configuration and the defaults combine, e.g. to
+    /// find out why code was formatted in a certain way. See [`ResolvedOptions::to_json`] for a
+    /// machine-readable encoding.
+    pub fn effective_options(&self) -> ResolvedOptions {
+        ResolvedOptions::of(self)
+    }
+
+    /// Set the maximum size of the input in bytes.
+    ///
+    /// Inputs that exceed this size are rejected with [`crate::Error::InputTooLarge`] before the
+    /// formatting tool is started. By default, the input size is not limited.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_input_bytes`: The maximum size in bytes.
+    pub fn max_input_bytes(mut self, max_input_bytes: u64) -> Self {
+        self.max_input_bytes = Some(max_input_bytes);
+        self
+    }
+
+    /// Retrieve the maximum size of the input in bytes, if any.
+    pub fn get_max_input_bytes(&self) -> Option<u64> {
+        self.max_input_bytes
+    }
+
+    /// Set the maximum number of bytes that are captured from the stdout of the formatting tool.
+    ///
+    /// If a successful run produces more output, it fails with [`crate::Error::OutputTooLarge`].
+    /// The stdout of failed runs is truncated to the limit and ends with a truncation marker. The
+    /// remaining output is discarded while it is read, so it never accumulates in memory. By
+    /// default, the output size is not limited.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_stdout_bytes`: The maximum size in bytes.
+    pub fn max_stdout_bytes(mut self, max_stdout_bytes: u64) -> Self {
+        self.max_stdout_bytes = Some(max_stdout_bytes);
+        self
+    }
+
+    /// Retrieve the maximum number of bytes that are captured from stdout, if any.
+    pub fn get_max_stdout_bytes(&self) -> Option<u64> {
+        self.max_stdout_bytes
+    }
+
+    /// Set the maximum number of bytes that are captured from the stderr of the formatting tool.
+    ///
+    /// Longer diagnostics are truncated to the limit and end with a truncation marker. By default,
+    /// the size is not limited.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_stderr_bytes`: The maximum size in bytes.
+    pub fn max_stderr_bytes(mut self, max_stderr_bytes: u64) -> Self {
+        self.max_stderr_bytes = Some(max_stderr_bytes);
+        self
+    }
+
+    /// Retrieve the maximum number of bytes that are captured from stderr, if any.
+    pub fn get_max_stderr_bytes(&self) -> Option<u64> {
+        self.max_stderr_bytes
+    }
+
+    /// Set the limits on the shape of the input. No limits are enforced by default.
+    ///
+    /// Inputs that violate the limits are rejected with [`crate::Error::InputRejected`] before the
+    /// formatting tool is started.
+    ///
+    /// # Arguments
+    ///
+    /// * `limits`: The limits.
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Retrieve the limits on the shape of the input.
+    pub fn get_limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    /// Enable syntax validation before formatting.
+    ///
+    /// When enabled, the source code is parsed with [`syn`] before the formatting tool is started
+    /// and invalid code is rejected with [`crate::Error::Syntax`]. Disabled by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `validate_syntax`: Whether to validate the syntax.
+    #[cfg(feature = "syn")]
+    pub fn validate_syntax(mut self, validate_syntax: bool) -> Self {
+        self.validate_syntax = validate_syntax;
+        self
+    }
+
+    /// Determine whether syntax validation is enabled.
+    #[cfg(feature = "syn")]
+    pub fn get_validate_syntax(&self) -> bool {
+        self.validate_syntax
+    }
+
+    /// Enable validation of the configuration before formatting.
+    ///
+    /// When enabled, every setting of the effective configuration is checked against the settings
+    /// that the backend reports (see [`FormatBackend::config_schema`]), and unknown settings or
+    /// settings that the tool does not support on its channel are rejected with
+    /// [`crate::Error::UnsupportedOption`]. Otherwise, the tool merely prints a warning and ignores
+    /// them. Backends that do not report any settings are not validated. Disabled by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `validate_config`: Whether to validate the configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rust_format::{Config, Error, Options};
+    ///
+    /// let options = Options::new()
+    ///     .config(Config::new().set("max_widht", 80))
+    ///     .validate_config(true);
+    /// assert!(matches!(
+    ///     rust_format::format_str_with("fn main() {}", &options),
+    ///     Err(Error::UnsupportedOption { .. })
+    /// ));
+    /// ```
+    pub fn validate_config(mut self, validate_config: bool) -> Self {
+        self.validate_config = validate_config;
+        self
+    }
+
+    /// Determine whether validation of the configuration is enabled.
+    pub fn get_validate_config(&self) -> bool {
+        self.validate_config
+    }
+
+    /// Enable graceful degradation of settings that the formatting tool does not support on its
+    /// channel, e.g. [`Config::format_macro_bodies`] with a stable tool.
+    ///
+    /// When enabled, such settings are not passed to the tool, and an enabled
+    /// [validation](Options::validate_config) does not reject them. Instead, every formatting
+    /// operation emits a warning event for each of them with the `tracing` feature, and they are
+    /// listed by [`Options::degraded_config`] and [`ResolvedOptions::degraded_config`]. The same
+    /// configuration can thus be used with stable and nightly versions of the tool. Unknown
+    /// settings are not affected. Disabled by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `degrade_unsupported_config`: Whether to skip unsupported settings.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rust_format::{Config, Options};
+    ///
+    /// let options = Options::new()
+    ///     .config(Config::new().format_macro_bodies(true))
+    ///     .validate_config(true)
+    ///     .degrade_unsupported_config(true);
+    /// let formatted = rust_format::format_str_with("macro_rules! m { () => {} }", &options);
+    /// assert!(formatted.is_ok());
+    /// ```
+    pub fn degrade_unsupported_config(mut self, degrade_unsupported_config: bool) -> Self {
+        self.degrade_unsupported_config = degrade_unsupported_config;
+        self
+    }
+
+    /// Determine whether graceful degradation of unsupported settings is enabled.
+    pub fn get_degrade_unsupported_config(&self) -> bool {
+        self.degrade_unsupported_config
+    }
+
+    /// Retrieve the settings that are not passed to the formatting tool because it does not
+    /// support them on its channel, if [degradation](Options::degrade_unsupported_config) is
+    /// enabled.
+    ///
+    /// # Returns
+    ///
+    /// The names of the settings in alphabetical order. The list is empty if degradation is
+    /// disabled, or if the backend does not report its settings.
+    pub fn degraded_config(&self) -> Vec<String> {
+        if !self.degrade_unsupported_config {
+            return Vec::new();
+        }
+        crate::schema::unsupported(&self.effective_config(), self.get_backend())
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|(_, required_channel)| required_channel.is_some())
+            .map(|(key, _)| key)
+            .collect()
+    }
+
+    /// Enable verification that no comments are lost during formatting.
+    ///
+    /// When enabled, a formatting result that lacks any comment of the input is rejected with
+    /// [`crate::Error::CommentsLost`]. When formatting a file, the original content is restored in
+    /// that case. Disabled by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `preserve_comments`: Whether to verify the comments.
+    pub fn preserve_comments(mut self, preserve_comments: bool) -> Self {
+        self.preserve_comments = preserve_comments;
+        self
+    }
+
+    /// Determine whether comment verification is enabled.
+    pub fn get_preserve_comments(&self) -> bool {
+        self.preserve_comments
+    }
+
+    /// Enable verification that string literals are not altered during formatting.
+    ///
+    /// When enabled, a formatting result that lacks any string, byte string or C string literal of
+    /// the input, byte for byte, is rejected with [`crate::Error::LiteralsAltered`]. This guards
+    /// content such as embedded SQL or templates. When formatting a file, the original content is
+    /// restored in that case. Disabled by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `preserve_literals`: Whether to verify the string literals.
+    pub fn preserve_literals(mut self, preserve_literals: bool) -> Self {
+        self.preserve_literals = preserve_literals;
+        self
+    }
+
+    /// Determine whether string literal verification is enabled.
+    pub fn get_preserve_literals(&self) -> bool {
+        self.preserve_literals
+    }
+
+    /// Enable rejection of formatting results with lines that exceed the maximum width.
+    ///
+    /// The formatting tool leaves lines that it cannot break, e.g. long string literals or
+    /// comments, as they are. When enabled, such a result is rejected with
+    /// [`crate::Error::LineOverflow`], which lists the lines (see [`crate::overflowing_lines`]).
+    /// When formatting a file, the original content is restored in that case. Disabled by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `reject_line_overflow`: Whether to reject lines that are too long.
+    pub fn reject_line_overflow(mut self, reject_line_overflow: bool) -> Self {
+        self.reject_line_overflow = reject_line_overflow;
+        self
+    }
+
+    /// Determine whether lines that exceed the maximum width are rejected.
+    pub fn get_reject_line_overflow(&self) -> bool {
+        self.reject_line_overflow
+    }
+
+    /// Enable verification that formatting does not alter the tokens of the source code.
+    ///
+    /// When enabled, a formatting result that does not consist of the same tokens as the input
+    /// (ignoring whitespace, comments, commas, block braces, the order of `use` and `mod`
+    /// declarations and the other rewrites that the tool applies by default, such as the explicit
+    /// `"C"` ABI) is rejected with [`crate::Error::SemanticDrift`]. When formatting a file, the
+    /// original content is restored in that case. Disabled by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `verify_tokens`: Whether to verify the tokens.
+    #[cfg(feature = "diff")]
+    pub fn verify_tokens(mut self, verify_tokens: bool) -> Self {
+        self.verify_tokens = verify_tokens;
+        self
+    }
+
+    /// Determine whether token verification is enabled.
+    #[cfg(feature = "diff")]
+    pub fn get_verify_tokens(&self) -> bool {
+        self.verify_tokens
+    }
+
+    /// Enable verification that the formatted code still compiles.
+    ///
+    /// When enabled, a formatting result that does not compile although the input does is rejected
+    /// with [`crate::Error::CompileCheckFailed`]. When formatting a file, the original content is
+    /// restored in that case. Disabled by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `check_compile`: The verification method.
+    pub fn check_compile(mut self, check_compile: CompileCheck) -> Self {
+        self.check_compile = check_compile;
+        self
+    }
+
+    /// Retrieve the method that verifies that the formatted code still compiles.
+    pub fn get_check_compile(&self) -> CompileCheck {
+        self.check_compile
+    }
+
+    /// Set the policy for the blank lines between top-level items, which is applied after
+    /// formatting. By default, the blank lines are preserved.
+    ///
+    /// # Arguments
+    ///
+    /// * `blank_lines`: The policy.
+    pub fn blank_lines(mut self, blank_lines: BlankLines) -> Self {
+        self.blank_lines = blank_lines;
+        self
+    }
+
+    /// Retrieve the policy for the blank lines between top-level items.
+    pub fn get_blank_lines(&self) -> BlankLines {
+        self.blank_lines
+    }
+
+    /// Set whether trailing spaces and tabs are removed after formatting. Disabled by default.
+    ///
+    /// The formatting tool leaves trailing whitespace in some places untouched, e.g. in comments
+    /// and macro bodies, which strict whitespace linters reject. Whitespace at the end of lines
+    /// that end inside a string literal is part of the value and is kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `trim_trailing_whitespace`: Whether to remove trailing whitespace.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rust_format::Options;
+    ///
+    /// let options = Options::new().trim_trailing_whitespace(true);
+    /// let formatted = rust_format::format_str_with("fn main() {} // done \n", &options).unwrap();
+    /// assert_eq!("fn main() {} // done\n", formatted);
+    /// ```
+    pub fn trim_trailing_whitespace(mut self, trim_trailing_whitespace: bool) -> Self {
+        self.trim_trailing_whitespace = trim_trailing_whitespace;
+        self
+    }
+
+    /// Retrieve whether trailing spaces and tabs are removed after formatting.
+    pub fn get_trim_trailing_whitespace(&self) -> bool {
+        self.trim_trailing_whitespace
+    }
+
+    /// Set a header and footer that are added to the formatted source code. No banner is added by
+    /// default.
+    ///
+    /// # Arguments
+    ///
+    /// * `banner`: The banner.
+    pub fn banner(mut self, banner: Banner) -> Self {
+        self.banner = Some(banner);
+        self
+    }
+
+    /// Retrieve the banner, if any.
+    pub fn get_banner(&self) -> Option<&Banner> {
+        self.banner.as_ref()
+    }
+
+    /// Set a block of a generated-code marker and lint attributes that is added to the formatted
+    /// source code. No block is added by default.
+    ///
+    /// The block is added before the [banner](Options::banner), so the banner becomes the first
+    /// line of new files.
+    ///
+    /// # Arguments
+    ///
+    /// * `profile`: The profile.
+    pub fn generated_file_profile(mut self, profile: GeneratedFileProfile) -> Self {
+        self.generated_file_profile = Some(profile);
+        self
+    }
+
+    /// Retrieve the generated-code profile, if any.
+    pub fn get_generated_file_profile(&self) -> Option<&GeneratedFileProfile> {
+        self.generated_file_profile.as_ref()
+    }
+
+    /// Set a shebang line that is put in front of formatted source code that has none, e.g. for
+    /// scripts that are run with `cargo script` or `rust-script`. Existing shebang lines are always
+    /// preserved.
+    ///
+    /// # Arguments
+    ///
+    /// * `shebang`: The shebang line, e.g. `#!/usr/bin/env rust-script`. The `#!` prefix is added
+    ///   if it is missing.
+    pub fn shebang(mut self, shebang: impl Into<String>) -> Self {
+        let shebang = shebang.into();
+        self.shebang = Some(match shebang.starts_with("#!") {
+            true => shebang,
+            false => format!("#!{}", shebang),
+        });
+        self
+    }
+
+    /// Retrieve the shebang line that is put in front of formatted source code, if any.
+    pub fn get_shebang(&self) -> Option<&str> {
+        self.shebang.as_deref()
+    }
+
+    /// Add a custom step that is applied to the formatted source code, after the built-in steps and
+    /// the steps that were added before.
+    ///
+    /// # Arguments
+    ///
+    /// * `processor`: The processor.
+    pub fn post_processor(mut self, processor: Arc<dyn PostProcessor>) -> Self {
+        self.post_processors.push(processor);
+        self
+    }
+
+    /// Retrieve the custom post-processing steps, in the order in which they are applied.
+    pub fn get_post_processors(&self) -> &[Arc<dyn PostProcessor>] {
+        &self.post_processors
+    }
+
+    /// Set whether formatting a file in place leaves the files of its `mod` declarations
+    /// untouched. By default, the formatting tool also formats the child modules of a file.
+    ///
+    /// This is useful when only the root file is generated, while its child modules are written by
+    /// hand or formatted with different settings.
+    ///
+    /// # Arguments
+    ///
+    /// * `skip_children`: Whether to skip child modules.
+    pub fn skip_children(mut self, skip_children: bool) -> Self {
+        self.skip_children = skip_children;
+        self
+    }
+
+    /// Retrieve whether formatting a file in place skips its child modules.
+    pub fn get_skip_children(&self) -> bool {
+        self.skip_children
+    }
+
+    /// Set the amount of detail in the logs and reports of operations. [`Verbosity::Normal`] is
+    /// used by default.
+    ///
+    /// The verbosity applies to:
+    ///
+    /// * Reports that are rendered with [`crate::report::ReportExt::render_with`].
+    /// * The logs of batch operations and directory walks (see `walk::Walk::verbosity`),
+    ///   which are emitted through `tracing` with the `tracing` feature: one line per reported
+    ///   file, as in rendered reports.
+    /// * The messages of blessing golden files (see `golden::bless`).
+    ///
+    /// # Arguments
+    ///
+    /// * `verbosity`: The verbosity.
+    pub fn verbosity(mut self, verbosity: Verbosity) -> Self {
+        self.verbosity = verbosity;
+        self
+    }
+
+    /// Retrieve the amount of detail in logs and reports.
+    pub fn get_verbosity(&self) -> Verbosity {
+        self.verbosity
+    }
+
+    /// Set the backup policy for files that are formatted in place. No backups are created by
+    /// default.
+    ///
+    /// # Arguments
+    ///
+    /// * `backup`: The backup policy.
+    pub fn backup(mut self, backup: Backup) -> Self {
+        self.backup = backup;
+        self
+    }
+
+    /// Retrieve the backup policy.
+    pub fn get_backup(&self) -> &Backup {
+        &self.backup
+    }
+
+    /// Set the policy for read-only files that are formatted in place. Such files are rejected by
+    /// default.
+    ///
+    /// # Arguments
+    ///
+    /// * `read_only`: The policy.
+    pub fn read_only(mut self, read_only: ReadOnlyPolicy) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// Retrieve the policy for read-only files.
+    pub fn get_read_only(&self) -> ReadOnlyPolicy {
+        self.read_only
+    }
+
+    /// Set the policy for files that are modified by another process while they are formatted in
+    /// place. Such files are overwritten by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `modification`: The policy.
+    pub fn modification(mut self, modification: ModificationPolicy) -> Self {
+        self.modification = modification;
+        self
+    }
+
+    /// Retrieve the policy for files that are modified by another process.
+    pub fn get_modification(&self) -> ModificationPolicy {
+        self.modification
+    }
+
+    /// Set the policy for writing files that are formatted in place. By default, the formatting
+    /// tool writes them itself.
+    ///
+    /// # Arguments
+    ///
+    /// * `write_policy`: The policy.
+    pub fn write_policy(mut self, write_policy: WritePolicy) -> Self {
+        self.write_policy = write_policy;
+        self
+    }
+
+    /// Retrieve the policy for writing files that are formatted in place.
+    pub fn get_write_policy(&self) -> WritePolicy {
+        self.write_policy
+    }
+
+    /// Set how often reading and writing a file that is formatted in place is retried if its file
+    /// handle is stale (`ESTALE`), e.g. because another client of a network filesystem replaced
+    /// it. Failures are not retried by default.
+    ///
+    /// The retries wait a little longer each time. Runs of the formatting tool are never retried.
+    ///
+    /// # Arguments
+    ///
+    /// * `stale_retries`: The maximum number of retries.
+    pub fn stale_retries(mut self, stale_retries: u32) -> Self {
+        self.stale_retries = stale_retries;
+        self
+    }
+
+    /// Retrieve how often operations on files with a stale file handle are retried.
+    pub fn get_stale_retries(&self) -> u32 {
+        self.stale_retries
+    }
+
+    /// Set whether to verify that the formatting tool applied its changes when it formats a file in
+    /// place. Disabled by default.
+    ///
+    /// The tool may exit successfully although the file is unchanged, e.g. because another process
+    /// restored it or because the tool did not write it as requested with `--emit files`. With
+    /// verification, the content hashes of the file before and after the run are compared. If the
+    /// file is unchanged, although a prior check found it not formatted correctly (see
+    /// [`crate::report::CheckResults::write_all_fixes`]) or it is still not formatted correctly,
+    /// formatting it fails with [`Error::ChangesNotApplied`]. Files that the crate writes itself,
+    /// e.g. with [`WritePolicy::Replace`], are not verified.
+    ///
+    /// # Arguments
+    ///
+    /// * `verify_applied`: Whether to verify that changes are applied.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rust_format::Options;
+    ///
+    /// let options = Options::new().verify_applied(true);
+    /// assert!(options.get_verify_applied());
+    /// ```
+    pub fn verify_applied(mut self, verify_applied: bool) -> Self {
+        self.verify_applied = verify_applied;
+        self
+    }
+
+    /// Retrieve whether to verify that the formatting tool applied its changes.
+    pub fn get_verify_applied(&self) -> bool {
+        self.verify_applied
+    }
+
+    /// Set whether the files are known not to be formatted correctly, so that verifying that
+    /// changes were applied does not need to check them again.
+    pub(crate) fn expect_changes(mut self, expect_changes: bool) -> Self {
+        self.expect_changes = expect_changes;
+        self
+    }
+
+    /// Retrieve whether the files are known not to be formatted correctly.
+    pub(crate) fn expects_changes(&self) -> bool {
+        self.expect_changes
+    }
+
+    /// Select the options that are safe for formatting files in place on network filesystems,
+    /// e.g. NFS and SMB shares.
+    ///
+    /// Files are formatted in memory and replace the original only once the result is flushed to
+    /// the storage ([`WritePolicy::Replace`]), and operations on stale file handles are retried 3
+    /// times. Select [`WritePolicy::Overwrite`] in addition for SMB shares on which renaming over a
+    /// file that other clients have open fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rust_format::{Options, WritePolicy};
+    ///
+    /// let options = Options::new().network_filesystem();
+    /// assert_eq!(WritePolicy::Replace, options.get_write_policy());
+    /// assert_eq!(3, options.get_stale_retries());
+    /// ```
+    pub fn network_filesystem(self) -> Self {
+        self.write_policy(WritePolicy::Replace).stale_retries(3)
+    }
+
+    /// Set the policy for the location of temporary files. The temporary directory of the system
+    /// is used by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `temp`: The policy.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rust_format::{Options, TempPolicy};
+    ///
+    /// let options = Options::new().temp(TempPolicy::Directory("target/tmp".into()));
+    /// ```
+    pub fn temp(mut self, temp: TempPolicy) -> Self {
+        self.temp = temp;
+        self
+    }
+
+    /// Retrieve the policy for the location of temporary files.
+    pub fn get_temp(&self) -> &TempPolicy {
+        &self.temp
+    }
+
+    /// Set whether the names of scratch files are derived from the hash of their content. Disabled
+    /// by default.
+    ///
+    /// Scratch files are passed to other tools, e.g. by [`crate::CompileCheck::Rustc`]. With random
+    /// names, repeated identical builds issue different commands, which defeats sandboxes that
+    /// cache by the arguments of processes. With deterministic names, the same content results in
+    /// the same command. If a file with the name exists, e.g. because the same content is checked
+    /// concurrently, a numbered suffix is appended.
+    ///
+    /// # Arguments
+    ///
+    /// * `deterministic_temp_names`: Whether to derive the names from the content.
+    pub fn deterministic_temp_names(mut self, deterministic_temp_names: bool) -> Self {
+        self.deterministic_temp_names = deterministic_temp_names;
+        self
+    }
+
+    /// Retrieve whether the names of scratch files are derived from the hash of their content.
+    pub fn get_deterministic_temp_names(&self) -> bool {
+        self.deterministic_temp_names
+    }
+
+    /// Set the audit log that records every file modified by formatting it in place.
+    ///
+    /// # Arguments
+    ///
+    /// * `audit_log`: The audit log.
+    pub fn audit_log(mut self, audit_log: AuditLog) -> Self {
+        self.audit_log = Some(audit_log);
+        self
+    }
+
+    /// Retrieve the audit log, if any.
+    pub fn get_audit_log(&self) -> Option<&AuditLog> {
+        self.audit_log.as_ref()
+    }
+
+    /// Set the sink for the events of batch runs, caches and formatting queues that use the
+    /// options. No events are emitted by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `events`: The event sink.
+    pub fn events(mut self, events: EventSink) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Retrieve the event sink, if any.
+    pub fn get_events(&self) -> Option<&EventSink> {
+        self.events.as_ref()
+    }
+
+    /// Set the callbacks that are invoked around the writes of files that are formatted in place.
+    /// No callbacks are invoked by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `hooks`: The callbacks.
+    pub fn hooks(mut self, hooks: Hooks) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    /// Retrieve the callbacks that are invoked around the writes of files.
+    pub fn get_hooks(&self) -> &Hooks {
+        &self.hooks
+    }
+
+    /// Set the backend that performs the formatting. The `Rustfmt` backend is used by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `backend`: The backend.
+    pub fn backend(mut self, backend: Arc<dyn FormatBackend>) -> Self {
+        self.backend = Some(backend);
+        self
+    }
+
+    /// Set a boxed backend that performs the formatting, e.g. one that was loaded by a plugin.
+    /// See [`Options::backend`].
+    ///
+    /// # Arguments
+    ///
+    /// * `backend`: The backend.
+    pub fn boxed_backend(self, backend: Box<dyn FormatBackend>) -> Self {
+        self.backend(Arc::from(backend))
+    }
+
+    /// Use the [`Rustfmt`] backend with the tool of a specific toolchain.
+    ///
+    /// # Arguments
+    ///
+    /// * `toolchain`: The name of the toolchain, e.g. `nightly` or `1.70.0`.
+    #[cfg(feature = "process-backend")]
+    pub fn toolchain(self, toolchain: impl Into<String>) -> Self {
+        self.backend(Arc::new(Rustfmt::with_toolchain(toolchain)))
+    }
+
+    /// Set whether the formatting tool is installed with `rustup` if it is missing. Disabled by
+    /// default.
+    ///
+    /// The tool is checked, and installed if necessary, before it is run for the first time in the
+    /// process. See [`crate::install_rustfmt`].
+    ///
+    /// # Arguments
+    ///
+    /// * `auto_install`: Whether to install the tool.
+    #[cfg(feature = "install")]
+    pub fn auto_install(mut self, auto_install: bool) -> Self {
+        self.auto_install = auto_install;
+        self
+    }
+
+    /// Retrieve whether the formatting tool is installed if it is missing.
+    #[cfg(feature = "install")]
+    pub fn get_auto_install(&self) -> bool {
+        self.auto_install
+    }
+
+    /// Set whether interrupts of the host, such as Ctrl-C, are forwarded to the formatting tool.
+    /// Disabled by default.
+    ///
+    /// Enabling this installs a Ctrl-C handler for the process when the tool is started for the
+    /// first time, which fails if the host installed a handler through the `ctrlc` crate already.
+    /// The tool is started in its own process group. On an interrupt, the handler forwards it to
+    /// the running tool processes, kills those that do not exit within two seconds and then exits
+    /// the host with code 130. This also covers interrupts that are sent to the host only, e.g. by
+    /// a build system, which would otherwise leave the tool running.
+    ///
+    /// Independent of this setting, a tool process is killed if the operation that started it is
+    /// aborted, e.g. by a panic.
+    ///
+    /// # Arguments
+    ///
+    /// * `forward_interrupts`: Whether to forward interrupts.
+    #[cfg(feature = "signals")]
+    pub fn forward_interrupts(mut self, forward_interrupts: bool) -> Self {
+        self.forward_interrupts = forward_interrupts;
+        self
+    }
+
+    /// Retrieve whether interrupts are forwarded to the formatting tool.
+    #[cfg(feature = "signals")]
+    pub fn get_forward_interrupts(&self) -> bool {
+        self.forward_interrupts
+    }
+
+    /// Retrieve the backend that performs the formatting.
+    pub fn get_backend(&self) -> &dyn FormatBackend {
+        match &self.backend {
+            Some(backend) => backend.as_ref(),
+            #[cfg(feature = "process-backend")]
+            None => {
+                static DEFAULT: Rustfmt = Rustfmt::new();
+                &DEFAULT
+            }
+            #[cfg(not(feature = "process-backend"))]
+            None => &crate::backend::Unavailable,
+        }
+    }
+
+    /// Compute a stable fingerprint of all settings that affect the formatting result, including
+    /// the version of the backend.
+    ///
+    /// Build systems can include the fingerprint in their cache keys, so that formatted outputs are
+    /// invalidated when the formatting behavior changes. Settings that do not affect the result,
+    /// such as the backup policy or the audit log, are not included. Post-processors are included
+    /// by their [identifiers](PostProcessor::id); custom
+    /// [blank line functions](BlankLines::Custom) cannot be told apart.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rust_format::{Config, Options};
+    ///
+    /// let narrow = Options::new().config(Config::new().max_width(80));
+    /// assert_ne!(Options::new().fingerprint().unwrap(), narrow.fingerprint().unwrap());
+    /// ```
+    pub fn fingerprint(&self) -> Result<u64, crate::Error> {
+        let mut fingerprint = Fingerprint::default();
+        fingerprint.text("version", Some(&self.get_backend().version()?));
+        fingerprint.text("edition", self.edition.map(Edition::as_str));
+        fingerprint.text("config", self.effective_config().to_arg().as_deref());
+        fingerprint.value(
+            "degrade_unsupported_config",
+            self.degrade_unsupported_config,
+        );
+        fingerprint.text(
+            "max_input_bytes",
+            self.max_input_bytes
+                .map(|limit| limit.to_string())
+                .as_deref(),
+        );
+        self.limits.fingerprint(&mut fingerprint);
+        fingerprint.value("preserve_comments", self.preserve_comments);
+        fingerprint.value("preserve_literals", self.preserve_literals);
+        fingerprint.value("reject_line_overflow", self.reject_line_overflow);
+        fingerprint.value("check_compile", self.check_compile.name());
+        self.blank_lines.fingerprint(&mut fingerprint);
+        fingerprint.value("trim_trailing_whitespace", self.trim_trailing_whitespace);
+        fingerprint.value("banner", self.banner.is_some());
+        if let Some(banner) = &self.banner {
+            banner.fingerprint(&mut fingerprint);
+        }
+        fingerprint.value(
+            "generated_file_profile",
+            self.generated_file_profile.is_some(),
+        );
+        if let Some(profile) = &self.generated_file_profile {
+            profile.fingerprint(&mut fingerprint);
+        }
+        fingerprint.text("shebang", self.shebang.as_deref());
+        fingerprint.value("post_processors", self.post_processors.len());
+        for processor in &self.post_processors {
+            fingerprint.text("post_processor", Some(processor.id()));
+        }
+        #[cfg(feature = "syn")]
+        fingerprint.value("validate_syntax", self.validate_syntax);
+        #[cfg(feature = "diff")]
+        fingerprint.value("verify_tokens", self.verify_tokens);
+        Ok(fingerprint.finish())
+    }
+
+    /// Add the arguments for these options to a formatting tool command.
+    #[cfg(feature = "process-backend")]
+    pub(crate) fn apply(&self, command: &mut std::process::Command) {
+        command.args(self.arguments());
+    }
+
+    /// Retrieve the configuration that is passed to the formatting tool, including the settings
+    /// that other options imply.
+    pub(crate) fn tool_config(&self) -> Config {
+        let config = self
+            .degraded_config()
+            .iter()
+            .fold(self.effective_config(), |config, key| config.remove(key));
+        match self.skip_children {
+            true => config.set("skip_children", true),
+            false => config,
+        }
+    }
+
+    /// Retrieve the arguments of the formatting tool for these options.
+    pub(crate) fn arguments(&self) -> Vec<String> {
+        let mut arguments = Vec::new();
+        if let Some(edition) = self.edition {
+            arguments.push("--edition".to_string());
+            arguments.push(edition.as_str().to_string());
+        }
+        if let Some(config) = self.tool_config().to_arg() {
+            arguments.push("--config".to_string());
+            arguments.push(config);
+        }
+        arguments
+    }
+
+    /// Verify that the backend supports the configuration, if validation is enabled.
+    pub(crate) fn check_config(&self) -> Result<(), crate::Error> {
+        let degraded = self.degraded_config();
+        #[cfg(feature = "tracing")]
+        for key in &degraded {
+            tracing::warn!(
+                key = key.as_str(),
+                "setting is not supported by the formatting tool on its channel and is ignored"
+            );
+        }
+        match self.validate_config {
+            true => {
+                let config = degraded
+                    .iter()
+                    .fold(self.effective_config(), |config, key| config.remove(key));
+                crate::schema::validate(&config, self.get_backend())
+            }
+            false => Ok(()),
+        }
+    }
+
+    /// Determine whether the original source code is needed before formatting a file.
+    pub(crate) fn requires_source(&self) -> bool {
+        #[cfg(feature = "syn")]
+        if self.validate_syntax {
+            return true;
+        }
+        #[cfg(feature = "diff")]
+        if self.verify_tokens {
+            return true;
+        }
+        self.limits.is_enabled()
+            || self.preserve_comments
+            || self.preserve_literals
+            || self.reject_line_overflow
+            || self.check_compile != CompileCheck::Off
+            || self.modification == ModificationPolicy::Refuse
+            || self.write_policy.in_memory()
+            || self.hooks.is_enabled()
+            || self.audit_log.is_some()
+    }
+
+    /// Determine whether the output of the formatting tool needs to be processed further.
+    pub(crate) fn requires_post_processing(&self) -> bool {
+        !matches!(self.blank_lines, BlankLines::Preserve)
+            || self.trim_trailing_whitespace
+            || self.banner.is_some()
+            || self.generated_file_profile.is_some()
+            || self.shebang.is_some()
+            || !self.post_processors.is_empty()
+    }
+
+    /// Determine whether files can be passed to the backend together, i.e. whether formatting a
+    /// file does not involve any steps besides running the backend.
+    pub(crate) fn supports_grouping(&self) -> bool {
+        !self.requires_source()
+            && !self.requires_post_processing()
+            && self.max_input_bytes.is_none()
+            && self.audit_log.is_none()
+            && !matches!(self.backup, Backup::Directory(_))
+            && !self.verify_applied
+    }
+
+    /// Verify that an input of the provided size is acceptable.
+    pub(crate) fn check_input_size(&self, size: u64) -> Result<(), crate::Error> {
+        match self.max_input_bytes {
+            Some(limit) if size > limit => Err(crate::Error::InputTooLarge { size, limit }),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Options;
+    use crate::Error;
+
+    #[test]
+    fn test_max_input_bytes() {
+        let options = Options::new().max_input_bytes(8);
+        assert!(options.check_input_size(8).is_ok());
+        assert!(matches!(
+            crate::format_str_with("fn main() {}", &options),
+            Err(Error::InputTooLarge { size: 12, limit: 8 })
+        ));
+    }
+
+    #[test]
+    fn test_skip_children() {
+        let dir = std::path::PathBuf::from("target/skip_children");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("main.rs"), "mod child;\nfn main(){}\n").unwrap();
+        std::fs::write(dir.join("child.rs"), "fn child(){}\n").unwrap();
+
+        crate::format_file_with(dir.join("main.rs"), &Options::new().skip_children(true)).unwrap();
+        assert_eq!(
+            "mod child;\nfn main() {}\n",
+            std::fs::read_to_string(dir.join("main.rs")).unwrap()
+        );
+        assert_eq!(
+            "fn child(){}\n",
+            std::fs::read_to_string(dir.join("child.rs")).unwrap()
+        );
+
+        crate::format_file_with(dir.join("main.rs"), &Options::new()).unwrap();
+        assert_eq!(
+            "fn child() {}\n",
+            std::fs::read_to_string(dir.join("child.rs")).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let mock = std::sync::Arc::new(crate::backend::Mock::new());
+        let options = Options::new().backend(mock);
+        let fingerprint = options.fingerprint().unwrap();
+
+        assert_eq!(
+            fingerprint,
+            options
+                .clone()
+                .read_only(crate::ReadOnlyPolicy::Skip)
+                .fingerprint()
+                .unwrap()
+        );
+        assert_ne!(
+            fingerprint,
+            options
+                .clone()
+                .config(crate::Config::new().tab_spaces(2))
+                .fingerprint()
+                .unwrap()
+        );
+
+        #[derive(Debug)]
+        struct Versioned(&'static str);
+
+        impl crate::PostProcessor for Versioned {
+            fn id(&self) -> &str {
+                self.0
+            }
+
+            fn process(&self, formatted: String, _options: &Options) -> Result<String, Error> {
+                Ok(formatted)
+            }
+        }
+
+        let processed = |id| {
+            options
+                .clone()
+                .post_processor(std::sync::Arc::new(Versioned(id)))
+                .fingerprint()
+                .unwrap()
+        };
+        assert_eq!(processed("footer:v1"), processed("footer:v1"));
+        assert_ne!(processed("footer:v1"), processed("footer:v2"));
+    }
+}
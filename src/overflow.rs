@@ -0,0 +1,97 @@
+//! Detection of lines that exceed the maximum width after formatting.
+
+use crate::Options;
+
+/// The maximum width of the formatting tool if the configuration does not set one.
+const DEFAULT_MAX_WIDTH: usize = 100;
+
+/// The number of spaces per indentation level if the configuration does not set one.
+const DEFAULT_TAB_SPACES: usize = 4;
+
+/// A line that exceeds the maximum width, e.g. because it contains a long string literal or a
+/// comment that the formatting tool cannot break.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct LineOverflow {
+    /// The line number (1-based).
+    pub line: usize,
+    /// The width of the line in characters, with tabs counted as an indentation level.
+    pub width: usize,
+    /// The maximum width.
+    pub max_width: usize,
+}
+
+/// Find the lines of formatted code that exceed the maximum width of the configuration.
+///
+/// The maximum width and the width of tabs are taken from the `max_width` and `tab_spaces`
+/// settings of the effective configuration (see [`Options::effective_config`]), falling back to
+/// the defaults of the formatting tool. Settings in configuration files are not considered.
+///
+/// # Arguments
+///
+/// * `text`: The formatted code.
+/// * `options`: The options the code was formatted with.
+///
+/// # Examples
+///
+/// ```
+/// use rust_format::{overflowing_lines, Config, Options};
+///
+/// let options = Options::new().config(Config::new().max_width(20));
+/// let text = "fn f() {\n    g(\"a long literal\");\n}\n";
+/// let overflows = overflowing_lines(text, &options);
+/// assert_eq!(1, overflows.len());
+/// assert_eq!((2, 24), (overflows[0].line, overflows[0].width));
+/// ```
+pub fn overflowing_lines(text: &str, options: &Options) -> Vec<LineOverflow> {
+    let config = options.effective_config();
+    let setting = |key: &str, default: usize| {
+        config
+            .get(key)
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(default)
+    };
+    let max_width = setting("max_width", DEFAULT_MAX_WIDTH);
+    let tab_spaces = setting("tab_spaces", DEFAULT_TAB_SPACES);
+    text.lines()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            let width = line
+                .chars()
+                .map(|c| match c {
+                    '\t' => tab_spaces,
+                    _ => 1,
+                })
+                .sum();
+            (width > max_width).then_some(LineOverflow {
+                line: index + 1,
+                width,
+                max_width,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Config, Error, Options};
+
+    #[test]
+    fn test_reject_line_overflow() {
+        let options = Options::new()
+            .config(Config::new().max_width(30).hard_tabs(true).tab_spaces(8))
+            .reject_line_overflow(true);
+        assert!(crate::format_str_with("fn f() { g(1); }", &options).is_ok());
+
+        let source = "fn f() { g(\"a literal that cannot be broken\"); }";
+        match crate::format_str_with(source, &options) {
+            Err(Error::LineOverflow { lines }) => {
+                assert_eq!(1, lines.len());
+                assert_eq!(
+                    (2, 45, 30),
+                    (lines[0].line, lines[0].width, lines[0].max_width)
+                );
+            }
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,83 @@
+//! Determination of the number of worker threads.
+
+/// Retrieve the number of threads that the crate uses for parallel formatting by default.
+///
+/// This is the number of CPUs that the process may run on, limited by the CPU quota of its Linux
+/// control group. In containers, the quota is usually much lower than the number of cores of the
+/// host. Embedders can use this value to size their own thread pools consistently.
+///
+/// The value is at least 1.
+pub fn available_parallelism_hint() -> usize {
+    let cpus = std::thread::available_parallelism().map_or(1, |count| count.get());
+    match cgroup_quota() {
+        Some(quota) => cpus.min(quota),
+        None => cpus,
+    }
+}
+
+/// Determine the CPU quota of the control group of the process, rounded up to whole CPUs.
+#[cfg(target_os = "linux")]
+fn cgroup_quota() -> Option<usize> {
+    use std::path::Path;
+
+    let groups = std::fs::read_to_string("/proc/self/cgroup").ok()?;
+    let root = Path::new("/sys/fs/cgroup");
+    groups.lines().find_map(|line| {
+        let mut fields = line.splitn(3, ':');
+        let (_, controllers, path) = (fields.next()?, fields.next()?, fields.next()?);
+        let path = path.trim_start_matches('/');
+        if controllers.is_empty() {
+            // A unified (v2) hierarchy.
+            let read = |dir: &Path| std::fs::read_to_string(dir.join("cpu.max")).ok();
+            let content = read(&root.join(path)).or_else(|| read(root))?;
+            parse_cpu_max(&content)
+        } else if controllers.split(',').any(|controller| controller == "cpu") {
+            let mount = root.join(controllers);
+            let dir = Some(mount.join(path))
+                .filter(|dir| dir.exists())
+                .unwrap_or(mount);
+            let read = |name| std::fs::read_to_string(dir.join(name)).ok();
+            quota(
+                read("cpu.cfs_quota_us")?.trim().parse().ok()?,
+                read("cpu.cfs_period_us")?.trim().parse().ok()?,
+            )
+        } else {
+            None
+        }
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn cgroup_quota() -> Option<usize> {
+    None
+}
+
+/// Parse the content of a `cpu.max` file of a v2 control group (e.g. `200000 100000`).
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_cpu_max(content: &str) -> Option<usize> {
+    let mut fields = content.split_whitespace();
+    let limit = fields.next()?.parse().ok()?;
+    let period = fields.next()?.parse().ok()?;
+    quota(limit, period)
+}
+
+/// Convert a quota and a period in microseconds to whole CPUs. Unlimited quotas are negative.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn quota(limit: i64, period: i64) -> Option<usize> {
+    if limit <= 0 || period <= 0 {
+        return None;
+    }
+    Some((limit as u64).div_ceil(period as u64) as usize)
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn test_parse_cpu_max() {
+        assert_eq!(Some(2), super::parse_cpu_max("200000 100000\n"));
+        assert_eq!(Some(1), super::parse_cpu_max("50000 100000\n"));
+        assert_eq!(None, super::parse_cpu_max("max 100000\n"));
+        assert_eq!(None, super::quota(-1, 100000));
+        assert!(super::available_parallelism_hint() >= 1);
+    }
+}
@@ -0,0 +1,90 @@
+//! Handling of read-only files.
+
+use crate::error::IoContext;
+use crate::Error;
+use std::fs::Permissions;
+use std::path::Path;
+
+/// The policy for files that are formatted in place, but are read-only.
+///
+/// Generated files are often checked out read-only by version control or build systems.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub enum ReadOnlyPolicy {
+    /// Fail with [`Error::ReadOnly`].
+    #[default]
+    Error,
+    /// Leave the file untouched. Batch operations report the file as
+    /// [`crate::batch::Outcome::Skipped`] with [`crate::batch::SkipReason::ReadOnly`].
+    Skip,
+    /// Make the file writable for the duration of the formatting and restore its permissions
+    /// afterwards.
+    MakeWritable,
+}
+
+/// Retrieve the permissions of a file if it is read-only.
+pub(crate) fn read_only(path: &Path) -> Result<Option<Permissions>, Error> {
+    let permissions = std::fs::metadata(path).at(path)?.permissions();
+    Ok(permissions.readonly().then_some(permissions))
+}
+
+/// Make a read-only file writable for its owner.
+pub(crate) fn make_writable(path: &Path, permissions: &Permissions) -> Result<(), Error> {
+    #[cfg(unix)]
+    let writable = {
+        use std::os::unix::fs::PermissionsExt;
+        Permissions::from_mode(permissions.mode() | 0o200)
+    };
+    #[cfg(not(unix))]
+    #[allow(clippy::permissions_set_readonly_false)]
+    let writable = {
+        let mut writable = permissions.clone();
+        writable.set_readonly(false);
+        writable
+    };
+    std::fs::set_permissions(path, writable).at(path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::ReadOnlyPolicy;
+    use crate::{Error, Options};
+    use std::fs::read_to_string;
+
+    #[test]
+    fn test_read_only_policy() {
+        const TARGET_PATH: &str = "target/read_only_sample_code.rs";
+        if let Ok(metadata) = std::fs::metadata(TARGET_PATH) {
+            let mut permissions = metadata.permissions();
+            #[allow(clippy::permissions_set_readonly_false)]
+            permissions.set_readonly(false);
+            std::fs::set_permissions(TARGET_PATH, permissions).unwrap();
+        }
+        std::fs::copy("resources/test/sample_code.rs", TARGET_PATH).unwrap();
+        let mut permissions = std::fs::metadata(TARGET_PATH).unwrap().permissions();
+        permissions.set_readonly(true);
+        std::fs::set_permissions(TARGET_PATH, permissions).unwrap();
+
+        assert!(matches!(
+            crate::format_file_with(TARGET_PATH, &Options::new()),
+            Err(Error::ReadOnly { .. })
+        ));
+        let skip = Options::new().read_only(ReadOnlyPolicy::Skip);
+        crate::format_file_with(TARGET_PATH, &skip).unwrap();
+        assert_eq!(
+            read_to_string("resources/test/sample_code.rs").unwrap(),
+            read_to_string(TARGET_PATH).unwrap()
+        );
+
+        let writable = Options::new().read_only(ReadOnlyPolicy::MakeWritable);
+        crate::format_file_with(TARGET_PATH, &writable).unwrap();
+        assert_eq!(
+            read_to_string("resources/test/expected.rs").unwrap(),
+            read_to_string(TARGET_PATH).unwrap()
+        );
+        assert!(std::fs::metadata(TARGET_PATH)
+            .unwrap()
+            .permissions()
+            .readonly());
+    }
+}
@@ -0,0 +1,177 @@
+//! Formatting of the output of common code generators.
+
+use crate::batch::{BatchOptions, FileResult};
+use crate::glob::Glob;
+use crate::walk::Walk;
+use crate::{Edition, Error, Options};
+use std::path::{Path, PathBuf};
+
+/// The files that a pipeline formats.
+#[derive(Debug, Clone)]
+enum Target {
+    /// All Rust source files below a directory.
+    Directory(PathBuf),
+    /// A single file.
+    File(PathBuf),
+}
+
+/// A pipeline that formats the output of a code generator in one call.
+///
+/// The generated code is formatted as 2021 edition code, unless the [`Options`] specify an edition.
+/// Files can be excluded with [`Pipeline::skip`].
+///
+/// # Examples
+///
+/// In a build script, after `prost-build` or `tonic-build` wrote its output to `OUT_DIR`:
+///
+/// ```no_run
+/// use rust_format::pipelines::Pipeline;
+///
+/// let results = Pipeline::out_dir()?.skip("_includes.rs").run()?;
+/// # Ok::<(), rust_format::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct Pipeline {
+    target: Target,
+    options: Options,
+    skip: Vec<Glob>,
+}
+
+impl Pipeline {
+    fn new(target: Target) -> Self {
+        Self {
+            target,
+            options: Options::default(),
+            skip: Vec::new(),
+        }
+    }
+
+    /// Create a pipeline for the output of `prost-build`.
+    ///
+    /// `prost-build` writes one file per protobuf package into the output directory. All `.rs`
+    /// files below the directory are formatted.
+    ///
+    /// # Arguments
+    ///
+    /// * `out_dir`: The output directory.
+    pub fn prost(out_dir: impl Into<PathBuf>) -> Self {
+        Self::new(Target::Directory(out_dir.into()))
+    }
+
+    /// Create a pipeline for the output of `tonic-build`.
+    ///
+    /// `tonic-build` uses the same layout as `prost-build`. See [`Pipeline::prost`].
+    ///
+    /// # Arguments
+    ///
+    /// * `out_dir`: The output directory.
+    pub fn tonic(out_dir: impl Into<PathBuf>) -> Self {
+        Self::prost(out_dir)
+    }
+
+    /// Create a pipeline for the output of `bindgen`, which is a single file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: The path to the generated bindings.
+    pub fn bindgen(path: impl Into<PathBuf>) -> Self {
+        Self::new(Target::File(path.into()))
+    }
+
+    /// Create a pipeline for the `OUT_DIR` of the running build script.
+    ///
+    /// This is equivalent to [`Pipeline::prost`] with the value of the `OUT_DIR` environment
+    /// variable.
+    pub fn out_dir() -> Result<Self, Error> {
+        let out_dir = std::env::var_os("OUT_DIR").ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "The OUT_DIR environment variable is not set.",
+            )
+        })?;
+        Ok(Self::prost(out_dir))
+    }
+
+    /// Set the options for formatting the files.
+    ///
+    /// # Arguments
+    ///
+    /// * `options`: The options.
+    pub fn options(mut self, options: Options) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Exclude files matching a glob pattern.
+    ///
+    /// The pattern is matched against the path relative to the output directory, or against the
+    /// file name for a single file. See [`BatchOptions::config_for`] for the pattern syntax.
+    ///
+    /// # Arguments
+    ///
+    /// * `glob`: The glob pattern.
+    pub fn skip(mut self, glob: impl Into<String>) -> Self {
+        self.skip.push(Glob::new(glob));
+        self
+    }
+
+    /// Format the generated files.
+    ///
+    /// The results are returned in alphabetical order of the paths. An error is only returned if
+    /// the files could not be determined; errors for individual files are part of the results.
+    pub fn run(&self) -> Result<Vec<FileResult>, Error> {
+        let (root, mut paths) = match &self.target {
+            Target::Directory(dir) => (dir.as_path(), Walk::new(dir).files()?),
+            Target::File(path) => (path.parent().unwrap_or(Path::new("")), vec![path.clone()]),
+        };
+        paths.retain(|path| {
+            let relative = path.strip_prefix(root).unwrap_or(path);
+            !self.skip.iter().any(|glob| glob.matches(relative))
+        });
+        paths.sort();
+
+        let options = match self.options.get_edition() {
+            Some(_) => self.options.clone(),
+            None => self.options.clone().edition(Edition::E2021),
+        };
+        Ok(crate::batch::format_files_with(
+            paths,
+            &BatchOptions::new().options(options),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Pipeline;
+    use crate::batch::Outcome;
+    use std::fs::read_to_string;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_prost() {
+        let dir = PathBuf::from("target/pipeline_prost");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("nested")).unwrap();
+        for name in ["a.rs", "nested/b.rs", "_includes.rs"] {
+            std::fs::copy("resources/test/sample_code.rs", dir.join(name)).unwrap();
+        }
+        std::fs::write(dir.join("descriptor.bin"), [0xff]).unwrap();
+
+        let results = Pipeline::prost(&dir).skip("_includes.rs").run().unwrap();
+
+        let paths: Vec<_> = results.iter().map(|result| result.path.clone()).collect();
+        assert_eq!(vec![dir.join("a.rs"), dir.join("nested/b.rs")], paths);
+        assert!(results
+            .iter()
+            .all(|result| matches!(result.result, Ok(Outcome::Formatted))));
+        assert_eq!(
+            read_to_string("resources/test/expected.rs").unwrap(),
+            read_to_string(dir.join("nested/b.rs")).unwrap()
+        );
+        assert_eq!(
+            read_to_string("resources/test/sample_code.rs").unwrap(),
+            read_to_string(dir.join("_includes.rs")).unwrap()
+        );
+    }
+}
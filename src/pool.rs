@@ -0,0 +1,111 @@
+//! A pool of byte buffers that the formatting path reuses, so that formatting many snippets in a
+//! single process does not allocate fresh buffers for every snippet.
+
+use std::sync::Mutex;
+
+/// The maximum number of buffers that are kept.
+const MAX_BUFFERS: usize = 32;
+
+/// The maximum capacity of a buffer that is kept, so that formatting one huge file does not pin
+/// its memory for the lifetime of the process.
+const MAX_CAPACITY: usize = 4 << 20;
+
+/// The pool of the process.
+static POOL: Pool = Pool::new();
+
+/// A pool of empty byte buffers.
+struct Pool {
+    buffers: Mutex<Vec<Vec<u8>>>,
+}
+
+impl Pool {
+    const fn new() -> Self {
+        Self {
+            buffers: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn take(&self, capacity: usize) -> Vec<u8> {
+        let mut buffer = self.lock().pop().unwrap_or_default();
+        buffer.reserve(capacity);
+        buffer
+    }
+
+    fn give(&self, mut buffer: Vec<u8>) {
+        if buffer.capacity() == 0 || buffer.capacity() > MAX_CAPACITY {
+            return;
+        }
+        buffer.clear();
+        let mut buffers = self.lock();
+        if buffers.len() < MAX_BUFFERS {
+            buffers.push(buffer);
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Vec<u8>>> {
+        self.buffers.lock().expect("buffer pool lock poisoned")
+    }
+}
+
+/// Take an empty buffer from the pool, or allocate one if the pool is empty.
+///
+/// # Arguments
+///
+/// * `capacity`: The minimum capacity of the buffer.
+pub(crate) fn take(capacity: usize) -> Vec<u8> {
+    POOL.take(capacity)
+}
+
+/// Take an empty string from the pool, or allocate one if the pool is empty.
+///
+/// # Arguments
+///
+/// * `capacity`: The minimum capacity of the string.
+pub(crate) fn take_string(capacity: usize) -> String {
+    String::from_utf8(take(capacity)).expect("pooled buffers are empty")
+}
+
+/// Return a buffer to the pool, unless the pool is full or the buffer is too large to keep.
+///
+/// # Arguments
+///
+/// * `buffer`: The buffer, whose content is discarded.
+pub(crate) fn give(buffer: Vec<u8>) {
+    POOL.give(buffer);
+}
+
+/// Return the buffer of a string to the pool. See [`give`].
+///
+/// # Arguments
+///
+/// * `string`: The string, whose content is discarded.
+pub(crate) fn give_string(string: String) {
+    give(string.into_bytes());
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Pool, MAX_BUFFERS, MAX_CAPACITY};
+
+    #[test]
+    fn test_pool() {
+        let pool = Pool::new();
+        let mut buffer = pool.take(1000);
+        buffer.extend_from_slice(b"fn main() {}\n");
+        let pointer = buffer.as_ptr();
+        pool.give(buffer);
+        let reused = pool.take(10);
+        assert!(reused.is_empty());
+        assert_eq!(pointer, reused.as_ptr());
+
+        pool.give(Vec::with_capacity(MAX_CAPACITY + 1));
+        for _ in 0..=MAX_BUFFERS {
+            pool.give(Vec::with_capacity(10));
+        }
+        assert_eq!(MAX_BUFFERS, pool.lock().len());
+        assert!(pool
+            .lock()
+            .iter()
+            .all(|buffer| buffer.capacity() < MAX_CAPACITY));
+    }
+}
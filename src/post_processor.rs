@@ -0,0 +1,58 @@
+//! Custom steps of embedders that are applied to formatted source code.
+
+use crate::{Error, Options};
+use std::fmt::Debug;
+
+/// A step that is applied to the output of the formatting tool, after the built-in steps such as
+/// the [banner](Options::banner).
+///
+/// Processors are set with [`Options::post_processor`] or registered by name in a
+/// [`crate::backend::Registry`], so that they can be chosen by configuration files. The trait is
+/// object safe. The [identifier](PostProcessor::id) of a processor is part of the
+/// [fingerprint](Options::fingerprint) of the options.
+///
+/// # Examples
+///
+/// ```
+/// use rust_format::{Error, Options, PostProcessor};
+/// use std::sync::Arc;
+///
+/// #[derive(Debug)]
+/// struct Footer;
+///
+/// impl PostProcessor for Footer {
+///     fn id(&self) -> &str {
+///         "footer"
+///     }
+///
+///     fn process(&self, formatted: String, _options: &Options) -> Result<String, Error> {
+///         Ok(formatted + "// End of file.\n")
+///     }
+/// }
+///
+/// let options = Options::new().post_processor(Arc::new(Footer));
+/// assert_eq!(
+///     "fn main() {}\n// End of file.\n",
+///     rust_format::format_str_with("fn main(){}", &options).unwrap()
+/// );
+/// ```
+pub trait PostProcessor: Debug + Send + Sync {
+    /// Retrieve the identifier of the processor, e.g. `license-footer:v2`.
+    ///
+    /// The identifier must be stable across runs and versions of the program, and it must change
+    /// whenever the output of the processor changes, e.g. by including its settings. Fingerprints
+    /// that are persisted, e.g. in stamp files, rely on it.
+    fn id(&self) -> &str;
+
+    /// Process formatted source code.
+    ///
+    /// # Arguments
+    ///
+    /// * `formatted`: The formatted source code.
+    /// * `options`: The options with which the code was formatted.
+    ///
+    /// # Returns
+    ///
+    /// The processed source code. An error fails the formatting of the code.
+    fn process(&self, formatted: String, options: &Options) -> Result<String, Error>;
+}
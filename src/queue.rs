@@ -0,0 +1,308 @@
+//! A prioritized formatting queue for long-running consumers such as editors.
+
+use crate::batch::Outcome;
+use crate::events::EventKind;
+use crate::{Error, Options};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread::JoinHandle;
+
+/// The priority of a queue submission.
+///
+/// Submissions with a higher priority are formatted first. Submissions with equal priority are
+/// formatted in the order in which they were submitted.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Priority {
+    /// A file that is not currently shown to the user.
+    Background,
+    /// A file that is currently shown to the user.
+    Visible,
+}
+
+/// The result of formatting a queue submission.
+#[derive(Debug)]
+pub struct QueueResult {
+    /// The path under which the content was submitted.
+    pub path: PathBuf,
+    /// The formatted content or the error that occurred.
+    pub result: Result<String, Error>,
+}
+
+/// A submission that has not been picked up by the worker yet.
+#[derive(Debug)]
+struct Pending {
+    content: String,
+    priority: Priority,
+    sequence: u64,
+}
+
+/// The state that is shared between the queue handle and its worker.
+#[derive(Debug, Default)]
+struct State {
+    pending: HashMap<PathBuf, Pending>,
+    next_sequence: u64,
+    busy: bool,
+    shutdown: bool,
+    exited: bool,
+}
+
+impl State {
+    /// Add a submission, replacing any pending submission for the same path.
+    ///
+    /// The priority of a replaced submission is retained if it was higher, so that re-submitting
+    /// a visible file from a background task does not demote it.
+    fn submit(&mut self, path: PathBuf, content: String, priority: Priority) {
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+        let priority = match self.pending.get(&path) {
+            Some(previous) => previous.priority.max(priority),
+            None => priority,
+        };
+        self.pending.insert(
+            path,
+            Pending {
+                content,
+                priority,
+                sequence,
+            },
+        );
+    }
+
+    /// Remove and return the submission that should be formatted next.
+    fn take_next(&mut self) -> Option<(PathBuf, String)> {
+        let path = self
+            .pending
+            .iter()
+            .max_by_key(|(_, pending)| (pending.priority, std::cmp::Reverse(pending.sequence)))
+            .map(|(path, _)| path.clone())?;
+        let pending = self.pending.remove(&path)?;
+        Some((path, pending.content))
+    }
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    state: Mutex<State>,
+    condvar: Condvar,
+}
+
+impl Shared {
+    /// Lock the state. The lock is never held while user code runs, so a poisoned lock still
+    /// guards a consistent state.
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Marks the worker as exited when it returns or unwinds, so that [`FormatQueue::join`] does not
+/// wait for a worker whose callback panicked.
+struct ExitGuard<'a>(&'a Shared);
+
+impl Drop for ExitGuard<'_> {
+    fn drop(&mut self) {
+        let mut state = self.0.lock();
+        state.busy = false;
+        state.exited = true;
+        self.0.condvar.notify_all();
+    }
+}
+
+/// A queue that formats Rust source code on a background thread.
+///
+/// Every submission is formatted like a buffer of the file at its path (see
+/// [`crate::format_buffer_with`]), so the configuration files and the edition of the package of
+/// the path apply on top of the options of the queue.
+///
+/// Submissions are keyed by path: submitting new content for a path that is still pending replaces
+/// the pending content, so only the latest content is formatted. Results are delivered to a
+/// callback on the worker thread, or through a channel when the queue is created with
+/// [`FormatQueue::with_channel`].
+///
+/// Dropping the queue discards all pending submissions and waits for the worker to finish the
+/// submission it is currently formatting. Use [`FormatQueue::join`] to process all pending
+/// submissions first. If the callback panics, the worker stops and all pending submissions are
+/// discarded.
+///
+/// # Examples
+///
+/// ```
+/// use rust_format::queue::{FormatQueue, Priority};
+/// use rust_format::Options;
+///
+/// let (queue, results) = FormatQueue::with_channel(Options::new());
+/// queue.submit("lib.rs", "fn main(){}", Priority::Visible);
+/// queue.join();
+///
+/// let result = results.recv().unwrap();
+/// assert_eq!("fn main() {}\n", result.result.unwrap());
+/// ```
+#[derive(Debug)]
+pub struct FormatQueue {
+    shared: Arc<Shared>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl FormatQueue {
+    /// Create a new queue that delivers results to a callback.
+    ///
+    /// # Arguments
+    ///
+    /// * `options`: The options with which submissions are formatted.
+    /// * `callback`: The callback. It is invoked on the worker thread.
+    pub fn new(options: Options, callback: impl FnMut(QueueResult) + Send + 'static) -> Self {
+        let shared = Arc::new(Shared::default());
+        let worker = {
+            let shared = shared.clone();
+            std::thread::spawn(move || work(&shared, &options, callback))
+        };
+
+        Self {
+            shared,
+            worker: Some(worker),
+        }
+    }
+
+    /// Create a new queue that delivers results through a channel.
+    ///
+    /// # Arguments
+    ///
+    /// * `options`: The options with which submissions are formatted.
+    pub fn with_channel(options: Options) -> (Self, std::sync::mpsc::Receiver<QueueResult>) {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let queue = Self::new(options, move |result| {
+            // The receiver may have been dropped, in which case nobody is interested anymore.
+            let _ = sender.send(result);
+        });
+        (queue, receiver)
+    }
+
+    /// Submit source code for formatting.
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: The path of the file that the content belongs to.
+    /// * `content`: The source code.
+    /// * `priority`: The priority.
+    pub fn submit(&self, path: impl Into<PathBuf>, content: impl Into<String>, priority: Priority) {
+        let mut state = self.shared.lock();
+        state.submit(path.into(), content.into(), priority);
+        self.shared.condvar.notify_all();
+    }
+
+    /// Retrieve the number of pending submissions.
+    pub fn pending(&self) -> usize {
+        self.shared.lock().pending.len()
+    }
+
+    /// Wait until all pending submissions have been processed and stop the worker. If the worker
+    /// stopped because the callback panicked, the remaining submissions are discarded instead.
+    pub fn join(mut self) {
+        {
+            let shared = &self.shared;
+            let mut state = shared.lock();
+            while !state.exited && (state.busy || !state.pending.is_empty()) {
+                state = shared
+                    .condvar
+                    .wait(state)
+                    .unwrap_or_else(PoisonError::into_inner);
+            }
+        }
+        self.stop();
+    }
+
+    fn stop(&mut self) {
+        if let Some(worker) = self.worker.take() {
+            {
+                let mut state = self.shared.lock();
+                state.shutdown = true;
+                state.pending.clear();
+                self.shared.condvar.notify_all();
+            }
+            // A panic in the callback has already been reported on the worker thread.
+            let _ = worker.join();
+        }
+    }
+}
+
+impl Drop for FormatQueue {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// The worker loop.
+fn work(shared: &Shared, options: &Options, mut callback: impl FnMut(QueueResult)) {
+    let _guard = ExitGuard(shared);
+    loop {
+        let (path, content) = {
+            let mut state = shared.lock();
+            loop {
+                if state.shutdown {
+                    return;
+                }
+                if let Some(next) = state.take_next() {
+                    state.busy = true;
+                    break next;
+                }
+                state = shared
+                    .condvar
+                    .wait(state)
+                    .unwrap_or_else(PoisonError::into_inner);
+            }
+        };
+
+        let events = options.get_events();
+        if let Some(events) = events {
+            events.emit(EventKind::FileStarted { path: path.clone() });
+        }
+        let start = std::time::Instant::now();
+        let result = crate::format_buffer_with(&path, content, options);
+        if let Some(events) = events {
+            let outcome = result.as_ref().map(|_| Outcome::Formatted);
+            events.file_finished(&path, outcome, start.elapsed());
+        }
+        callback(QueueResult { path, result });
+
+        shared.lock().busy = false;
+        shared.condvar.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{FormatQueue, Priority, State};
+    use crate::Options;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_take_next() {
+        let mut state = State::default();
+        state.submit("a.rs".into(), "a1".into(), Priority::Background);
+        state.submit("b.rs".into(), "b".into(), Priority::Background);
+        state.submit("c.rs".into(), "c".into(), Priority::Visible);
+        state.submit("a.rs".into(), "a2".into(), Priority::Background);
+
+        assert_eq!(
+            Some((PathBuf::from("c.rs"), "c".to_string())),
+            state.take_next()
+        );
+        assert_eq!(
+            Some((PathBuf::from("b.rs"), "b".to_string())),
+            state.take_next()
+        );
+        assert_eq!(
+            Some((PathBuf::from("a.rs"), "a2".to_string())),
+            state.take_next()
+        );
+        assert_eq!(None, state.take_next());
+    }
+
+    #[test]
+    fn test_panicking_callback() {
+        let queue = FormatQueue::new(Options::new(), |_| panic!("callback failed"));
+        queue.submit("a.rs", "fn a(){}", Priority::Visible);
+        queue.submit("b.rs", "fn b(){}", Priority::Background);
+        queue.join();
+    }
+}
@@ -0,0 +1,718 @@
+//! Evaluation of batch results.
+
+use crate::batch::{BatchOptions, Duplicate, FileResult, Outcome, SkipReason};
+use crate::Error;
+use std::fmt::{Display, Formatter};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+/// The exit codes that are assigned to the possible states of a batch result.
+///
+/// The default codes are `0` when all files are formatted correctly, `1` when some files are not
+/// formatted correctly, `2` when every file failed with an error and `3` when some files failed
+/// with an error.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct ExitCodePolicy {
+    /// The code if all files were processed successfully and none of them are unformatted.
+    pub clean: i32,
+    /// The code if all files were processed successfully, but some of them are unformatted.
+    pub unformatted: i32,
+    /// The code if processing failed for all files.
+    pub tool_error: i32,
+    /// The code if processing failed for some, but not all files.
+    pub partial_failure: i32,
+}
+
+impl Default for ExitCodePolicy {
+    fn default() -> Self {
+        Self {
+            clean: 0,
+            unformatted: 1,
+            tool_error: 2,
+            partial_failure: 3,
+        }
+    }
+}
+
+/// The amount of detail in reports and logs.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Verbosity {
+    /// Only errors are reported.
+    Quiet,
+    /// Errors and the files that were formatted or are not formatted correctly are reported.
+    #[default]
+    Normal,
+    /// All files are reported, along with the time spent on them.
+    Verbose,
+}
+
+/// Statistics about the duplicates in a batch (see [`crate::batch::Duplicate`]).
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct DedupStats {
+    /// The number of files that were passed to the formatting tool.
+    pub unique: usize,
+    /// The number of files whose result was reused from a file with identical content.
+    pub duplicates: usize,
+    /// The number of paths that refer to a file that was already part of the batch.
+    pub same_file: usize,
+}
+
+/// Statistics about the files of a batch that were left untouched (see
+/// [`crate::batch::SkipReason`]).
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct SkipStats {
+    /// The number of files that were skipped because they are read-only.
+    pub read_only: usize,
+    /// The number of files that were skipped because of a file-level `#![rustfmt::skip]`
+    /// attribute.
+    pub file_level_skip: usize,
+}
+
+/// Files that failed with the same error.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct ErrorGroup {
+    /// The error message. Occurrences of the path of a file are replaced with `<file>`.
+    pub message: String,
+    /// The number of files that failed with the error.
+    pub count: usize,
+    /// The first few files that failed with the error (at most [`ErrorGroup::MAX_EXEMPLARS`]).
+    pub exemplars: Vec<PathBuf>,
+}
+
+impl ErrorGroup {
+    /// The maximum number of exemplar files per group.
+    pub const MAX_EXEMPLARS: usize = 3;
+}
+
+impl Display for ErrorGroup {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let exemplars: Vec<String> = self
+            .exemplars
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect();
+        write!(f, "{} ({} file(s)", self.message, self.count)?;
+        if !exemplars.is_empty() {
+            write!(f, ", e.g. {}", exemplars.join(", "))?;
+        }
+        if self.count > exemplars.len() {
+            f.write_str(", ...")?;
+        }
+        f.write_str(")")
+    }
+}
+
+/// A summary of the changes to files, as in `git diff --stat`.
+///
+/// The summary is displayed like `3 files changed, 42 insertions(+), 17 deletions(-)`, leaving out
+/// insertions and deletions if there are none.
+///
+/// # Examples
+///
+/// ```
+/// use rust_format::report::DiffStat;
+///
+/// let stat = DiffStat {
+///     files_changed: 3,
+///     insertions: 42,
+///     deletions: 1,
+/// };
+/// assert_eq!("3 files changed, 42 insertions(+), 1 deletion(-)", stat.to_string());
+/// ```
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct DiffStat {
+    /// The number of changed files.
+    pub files_changed: usize,
+    /// The number of inserted lines.
+    pub insertions: usize,
+    /// The number of deleted lines.
+    pub deletions: usize,
+}
+
+impl DiffStat {
+    /// Compute the changes between two versions of a file.
+    ///
+    /// # Returns
+    ///
+    /// The changes, or `None` if the versions are identical.
+    #[cfg(feature = "diff")]
+    pub(crate) fn between(old: &str, new: &str) -> Option<Self> {
+        use crate::diff::Edit;
+
+        if old == new {
+            return None;
+        }
+        let old_lines: Vec<&str> = old.lines().collect();
+        let new_lines: Vec<&str> = new.lines().collect();
+        let mut stat = DiffStat {
+            files_changed: 1,
+            ..DiffStat::default()
+        };
+        for edit in crate::diff::diff(&old_lines, &new_lines) {
+            match edit {
+                Edit::Delete(_) => stat.deletions += 1,
+                Edit::Insert(_) => stat.insertions += 1,
+                Edit::Equal(..) => {}
+            }
+        }
+        Some(stat)
+    }
+}
+
+impl std::ops::Add for DiffStat {
+    type Output = DiffStat;
+
+    fn add(self, other: DiffStat) -> DiffStat {
+        DiffStat {
+            files_changed: self.files_changed + other.files_changed,
+            insertions: self.insertions + other.insertions,
+            deletions: self.deletions + other.deletions,
+        }
+    }
+}
+
+impl Display for DiffStat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let plural = |count: usize| if count == 1 { "" } else { "s" };
+        write!(
+            f,
+            "{} file{} changed",
+            self.files_changed,
+            plural(self.files_changed)
+        )?;
+        if self.insertions > 0 {
+            write!(
+                f,
+                ", {} insertion{}(+)",
+                self.insertions,
+                plural(self.insertions)
+            )?;
+        }
+        if self.deletions > 0 {
+            write!(
+                f,
+                ", {} deletion{}(-)",
+                self.deletions,
+                plural(self.deletions)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Extension methods for the results of a batch operation.
+///
+/// The entries of reports are sorted by path, so that they do not depend on the order in which the
+/// files were passed or processed, e.g. for snapshot tests of reports of parallel runs. Entries of
+/// the same path keep the order of the results.
+pub trait ReportExt {
+    /// Translate the results into a process exit code.
+    ///
+    /// # Arguments
+    ///
+    /// * `policy`: The exit codes to use.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use rust_format::batch::{format_files_with, BatchOptions};
+    /// use rust_format::report::{ExitCodePolicy, ReportExt};
+    ///
+    /// let results = format_files_with(["src/lib.rs"], &BatchOptions::new().check(true));
+    /// std::process::exit(results.exit_code(&ExitCodePolicy::default()));
+    /// ```
+    fn exit_code(&self, policy: &ExitCodePolicy) -> i32;
+
+    /// Count the files that were processed and the duplicates whose result was reused.
+    fn dedup_stats(&self) -> DedupStats;
+
+    /// Count the files that were left untouched, by their reason.
+    fn skip_stats(&self) -> SkipStats;
+
+    /// Group the failed files by their error, so that an error that occurs for many files is
+    /// reported only once.
+    ///
+    /// The groups are ordered by the first file, by path, that failed with the error.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use rust_format::batch::format_files;
+    /// use rust_format::report::ReportExt;
+    ///
+    /// for group in format_files(["a.rs", "b.rs"]).error_groups() {
+    ///     eprintln!("{}", group);
+    /// }
+    /// ```
+    fn error_groups(&self) -> Vec<ErrorGroup>;
+
+    /// Collect the files that were formatted in place, including the files of child modules that
+    /// the formatting tool modified along with them (see [`FileResult::children`]).
+    ///
+    /// Every file is listed once, sorted by path.
+    fn formatted_files(&self) -> Vec<PathBuf>;
+
+    /// Sum up the changes to the files (see [`FileResult::diff_stat`]).
+    ///
+    /// Duplicates whose result was reused for a path that refers to the same file are counted
+    /// once.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use rust_format::batch::{format_files_with, BatchOptions};
+    /// use rust_format::report::ReportExt;
+    ///
+    /// let options = BatchOptions::new().diff_stats(true);
+    /// println!("{}", format_files_with(["a.rs", "b.rs"], &options).diff_stat());
+    /// ```
+    fn diff_stat(&self) -> DiffStat;
+
+    /// Render a human-readable report with one line per reported file.
+    ///
+    /// # Arguments
+    ///
+    /// * `verbosity`: The amount of detail (see [`crate::Options::verbosity`]).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use rust_format::batch::format_files;
+    /// use rust_format::report::{ReportExt, Verbosity};
+    ///
+    /// eprint!("{}", format_files(["a.rs", "b.rs"]).render(Verbosity::Verbose));
+    /// ```
+    fn render(&self, verbosity: Verbosity) -> String;
+
+    /// Render a human-readable report with the verbosity of options (see
+    /// [`crate::Options::verbosity`]), e.g. the options of the batch that produced the results.
+    ///
+    /// # Arguments
+    ///
+    /// * `options`: The options.
+    fn render_with(&self, options: &crate::Options) -> String {
+        self.render(options.get_verbosity())
+    }
+}
+
+impl ReportExt for [FileResult] {
+    fn exit_code(&self, policy: &ExitCodePolicy) -> i32 {
+        let failed = self.iter().filter(|file| file.result.is_err()).count();
+        let unformatted = self
+            .iter()
+            .any(|file| matches!(file.result, Ok(Outcome::Unformatted)));
+
+        if failed > 0 && failed == self.len() {
+            policy.tool_error
+        } else if failed > 0 {
+            policy.partial_failure
+        } else if unformatted {
+            policy.unformatted
+        } else {
+            policy.clean
+        }
+    }
+
+    fn error_groups(&self) -> Vec<ErrorGroup> {
+        let mut groups: Vec<ErrorGroup> = Vec::new();
+        for file in by_path(self) {
+            let error = match &file.result {
+                Err(error) => error,
+                Ok(_) => continue,
+            };
+            let path = file.path.display().to_string();
+            let mut message = error.to_string();
+            if !path.is_empty() {
+                message = message.replace(&path, "<file>");
+            }
+            match groups.iter_mut().find(|group| group.message == message) {
+                Some(group) => {
+                    group.count += 1;
+                    if group.exemplars.len() < ErrorGroup::MAX_EXEMPLARS {
+                        group.exemplars.push(file.path.clone());
+                    }
+                }
+                None => groups.push(ErrorGroup {
+                    message,
+                    count: 1,
+                    exemplars: vec![file.path.clone()],
+                }),
+            }
+        }
+        groups
+    }
+
+    fn formatted_files(&self) -> Vec<PathBuf> {
+        let mut files: Vec<PathBuf> = self
+            .iter()
+            .filter(|file| matches!(file.result, Ok(Outcome::Formatted)))
+            .flat_map(|file| std::iter::once(&file.path).chain(&file.children))
+            .cloned()
+            .collect();
+        files.sort();
+        files.dedup();
+        files
+    }
+
+    fn diff_stat(&self) -> DiffStat {
+        self.iter()
+            .filter(|file| !matches!(file.duplicate, Some(Duplicate::SameFile(_))))
+            .filter_map(|file| file.diff_stat)
+            .fold(DiffStat::default(), |total, stat| total + stat)
+    }
+
+    fn render(&self, verbosity: Verbosity) -> String {
+        by_path(self)
+            .into_iter()
+            .filter_map(|file| render_file(file, verbosity))
+            .collect()
+    }
+
+    fn dedup_stats(&self) -> DedupStats {
+        let mut stats = DedupStats::default();
+        for file in self {
+            match file.duplicate {
+                None => stats.unique += 1,
+                Some(Duplicate::SameContent(_)) => stats.duplicates += 1,
+                Some(Duplicate::SameFile(_)) => stats.same_file += 1,
+            }
+        }
+        stats
+    }
+
+    fn skip_stats(&self) -> SkipStats {
+        let mut stats = SkipStats::default();
+        for file in self {
+            match file.result {
+                Ok(Outcome::Skipped(SkipReason::ReadOnly)) => stats.read_only += 1,
+                Ok(Outcome::Skipped(SkipReason::FileLevelSkip)) => stats.file_level_skip += 1,
+                _ => {}
+            }
+        }
+        stats
+    }
+}
+
+/// Sort the results of files by their path, keeping the order of results of the same path.
+fn by_path(results: &[FileResult]) -> Vec<&FileResult> {
+    let mut sorted: Vec<&FileResult> = results.iter().collect();
+    sorted.sort_by(|a, b| a.path.cmp(&b.path));
+    sorted
+}
+
+/// Render the report lines of a single file, or `None` if the file is not reported with the
+/// verbosity.
+pub(crate) fn render_file(file: &FileResult, verbosity: Verbosity) -> Option<String> {
+    use std::fmt::Write;
+
+    let mut report = String::new();
+    let path = file.path.display();
+    let _ = match (&file.result, verbosity) {
+        (Err(error), _) => match file.request_id {
+            Some(request) => write!(
+                report,
+                "Failed to process '{}' (request {}): {}",
+                path, request, error
+            ),
+            None => write!(report, "Failed to process '{}': {}", path, error),
+        },
+        (Ok(Outcome::Formatted), Verbosity::Normal | Verbosity::Verbose) => {
+            write!(report, "Formatted '{}'.", path)
+        }
+        (Ok(Outcome::Unformatted), Verbosity::Normal | Verbosity::Verbose) => {
+            write!(report, "Unformatted '{}'.", path)
+        }
+        (Ok(Outcome::Clean), Verbosity::Verbose) => write!(report, "Clean '{}'.", path),
+        (Ok(Outcome::Skipped(reason)), Verbosity::Verbose) => {
+            write!(report, "Skipped '{}' ({}).", path, reason)
+        }
+        _ => return None,
+    };
+    if verbosity == Verbosity::Verbose {
+        let _ = write!(report, " ({} ms)", file.elapsed.as_millis());
+    }
+    report.push('\n');
+    if verbosity != Verbosity::Quiet && file.result.is_ok() {
+        for child in &file.children {
+            let _ = writeln!(report, "Formatted '{}'.", child.display());
+        }
+    }
+    Some(report)
+}
+
+/// The results of a check of multiple files, e.g. by [`crate::batch::check_files_with`].
+///
+/// The results dereference to the results of the files, so the methods of [`ReportExt`] are
+/// available as well.
+#[must_use = "the files of a check may not be formatted correctly"]
+#[derive(Debug)]
+pub struct CheckResults {
+    results: Vec<FileResult>,
+    options: BatchOptions,
+}
+
+impl CheckResults {
+    /// Collect the results of a check.
+    ///
+    /// # Arguments
+    ///
+    /// * `results`: The results of the files.
+    /// * `options`: The options of the check, which are used to fix the files.
+    pub(crate) fn new(results: Vec<FileResult>, options: BatchOptions) -> Self {
+        Self { results, options }
+    }
+
+    /// Determine whether all files were checked successfully and are formatted correctly.
+    pub fn is_clean(&self) -> bool {
+        self.results
+            .iter()
+            .all(|file| matches!(file.result, Ok(outcome) if outcome != Outcome::Unformatted))
+    }
+
+    /// Collect the files that are not formatted correctly, sorted by path.
+    pub fn unformatted_files(&self) -> Vec<&Path> {
+        by_path(&self.results)
+            .into_iter()
+            .filter(|file| matches!(file.result, Ok(Outcome::Unformatted)))
+            .map(|file| file.path.as_path())
+            .collect()
+    }
+
+    /// Convert the results into an error if the check did not pass.
+    ///
+    /// # Returns
+    ///
+    /// The error of the first file, by path, that could not be checked, if any. Otherwise, an
+    /// [`Error::Unformatted`] with the files that are not formatted correctly, if any, sorted by
+    /// path.
+    pub fn into_error_if_dirty(mut self) -> Result<(), Error> {
+        self.results.sort_by(|a, b| a.path.cmp(&b.path));
+        let mut files = Vec::new();
+        for file in self.results {
+            match file.result {
+                Err(error) => return Err(error),
+                Ok(Outcome::Unformatted) => files.push(file.path),
+                Ok(_) => {}
+            }
+        }
+        match files.is_empty() {
+            true => Ok(()),
+            false => Err(Error::Unformatted { files }),
+        }
+    }
+
+    /// Format the files that are not formatted correctly in place.
+    ///
+    /// The files are formatted with the options of the check. Rules that enable checking for some
+    /// files (see [`BatchOptions::check_for`]) do not apply.
+    ///
+    /// # Returns
+    ///
+    /// The results of formatting the files.
+    pub fn write_all_fixes(&self) -> Vec<FileResult> {
+        let files: Vec<PathBuf> = self
+            .unformatted_files()
+            .into_iter()
+            .map(Path::to_path_buf)
+            .collect();
+        crate::batch::format_files_with(files, &self.options.fixing())
+    }
+
+    /// Retrieve the results of the files.
+    pub fn into_results(self) -> Vec<FileResult> {
+        self.results
+    }
+}
+
+impl Deref for CheckResults {
+    type Target = [FileResult];
+
+    fn deref(&self) -> &Self::Target {
+        &self.results
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ExitCodePolicy, ReportExt, Verbosity};
+    use crate::batch::{FileResult, Outcome};
+    use crate::Error;
+
+    fn file(result: Result<Outcome, Error>) -> FileResult {
+        named("a.rs", result)
+    }
+
+    fn named(path: &str, result: Result<Outcome, Error>) -> FileResult {
+        FileResult {
+            path: path.into(),
+            result,
+            duplicate: None,
+            children: Vec::new(),
+            elapsed: Default::default(),
+            diff_stat: None,
+            request_id: None,
+        }
+    }
+
+    #[test]
+    fn test_exit_code() {
+        let policy = ExitCodePolicy::default();
+        assert_eq!(0, [file(Ok(Outcome::Clean))].exit_code(&policy));
+        assert_eq!(
+            1,
+            [file(Ok(Outcome::Clean)), file(Ok(Outcome::Unformatted))].exit_code(&policy)
+        );
+        assert_eq!(
+            2,
+            [file(Err(Error::NoResultCode {
+                backend: "mock".into(),
+                path: None
+            }))]
+            .exit_code(&policy)
+        );
+        assert_eq!(
+            3,
+            [
+                file(Err(Error::NoResultCode {
+                    backend: "mock".into(),
+                    path: None
+                })),
+                file(Ok(Outcome::Formatted))
+            ]
+            .exit_code(&policy)
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        // Reports are sorted by path, regardless of the order of the results.
+        let results = [
+            named(
+                "c.rs",
+                Err(Error::NoResultCode {
+                    backend: "mock".into(),
+                    path: None,
+                }),
+            ),
+            named("a.rs", Ok(Outcome::Clean)),
+            named("b.rs", Ok(Outcome::Formatted)),
+        ];
+        assert_eq!(
+            "Failed to process 'c.rs': No result code received from formatting tool process.\n",
+            results.render(Verbosity::Quiet)
+        );
+        assert_eq!(
+            "Formatted 'b.rs'.\nFailed to process 'c.rs': No result code received from formatting tool process.\n",
+            results.render(Verbosity::Normal)
+        );
+        assert_eq!(
+            3,
+            results.render(Verbosity::Verbose).matches(" ms)").count()
+        );
+        let quiet = crate::Options::new().verbosity(Verbosity::Quiet);
+        assert_eq!(
+            results.render(Verbosity::Quiet),
+            results.render_with(&quiet)
+        );
+    }
+
+    #[test]
+    fn test_check_results() {
+        use crate::batch::{check_files_with, BatchOptions};
+        use std::path::Path;
+
+        let dir = Path::new("target/check_results");
+        let _ = std::fs::remove_dir_all(dir);
+        std::fs::create_dir_all(dir).unwrap();
+        std::fs::write(dir.join("clean.rs"), "fn main() {}\n").unwrap();
+        std::fs::write(dir.join("dirty.rs"), "fn main(){}").unwrap();
+        let paths = [dir.join("clean.rs"), dir.join("dirty.rs")];
+
+        let results = check_files_with(&paths, &BatchOptions::new());
+        assert!(!results.is_clean());
+        assert_eq!(vec![paths[1].as_path()], results.unformatted_files());
+        assert_eq!(1, results.exit_code(&ExitCodePolicy::default()));
+        let fixes = results.write_all_fixes();
+        assert_eq!(Outcome::Formatted, *fixes[0].result.as_ref().unwrap());
+        assert!(matches!(
+            results.into_error_if_dirty(),
+            Err(Error::Unformatted { files }) if files == [paths[1].clone()]
+        ));
+
+        let results = check_files_with(&paths, &BatchOptions::new());
+        assert!(results.is_clean());
+        assert!(results.into_error_if_dirty().is_ok());
+    }
+
+    #[cfg(feature = "diff")]
+    #[test]
+    fn test_diff_stat() {
+        use super::DiffStat;
+        use crate::batch::{format_files_with, BatchOptions};
+        use std::path::Path;
+
+        let dir = Path::new("target/diff_stat");
+        let _ = std::fs::remove_dir_all(dir);
+        std::fs::create_dir_all(dir).unwrap();
+        std::fs::write(dir.join("clean.rs"), "fn main() {}\n").unwrap();
+        std::fs::write(dir.join("dirty.rs"), "fn main(){ run(); }\n").unwrap();
+        let paths = [
+            dir.join("clean.rs"),
+            dir.join("dirty.rs"),
+            dir.join("./dirty.rs"),
+        ];
+        let expected = DiffStat {
+            files_changed: 1,
+            insertions: 3,
+            deletions: 1,
+        };
+
+        let options = BatchOptions::new().diff_stats(true);
+        let checked = format_files_with(&paths, &options.clone().check(true));
+        assert_eq!(None, checked[0].diff_stat);
+        assert_eq!(Some(expected), checked[1].diff_stat);
+        assert_eq!(expected, checked.diff_stat());
+        let formatted = format_files_with(&paths, &options);
+        assert_eq!(Some(expected), formatted[2].diff_stat);
+        assert_eq!(
+            "1 file changed, 3 insertions(+), 1 deletion(-)",
+            formatted.diff_stat().to_string()
+        );
+        assert_eq!(None, format_files_with(&paths, &options)[1].diff_stat);
+    }
+
+    #[test]
+    fn test_error_groups() {
+        let missing = |path: &str| {
+            named(
+                path,
+                Err(Error::NoBackup {
+                    path: format!("target/{}", path).into(),
+                }),
+            )
+        };
+        let results: Vec<FileResult> = (0..5)
+            .rev()
+            .map(|index| missing(&format!("{}.rs", index)))
+            .chain([
+                named(
+                    "x.rs",
+                    Err(Error::NoResultCode {
+                        backend: "mock".into(),
+                        path: None,
+                    }),
+                ),
+                named("y.rs", Ok(Outcome::Clean)),
+            ])
+            .collect();
+
+        let groups = results.error_groups();
+        assert_eq!(2, groups.len());
+        assert_eq!(5, groups[0].count);
+        assert_eq!(
+            "No backup found for 'target/<file>'. (5 file(s), e.g. 0.rs, 1.rs, 2.rs, ...)",
+            groups[0].to_string()
+        );
+        assert_eq!(1, groups[1].count);
+    }
+}
@@ -0,0 +1,132 @@
+//! Identifiers of formatting operations, for correlating errors and reports with logs.
+
+use std::cell::Cell;
+use std::fmt::{Display, Formatter};
+use std::num::NonZeroU64;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// The identifier of a formatting operation.
+///
+/// Every operation, e.g. formatting a file or a string, is assigned an identifier that is unique
+/// among all processes of a machine at a time. All runs of the formatting tool within the
+/// operation share it. It is part of the errors of the tool (see
+/// [`crate::Error::ToolExecutionError`]), of the results of batch operations (see
+/// [`crate::batch::FileResult::request_id`]) and, with the `tracing` feature, of the spans of the
+/// runs of the tool, so that a failure report can be matched with the logs of the run.
+///
+/// The identifier is displayed as the hexadecimal process ID and the sequence number of the
+/// operation within the process, e.g. `1f3a-42`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct RequestId {
+    process: u32,
+    sequence: NonZeroU64,
+}
+
+thread_local! {
+    /// The identifier of the operation that the current thread performs, if any.
+    static CURRENT: Cell<Option<RequestId>> = const { Cell::new(None) };
+}
+
+impl RequestId {
+    /// Create a new identifier.
+    fn next() -> Self {
+        static SEQUENCE: AtomicU64 = AtomicU64::new(1);
+        Self {
+            process: std::process::id(),
+            sequence: NonZeroU64::new(SEQUENCE.fetch_add(1, Ordering::Relaxed))
+                .unwrap_or(NonZeroU64::MAX),
+        }
+    }
+
+    /// Retrieve the identifier of the operation that the current thread performs, if any.
+    pub fn current() -> Option<RequestId> {
+        CURRENT.with(Cell::get)
+    }
+
+    /// Retrieve the ID of the process that performed the operation.
+    pub fn process(&self) -> u32 {
+        self.process
+    }
+
+    /// Retrieve the sequence number of the operation within its process.
+    pub fn sequence(&self) -> u64 {
+        self.sequence.get()
+    }
+}
+
+impl Display for RequestId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:x}-{}", self.process, self.sequence)
+    }
+}
+
+/// Restores the identifier of the enclosing operation, even if the thread panics.
+struct Restore(Option<RequestId>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        CURRENT.with(|current| current.set(self.0));
+    }
+}
+
+/// Perform an operation with an identifier.
+///
+/// Operations that are performed within another operation on the same thread share its
+/// identifier.
+pub(crate) fn scope<T>(operation: impl FnOnce(RequestId) -> T) -> T {
+    let previous = RequestId::current();
+    let id = previous.unwrap_or_else(RequestId::next);
+    CURRENT.with(|current| current.set(Some(id)));
+    let _restore = Restore(previous);
+    operation(id)
+}
+
+#[cfg(test)]
+mod test {
+    use super::RequestId;
+
+    #[test]
+    fn test_scope() {
+        assert_eq!(None, RequestId::current());
+        let (outer, inner) = super::scope(|outer| (outer, super::scope(|inner| inner)));
+        assert_eq!(outer, inner);
+        assert_eq!(None, RequestId::current());
+        let next = super::scope(|id| id);
+        assert!(next.sequence() > outer.sequence());
+        assert_eq!(
+            format!("{:x}-{}", std::process::id(), next.sequence()),
+            next.to_string()
+        );
+    }
+
+    #[cfg(feature = "process-backend")]
+    #[test]
+    fn test_error_request() {
+        use crate::batch::{format_files_with, BatchOptions};
+        use crate::report::{ReportExt, Verbosity};
+        use crate::Error;
+
+        let error = crate::format_str("fn main() {").unwrap_err();
+        let request = match &error {
+            Error::ToolExecutionError { request, .. } => request.unwrap(),
+            other => panic!("Unexpected error: {:?}", other),
+        };
+        assert!(error
+            .to_string()
+            .contains(&format!(", request {})", request)));
+
+        let path = std::path::PathBuf::from("target/request.rs");
+        std::fs::write(&path, "fn main() {").unwrap();
+        let results = format_files_with([&path], &BatchOptions::new());
+        let request = results[0].request_id.unwrap();
+        assert!(results.render(Verbosity::Normal).starts_with(&format!(
+            "Failed to process '{}' (request {})",
+            path.display(),
+            request
+        )));
+        assert!(matches!(
+            &results[0].result,
+            Err(Error::ToolExecutionError { request: Some(id), .. }) if *id == request
+        ));
+    }
+}
@@ -0,0 +1,197 @@
+//! The fully resolved settings of a formatting operation, for diagnostics and tooling.
+
+use crate::json::JsonObject;
+use crate::{CompileCheck, Config, Edition, Limits, Options, Profile};
+
+/// The version of the JSON encoding of [`ResolvedOptions`]. It is incremented whenever a member
+/// changes its meaning or is removed; new members may be added without a new version.
+pub const RESOLVED_OPTIONS_VERSION: u64 = 1;
+
+/// The settings that a formatting operation runs with, after the profile, the explicit
+/// configuration and the defaults are combined. Created by [`Options::effective_options`].
+///
+/// # Examples
+///
+/// ```
+/// use rust_format::{Config, Edition, Options, Profile};
+///
+/// let options = Options::new()
+///     .edition(Edition::E2021)
+///     .profile(Profile::Expanded)
+///     .config(Config::new().max_width(80));
+/// let resolved = options.effective_options();
+/// assert_eq!(Some("80"), resolved.config.get("max_width"));
+/// assert_eq!(Some("Off"), resolved.config.get("use_small_heuristics"));
+/// assert_eq!(["--edition", "2021", "--config"], resolved.arguments[..3]);
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub struct ResolvedOptions {
+    /// The edition, if set. Otherwise, the formatting tool determines the edition itself.
+    pub edition: Option<Edition>,
+    /// The profile that provides the base configuration.
+    pub profile: Profile,
+    /// The configuration passed to the formatting tool, including the profile settings.
+    pub config: Config,
+    /// The settings that are not passed to the formatting tool because it does not support them on
+    /// its channel (see [`Options::degrade_unsupported_config`]), in alphabetical order.
+    pub degraded_config: Vec<String>,
+    /// The arguments passed to the formatting tool, in addition to the input.
+    pub arguments: Vec<String>,
+    /// A description of the backend, e.g. `Rustfmt { toolchain: Some("nightly") }`. The
+    /// description is meant for humans and may change between versions of the crate.
+    pub backend: String,
+    /// The maximum size of the input in bytes, if any.
+    pub max_input_bytes: Option<u64>,
+    /// The maximum number of bytes that are captured from stdout, if any.
+    pub max_stdout_bytes: Option<u64>,
+    /// The maximum number of bytes that are captured from stderr, if any.
+    pub max_stderr_bytes: Option<u64>,
+    /// The limits on the shape of the input.
+    pub limits: Limits,
+    /// The names of the enabled verifications, e.g. `preserve_comments`, in alphabetical order.
+    pub verifications: Vec<&'static str>,
+    /// The verification that the output compiles.
+    pub check_compile: CompileCheck,
+}
+
+impl ResolvedOptions {
+    /// Resolve the settings of options.
+    pub(crate) fn of(options: &Options) -> Self {
+        let mut verifications = Vec::new();
+        if options.get_preserve_comments() {
+            verifications.push("preserve_comments");
+        }
+        if options.get_preserve_literals() {
+            verifications.push("preserve_literals");
+        }
+        if options.get_reject_line_overflow() {
+            verifications.push("reject_line_overflow");
+        }
+        if options.get_validate_config() {
+            verifications.push("validate_config");
+        }
+        #[cfg(feature = "syn")]
+        if options.get_validate_syntax() {
+            verifications.push("validate_syntax");
+        }
+        #[cfg(feature = "diff")]
+        if options.get_verify_tokens() {
+            verifications.push("verify_tokens");
+        }
+        Self {
+            edition: options.get_edition(),
+            profile: options.get_profile(),
+            config: options.tool_config(),
+            degraded_config: options.degraded_config(),
+            arguments: options.arguments(),
+            backend: format!("{:?}", options.get_backend()),
+            max_input_bytes: options.get_max_input_bytes(),
+            max_stdout_bytes: options.get_max_stdout_bytes(),
+            max_stderr_bytes: options.get_max_stderr_bytes(),
+            limits: *options.get_limits(),
+            verifications,
+            check_compile: options.get_check_compile(),
+        }
+    }
+
+    /// Encode the settings as a single-line JSON object.
+    ///
+    /// The object has a `version` member with the value of [`RESOLVED_OPTIONS_VERSION`]. Absent
+    /// values are encoded as `null`, and the values of configuration settings as strings.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rust_format::Options;
+    ///
+    /// let json = Options::new().max_input_bytes(1024).effective_options().to_json();
+    /// assert!(json.starts_with(r#"{"version":1,"edition":null,"profile":"default","config":{}"#));
+    /// assert!(json.contains(r#""max_input_bytes":1024,"#));
+    /// ```
+    pub fn to_json(&self) -> String {
+        let config = self
+            .config
+            .iter()
+            .fold(JsonObject::new(), |object, (key, value)| {
+                object.string(key, value)
+            });
+        let limits = JsonObject::new()
+            .optional_number(
+                "max_line_length",
+                self.limits.max_line_length.map(|length| length as u64),
+            )
+            .optional_number(
+                "max_nesting_depth",
+                self.limits.max_nesting_depth.map(|depth| depth as u64),
+            )
+            .bool("reject_nul_bytes", self.limits.reject_nul_bytes);
+        JsonObject::new()
+            .number("version", RESOLVED_OPTIONS_VERSION)
+            .optional_string("edition", self.edition.map(Edition::as_str))
+            .string("profile", profile_name(self.profile))
+            .object("config", config)
+            .strings("degraded_config", &self.degraded_config)
+            .strings("arguments", &self.arguments)
+            .string("backend", &self.backend)
+            .optional_number("max_input_bytes", self.max_input_bytes)
+            .optional_number("max_stdout_bytes", self.max_stdout_bytes)
+            .optional_number("max_stderr_bytes", self.max_stderr_bytes)
+            .object("limits", limits)
+            .strings("verifications", &self.verifications)
+            .string("check_compile", compile_check_name(self.check_compile))
+            .build()
+    }
+}
+
+/// Retrieve the name of a profile in the JSON encoding.
+fn profile_name(profile: Profile) -> &'static str {
+    match profile {
+        Profile::Compact => "compact",
+        Profile::Default => "default",
+        Profile::Expanded => "expanded",
+    }
+}
+
+/// Retrieve the name of a compile check in the JSON encoding.
+fn compile_check_name(check: CompileCheck) -> &'static str {
+    match check {
+        CompileCheck::Off => "off",
+        #[cfg(feature = "syn")]
+        CompileCheck::Syntax => "syntax",
+        CompileCheck::Rustc => "rustc",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Config, Edition, Limits, Options, Profile};
+
+    #[test]
+    fn test_to_json() {
+        let options = Options::new()
+            .edition(Edition::E2018)
+            .profile(Profile::Compact)
+            .config(Config::new().max_width(90))
+            .limits(Limits::recommended())
+            .preserve_comments(true)
+            .skip_children(true);
+        let resolved = options.effective_options();
+        assert_eq!(Some("90"), resolved.config.get("max_width"));
+        assert_eq!(Some("true"), resolved.config.get("skip_children"));
+        assert_eq!(vec!["preserve_comments"], resolved.verifications);
+
+        let json = resolved.to_json();
+        assert!(json.starts_with(
+            r#"{"version":1,"edition":"2018","profile":"compact","config":{"fn_params_layout":"Compressed","#
+        ));
+        assert!(json.contains(
+            r#""arguments":["--edition","2018","--config","fn_params_layout=Compressed,"#
+        ));
+        assert!(json.ends_with(concat!(
+            r#""max_input_bytes":null,"max_stdout_bytes":null,"max_stderr_bytes":null,"#,
+            r#""limits":{"max_line_length":100000,"max_nesting_depth":256,"reject_nul_bytes":true},"#,
+            r#""verifications":["preserve_comments"],"check_compile":"off"}"#
+        )));
+    }
+}
@@ -0,0 +1,211 @@
+//! Interactive review of formatting changes before they are applied.
+
+use crate::batch::{BatchOptions, FileResult, Outcome, SkipReason};
+use crate::error::IoContext;
+use crate::permissions::{make_writable, read_only};
+use crate::{Error, Options, ReadOnlyPolicy};
+use std::path::{Path, PathBuf};
+
+/// The decision of a review callback about a change.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Decision {
+    /// Write the formatted content to the file.
+    Apply,
+    /// Leave the file untouched and continue with the next file.
+    Skip,
+    /// Leave the file untouched and stop reviewing.
+    Abort,
+}
+
+/// A formatting change of a single file that is presented for review.
+#[derive(Debug)]
+pub struct Change<'a> {
+    path: &'a Path,
+    original: &'a str,
+    formatted: &'a str,
+}
+
+impl Change<'_> {
+    /// Retrieve the path of the file.
+    pub fn path(&self) -> &Path {
+        self.path
+    }
+
+    /// Retrieve the current content of the file.
+    pub fn original(&self) -> &str {
+        self.original
+    }
+
+    /// Retrieve the content of the file after formatting.
+    pub fn formatted(&self) -> &str {
+        self.formatted
+    }
+
+    /// Render the change as a unified diff.
+    pub fn diff(&self) -> String {
+        let name = self.path.display();
+        let mut output = format!("--- a/{}\n+++ b/{}\n", name, name);
+        output.push_str(&crate::diff::unified_diff(self.original, self.formatted));
+        output
+    }
+}
+
+/// Format multiple Rust source files, letting a callback decide about each change.
+///
+/// The files are processed one after another with the options of the
+/// [global formatter](crate::set_global). See [`review_files_with`].
+///
+/// # Arguments
+///
+/// * `paths`: The paths to the target files.
+/// * `callback`: The callback that decides about each change.
+pub fn review_files<P: Into<PathBuf>>(
+    paths: impl IntoIterator<Item = P>,
+    callback: impl FnMut(&Change<'_>) -> Decision,
+) -> Vec<FileResult> {
+    let options = BatchOptions::new().options(crate::global().options().clone());
+    review_files_with(paths, &options, callback)
+}
+
+/// Format multiple Rust source files using the provided options, letting a callback decide about
+/// each change.
+///
+/// Every file is formatted without modifying it. For each file whose formatted content differs,
+/// the callback is invoked with the change. Files are only written if the callback returns
+/// [`Decision::Apply`], in which case backups and audit logging apply as for
+/// [`crate::format_file_with`]. Skipped changes are reported as [`Outcome::Unformatted`], as is the
+/// change for which the callback returned [`Decision::Abort`]. No results are returned for the
+/// files after it.
+///
+/// The check mode of the batch options is ignored, since every change is either applied or
+/// skipped explicitly.
+///
+/// # Arguments
+///
+/// * `paths`: The paths to the target files.
+/// * `options`: The options.
+/// * `callback`: The callback that decides about each change.
+///
+/// # Examples
+///
+/// ```no_run
+/// use rust_format::batch::BatchOptions;
+/// use rust_format::review::{self, Decision};
+///
+/// let results = review::review_files_with(["src/lib.rs"], &BatchOptions::new(), |change| {
+///     print!("{}", change.diff());
+///     Decision::Apply
+/// });
+/// ```
+pub fn review_files_with<P: Into<PathBuf>>(
+    paths: impl IntoIterator<Item = P>,
+    options: &BatchOptions,
+    mut callback: impl FnMut(&Change<'_>) -> Decision,
+) -> Vec<FileResult> {
+    let mut results = Vec::new();
+    for path in paths {
+        let path = path.into();
+        let (options, _) = options.resolve(&path);
+        let mut decision = None;
+        let start = std::time::Instant::now();
+        let result = review(&path, &options, |change| {
+            let chosen = callback(change);
+            decision = Some(chosen);
+            chosen
+        });
+        results.push(FileResult {
+            path,
+            result,
+            duplicate: None,
+            children: Vec::new(),
+            elapsed: start.elapsed(),
+            diff_stat: None,
+            request_id: None,
+        });
+        if decision == Some(Decision::Abort) {
+            break;
+        }
+    }
+    results
+}
+
+/// Review the formatting change of a single file.
+fn review(
+    path: &Path,
+    options: &Options,
+    callback: impl FnOnce(&Change<'_>) -> Decision,
+) -> Result<Outcome, Error> {
+    let permissions = read_only(path)?;
+    if permissions.is_some() && options.get_read_only() == ReadOnlyPolicy::Skip {
+        return Ok(Outcome::Skipped(SkipReason::ReadOnly));
+    }
+
+    let original = std::fs::read_to_string(path).at(path)?;
+    let formatted = crate::format_file_to_string_with(path, options)?;
+    if original == formatted {
+        return Ok(Outcome::Clean);
+    }
+
+    let change = Change {
+        path,
+        original: &original,
+        formatted: &formatted,
+    };
+    if callback(&change) != Decision::Apply {
+        return Ok(Outcome::Unformatted);
+    }
+
+    match permissions {
+        None => crate::format::write_formatted(path, &original, &formatted, options)?,
+        Some(_) if options.get_read_only() == ReadOnlyPolicy::Error => {
+            return Err(Error::ReadOnly {
+                path: path.to_path_buf(),
+            });
+        }
+        Some(permissions) => {
+            make_writable(path, &permissions)?;
+            let result = crate::format::write_formatted(path, &original, &formatted, options);
+            std::fs::set_permissions(path, permissions).at(path)?;
+            result?;
+        }
+    }
+    Ok(Outcome::Formatted)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Decision, Outcome};
+    use crate::batch::BatchOptions;
+    use std::fs::{copy, read_to_string};
+
+    #[test]
+    fn test_review_files() {
+        let skipped = "target/review_skipped.rs";
+        let applied = "target/review_applied.rs";
+        copy("resources/test/sample_code.rs", skipped).unwrap();
+        copy("resources/test/sample_code.rs", applied).unwrap();
+
+        let mut reviewed = Vec::new();
+        let results =
+            super::review_files_with([skipped, applied], &BatchOptions::new(), |change| {
+                assert!(change.diff().starts_with("--- a/target/review_"));
+                reviewed.push(change.path().to_path_buf());
+                match reviewed.len() {
+                    1 => Decision::Skip,
+                    _ => Decision::Apply,
+                }
+            });
+
+        assert_eq!(2, reviewed.len());
+        assert_eq!(Outcome::Unformatted, *results[0].result.as_ref().unwrap());
+        assert_eq!(Outcome::Formatted, *results[1].result.as_ref().unwrap());
+        assert_eq!(
+            read_to_string("resources/test/sample_code.rs").unwrap(),
+            read_to_string(skipped).unwrap()
+        );
+        assert_eq!(
+            read_to_string("resources/test/expected.rs").unwrap(),
+            read_to_string(applied).unwrap()
+        );
+    }
+}
@@ -0,0 +1,291 @@
+//! The configuration settings that the formatting tool supports.
+
+use crate::backend::FormatBackend;
+use crate::{Config, Error};
+
+/// The settings that only nightly versions of the tool support, as of version 1.9. Stable
+/// versions do not report them, so they would be indistinguishable from unknown settings.
+const UNSTABLE_KEYS: &[&str] = &[
+    "binop_separator",
+    "blank_lines_lower_bound",
+    "blank_lines_upper_bound",
+    "brace_style",
+    "color",
+    "combine_control_expr",
+    "comment_width",
+    "condense_wildcard_suffixes",
+    "control_brace_style",
+    "doc_comment_code_block_width",
+    "emit_mode",
+    "empty_item_single_line",
+    "enum_discrim_align_threshold",
+    "error_on_line_overflow",
+    "error_on_unformatted",
+    "float_literal_trailing_zero",
+    "fn_single_line",
+    "force_multiline_blocks",
+    "format_code_in_doc_comments",
+    "format_generated_files",
+    "format_macro_bodies",
+    "format_macro_matchers",
+    "format_strings",
+    "generated_marker_line_search_limit",
+    "group_imports",
+    "hex_literal_case",
+    "ignore",
+    "imports_granularity",
+    "imports_indent",
+    "imports_layout",
+    "indent_style",
+    "inline_attribute_width",
+    "make_backup",
+    "match_arm_blocks",
+    "match_arm_indent",
+    "normalize_comments",
+    "normalize_doc_attributes",
+    "overflow_delimited_expr",
+    "reorder_impl_items",
+    "required_version",
+    "show_parse_errors",
+    "skip_children",
+    "skip_macro_invocations",
+    "space_after_colon",
+    "space_before_colon",
+    "spaces_around_ranges",
+    "struct_field_align_threshold",
+    "struct_lit_single_line",
+    "style_edition",
+    "trailing_comma",
+    "trailing_semicolon",
+    "type_punctuation_density",
+    "unstable_features",
+    "version",
+    "where_single_line",
+    "wrap_comments",
+];
+
+/// The type of the value of a configuration setting.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum ConfigKind {
+    /// `true` or `false`.
+    Boolean,
+    /// A non-negative integer.
+    UnsignedInteger,
+    /// An arbitrary string.
+    String,
+    /// One of a fixed set of values.
+    Choice(Vec<String>),
+    /// A list of strings, e.g. `["a", "b"]`.
+    List,
+    /// Another type, as described by the tool.
+    Other(String),
+}
+
+/// A configuration setting that the formatting tool supports.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct ConfigKey {
+    /// The name of the setting, e.g. `max_width`.
+    pub name: String,
+    /// The type of the value.
+    pub kind: ConfigKind,
+    /// The default value, as described by the tool.
+    pub default: String,
+    /// Whether the setting is available on stable versions of the tool.
+    pub stable: bool,
+    /// A description of the setting.
+    pub description: String,
+}
+
+/// Retrieve the configuration settings that the formatting backend supports.
+///
+/// The settings are queried from the backend of the [global formatter](crate::set_global), so they
+/// always match the tool version that is actually used. Nightly versions of the tool also report
+/// their unstable settings.
+///
+/// # Examples
+///
+/// ```
+/// let schema = rust_format::rustfmt_config_schema().unwrap();
+/// let max_width = schema.iter().find(|key| key.name == "max_width").unwrap();
+/// assert!(max_width.stable);
+/// ```
+pub fn rustfmt_config_schema() -> Result<Vec<ConfigKey>, Error> {
+    crate::global().options().get_backend().config_schema()
+}
+
+/// Verify that a backend supports all settings of a configuration.
+///
+/// Backends that do not report any settings are assumed to support all of them.
+pub(crate) fn validate(config: &Config, backend: &dyn FormatBackend) -> Result<(), Error> {
+    match unsupported(config, backend)?.into_iter().next() {
+        Some((key, required_channel)) => Err(Error::UnsupportedOption {
+            key,
+            required_channel,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Find the settings of a configuration that a backend does not support.
+///
+/// Backends that do not report any settings are assumed to support all of them.
+///
+/// # Returns
+///
+/// The names of the unsupported settings in alphabetical order, along with the channel of the
+/// tool that supports them, or `None` for unknown settings.
+pub(crate) fn unsupported(
+    config: &Config,
+    backend: &dyn FormatBackend,
+) -> Result<Vec<(String, Option<String>)>, Error> {
+    if config.is_empty() {
+        return Ok(Vec::new());
+    }
+    let schema = backend.config_schema()?;
+    if schema.is_empty() {
+        return Ok(Vec::new());
+    }
+    let nightly = backend.version()?.contains("nightly");
+    let mut unsupported = Vec::new();
+    for (key, _) in config.iter() {
+        let unstable = match schema.iter().find(|candidate| candidate.name == key) {
+            Some(candidate) => !candidate.stable,
+            None if !nightly && UNSTABLE_KEYS.contains(&key) => true,
+            None => {
+                unsupported.push((key.to_string(), None));
+                continue;
+            }
+        };
+        if unstable && !nightly {
+            unsupported.push((key.to_string(), Some("nightly".to_string())));
+        }
+    }
+    Ok(unsupported)
+}
+
+/// Parse the output of `rustfmt --help=config`.
+///
+/// Every setting is described by a line with its name, type and default value, followed by the
+/// lines of its description and an empty line.
+#[cfg(feature = "process-backend")]
+pub(crate) fn parse(help: &str) -> Vec<ConfigKey> {
+    let mut keys: Vec<ConfigKey> = Vec::new();
+    let mut lines = help.lines().map(str::trim);
+    while let Some(line) = lines.next() {
+        let Some((declaration, default)) = line.split_once(" Default: ") else {
+            continue;
+        };
+        let Some((name, kind)) = declaration.split_once(' ') else {
+            continue;
+        };
+        let (default, stable) = match default.strip_suffix(" (unstable)") {
+            Some(default) => (default, false),
+            None => (default, true),
+        };
+        let description: Vec<&str> = lines.by_ref().take_while(|line| !line.is_empty()).collect();
+        keys.push(ConfigKey {
+            name: name.to_string(),
+            kind: parse_kind(kind.trim()),
+            default: default.to_string(),
+            stable,
+            description: description.join(" "),
+        });
+    }
+    keys
+}
+
+/// Parse the type of a setting, e.g. `<boolean>` or `[Off|Max|Default]`.
+#[cfg(feature = "process-backend")]
+fn parse_kind(kind: &str) -> ConfigKind {
+    match kind {
+        "<boolean>" => ConfigKind::Boolean,
+        "<unsigned integer>" => ConfigKind::UnsignedInteger,
+        "<string>" => ConfigKind::String,
+        _ if kind.starts_with("[<string>") => ConfigKind::List,
+        _ if kind.starts_with('[') && kind.contains('|') => ConfigKind::Choice(
+            kind.trim_start_matches('[')
+                .trim_end_matches(']')
+                .split('|')
+                // Unstable values are marked individually.
+                .map(|value| value.trim_end_matches(" (unstable)").to_string())
+                .collect(),
+        ),
+        _ => ConfigKind::Other(kind.to_string()),
+    }
+}
+
+#[cfg(all(test, feature = "process-backend"))]
+mod test {
+    use super::ConfigKind;
+    use crate::{Config, Error, Options};
+
+    #[test]
+    fn test_parse() {
+        let help = "Configuration Options:
+                          max_width <unsigned integer> Default: 100
+                                    Maximum width of each line
+
+                     imports_layout [Vertical|Mixed] Default: Mixed (unstable)
+                                    Item layout inside a import block
+";
+        let keys = super::parse(help);
+
+        assert_eq!(2, keys.len());
+        assert_eq!("max_width", keys[0].name);
+        assert_eq!(ConfigKind::UnsignedInteger, keys[0].kind);
+        assert_eq!("100", keys[0].default);
+        assert!(keys[0].stable);
+        assert_eq!("Maximum width of each line", keys[0].description);
+        assert_eq!(
+            ConfigKind::Choice(vec!["Vertical".to_string(), "Mixed".to_string()]),
+            keys[1].kind
+        );
+        assert!(!keys[1].stable);
+    }
+
+    #[test]
+    fn test_validate() {
+        let backend = Options::new();
+        let backend = backend.get_backend();
+        super::validate(&Config::new().max_width(80), backend).unwrap();
+        assert!(matches!(
+            super::validate(&Config::new().set("max_widht", 80), backend),
+            Err(Error::UnsupportedOption {
+                required_channel: None,
+                ..
+            })
+        ));
+        if !backend.version().unwrap().contains("nightly") {
+            let config = Config::new()
+                .format_macro_bodies(true)
+                .format_macro_matchers(true)
+                .max_width(80);
+            let options = Options::new()
+                .config(config)
+                .validate_config(true)
+                .degrade_unsupported_config(true);
+            assert_eq!(
+                vec!["format_macro_bodies", "format_macro_matchers"],
+                options.degraded_config()
+            );
+            let resolved = options.effective_options();
+            assert_eq!(None, resolved.config.get("format_macro_bodies"));
+            assert_eq!(Some("80"), resolved.config.get("max_width"));
+            assert_eq!(
+                "macro_rules! m {\n    () => {};\n}\n",
+                crate::format_str_with("macro_rules! m { () => {} }", &options).unwrap()
+            );
+            assert!(matches!(
+                crate::format_str_with("fn main() {}", &options.degrade_unsupported_config(false)),
+                Err(Error::UnsupportedOption { .. })
+            ));
+            assert_eq!(
+                "Configuration setting 'fn_single_line' is not supported by the formatting tool \
+                 (requires the nightly channel).",
+                super::validate(&Config::new().fn_single_line(true), backend)
+                    .unwrap_err()
+                    .to_string()
+            );
+        }
+    }
+}
@@ -0,0 +1,162 @@
+//! Building blocks for formatting services, e.g. an internal HTTP endpoint that formats code.
+//!
+//! The types can be (de)serialized with `serde`, so they can be used as the bodies of requests and
+//! responses with any web framework.
+
+use crate::{Edition, Options};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A request to format source code.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FormatRequest {
+    /// The source code.
+    pub source: String,
+    /// The edition of the source code, e.g. `"2021"`. The edition of the service options is used
+    /// if none is provided.
+    pub edition: Option<Edition>,
+    /// Configuration settings of the formatting tool, which override the settings of the service
+    /// options.
+    pub config: BTreeMap<String, String>,
+    /// Whether to only check the source code instead of returning the formatted source code.
+    pub check: bool,
+}
+
+/// An error that is reported to the client of a service.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ServiceError {
+    /// The stable code of the error (see [`crate::Error::code`]).
+    pub code: String,
+    /// The error message.
+    pub message: String,
+}
+
+/// The response to a [`FormatRequest`].
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FormatResponse {
+    /// The formatted source code. Not provided for checks and failed requests.
+    pub formatted: Option<String>,
+    /// Whether formatting changes the source code.
+    pub changed: bool,
+    /// The error, if the request failed.
+    pub error: Option<ServiceError>,
+}
+
+impl FormatResponse {
+    /// Retrieve the HTTP status code that corresponds to the response.
+    ///
+    /// Inputs that exceed the size limit are reported as `413`, inputs that are rejected or cannot
+    /// be parsed as `422` and all other errors as `500`.
+    pub fn http_status(&self) -> u16 {
+        match self.error.as_ref().map(|error| error.code.as_str()) {
+            None => 200,
+            // `InputTooLarge`.
+            Some("RF0010") => 413,
+            // `ToolExecutionError`, `InputRejected` and `Syntax`.
+            Some("RF0002" | "RF0008" | "RF0011") => 422,
+            Some(_) => 500,
+        }
+    }
+}
+
+/// Handle a formatting request.
+///
+/// The request is handled with the options of the [global formatter](crate::set_global).
+///
+/// # Arguments
+///
+/// * `request`: The request.
+pub fn handle(request: FormatRequest) -> FormatResponse {
+    handle_with(request, crate::global().options())
+}
+
+/// Handle a formatting request using the provided options.
+///
+/// The options of the service limit what clients can do: size limits and [`crate::Limits`] are
+/// always enforced, while clients may override the edition and configuration settings.
+///
+/// # Arguments
+///
+/// * `request`: The request.
+/// * `options`: The options of the service.
+///
+/// # Examples
+///
+/// ```
+/// use rust_format::service::{handle_with, FormatRequest};
+/// use rust_format::Options;
+///
+/// let request = FormatRequest {
+///     source: "fn main(){}".to_string(),
+///     ..Default::default()
+/// };
+/// let response = handle_with(request, &Options::new().max_input_bytes(1024 * 1024));
+/// assert_eq!(200, response.http_status());
+/// assert_eq!(Some("fn main() {}\n".to_string()), response.formatted);
+/// ```
+pub fn handle_with(request: FormatRequest, options: &Options) -> FormatResponse {
+    let mut options = options.clone();
+    if let Some(edition) = request.edition {
+        options = options.edition(edition);
+    }
+    if !request.config.is_empty() {
+        let config = request
+            .config
+            .iter()
+            .fold(options.get_config().clone(), |config, (key, value)| {
+                config.set(key, value)
+            });
+        options = options.config(config);
+    }
+
+    match crate::format_str_with(&request.source, &options) {
+        Ok(formatted) => FormatResponse {
+            changed: formatted != request.source,
+            formatted: (!request.check).then_some(formatted),
+            error: None,
+        },
+        Err(error) => FormatResponse {
+            formatted: None,
+            changed: false,
+            error: Some(ServiceError {
+                code: error.code().to_string(),
+                message: error.to_string(),
+            }),
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::FormatRequest;
+    use crate::{Edition, Options};
+
+    #[test]
+    fn test_handle_with() {
+        let request = FormatRequest {
+            source: "fn main(){\n\tf();\n}".to_string(),
+            edition: Some(Edition::E2021),
+            config: [("hard_tabs".to_string(), "true".to_string())].into(),
+            check: false,
+        };
+        let response = super::handle_with(request.clone(), &Options::new());
+        assert_eq!(200, response.http_status());
+        assert_eq!(
+            Some("fn main() {\n\tf();\n}\n".to_string()),
+            response.formatted
+        );
+        assert!(response.changed);
+
+        let check = FormatRequest {
+            check: true,
+            ..request.clone()
+        };
+        assert_eq!(None, super::handle_with(check, &Options::new()).formatted);
+
+        let response = super::handle_with(request, &Options::new().max_input_bytes(4));
+        assert_eq!(413, response.http_status());
+        assert_eq!("RF0010", response.error.unwrap().code);
+    }
+}
@@ -0,0 +1,259 @@
+//! A single integration point for long-lived embedders of the crate.
+
+use crate::batch::{BatchOptions, FileResult};
+use crate::report::CheckResults;
+use crate::{CacheStats, CleanCache, Error, Options};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// The activity of a [`Session`].
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct SessionMetrics {
+    /// The number of sources and files that were formatted or checked successfully.
+    pub succeeded: u64,
+    /// The number of sources and files that failed.
+    pub failed: u64,
+    /// The lookups of the cache of the session.
+    pub cache: CacheStats,
+    /// The time spent on requests, summed over all threads.
+    pub busy: Duration,
+}
+
+/// The counters behind [`SessionMetrics`].
+#[derive(Debug, Default)]
+struct Counters {
+    succeeded: AtomicU64,
+    failed: AtomicU64,
+    busy_nanos: AtomicU64,
+}
+
+impl Counters {
+    fn count(&self, succeeded: u64, failed: u64, started: Instant) {
+        self.succeeded.fetch_add(succeeded, Ordering::Relaxed);
+        self.failed.fetch_add(failed, Ordering::Relaxed);
+        let busy = u64::try_from(started.elapsed().as_nanos()).unwrap_or(u64::MAX);
+        self.busy_nanos.fetch_add(busy, Ordering::Relaxed);
+    }
+
+    fn count_results(&self, results: &[FileResult], started: Instant) {
+        let failed = results.iter().filter(|file| file.result.is_err()).count() as u64;
+        self.count(results.len() as u64 - failed, failed, started);
+    }
+}
+
+/// A long-lived owner of the options, the cache and the metrics of a formatting service, e.g. a
+/// language server or a code generation service.
+///
+/// A session is created once with the options of the service and then serves requests, from any
+/// number of threads, until it is [shut down](Session::shutdown):
+///
+/// * The formatting tool of the [backend](Options::backend) is resolved when the session is
+///   created, so that a missing tool or toolchain (see [`Options::toolchain`]) is reported at
+///   startup instead of failing the first request.
+/// * Sources are formatted through a [`CleanCache`] that remembers formatted code as well (see
+///   [`CleanCache::cache_formatted`]), and batches of files share it. The buffers of the
+///   formatting path are pooled across requests.
+/// * The [audit log](Options::audit_log) and the [event sink](Options::events) of the options
+///   receive the activity of all requests.
+/// * The successes, failures and time of all requests are counted (see [`Session::metrics`]).
+///
+/// # Examples
+///
+/// ```
+/// use rust_format::{Options, Session};
+///
+/// let session = Session::new(Options::new()).unwrap();
+/// assert_eq!("fn main() {}\n", session.format("fn main(){}").unwrap());
+/// assert_eq!("fn main() {}\n", session.format("fn main(){}").unwrap());
+///
+/// let metrics = session.shutdown();
+/// assert_eq!(2, metrics.succeeded);
+/// assert_eq!(1, metrics.cache.hits);
+/// ```
+#[derive(Debug)]
+pub struct Session {
+    options: Options,
+    tool_version: String,
+    cache: Arc<CleanCache>,
+    counters: Counters,
+}
+
+impl Session {
+    /// Create a session and resolve its formatting tool.
+    ///
+    /// # Arguments
+    ///
+    /// * `options`: The options with which all requests are served.
+    ///
+    /// # Returns
+    ///
+    /// The session, or the error of the backend if its tool is not available.
+    pub fn new(options: Options) -> Result<Self, Error> {
+        let tool_version = options.get_backend().version()?;
+        let cache = CleanCache::new(options.clone()).cache_formatted(true);
+        Ok(Self {
+            options,
+            tool_version,
+            cache: Arc::new(cache),
+            counters: Counters::default(),
+        })
+    }
+
+    /// Retrieve the options of the session.
+    pub fn options(&self) -> &Options {
+        &self.options
+    }
+
+    /// Retrieve the version of the formatting tool, as it was reported when the session was
+    /// created.
+    pub fn tool_version(&self) -> &str {
+        &self.tool_version
+    }
+
+    /// Retrieve the cache of the session.
+    pub fn cache(&self) -> &CleanCache {
+        &self.cache
+    }
+
+    /// Create the options for batches that share the options and the cache of the session, e.g. for
+    /// adding per-path rules before passing them to the [`crate::batch`] functions. Such batches
+    /// are not counted in the metrics of the session.
+    pub fn batch_options(&self) -> BatchOptions {
+        BatchOptions::new()
+            .options(self.options.clone())
+            .cache(self.cache.clone())
+    }
+
+    /// Format Rust source code, unless the result is cached.
+    ///
+    /// # Arguments
+    ///
+    /// * `source`: The source code.
+    ///
+    /// # Returns
+    ///
+    /// The formatted code.
+    pub fn format(&self, source: impl AsRef<str>) -> Result<String, Error> {
+        let started = Instant::now();
+        let result = self
+            .cache
+            .format(source.as_ref())
+            .map(|formatted| formatted.into_owned());
+        match result {
+            Ok(_) => self.counters.count(1, 0, started),
+            Err(_) => self.counters.count(0, 1, started),
+        }
+        result
+    }
+
+    /// Format Rust source files in place. See [`crate::batch::format_files_with`].
+    ///
+    /// # Arguments
+    ///
+    /// * `paths`: The paths of the files.
+    pub fn format_files<P: Into<PathBuf>>(
+        &self,
+        paths: impl IntoIterator<Item = P>,
+    ) -> Vec<FileResult> {
+        let started = Instant::now();
+        let results = crate::batch::format_files_with(paths, &self.batch_options());
+        self.counters.count_results(&results, started);
+        results
+    }
+
+    /// Check whether Rust source files are formatted correctly. See
+    /// [`crate::batch::check_files_with`].
+    ///
+    /// # Arguments
+    ///
+    /// * `paths`: The paths of the files.
+    pub fn check_files<P: Into<PathBuf>>(
+        &self,
+        paths: impl IntoIterator<Item = P>,
+    ) -> CheckResults {
+        let started = Instant::now();
+        let results = crate::batch::check_files_with(paths, &self.batch_options());
+        self.counters.count_results(&results, started);
+        results
+    }
+
+    /// Format all Rust source files below a directory in place. See
+    /// [`crate::batch::format_dir_with`].
+    ///
+    /// # Arguments
+    ///
+    /// * `dir`: The directory.
+    #[cfg(feature = "walk")]
+    pub fn format_dir(&self, dir: impl AsRef<std::path::Path>) -> Result<Vec<FileResult>, Error> {
+        let started = Instant::now();
+        let results = crate::batch::format_dir_with(dir, &self.batch_options());
+        match &results {
+            Ok(results) => self.counters.count_results(results, started),
+            Err(_) => self.counters.count(0, 1, started),
+        }
+        results
+    }
+
+    /// Retrieve the activity of the session so far.
+    pub fn metrics(&self) -> SessionMetrics {
+        SessionMetrics {
+            succeeded: self.counters.succeeded.load(Ordering::Relaxed),
+            failed: self.counters.failed.load(Ordering::Relaxed),
+            cache: self.cache.stats(),
+            busy: Duration::from_nanos(self.counters.busy_nanos.load(Ordering::Relaxed)),
+        }
+    }
+
+    /// End the session and release its cache.
+    ///
+    /// # Returns
+    ///
+    /// The activity of the whole session.
+    pub fn shutdown(self) -> SessionMetrics {
+        let metrics = self.metrics();
+        self.cache.clear();
+        metrics
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Session;
+    use crate::backend::{Call, Mock};
+    use crate::{Error, Options};
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_session() {
+        let mock = Arc::new(Mock::new());
+        let session = Session::new(Options::new().backend(mock.clone())).unwrap();
+        assert_eq!("mock", session.tool_version());
+        mock.respond(Ok("fn main() {}\n".to_string()));
+        mock.respond(Err(Error::NoResultCode {
+            backend: "mock".into(),
+            path: None,
+        }));
+        assert_eq!("fn main() {}\n", session.format("fn main(){}").unwrap());
+        assert_eq!("fn main() {}\n", session.format("fn main(){}").unwrap());
+        assert!(session.format("fn f(){}").is_err());
+
+        let path = PathBuf::from("target/session.rs");
+        std::fs::write(&path, "fn main() {}\n").unwrap();
+        assert!(session.check_files([&path]).is_clean());
+        assert_eq!(
+            vec![
+                Call::Version,
+                Call::FormatSource("fn main(){}".to_string()),
+                Call::FormatSource("fn f(){}".to_string()),
+            ],
+            mock.calls()[..3]
+        );
+
+        let metrics = session.shutdown();
+        assert_eq!((3, 1), (metrics.succeeded, metrics.failed));
+        assert_eq!(1, metrics.cache.hits);
+    }
+}
@@ -0,0 +1,182 @@
+//! A unified representation of the inputs that can be formatted.
+
+use crate::error::IoContext;
+use crate::{Error, Options};
+use std::fmt::{Debug, Formatter};
+use std::io::Read;
+use std::path::PathBuf;
+
+/// An input for formatting.
+///
+/// # Examples
+///
+/// ```
+/// use rust_format::{Options, Source};
+///
+/// let output = rust_format::format(Source::from("fn main(){}".to_string()), &Options::new()).unwrap();
+/// assert_eq!("fn main() {}\n", output.text);
+/// assert!(output.changed);
+/// ```
+pub enum Source {
+    /// A file. Configuration files are discovered starting at its directory. The file is not
+    /// modified.
+    Path(PathBuf),
+    /// Source code.
+    Text(String),
+    /// A token stream, e.g. the output of a code generator.
+    #[cfg(feature = "tokens")]
+    Tokens(proc_macro2::TokenStream),
+    /// A reader that provides source code in UTF-8.
+    Reader(Box<dyn Read>),
+}
+
+impl Source {
+    /// Retrieve the path of the source, if it is a file.
+    pub fn path(&self) -> Option<&std::path::Path> {
+        match self {
+            Source::Path(path) => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Convert the source into a form that can be handed to another thread.
+    ///
+    /// Token streams and readers are bound to the current thread, so they are converted into text.
+    pub(crate) fn into_sendable(self, options: &Options) -> Result<Sendable, Error> {
+        match self {
+            Source::Path(path) => Ok(Sendable::Path(path)),
+            Source::Text(text) => Ok(Sendable::Text(text)),
+            #[cfg(feature = "tokens")]
+            Source::Tokens(tokens) => Ok(Sendable::Text(tokens.to_string())),
+            Source::Reader(reader) => Ok(Sendable::Text(read(reader, options)?)),
+        }
+    }
+}
+
+/// A [`Source`] that can be handed to another thread.
+#[derive(Debug)]
+pub(crate) enum Sendable {
+    Path(PathBuf),
+    Text(String),
+}
+
+impl From<Sendable> for Source {
+    fn from(source: Sendable) -> Self {
+        match source {
+            Sendable::Path(path) => Source::Path(path),
+            Sendable::Text(text) => Source::Text(text),
+        }
+    }
+}
+
+impl Debug for Source {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::Path(path) => f.debug_tuple("Path").field(path).finish(),
+            Source::Text(text) => f.debug_tuple("Text").field(text).finish(),
+            #[cfg(feature = "tokens")]
+            Source::Tokens(tokens) => f.debug_tuple("Tokens").field(tokens).finish(),
+            Source::Reader(_) => f.write_str("Reader"),
+        }
+    }
+}
+
+impl From<PathBuf> for Source {
+    fn from(path: PathBuf) -> Self {
+        Source::Path(path)
+    }
+}
+
+impl From<String> for Source {
+    fn from(text: String) -> Self {
+        Source::Text(text)
+    }
+}
+
+#[cfg(feature = "tokens")]
+impl From<proc_macro2::TokenStream> for Source {
+    fn from(tokens: proc_macro2::TokenStream) -> Self {
+        Source::Tokens(tokens)
+    }
+}
+
+/// The result of formatting a [`Source`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Output {
+    /// The path of the source, if it is a file.
+    pub path: Option<PathBuf>,
+    /// The formatted source code.
+    pub text: String,
+    /// Whether formatting changed the source code.
+    pub changed: bool,
+}
+
+/// Format a source.
+///
+/// This is the single entry point for all kinds of input. Files are not modified; use
+/// [`crate::format_file_with`] to format a file in place.
+///
+/// # Arguments
+///
+/// * `source`: The source.
+/// * `options`: The options.
+pub fn format(source: Source, options: &Options) -> Result<Output, Error> {
+    let (path, original) = match source {
+        Source::Path(path) => {
+            if options.get_max_input_bytes().is_some() {
+                options.check_input_size(std::fs::metadata(&path).at(&path)?.len())?;
+            }
+            let original = std::fs::read_to_string(&path).at(&path)?;
+            (Some(path), original)
+        }
+        Source::Text(text) => (None, text),
+        #[cfg(feature = "tokens")]
+        Source::Tokens(tokens) => (None, tokens.to_string()),
+        Source::Reader(reader) => (None, read(reader, options)?),
+    };
+
+    let dir = path.as_deref().map(crate::format::parent_dir);
+    let text = crate::format::format_source(&original, dir, options)?;
+    Ok(Output {
+        changed: text != original,
+        path,
+        text,
+    })
+}
+
+/// Read source code from a reader, honoring the maximum input size.
+pub(crate) fn read(reader: impl Read, options: &Options) -> Result<String, Error> {
+    let mut text = String::new();
+    match options.get_max_input_bytes() {
+        // Read one byte more than allowed to detect an oversized input without reading all of it.
+        Some(limit) => reader
+            .take(limit.saturating_add(1))
+            .read_to_string(&mut text)?,
+        None => { reader }.read_to_string(&mut text)?,
+    };
+    Ok(text)
+}
+
+#[cfg(test)]
+mod test {
+    use super::Source;
+    use crate::{Error, Options};
+
+    #[test]
+    fn test_format_reader() {
+        let reader = Box::new(std::io::Cursor::new(b"fn main(){}".to_vec()));
+        let output = super::format(Source::Reader(reader), &Options::new()).unwrap();
+        assert_eq!("fn main() {}\n", output.text);
+        assert_eq!(None, output.path);
+
+        let reader = Box::new(std::io::Cursor::new(vec![b' '; 100]));
+        let options = Options::new().max_input_bytes(10);
+        assert!(matches!(
+            super::format(Source::Reader(reader), &options),
+            Err(Error::InputTooLarge {
+                size: 11,
+                limit: 10
+            })
+        ));
+    }
+}
@@ -0,0 +1,157 @@
+//! Mapping of positions between unformatted and formatted source code.
+
+use crate::diff::{diff, Edit};
+use crate::Error;
+
+/// A map that translates positions in unformatted source code to positions in the formatted
+/// output.
+///
+/// The map is derived by aligning the non-whitespace characters of both texts. Positions of
+/// characters that the formatter removed or moved are mapped to the nearest preceding character
+/// that is present in both texts.
+#[derive(Debug, Clone)]
+pub struct SourceMap {
+    /// Pairs of byte offsets `(input, output)` of aligned characters, sorted by both offsets.
+    anchors: Vec<(usize, usize)>,
+    input_line_starts: Vec<usize>,
+    output_line_starts: Vec<usize>,
+    output_len: usize,
+}
+
+impl SourceMap {
+    /// Create a map between two versions of the same source code.
+    ///
+    /// # Arguments
+    ///
+    /// * `input`: The unformatted source code.
+    /// * `output`: The formatted source code.
+    pub fn new(input: &str, output: &str) -> Self {
+        let significant = |text: &str| -> Vec<(usize, char)> {
+            text.char_indices()
+                .filter(|(_, c)| !c.is_whitespace())
+                .collect()
+        };
+        let input_chars = significant(input);
+        let output_chars = significant(output);
+        let input_values: Vec<char> = input_chars.iter().map(|(_, c)| *c).collect();
+        let output_values: Vec<char> = output_chars.iter().map(|(_, c)| *c).collect();
+
+        let anchors = diff(&input_values, &output_values)
+            .into_iter()
+            .filter_map(|edit| match edit {
+                Edit::Equal(a, b) => Some((input_chars[a].0, output_chars[b].0)),
+                _ => None,
+            })
+            .collect();
+
+        Self {
+            anchors,
+            input_line_starts: line_starts(input),
+            output_line_starts: line_starts(output),
+            output_len: output.len(),
+        }
+    }
+
+    /// Translate a byte offset in the input to a byte offset in the output.
+    ///
+    /// # Arguments
+    ///
+    /// * `offset`: The byte offset in the input.
+    pub fn map_offset(&self, offset: usize) -> usize {
+        let index = self.anchors.partition_point(|(input, _)| *input <= offset);
+        if index == 0 {
+            return 0;
+        }
+        let (input, output) = self.anchors[index - 1];
+        if input == offset {
+            output
+        } else {
+            // The position lies behind an aligned character; place it directly after that
+            // character, but never beyond the next aligned character.
+            let limit = self
+                .anchors
+                .get(index)
+                .map_or(self.output_len, |(_, output)| *output);
+            (output + 1).min(limit)
+        }
+    }
+
+    /// Translate a line number in the input to a line number in the output.
+    ///
+    /// Line numbers are 1-based. The line is located by its first non-whitespace character.
+    ///
+    /// # Arguments
+    ///
+    /// * `line`: The line number in the input.
+    pub fn map_line(&self, line: usize) -> usize {
+        let start = match self.input_line_starts.get(line.saturating_sub(1)) {
+            Some(start) => *start,
+            None => return self.output_line_starts.len(),
+        };
+        let end = self
+            .input_line_starts
+            .get(line)
+            .copied()
+            .unwrap_or(usize::MAX);
+        let index = self.anchors.partition_point(|(input, _)| *input < start);
+        let output = match self.anchors.get(index) {
+            Some((input, output)) if *input < end => *output,
+            _ => self.map_offset(start),
+        };
+        self.output_line_starts
+            .partition_point(|start| *start <= output)
+    }
+}
+
+/// Determine the byte offsets at which the lines of a text start.
+fn line_starts(text: &str) -> Vec<usize> {
+    std::iter::once(0)
+        .chain(text.match_indices('\n').map(|(index, _)| index + 1))
+        .filter(|start| *start < text.len() || *start == 0)
+        .collect()
+}
+
+/// Format Rust source code and create a map from input to output positions.
+///
+/// # Arguments
+///
+/// * `source`: The source code.
+///
+/// # Examples
+///
+/// ```
+/// let (formatted, map) = rust_format::format_str_with_map("fn main() { let x = 1; }").unwrap();
+/// assert_eq!("fn main() {\n    let x = 1;\n}\n", formatted);
+/// assert_eq!(1, map.map_line(1));
+/// assert_eq!(formatted.find("let").unwrap(), map.map_offset(12));
+/// ```
+pub fn format_str_with_map(source: impl AsRef<str>) -> Result<(String, SourceMap), Error> {
+    let source = source.as_ref();
+    let formatted = crate::format_str(source)?;
+    let map = SourceMap::new(source, &formatted);
+    Ok((formatted, map))
+}
+
+#[cfg(test)]
+mod test {
+    use super::SourceMap;
+
+    #[test]
+    fn test_source_map() {
+        let input = "struct Hello {\n    world: u32, }\n\n\n\nfn main() { }\n";
+        let output = "struct Hello {\n    world: u32,\n}\n\nfn main() {}\n";
+        let map = SourceMap::new(input, output);
+
+        assert_eq!(1, map.map_line(1));
+        assert_eq!(2, map.map_line(2));
+        assert_eq!(5, map.map_line(6));
+        assert_eq!(
+            output.find('}').unwrap(),
+            map.map_offset(input.find('}').unwrap())
+        );
+        assert_eq!(
+            output.find("main").unwrap(),
+            map.map_offset(input.find("main").unwrap())
+        );
+    }
+}
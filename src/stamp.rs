@@ -0,0 +1,181 @@
+//! Stamp files, which record that files are formatted, so that later runs can skip them.
+
+use crate::error::IoContext;
+use crate::file_system::FileSystem;
+use crate::{Error, Options};
+use std::path::{Path, PathBuf};
+
+/// The first line of every stamp file.
+const HEADER: &str = "rust-format stamp 2";
+
+/// The state in which a file was left by a batch run.
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct Stamp {
+    /// The hexadecimal SHA-256 hash of the content of the file.
+    content: String,
+    /// The [fingerprint](Options::fingerprint) of the options of the file.
+    fingerprint: u64,
+    /// The version of the formatting tool.
+    version: String,
+    /// The hexadecimal SHA-256 hash of the configuration file that the tool discovers for the
+    /// file, or `none`.
+    config: String,
+}
+
+impl Stamp {
+    /// Create the stamp of a file with its current content.
+    ///
+    /// # Arguments
+    ///
+    /// * `content`: The content of the file.
+    /// * `options`: The options of the file.
+    /// * `config`: The configuration file that the tool discovers for the file, if any.
+    fn of(content: &[u8], options: &Options, config: Option<&Path>) -> Result<Self, Error> {
+        Ok(Self {
+            content: crate::hash::sha256_hex(content),
+            fingerprint: options.fingerprint()?,
+            version: options.get_backend().version()?,
+            config: match config {
+                Some(config) => crate::hash::sha256_hex(&std::fs::read(config).at(config)?),
+                None => "none".to_string(),
+            },
+        })
+    }
+
+    /// Encode the stamp as the content of a stamp file.
+    fn encode(&self) -> String {
+        format!(
+            "{}\ncontent {}\nfingerprint {:016x}\nversion {}\nconfig {}\n",
+            HEADER, self.content, self.fingerprint, self.version, self.config
+        )
+    }
+
+    /// Decode the content of a stamp file.
+    fn decode(text: &str) -> Option<Self> {
+        let mut lines = text.lines();
+        if lines.next() != Some(HEADER) {
+            return None;
+        }
+        let content = lines.next()?.strip_prefix("content ")?.to_string();
+        let fingerprint = lines.next()?.strip_prefix("fingerprint ")?;
+        let fingerprint = u64::from_str_radix(fingerprint, 16).ok()?;
+        let version = lines.next()?.strip_prefix("version ")?.to_string();
+        let config = lines.next()?.strip_prefix("config ")?.to_string();
+        Some(Self {
+            content,
+            fingerprint,
+            version,
+            config,
+        })
+    }
+}
+
+/// Retrieve the path of the stamp file of a file, e.g. `lib.rs.rustfmt-stamp` for `lib.rs`.
+pub(crate) fn stamp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".rustfmt-stamp");
+    path.with_file_name(name)
+}
+
+/// Determine whether a file is still in the state that its stamp file records, i.e. it has the
+/// same content and would be formatted with the same options, configuration file and version of
+/// the tool.
+///
+/// Files without a valid stamp file are not stamped.
+pub(crate) fn is_stamped(
+    file_system: &dyn FileSystem,
+    path: &Path,
+    options: &Options,
+    config: Option<&Path>,
+) -> bool {
+    let Ok(stamp) = file_system.read(&stamp_path(path)) else {
+        return false;
+    };
+    let Some(stamp) = Stamp::decode(&String::from_utf8_lossy(&stamp)) else {
+        return false;
+    };
+    match file_system.read(path) {
+        Ok(content) => Stamp::of(&content, options, config).is_ok_and(|current| current == stamp),
+        Err(_) => false,
+    }
+}
+
+/// Record the current state of a file in its stamp file.
+pub(crate) fn write_stamp(
+    file_system: &dyn FileSystem,
+    path: &Path,
+    options: &Options,
+    config: Option<&Path>,
+) -> Result<(), Error> {
+    let stamp = Stamp::of(&file_system.read(path)?, options, config)?;
+    file_system.write(&stamp_path(path), stamp.encode().as_bytes())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::backend::{Call, Mock};
+    use crate::batch::{format_files_with, BatchOptions, Outcome};
+    use crate::file_system::MemoryFileSystem;
+    use crate::{Config, Options};
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_stamps() {
+        let file_system = Arc::new(MemoryFileSystem::new());
+        file_system.insert("gen/a.rs", "fn a() {}\n");
+        file_system.insert("gen/b.rs", "fn b() {}\n");
+        let mock = Arc::new(Mock::new());
+        let options = |config: Config| {
+            BatchOptions::new()
+                .options(Options::new().backend(mock.clone()).config(config))
+                .file_system(file_system.clone())
+                .stamps(true)
+        };
+        let formats = || {
+            mock.calls()
+                .iter()
+                .filter(|call| matches!(call, Call::FormatSource(_)))
+                .count()
+        };
+        let paths = ["gen/a.rs", "gen/b.rs"];
+
+        format_files_with(paths, &options(Config::new()));
+        assert_eq!(2, formats());
+        let stamp = file_system.get("gen/a.rs.rustfmt-stamp").unwrap();
+        assert!(String::from_utf8(stamp)
+            .unwrap()
+            .starts_with("rust-format stamp 2\ncontent "));
+
+        let results = format_files_with(paths, &options(Config::new()));
+        assert!(matches!(results[0].result, Ok(Outcome::Formatted)));
+        assert_eq!(2, formats());
+
+        file_system.insert("gen/a.rs", "fn changed() {}\n");
+        format_files_with(paths, &options(Config::new()));
+        assert_eq!(3, formats());
+        format_files_with(paths, &options(Config::new().max_width(80)));
+        assert_eq!(5, formats());
+    }
+
+    #[test]
+    fn test_stamps_config_file() {
+        let dir = PathBuf::from("target/stamp_config");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("x.rs");
+        std::fs::write(&path, "fn f() {\n    x();\n}\n").unwrap();
+        let options = BatchOptions::new().stamps(true);
+
+        let results = format_files_with([&path], &options);
+        assert!(matches!(results[0].result, Ok(Outcome::Formatted)));
+        assert!(dir.join("x.rs.rustfmt-stamp").is_file());
+
+        std::fs::write(dir.join("rustfmt.toml"), "hard_tabs = true\n").unwrap();
+        format_files_with([&path], &options);
+        assert_eq!(
+            "fn f() {\n\tx();\n}\n",
+            std::fs::read_to_string(&path).unwrap()
+        );
+    }
+}
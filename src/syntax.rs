@@ -0,0 +1,106 @@
+//! Syntax validation and formatting of syntax trees using [`syn`].
+
+use crate::{Error, Options, ToFormattedString};
+
+/// Verify that the source code is syntactically valid Rust.
+///
+/// # Arguments
+///
+/// * `source`: The source code.
+///
+/// # Examples
+///
+/// ```
+/// assert!(rust_format::validate_syntax("fn main() {}").is_ok());
+/// assert!(rust_format::validate_syntax("fn main() {").is_err());
+/// ```
+pub fn validate_syntax(source: impl AsRef<str>) -> Result<(), Error> {
+    syn::parse_file(source.as_ref())?;
+    Ok(())
+}
+
+/// Format a syntax tree.
+///
+/// The syntax tree is formatted with the options of the [global formatter](crate::set_global).
+/// Since syntax trees do not contain comments (other than doc comments), the result does not
+/// either.
+///
+/// # Arguments
+///
+/// * `file`: The syntax tree.
+pub fn format_syn_file(file: &syn::File) -> Result<String, Error> {
+    format_syn_file_with(file, crate::global().options())
+}
+
+/// Format a syntax tree using the provided options.
+///
+/// # Arguments
+///
+/// * `file`: The syntax tree.
+/// * `options`: The options.
+pub fn format_syn_file_with(file: &syn::File, options: &Options) -> Result<String, Error> {
+    file.to_formatted_string(options)
+}
+
+/// Parse and format source code.
+///
+/// The source code is formatted with the options of the [global formatter](crate::set_global).
+///
+/// # Arguments
+///
+/// * `source`: The source code.
+///
+/// # Examples
+///
+/// ```
+/// let (file, formatted) = rust_format::parse_and_format("fn main(){}").unwrap();
+/// assert_eq!(1, file.items.len());
+/// assert_eq!("fn main() {}\n", formatted);
+/// ```
+pub fn parse_and_format(source: impl AsRef<str>) -> Result<(syn::File, String), Error> {
+    parse_and_format_with(source, crate::global().options())
+}
+
+/// Parse and format source code using the provided options.
+///
+/// The source code is parsed once and formatted as it is, so comments are preserved. The syntax
+/// tree refers to the unformatted source code.
+///
+/// # Arguments
+///
+/// * `source`: The source code.
+/// * `options`: The options.
+pub fn parse_and_format_with(
+    source: impl AsRef<str>,
+    options: &Options,
+) -> Result<(syn::File, String), Error> {
+    let source = source.as_ref();
+    let file = syn::parse_file(source)?;
+    // The source is known to be valid, so validating it again would be wasted effort.
+    let options = options.clone().validate_syntax(false);
+    let formatted = crate::format_str_with(source, &options)?;
+    Ok((file, formatted))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Error, Options};
+
+    #[test]
+    fn test_validate_syntax() {
+        let options = Options::new().validate_syntax(true);
+        match crate::format_str_with("fn main() { let x = ; }", &options) {
+            Err(Error::Syntax { source: error }) => assert_eq!(1, error.span().start().line),
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_format_syn_file() {
+        let file = syn::parse_file("struct Hello{world:u32}").unwrap();
+        assert_eq!(
+            "struct Hello {\n    world: u32,\n}\n",
+            super::format_syn_file_with(&file, &Options::new()).unwrap()
+        );
+    }
+}
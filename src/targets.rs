@@ -0,0 +1,301 @@
+//! Location of the targets of a crate, e.g. its library and its integration tests, and of the
+//! packages of a workspace.
+
+use crate::error::IoContext;
+use crate::file_system::{FileSystem, StdFileSystem};
+use crate::glob::Glob;
+use crate::Error;
+use std::path::{Path, PathBuf};
+
+/// The kind of a target of a crate, by the conventional location of its sources.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum TargetKind {
+    /// The library and binaries below `src/`.
+    Source,
+    /// The integration tests below `tests/`.
+    Tests,
+    /// The benchmarks below `benches/`.
+    Benches,
+    /// The examples below `examples/`.
+    Examples,
+    /// The build script `build.rs`.
+    BuildScript,
+}
+
+impl TargetKind {
+    /// All kinds, in the order in which their targets are formatted.
+    pub const ALL: [TargetKind; 5] = [
+        TargetKind::Source,
+        TargetKind::Tests,
+        TargetKind::Benches,
+        TargetKind::Examples,
+        TargetKind::BuildScript,
+    ];
+
+    /// Retrieve the location of the targets relative to the root of the crate.
+    pub fn location(self) -> &'static str {
+        match self {
+            TargetKind::Source => "src",
+            TargetKind::Tests => "tests",
+            TargetKind::Benches => "benches",
+            TargetKind::Examples => "examples",
+            TargetKind::BuildScript => "build.rs",
+        }
+    }
+
+    /// Retrieve the glob pattern that matches the files of the targets relative to the root of
+    /// the crate.
+    pub(crate) fn glob(self) -> String {
+        match self {
+            TargetKind::BuildScript => self.location().to_string(),
+            _ => format!("{}/**", self.location()),
+        }
+    }
+}
+
+/// A located target of a crate.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Target {
+    /// The kind of the target.
+    pub kind: TargetKind,
+    /// The path of the directory or file, i.e. the root of the crate joined with the location of
+    /// the kind.
+    pub path: PathBuf,
+}
+
+/// Locate the targets of a crate.
+///
+/// # Arguments
+///
+/// * `root`: The root directory of the crate, which contains its `Cargo.toml`.
+///
+/// # Returns
+///
+/// The targets that exist, in the order of [`TargetKind::ALL`].
+///
+/// # Examples
+///
+/// ```no_run
+/// use rust_format::targets::{find_targets, TargetKind};
+///
+/// let kinds: Vec<TargetKind> = find_targets(".").into_iter().map(|target| target.kind).collect();
+/// ```
+pub fn find_targets(root: impl AsRef<Path>) -> Vec<Target> {
+    find_targets_in(root.as_ref(), &StdFileSystem)
+}
+
+/// Locate the targets of a crate through a file system.
+///
+/// Directories must be directories and the build script must be a file; anything else is ignored.
+pub(crate) fn find_targets_in(root: &Path, file_system: &dyn FileSystem) -> Vec<Target> {
+    TargetKind::ALL
+        .into_iter()
+        .map(|kind| Target {
+            kind,
+            path: root.join(kind.location()),
+        })
+        .filter(|target| {
+            file_system
+                .metadata(&target.path)
+                .is_ok_and(|metadata| metadata.is_dir == (target.kind != TargetKind::BuildScript))
+        })
+        .collect()
+}
+
+/// A package of a workspace.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Package {
+    /// The name of the package.
+    pub name: String,
+    /// The root directory of the package, which contains its `Cargo.toml`.
+    pub root: PathBuf,
+}
+
+/// Locate the packages of a workspace.
+///
+/// The members of the `[workspace]` section of the manifest may contain the glob patterns `*`
+/// and `?`, and its `exclude` setting is respected. As for `cargo`, the manifest may also declare
+/// a package itself, and a manifest without a `[workspace]` section is a workspace of a single
+/// package.
+///
+/// # Arguments
+///
+/// * `root`: The root directory of the workspace, which contains its `Cargo.toml`.
+///
+/// # Returns
+///
+/// The packages, starting with the package of the root manifest, if any, followed by the members
+/// in the order in which they are declared.
+///
+/// # Examples
+///
+/// ```no_run
+/// use rust_format::targets::find_packages;
+///
+/// for package in find_packages(".")? {
+///     println!("{}: {}", package.name, package.root.display());
+/// }
+/// # Ok::<(), rust_format::Error>(())
+/// ```
+pub fn find_packages(root: impl AsRef<Path>) -> Result<Vec<Package>, Error> {
+    let root = root.as_ref();
+    let workspace = read_manifest(root)?;
+    let mut packages: Vec<Package> = Vec::new();
+    if let Some(name) = workspace.package {
+        packages.push(Package {
+            name,
+            root: root.to_path_buf(),
+        });
+    }
+    let excluded: Vec<PathBuf> = workspace
+        .exclude
+        .iter()
+        .map(|path| root.join(path.trim_end_matches('/')))
+        .collect();
+    for member in &workspace.members {
+        for dir in expand(root, member.trim_end_matches('/')) {
+            if excluded.contains(&dir) || packages.iter().any(|package| package.root == dir) {
+                continue;
+            }
+            if let Some(name) = read_manifest(&dir)?.package {
+                packages.push(Package { name, root: dir });
+            }
+        }
+    }
+    Ok(packages)
+}
+
+/// Read the package and workspace settings of the manifest in a directory.
+fn read_manifest(dir: &Path) -> Result<crate::manifest::Workspace, Error> {
+    let path = dir.join("Cargo.toml");
+    let text = std::fs::read_to_string(&path).at(&path)?;
+    Ok(crate::manifest::parse_workspace(&text))
+}
+
+/// Expand a member pattern of a workspace into the directories with a `Cargo.toml` that it
+/// matches, in alphabetical order.
+fn expand(root: &Path, pattern: &str) -> Vec<PathBuf> {
+    let mut dirs = vec![root.to_path_buf()];
+    for segment in pattern.split('/') {
+        if !segment.contains(['*', '?']) {
+            dirs = dirs.into_iter().map(|dir| dir.join(segment)).collect();
+            continue;
+        }
+        let glob = Glob::new(segment);
+        let mut matches = Vec::new();
+        for dir in dirs {
+            let Ok(entries) = std::fs::read_dir(&dir) else {
+                continue;
+            };
+            matches.extend(
+                entries
+                    .filter_map(Result::ok)
+                    .filter(|entry| entry.path().is_dir())
+                    .filter(|entry| glob.matches(Path::new(&entry.file_name())))
+                    .map(|entry| entry.path()),
+            );
+        }
+        matches.sort();
+        dirs = matches;
+    }
+    dirs.retain(|dir| dir.join("Cargo.toml").is_file());
+    dirs
+}
+
+#[cfg(test)]
+mod test {
+    use super::TargetKind;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_find_packages() {
+        let root = PathBuf::from("target/packages");
+        let _ = std::fs::remove_dir_all(&root);
+        for dir in [
+            "crates/b",
+            "crates/a",
+            "crates/skipped",
+            "tools/cli",
+            "docs",
+        ] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        std::fs::write(
+            root.join("Cargo.toml"),
+            "[package]\nname = \"root\"\n\n[workspace]\nmembers = [\n    \"crates/*\", # all\n    \"tools/cli\",\n]\nexclude = [\"crates/skipped\"]\n\n[[bin]]\nname = \"other\"\n",
+        )
+        .unwrap();
+        for (dir, name) in [
+            ("crates/a", "a"),
+            ("crates/b", "b"),
+            ("crates/skipped", "skipped"),
+            ("tools/cli", "cli"),
+        ] {
+            std::fs::write(
+                root.join(dir).join("Cargo.toml"),
+                format!("[package]\nname = \"{}\"\n", name),
+            )
+            .unwrap();
+        }
+
+        let packages = super::find_packages(&root).unwrap();
+        assert_eq!(
+            vec!["root", "a", "b", "cli"],
+            packages
+                .iter()
+                .map(|package| package.name.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(root.join("crates/a"), packages[1].root);
+    }
+
+    #[test]
+    fn test_find_targets() {
+        let root = PathBuf::from("target/targets");
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::create_dir_all(root.join("tests")).unwrap();
+        // A file where a directory is expected is not a target.
+        std::fs::write(root.join("benches"), "").unwrap();
+        let call = "fn test() {\n    assert_eq!(expected, compute(first, second));\n}\n";
+        std::fs::write(root.join("src/lib.rs"), call).unwrap();
+        std::fs::write(root.join("tests/it.rs"), call).unwrap();
+        std::fs::write(root.join("build.rs"), "fn main(){}").unwrap();
+
+        let kinds: Vec<TargetKind> = super::find_targets(&root)
+            .into_iter()
+            .map(|target| target.kind)
+            .collect();
+        assert_eq!(
+            vec![
+                TargetKind::Source,
+                TargetKind::Tests,
+                TargetKind::BuildScript
+            ],
+            kinds
+        );
+
+        #[cfg(feature = "walk")]
+        {
+            use crate::batch::BatchOptions;
+            use crate::Config;
+
+            let options = BatchOptions::new()
+                .config_for_target(TargetKind::Source, Config::new().max_width(40));
+            let results = crate::batch::format_crate_with(&root, &options).unwrap();
+            let paths: Vec<PathBuf> = results.iter().map(|result| result.path.clone()).collect();
+            assert_eq!(
+                vec![
+                    root.join("src/lib.rs"),
+                    root.join("tests/it.rs"),
+                    root.join("build.rs")
+                ],
+                paths
+            );
+            let read = |path: &str| std::fs::read_to_string(root.join(path)).unwrap();
+            assert_ne!(call, read("src/lib.rs"));
+            assert_eq!(call, read("tests/it.rs"));
+            assert_eq!("fn main() {}\n", read("build.rs"));
+        }
+    }
+}
@@ -0,0 +1,240 @@
+//! Temporary files, which are private to the current user, never collide and are removed even if
+//! the thread panics.
+
+use crate::error::IoContext;
+use crate::Error;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The number of attempts to find an unused name for a temporary file.
+const ATTEMPTS: usize = 16;
+
+/// The policy for the location of temporary files.
+///
+/// Scratch files, such as the inputs of [`crate::CompileCheck::Rustc`], are created in the
+/// directory of the policy. Files that replace a formatted file are always created next to that
+/// file, so that they are on the same filesystem and replacing the file is atomic.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub enum TempPolicy {
+    /// Use the temporary directory of the system (see [`std::env::temp_dir`]).
+    #[default]
+    System,
+    /// Use a custom directory, e.g. because the temporary directory of the system is not writable
+    /// in a sandbox.
+    Directory(PathBuf),
+}
+
+impl TempPolicy {
+    /// Retrieve the directory for scratch files.
+    pub(crate) fn dir(&self) -> PathBuf {
+        match self {
+            TempPolicy::System => std::env::temp_dir(),
+            TempPolicy::Directory(dir) => dir.clone(),
+        }
+    }
+}
+
+/// A temporary file that is removed when it is dropped.
+#[derive(Debug)]
+pub(crate) struct TempFile {
+    path: PathBuf,
+}
+
+impl TempFile {
+    /// Create a new, empty temporary file that only the current user can access.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir`: The directory.
+    /// * `prefix`: The start of the file name.
+    /// * `suffix`: The end of the file name, e.g. an extension.
+    pub(crate) fn create(dir: &Path, prefix: &str, suffix: &str) -> Result<(Self, File), Error> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        let options = private_options();
+
+        let mut attempt = 0;
+        loop {
+            let nanos = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.subsec_nanos());
+            let path = dir.join(format!(
+                "{}{}-{}-{:x}{}",
+                prefix,
+                std::process::id(),
+                COUNTER.fetch_add(1, Ordering::Relaxed),
+                nanos,
+                suffix
+            ));
+            match options.open(&path) {
+                Ok(file) => return Ok((Self { path }, file)),
+                Err(error)
+                    if error.kind() == std::io::ErrorKind::AlreadyExists && attempt < ATTEMPTS =>
+                {
+                    attempt += 1;
+                }
+                Err(error) => return Err(error).at(&path),
+            }
+        }
+    }
+
+    /// Create a new, empty temporary file whose name is derived from a hash of its content.
+    ///
+    /// Unless a file with the name exists, the same key always results in the same name. Otherwise,
+    /// the first free name with a numbered suffix is used.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir`: The directory.
+    /// * `prefix`: The start of the file name.
+    /// * `suffix`: The end of the file name, e.g. an extension.
+    /// * `key`: The content hash, of which the first 8 bytes are used.
+    pub(crate) fn create_deterministic(
+        dir: &Path,
+        prefix: &str,
+        suffix: &str,
+        key: &[u8; 32],
+    ) -> Result<(Self, File), Error> {
+        let hex: String = key[..8]
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        let mut attempt = 0;
+        loop {
+            let path = match attempt {
+                0 => dir.join(format!("{}{}{}", prefix, hex, suffix)),
+                _ => dir.join(format!("{}{}-{}{}", prefix, hex, attempt, suffix)),
+            };
+            match private_options().open(&path) {
+                Ok(file) => return Ok((Self { path }, file)),
+                Err(error)
+                    if error.kind() == std::io::ErrorKind::AlreadyExists && attempt < ATTEMPTS =>
+                {
+                    attempt += 1;
+                }
+                Err(error) => return Err(error).at(&path),
+            }
+        }
+    }
+
+    /// Retrieve the path of the file.
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Move the file to its final location, replacing any existing file.
+    ///
+    /// # Arguments
+    ///
+    /// * `target`: The final location, which should be on the same filesystem. Otherwise, e.g.
+    ///   for a bind mount of the file, the content is copied into the existing file instead.
+    pub(crate) fn persist(self, target: &Path) -> Result<(), Error> {
+        match std::fs::rename(&self.path, target) {
+            Ok(()) => {}
+            Err(error) if error.kind() == std::io::ErrorKind::CrossesDevices => {
+                let mut file = File::create(target).at(target)?;
+                std::io::copy(&mut File::open(&self.path).at(&self.path)?, &mut file).at(target)?;
+                file.sync_all().at(target)?;
+                return Ok(());
+            }
+            Err(error) => return Err(error).at(target),
+        }
+        std::mem::forget(self);
+        Ok(())
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Create the options for creating a new file that only the current user can access.
+fn private_options() -> std::fs::OpenOptions {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options
+}
+
+/// Replace the content of a file atomically.
+///
+/// The content is written to a temporary file next to the file, which then replaces it. Readers
+/// never observe a partially written file, and the file is left untouched if writing fails. The
+/// permissions of an existing file are preserved, and symbolic links are followed.
+///
+/// # Arguments
+///
+/// * `path`: The path of the file.
+/// * `contents`: The new content.
+pub(crate) fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> Result<(), Error> {
+    let target = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let permissions = std::fs::metadata(&target)
+        .ok()
+        .map(|metadata| metadata.permissions());
+    let name = target
+        .file_name()
+        .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+    let (temp, mut file) = TempFile::create(
+        crate::format::parent_dir(&target),
+        &format!(".{}.", name),
+        ".tmp",
+    )?;
+    file.write_all(contents.as_ref()).at(temp.path())?;
+    file.sync_all().at(temp.path())?;
+    drop(file);
+    if let Some(permissions) = permissions {
+        std::fs::set_permissions(temp.path(), permissions).at(temp.path())?;
+    }
+    temp.persist(&target)
+}
+
+#[cfg(test)]
+mod test {
+    use super::TempFile;
+    use std::path::Path;
+
+    #[test]
+    fn test_temp_file() {
+        let dir = Path::new("target/temp");
+        let _ = std::fs::remove_dir_all(dir);
+        std::fs::create_dir_all(dir).unwrap();
+        let (first, _) = TempFile::create(dir, "test-", ".rs").unwrap();
+        let (second, _) = TempFile::create(dir, "test-", ".rs").unwrap();
+        assert_ne!(first.path(), second.path());
+        let key = crate::hash::sha256(b"fn main() {}");
+        let (named, _) = TempFile::create_deterministic(dir, "test-", ".rs", &key).unwrap();
+        let (taken, _) = TempFile::create_deterministic(dir, "test-", ".rs", &key).unwrap();
+        assert_eq!(dir.join("test-ef32637cb9c3ec2e.rs"), named.path());
+        assert_eq!(dir.join("test-ef32637cb9c3ec2e-1.rs"), taken.path());
+        drop((named, taken));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(first.path())
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(0o600, mode & 0o777);
+        }
+
+        let path = first.path().to_path_buf();
+        let result = std::panic::catch_unwind(move || {
+            let _first = first;
+            panic!("formatting failed");
+        });
+        assert!(result.is_err());
+        assert!(!path.exists());
+
+        let target = dir.join("target.rs");
+        std::fs::write(&target, "old").unwrap();
+        super::write_atomic(&target, "new").unwrap();
+        assert_eq!("new", std::fs::read_to_string(&target).unwrap());
+        drop(second);
+        assert_eq!(1, std::fs::read_dir(dir).unwrap().count());
+    }
+}
@@ -0,0 +1,245 @@
+//! Helpers for testing code generators and their integration with the formatter.
+
+use crate::batch::{check_dir_with, format_dir_with, BatchOptions, Outcome};
+use crate::golden::{is_blessing, BLESS_VAR};
+use crate::report::ReportExt;
+use crate::Error;
+use proptest::prelude::*;
+use std::path::Path;
+
+/// Generate an identifier that is not a keyword.
+fn ident() -> impl Strategy<Value = String> {
+    "[a-z][a-z0-9_]{0,8}".prop_map(|name| format!("r_{}", name))
+}
+
+/// Generate whitespace, including newlines, to separate tokens.
+fn space() -> impl Strategy<Value = &'static str> {
+    prop_oneof![
+        Just(" "),
+        Just("  "),
+        Just("\n"),
+        Just("\n\n    "),
+        Just("\t")
+    ]
+}
+
+/// Generate a simple expression.
+fn expr() -> impl Strategy<Value = String> {
+    let leaf = prop_oneof![
+        any::<u32>().prop_map(|value| value.to_string()),
+        ident(),
+        "[a-zA-Z0-9 ]{0,12}".prop_map(|text| format!("{:?}", text)),
+    ];
+    leaf.prop_recursive(4, 32, 4, |inner| {
+        prop_oneof![
+            (inner.clone(), space(), inner.clone())
+                .prop_map(|(a, s, b)| format!("{}{}+{}{}", a, s, s, b)),
+            inner.clone().prop_map(|a| format!("({})", a)),
+            (ident(), prop::collection::vec(inner, 0..4)).prop_map(|(name, args)| format!(
+                "{}({})",
+                name,
+                args.join(", ")
+            )),
+        ]
+    })
+}
+
+/// Generate a top-level item.
+fn item() -> impl Strategy<Value = String> {
+    prop_oneof![
+        (
+            ident(),
+            space(),
+            prop::collection::vec((ident(), expr()), 0..4)
+        )
+            .prop_map(|(name, s, statements)| {
+                let body: String = statements
+                    .iter()
+                    .map(|(var, value)| format!("let {}{}={}{};", var, s, s, value))
+                    .collect();
+                format!("fn {}(){}{{{}}}", name, s, body)
+            }),
+        (ident(), space(), prop::collection::vec(ident(), 0..5)).prop_map(|(name, s, fields)| {
+            let fields: Vec<String> = fields
+                .iter()
+                .map(|field| format!("{}:{}u32", field, s))
+                .collect();
+            format!("pub struct {}{}{{{}}}", name, s, fields.join(","))
+        }),
+        (ident(), expr()).prop_map(|(name, value)| {
+            format!("const {}: u32 = {};", name.to_uppercase(), value)
+        }),
+    ]
+}
+
+/// A strategy that generates syntactically valid, unformatted Rust source code.
+///
+/// The sources consist of functions, structs and constants with irregular whitespace. Identifiers
+/// that are used in expressions are not necessarily declared, so the sources are not guaranteed to
+/// compile.
+///
+/// # Examples
+///
+/// ```no_run
+/// use proptest::prelude::*;
+/// use rust_format::testing::{arbitrary_source, formatting_is_idempotent};
+///
+/// proptest! {
+///     #[test]
+///     fn idempotent(source in arbitrary_source()) {
+///         prop_assert!(formatting_is_idempotent(&source).unwrap());
+///     }
+/// }
+/// ```
+pub fn arbitrary_source() -> impl Strategy<Value = String> {
+    (prop::collection::vec((item(), space()), 1..8)).prop_map(|items| {
+        items
+            .into_iter()
+            .map(|(item, space)| format!("{}{}", item, space))
+            .collect()
+    })
+}
+
+/// Determine whether formatting already formatted source code leaves it unchanged.
+///
+/// # Arguments
+///
+/// * `source`: The source code, formatted or not.
+pub fn formatting_is_idempotent(source: &str) -> Result<bool, Error> {
+    let once = crate::format_str(source)?;
+    let twice = crate::format_str(&once)?;
+    Ok(once == twice)
+}
+
+/// Assert that all Rust source files below a directory are formatted, e.g. in a `#[test]` that
+/// enforces formatting with the test suite.
+///
+/// The files are checked with the options of the [global formatter](crate::set_global). See
+/// [`assert_source_tree_formatted_with`].
+///
+/// # Arguments
+///
+/// * `dir`: The directory.
+///
+/// # Panics
+///
+/// Panics with a diff of every file that is not formatted correctly, or if checking fails.
+///
+/// # Examples
+///
+/// ```no_run
+/// #[test]
+/// fn formatted() {
+///     rust_format::testing::assert_source_tree_formatted("src");
+/// }
+/// ```
+#[track_caller]
+pub fn assert_source_tree_formatted(dir: impl AsRef<Path>) {
+    assert_source_tree_formatted_with(dir, &BatchOptions::global());
+}
+
+/// Assert that all Rust source files below a directory are formatted, using the provided options.
+///
+/// The files are discovered as by [`check_dir_with`] and never modified. In bless mode (see
+/// [`is_blessing`]), the files are formatted in place instead, so that setting the
+/// [`BLESS_VAR`] environment variable for a run of `cargo test` fixes them.
+///
+/// # Arguments
+///
+/// * `dir`: The directory.
+/// * `options`: The options.
+///
+/// # Panics
+///
+/// Panics with a diff of every file that is not formatted correctly, or if checking or formatting
+/// fails.
+#[track_caller]
+pub fn assert_source_tree_formatted_with(dir: impl AsRef<Path>, options: &BatchOptions) {
+    if let Err(message) = check_source_tree(dir.as_ref(), options, is_blessing()) {
+        panic!("{}", message);
+    }
+}
+
+/// Check or format the Rust source files below a directory and describe the failures.
+fn check_source_tree(dir: &Path, options: &BatchOptions, bless: bool) -> Result<(), String> {
+    let failed = |error: Error| format!("Failed to check '{}': {}", dir.display(), error);
+    let results = match bless {
+        true => format_dir_with(dir, options).map_err(failed)?,
+        false => check_dir_with(dir, options).map_err(failed)?.into_results(),
+    };
+    let mut message = String::new();
+    for group in results.error_groups() {
+        message.push_str(&format!("{}\n", group));
+    }
+    let mut unformatted: Vec<&Path> = results
+        .iter()
+        .filter(|file| matches!(file.result, Ok(Outcome::Unformatted)))
+        .map(|file| file.path.as_path())
+        .collect();
+    unformatted.sort();
+    for path in &unformatted {
+        let (resolved, _) = options.resolve(path);
+        let diff = std::fs::read_to_string(path)
+            .map_err(Error::from)
+            .and_then(|original| {
+                let formatted = crate::format_file_to_string_with(path, &resolved)?;
+                Ok(crate::diff::unified_diff(&original, &formatted))
+            })
+            .unwrap_or_else(|error| format!("{}\n", error));
+        message.push_str(&format!("--- {}\n{}", path.display(), diff));
+    }
+    if !unformatted.is_empty() {
+        message.insert_str(
+            0,
+            &format!(
+                "{} file(s) below '{}' are not formatted correctly (set {}=1 to format them):\n",
+                unformatted.len(),
+                dir.display(),
+                BLESS_VAR
+            ),
+        );
+    }
+    match message.is_empty() {
+        true => Ok(()),
+        false => Err(message),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{arbitrary_source, formatting_is_idempotent};
+    use crate::batch::BatchOptions;
+    use proptest::prelude::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_check_source_tree() {
+        let dir = PathBuf::from("target/source_tree");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("lib.rs"), "fn clean() {}\n").unwrap();
+        std::fs::write(dir.join("sub/dirty.rs"), "fn dirty(){}\n").unwrap();
+        let options = BatchOptions::new();
+
+        let message = super::check_source_tree(&dir, &options, false).unwrap_err();
+        assert!(message.starts_with("1 file(s) below 'target/source_tree' are not formatted"));
+        assert!(message.contains("-fn dirty(){}\n+fn dirty() {}\n"));
+        assert_eq!(
+            "fn dirty(){}\n",
+            std::fs::read_to_string(dir.join("sub/dirty.rs")).unwrap()
+        );
+
+        super::check_source_tree(&dir, &options, true).unwrap();
+        super::check_source_tree(&dir, &options, false).unwrap();
+        super::assert_source_tree_formatted_with(&dir, &options);
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(8))]
+
+        #[test]
+        fn test_arbitrary_source(source in arbitrary_source()) {
+            prop_assert!(formatting_is_idempotent(&source).unwrap());
+        }
+    }
+}
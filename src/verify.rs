@@ -0,0 +1,143 @@
+//! Verification of formatting results.
+
+use crate::lexer::{tokenize, TokenKind};
+use crate::{Error, Options};
+use std::collections::HashMap;
+
+mod compile;
+#[cfg(feature = "diff")]
+mod tokens;
+
+pub use compile::CompileCheck;
+
+/// Run all verifications that are enabled in the options.
+///
+/// # Arguments
+///
+/// * `options`: The options.
+/// * `input`: The unformatted source code.
+/// * `output`: The formatted source code.
+pub(crate) fn verify(options: &Options, input: &str, output: &str) -> Result<(), Error> {
+    if options.get_preserve_comments() {
+        check_comments(input, output)?;
+    }
+    if options.get_preserve_literals() {
+        check_literals(input, output)?;
+    }
+    #[cfg(feature = "diff")]
+    if options.get_verify_tokens() {
+        tokens::check_tokens(input, output)?;
+    }
+    if options.get_reject_line_overflow() {
+        let lines = crate::overflowing_lines(output, options);
+        if !lines.is_empty() {
+            return Err(Error::LineOverflow { lines });
+        }
+    }
+    if options.get_check_compile() != CompileCheck::Off {
+        compile::check_compile(options, input, output)?;
+    }
+    Ok(())
+}
+
+/// Verify that all comments of the input are still present in the output.
+///
+/// Comments are compared with normalized whitespace, since the formatter may re-indent the lines
+/// of block comments and strip trailing whitespace.
+pub(crate) fn check_comments(input: &str, output: &str) -> Result<(), Error> {
+    let missing = missing(comments(input), comments(output));
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::CommentsLost { missing })
+    }
+}
+
+/// Verify that all string literals of the input are still present in the output, byte for byte.
+///
+/// This covers string, byte string and C string literals, including their raw variants. The
+/// literals are compared as an unordered collection, since the formatter may reorder items.
+pub(crate) fn check_literals(input: &str, output: &str) -> Result<(), Error> {
+    let missing = missing(literals(input), literals(output));
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::LiteralsAltered { missing })
+    }
+}
+
+/// Determine the elements of the input that are missing from the output, respecting multiplicity.
+fn missing(input: Vec<String>, output: Vec<String>) -> Vec<String> {
+    let mut remaining: HashMap<String, usize> = HashMap::new();
+    for element in output {
+        *remaining.entry(element).or_default() += 1;
+    }
+
+    let mut missing = Vec::new();
+    for element in input {
+        match remaining.get_mut(&element) {
+            Some(count) if *count > 0 => *count -= 1,
+            _ => missing.push(element),
+        }
+    }
+    missing
+}
+
+/// Extract all string literals.
+fn literals(source: &str) -> Vec<String> {
+    tokenize(source)
+        .into_iter()
+        .filter(|token| token.kind == TokenKind::Str)
+        .map(|token| token.text(source).to_string())
+        .collect()
+}
+
+/// Extract all comments with normalized whitespace.
+fn comments(source: &str) -> Vec<String> {
+    tokenize(source)
+        .into_iter()
+        .filter(|token| token.kind.is_comment())
+        .map(|token| {
+            token
+                .text(source)
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Error;
+
+    #[test]
+    fn test_check_comments() {
+        let input = "// SAFETY: checked\nfn f() { /* a\n   b */ }\n";
+        assert!(super::check_comments(
+            input,
+            "// SAFETY: checked\nfn f() {\n    /* a\n    b */\n}\n"
+        )
+        .is_ok());
+
+        match super::check_comments(input, "fn f() { /* a\n   b */ }\n") {
+            Err(Error::CommentsLost { missing }) => {
+                assert_eq!(vec!["// SAFETY: checked".to_string()], missing)
+            }
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_check_literals() {
+        let input = "const Q: &str = \"SELECT *  FROM t\";\nconst R: &[u8] = br#\"a  b\"#;\n";
+        assert!(super::check_literals(input, input).is_ok());
+
+        match super::check_literals(input, &input.replace("*  FROM", "* FROM")) {
+            Err(Error::LiteralsAltered { missing }) => {
+                assert_eq!(vec!["\"SELECT *  FROM t\"".to_string()], missing)
+            }
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+}
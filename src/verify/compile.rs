@@ -0,0 +1,131 @@
+//! Verification that formatted code still compiles.
+
+use crate::error::IoContext;
+use crate::temp::TempFile;
+use crate::{Error, IoStream, Options};
+use std::io::Write;
+use std::path::PathBuf;
+
+/// The method that verifies that the formatted code still compiles.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub enum CompileCheck {
+    /// No verification.
+    #[default]
+    Off,
+    /// Parse the code with `syn`. This is a cheap proxy that catches syntax errors only.
+    #[cfg(feature = "syn")]
+    Syntax,
+    /// Compile the code as a library with `rustc --emit=metadata`. The compiler is taken from the
+    /// `RUSTC` environment variable, falling back to `rustc`.
+    Rustc,
+}
+
+impl CompileCheck {
+    /// Retrieve the name of the method, which is stable across versions of the crate.
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            CompileCheck::Off => "off",
+            #[cfg(feature = "syn")]
+            CompileCheck::Syntax => "syntax",
+            CompileCheck::Rustc => "rustc",
+        }
+    }
+}
+
+/// Verify that the output compiles, unless the input does not compile either.
+///
+/// Code frequently does not compile in isolation, e.g. because it refers to other crates. Only
+/// breakage that was introduced by formatting is reported.
+pub(crate) fn check_compile(options: &Options, input: &str, output: &str) -> Result<(), Error> {
+    let check = options.get_check_compile();
+    match compile(check, options, output)? {
+        None => Ok(()),
+        Some(diagnostics) => match compile(check, options, input)? {
+            None => Err(Error::CompileCheckFailed { diagnostics }),
+            Some(_) => Ok(()),
+        },
+    }
+}
+
+/// Compile source code and return the diagnostics if that fails.
+fn compile(
+    check: CompileCheck,
+    options: &Options,
+    source: &str,
+) -> Result<Option<IoStream>, Error> {
+    match check {
+        CompileCheck::Off => Ok(None),
+        #[cfg(feature = "syn")]
+        CompileCheck::Syntax => Ok(syn::parse_file(source)
+            .err()
+            .map(|error| IoStream::Text(error.to_string()))),
+        CompileCheck::Rustc => rustc(options, source),
+    }
+}
+
+fn rustc(options: &Options, source: &str) -> Result<Option<IoStream>, Error> {
+    // The guards remove the files even if the thread panics.
+    let dir = options.get_temp().dir();
+    let key = options
+        .get_deterministic_temp_names()
+        .then(|| crate::hash::sha256(source.as_bytes()));
+    let create = |prefix: &str, suffix: &str| match &key {
+        Some(key) => TempFile::create_deterministic(&dir, prefix, suffix, key),
+        None => TempFile::create(&dir, prefix, suffix),
+    };
+    let (input, mut file) = create("rust-format-check-", ".rs")?;
+    file.write_all(source.as_bytes()).at(input.path())?;
+    drop(file);
+    let (metadata, _) = create("librust-format-check-", ".rmeta")?;
+
+    let rustc = std::env::var_os("RUSTC").map_or_else(|| PathBuf::from("rustc"), PathBuf::from);
+    let mut command = std::process::Command::new(rustc);
+    if let Some(edition) = options.get_edition() {
+        command.arg("--edition").arg(edition.as_str());
+    }
+    command
+        .args([
+            "--emit=metadata",
+            "--crate-type",
+            "lib",
+            "--crate-name",
+            "check",
+            "-o",
+        ])
+        .arg(metadata.path())
+        .arg(input.path());
+    let output = command.output()?;
+    if output.status.success() {
+        Ok(None)
+    } else {
+        Ok(Some(String::from_utf8(output.stderr).into()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::CompileCheck;
+    use crate::backend::Mock;
+    use crate::{Error, Options};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_check_compile() {
+        let mock = Arc::new(Mock::new());
+        let options = Options::new()
+            .backend(mock.clone())
+            .check_compile(CompileCheck::Rustc);
+
+        mock.respond(Ok("pub fn f() -> u32 { \"1\" }\n".to_string()));
+        match crate::format_str_with("pub fn f() -> u32 { 1 }", &options) {
+            Err(Error::CompileCheckFailed { diagnostics }) => {
+                assert!(diagnostics.to_string().contains("mismatched types"))
+            }
+            other => panic!("Unexpected result: {:?}", other),
+        }
+
+        // Code that did not compile before formatting is not reported.
+        mock.respond(Ok("pub fn f() -> u32 { missing() }\n".to_string()));
+        assert!(crate::format_str_with("pub fn f() -> u32 { missing() }", &options).is_ok());
+    }
+}
@@ -0,0 +1,329 @@
+//! Verification that formatting does not alter the tokens of the source code.
+
+use crate::diff::{diff, Edit};
+use crate::lexer::{tokenize, TokenKind};
+use crate::Error;
+
+/// Verify that the input and output consist of the same tokens.
+///
+/// Whitespace and comments are ignored, as are commas and braces, since the formatter adds and
+/// removes trailing commas and block braces around single expressions. `use` declarations and
+/// `mod` declarations without a body may be reordered by the formatter and are therefore compared
+/// as an unordered collection. The other rewrites of the default configuration of the formatter
+/// are normalized away (see [`normalize`]).
+pub(crate) fn check_tokens(input: &str, output: &str) -> Result<(), Error> {
+    let input_tokens = significant_tokens(input);
+    let output_tokens = significant_tokens(output);
+
+    let input_main: Vec<&str> = input_tokens
+        .main
+        .iter()
+        .map(|(_, text)| text.as_str())
+        .collect();
+    let output_main: Vec<&str> = output_tokens
+        .main
+        .iter()
+        .map(|(_, text)| text.as_str())
+        .collect();
+    let (mut next_input, mut next_output) = (0, 0);
+    for edit in diff(&input_main, &output_main) {
+        let (position, found) = match edit {
+            Edit::Equal(a, b) => {
+                next_input = a + 1;
+                next_output = b + 1;
+                continue;
+            }
+            Edit::Delete(a) if is_tolerated(input_main[a]) => continue,
+            Edit::Insert(b) if is_tolerated(output_main[b]) => continue,
+            Edit::Delete(a) => (a, output_tokens.main.get(next_output)),
+            Edit::Insert(b) => (next_input, output_tokens.main.get(b)),
+        };
+        let offset = input_tokens
+            .main
+            .get(position)
+            .map_or(input.len(), |(offset, _)| *offset);
+        return Err(Error::SemanticDrift {
+            line: line_of(input, offset),
+            expected: token_or_end(input_tokens.main.get(position)),
+            found: token_or_end(found),
+        });
+    }
+
+    let mut input_declarations = input_tokens.declarations;
+    let mut output_declarations = output_tokens.declarations;
+    input_declarations.sort_by(|a, b| a.1.cmp(&b.1));
+    output_declarations.sort_by(|a, b| a.1.cmp(&b.1));
+    if let Some(position) =
+        (0..input_declarations.len().max(output_declarations.len())).find(|index| {
+            input_declarations.get(*index).map(|(_, text)| text)
+                != output_declarations.get(*index).map(|(_, text)| text)
+        })
+    {
+        let offset = input_declarations
+            .get(position)
+            .map_or(input.len(), |(offset, _)| *offset);
+        return Err(Error::SemanticDrift {
+            line: line_of(input, offset),
+            expected: token_or_end(input_declarations.get(position)),
+            found: token_or_end(output_declarations.get(position)),
+        });
+    }
+    Ok(())
+}
+
+/// Determine whether the formatter may add or remove a token.
+///
+/// The formatter wraps closure and match arm bodies in blocks or unwraps them, depending on their
+/// length.
+fn is_tolerated(token: &str) -> bool {
+    matches!(token, "{" | "}")
+}
+
+fn token_or_end(token: Option<&(usize, String)>) -> String {
+    token.map_or_else(|| "<end of input>".to_string(), |(_, text)| text.clone())
+}
+
+fn line_of(source: &str, offset: usize) -> usize {
+    source[..offset].matches('\n').count() + 1
+}
+
+/// The significant tokens of source code, as pairs of byte offset and text.
+struct SignificantTokens {
+    /// The tokens outside reorderable declarations.
+    main: Vec<(usize, String)>,
+    /// The reorderable declarations, with their tokens joined and sorted.
+    declarations: Vec<(usize, String)>,
+}
+
+fn significant_tokens(source: &str) -> SignificantTokens {
+    let tokens: Vec<(usize, &str)> = tokenize(source)
+        .into_iter()
+        .filter(|token| !token.kind.is_trivia() && token.kind != TokenKind::Shebang)
+        .map(|token| (token.start, token.text(source)))
+        .collect();
+    let tokens = normalize(tokens);
+
+    let mut main: Vec<(usize, String)> = Vec::new();
+    let mut declarations = Vec::new();
+    let mut index = 0;
+    while index < tokens.len() {
+        let end = declaration_end(&tokens, index);
+        match end {
+            Some(end) => {
+                // Move a preceding visibility into the declaration.
+                let visibility = visibility_len(&main);
+                let start = main.len() - visibility;
+                let mut parts: Vec<String> = main.drain(start..).map(|(_, text)| text).collect();
+                let offset = tokens[index].0;
+                parts.extend(tokens[index..end].iter().map(|(_, text)| text.to_string()));
+                // The formatter sorts the items of nested import lists as well.
+                parts.sort();
+                declarations.push((offset, parts.join(" ")));
+                index = end;
+            }
+            None => {
+                main.push((tokens[index].0, tokens[index].1.to_string()));
+                index += 1;
+            }
+        }
+    }
+    SignificantTokens { main, declarations }
+}
+
+/// Normalize the rewrites that the formatter applies by default and drop all commas.
+///
+/// * Redundant nested parentheses are collapsed, e.g. `((1))` to `(1)`, except around tuples and
+///   in macro invocations.
+/// * `extern` blocks and functions without an ABI get the implicit `"C"` ABI.
+/// * The semicolon of a `break`, `continue` or `return` statement at the end of a block is
+///   dropped, since these expressions never produce a value.
+fn normalize(mut tokens: Vec<(usize, &str)>) -> Vec<(usize, &str)> {
+    while let Some((open, close)) = redundant_parens(&tokens) {
+        tokens.remove(close);
+        tokens.remove(open);
+    }
+    tokens.retain(|(_, text)| *text != ",");
+
+    let mut normalized: Vec<(usize, &str)> = Vec::with_capacity(tokens.len());
+    for (index, &(offset, text)) in tokens.iter().enumerate() {
+        let next = tokens.get(index + 1).map(|(_, text)| *text);
+        if text == ";" && next == Some("}") && ends_in_diverging_statement(&normalized) {
+            continue;
+        }
+        normalized.push((offset, text));
+        if text == "extern" && matches!(next, Some("{" | "fn")) {
+            normalized.push((offset, "\"C\""));
+        }
+    }
+    normalized
+}
+
+/// Find a pair of parentheses that is directly enclosed by another pair and does not form a tuple,
+/// returning its indices.
+fn redundant_parens(tokens: &[(usize, &str)]) -> Option<(usize, usize)> {
+    let mut stack = Vec::new();
+    // The innermost closed pair, as its indices and whether it contains a top-level comma.
+    let mut closed: Option<(usize, usize, bool)> = None;
+    for (index, (_, text)) in tokens.iter().enumerate() {
+        match *text {
+            "(" | "[" | "{" => stack.push((index, false)),
+            ")" | "]" | "}" => {
+                let (open, comma) = stack.pop()?;
+                if let Some((inner_open, inner_close, inner_comma)) = closed {
+                    let is_macro = open > 0 && tokens[open - 1].1 == "!";
+                    if *text == ")"
+                        && tokens[open].1 == "("
+                        && inner_open == open + 1
+                        && inner_close + 1 == index
+                        && tokens[inner_open].1 == "("
+                        && !inner_comma
+                        && !is_macro
+                    {
+                        return Some((inner_open, inner_close));
+                    }
+                }
+                closed = Some((open, index, comma));
+            }
+            "," => {
+                if let Some((_, comma)) = stack.last_mut() {
+                    *comma = true;
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Determine whether the tokens end in a statement that starts with `break`, `continue` or
+/// `return`.
+fn ends_in_diverging_statement(tokens: &[(usize, &str)]) -> bool {
+    let mut depth = 0usize;
+    for (index, (_, text)) in tokens.iter().enumerate().rev() {
+        match *text {
+            ")" | "]" | "}" => depth += 1,
+            "(" | "[" | "{" if depth > 0 => depth -= 1,
+            "(" | "[" | "{" | ";" => return false,
+            "break" | "continue" | "return" if depth == 0 => {
+                let previous = index.checked_sub(1).map(|index| tokens[index].1);
+                if matches!(previous, None | Some("{" | ";" | "}")) {
+                    return true;
+                }
+            }
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Determine the end (exclusive) of a reorderable declaration starting at `index`, if any.
+fn declaration_end(tokens: &[(usize, &str)], index: usize) -> Option<usize> {
+    let text = |offset: usize| tokens.get(index + offset).map(|(_, text)| *text);
+    match (text(0)?, text(1), text(2)) {
+        ("mod", Some(_), Some(";")) => Some(index + 3),
+        // `use<...>` is precise capturing syntax and not a declaration.
+        ("use", Some(next), _) if next != "<" => {
+            let mut depth = 0usize;
+            for (end, (_, text)) in tokens.iter().enumerate().skip(index) {
+                match *text {
+                    "{" => depth += 1,
+                    "}" => depth = depth.checked_sub(1)?,
+                    ";" if depth == 0 => return Some(end + 1),
+                    _ => {}
+                }
+            }
+            None
+        }
+        _ => None,
+    }
+}
+
+/// Determine the number of trailing tokens that form a visibility, such as `pub` or `pub(crate)`.
+fn visibility_len(tokens: &[(usize, String)]) -> usize {
+    let text = |offset: usize| {
+        tokens
+            .len()
+            .checked_sub(offset)
+            .map(|index| tokens[index].1.as_str())
+    };
+    match text(1) {
+        Some("pub") => 1,
+        Some(")") => (2..=5)
+            .find(|length| text(*length) == Some("(") && text(length + 1) == Some("pub"))
+            .map_or(0, |length| length + 1),
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Error;
+
+    #[test]
+    fn test_check_tokens() {
+        let input = "use std::{io, fmt};\nuse a::b;\npub(crate) mod x;\nmod a;\nfn f() { match 1 { _ => {}, } }\n";
+        let output = "mod a;\npub(crate) mod x;\nuse a::b;\nuse std::{fmt, io};\nfn f() {\n    match 1 {\n        _ => {}\n    }\n}\n";
+        assert!(super::check_tokens(input, output).is_ok());
+
+        match super::check_tokens(input, &output.replace("match 1", "match 2")) {
+            Err(Error::SemanticDrift {
+                line,
+                expected,
+                found,
+            }) => {
+                assert_eq!(5, line);
+                assert_eq!("1", expected);
+                assert_eq!("2", found);
+            }
+            other => panic!("Unexpected result: {:?}", other),
+        }
+        assert!(super::check_tokens(input, &output.replace("fmt", "fmx")).is_err());
+    }
+
+    #[test]
+    fn test_default_rewrites() {
+        let tolerated = [
+            (
+                "fn f() { loop { break } }",
+                "fn f() {\n    loop {\n        break;\n    }\n}\n",
+            ),
+            (
+                "fn f() { 'a: loop { continue 'a } }",
+                "fn f() {\n    'a: loop {\n        continue 'a;\n    }\n}\n",
+            ),
+            (
+                "fn f() -> u8 { if x {} return match y { _ => 1 } }",
+                "fn f() -> u8 {\n    if x {}\n    return match y {\n        _ => 1,\n    };\n}\n",
+            ),
+            ("extern { fn g(); }", "extern \"C\" {\n    fn g();\n}\n"),
+            ("extern fn h() {}", "extern \"C\" fn h() {}\n"),
+            (
+                "fn f() { let a = (((1))); g(((a))); }",
+                "fn f() {\n    let a = (1);\n    g((a));\n}\n",
+            ),
+            ("fn f() { g(((1, 2))); }", "fn f() {\n    g((1, 2));\n}\n"),
+        ];
+        for (input, output) in tolerated {
+            assert!(super::check_tokens(input, output).is_ok(), "{}", input);
+        }
+
+        let rejected = [
+            ("fn f() -> u8 { 1 }", "fn f() -> u8 {\n    1;\n}\n"),
+            ("extern crate a;", "extern \"C\" crate a;\n"),
+            ("fn f() { g((1, 2)); }", "fn f() {\n    g(1, 2);\n}\n"),
+            ("fn f() { m!((1)); }", "fn f() {\n    m!(1);\n}\n"),
+        ];
+        for (input, output) in rejected {
+            assert!(super::check_tokens(input, output).is_err(), "{}", input);
+        }
+
+        let options = crate::Options::new().verify_tokens(true);
+        for input in [
+            "fn f() { loop { break } }",
+            "extern { fn g(); }",
+            "fn f() { let a = ((1)); }",
+        ] {
+            assert!(crate::format_str_with(input, &options).is_ok(), "{}", input);
+        }
+    }
+}
@@ -0,0 +1,204 @@
+//! Discovery of Rust source files in directory trees.
+
+use crate::file_system::{FileSystem, StdFileSystem};
+use crate::report::Verbosity;
+use crate::Error;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// A walk over the Rust source files below a directory.
+///
+/// By default, hidden files and directories (whose name starts with `.`) and temporary files of
+/// editors (e.g. `.#lib.rs` and `#lib.rs#`) are skipped.
+///
+/// The files are returned in sorted order, independent of the order in which the file system
+/// lists the entries of directories, so that the results of tools are stable from run to run.
+///
+/// # Examples
+///
+/// ```no_run
+/// use rust_format::batch::{format_files_with, BatchOptions};
+/// use rust_format::walk::Walk;
+///
+/// let files = Walk::new("src").files()?;
+/// let results = format_files_with(files, &BatchOptions::new());
+/// # Ok::<(), rust_format::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct Walk {
+    root: PathBuf,
+    include_hidden: bool,
+    include_temporary: bool,
+    file_system: Arc<dyn FileSystem>,
+    verbosity: Verbosity,
+}
+
+impl Walk {
+    /// Create a walk over the files below a directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `root`: The directory.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            include_hidden: false,
+            include_temporary: false,
+            file_system: Arc::new(StdFileSystem),
+            verbosity: Verbosity::default(),
+        }
+    }
+
+    /// Set whether hidden files and directories are included. Disabled by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `include_hidden`: Whether to include hidden entries.
+    pub fn include_hidden(mut self, include_hidden: bool) -> Self {
+        self.include_hidden = include_hidden;
+        self
+    }
+
+    /// Set whether temporary files of editors are included. Disabled by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `include_temporary`: Whether to include temporary files.
+    pub fn include_temporary(mut self, include_temporary: bool) -> Self {
+        self.include_temporary = include_temporary;
+        self
+    }
+
+    /// Set the file system to walk. The file system of the operating system is used by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `file_system`: The file system.
+    pub fn file_system(mut self, file_system: Arc<dyn FileSystem>) -> Self {
+        self.file_system = file_system;
+        self
+    }
+
+    /// Set the amount of detail in the logs of the walk (see [`crate::Options::verbosity`]). With
+    /// [`Verbosity::Verbose`], the skipped hidden and temporary entries are logged.
+    ///
+    /// # Arguments
+    ///
+    /// * `verbosity`: The verbosity.
+    pub fn verbosity(mut self, verbosity: Verbosity) -> Self {
+        self.verbosity = verbosity;
+        self
+    }
+
+    /// Collect the paths of all Rust source files.
+    ///
+    /// The rules are not applied to the root directory itself.
+    pub fn files(&self) -> Result<Vec<PathBuf>, Error> {
+        self.files_matching(|path| path.extension().is_some_and(|extension| extension == "rs"))
+    }
+
+    /// Collect the paths of all files that satisfy a predicate, instead of Rust source files.
+    pub(crate) fn files_matching(
+        &self,
+        predicate: impl Fn(&Path) -> bool,
+    ) -> Result<Vec<PathBuf>, Error> {
+        let mut paths = Vec::new();
+        self.collect(&self.root, &predicate, &mut paths)?;
+        Ok(paths)
+    }
+
+    fn collect(
+        &self,
+        dir: &Path,
+        predicate: &dyn Fn(&Path) -> bool,
+        paths: &mut Vec<PathBuf>,
+    ) -> Result<(), Error> {
+        let mut entries = self.file_system.read_dir(dir)?;
+        // Visiting the entries of every directory by name yields the paths in sorted order.
+        entries.sort();
+        for path in entries {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            if !self.include_hidden && is_hidden(&name) {
+                self.skipped(&path, "hidden");
+                continue;
+            }
+            let is_dir = self
+                .file_system
+                .metadata(&path)
+                .is_ok_and(|metadata| metadata.is_dir);
+            if is_dir {
+                self.collect(&path, predicate, paths)?;
+            } else if !predicate(&path) {
+                continue;
+            } else if self.include_temporary || !is_temporary(&name) {
+                paths.push(path);
+            } else {
+                self.skipped(&path, "temporary");
+            }
+        }
+        Ok(())
+    }
+
+    /// Log an entry that is skipped by the walk, if the verbosity asks for it.
+    fn skipped(&self, path: &Path, kind: &str) {
+        #[cfg(feature = "tracing")]
+        if self.verbosity == Verbosity::Verbose {
+            tracing::info!("Skipped {} '{}'.", kind, path.display());
+        }
+        #[cfg(not(feature = "tracing"))]
+        let _ = (path, kind, self.verbosity);
+    }
+}
+
+/// Determine whether a file or directory name denotes a hidden entry.
+fn is_hidden(name: &str) -> bool {
+    // An Emacs lock file (`.#lib.rs`) is a temporary file rather than a hidden one.
+    name.starts_with('.') && !name.starts_with(".#")
+}
+
+/// Determine whether a file name denotes a temporary file of an editor.
+fn is_temporary(name: &str) -> bool {
+    name.starts_with(".#") || name.starts_with('#') || name.ends_with('~') || name.ends_with('#')
+}
+
+#[cfg(test)]
+mod test {
+    use super::Walk;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_files() {
+        let root = PathBuf::from("target/walk");
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join(".hidden")).unwrap();
+        std::fs::create_dir_all(root.join("nested")).unwrap();
+        for name in [
+            "lib.rs",
+            ".#lib.rs",
+            "#lib.rs#",
+            "nested/mod.rs",
+            ".hidden/a.rs",
+            "notes.md",
+        ] {
+            std::fs::write(root.join(name), "").unwrap();
+        }
+
+        let files = Walk::new(&root).files().unwrap();
+        assert_eq!(vec![root.join("lib.rs"), root.join("nested/mod.rs")], files);
+
+        let files = Walk::new(&root)
+            .include_hidden(true)
+            .include_temporary(true)
+            .files()
+            .unwrap();
+        assert_eq!(
+            vec![
+                root.join(".#lib.rs"),
+                root.join(".hidden/a.rs"),
+                root.join("lib.rs"),
+                root.join("nested/mod.rs"),
+            ],
+            files
+        );
+    }
+}
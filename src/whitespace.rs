@@ -0,0 +1,71 @@
+//! Removal of the trailing whitespace that the formatting tool leaves behind, e.g. in comments and
+//! macro bodies.
+
+use crate::lexer::{tokenize, TokenKind};
+
+/// Remove the spaces and tabs at the end of every line, except for lines that end inside a string
+/// literal, where the whitespace is part of the value.
+pub(crate) fn trim_trailing_whitespace(input: String) -> String {
+    let code = input.as_str();
+    // The output of the formatting tool rarely has trailing whitespace, so it keeps its buffer.
+    let has_trailing_whitespace = code
+        .split('\n')
+        .any(|line| line.trim_end_matches('\r').ends_with([' ', '\t']));
+    if !has_trailing_whitespace {
+        return input;
+    }
+    let strings: Vec<(usize, usize)> = tokenize(code)
+        .into_iter()
+        .filter(|token| token.kind == TokenKind::Str)
+        .map(|token| (token.start, token.end))
+        .collect();
+    let mut strings = strings.into_iter().peekable();
+    let mut result = crate::pool::take_string(code.len());
+    let mut offset = 0;
+    for line in code.split_inclusive('\n') {
+        let content = line.trim_end_matches(['\n', '\r']);
+        let end = offset + content.len();
+        offset += line.len();
+        while strings
+            .next_if(|&(_, string_end)| string_end <= end)
+            .is_some()
+        {}
+        match strings.peek() {
+            Some(&(start, _)) if start < end => result.push_str(content),
+            _ => result.push_str(content.trim_end_matches([' ', '\t'])),
+        }
+        result.push_str(&line[content.len()..]);
+    }
+    crate::pool::give_string(input);
+    result
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn test_trim_trailing_whitespace() {
+        let code = concat!(
+            "/* header \t\r\n",
+            " */\n",
+            "macro_rules! m {\n",
+            "    () => {  \n",
+            "        \"kept  \n",
+            "  \" // note \n",
+            "    };\n",
+            "}\t"
+        );
+        assert_eq!(
+            concat!(
+                "/* header\r\n",
+                " */\n",
+                "macro_rules! m {\n",
+                "    () => {\n",
+                "        \"kept  \n",
+                "  \" // note\n",
+                "    };\n",
+                "}"
+            ),
+            super::trim_trailing_whitespace(code.to_string())
+        );
+    }
+}